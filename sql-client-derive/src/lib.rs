@@ -0,0 +1,136 @@
+extern crate proc_macro;
+
+use darling::util::Flag;
+use darling::{ast, FromDeriveInput, FromField};
+use quote::{quote, quote_spanned};
+use syn::spanned::Spanned;
+
+/// A struct that a row trait is derived for.
+#[derive(FromDeriveInput)]
+#[darling(attributes(sql), supports(struct_named))]
+struct RowStruct {
+    /// The struct's name.
+    ident: syn::Ident,
+    /// The struct's generic parameters.
+    generics: syn::Generics,
+    /// The struct's fields.
+    data: ast::Data<(), RowField>,
+}
+
+/// A field of a struct that a row trait is derived for.
+#[derive(FromField)]
+#[darling(attributes(sql))]
+struct RowField {
+    /// The field's name.
+    ident: Option<syn::Ident>,
+    /// The field's type.
+    ty: syn::Type,
+    /// The name of the column the field is read from, if it's not the field's name.
+    rename: Option<String>,
+    /// Whether the field is built from the same row, rather than read from a single column.
+    flatten: Flag,
+}
+
+impl RowStruct {
+    /// Parses the struct.
+    fn parse(input: proc_macro::TokenStream) -> Result<RowStruct, proc_macro::TokenStream> {
+        let input = syn::parse::<syn::DeriveInput>(input)
+            .map_err(|e| proc_macro::TokenStream::from(e.to_compile_error()))?;
+        RowStruct::from_derive_input(&input).map_err(|e| e.write_errors().into())
+    }
+
+    /// The struct's fields.
+    fn fields(&self) -> Vec<&RowField> {
+        self.data
+            .as_ref()
+            .take_struct()
+            .expect("only named structs are supported")
+            .fields
+    }
+}
+
+/// Derives `sql_client::FromSqlRow`, reading each field from the column with the same name.
+///
+/// Fields can be given `#[sql(rename = "column")]` to read them from a differently named column,
+/// or `#[sql(flatten)]` to build them from the same row with their own `FromSqlRow`.  Each field's
+/// type must implement `FromSql` (or `FromSqlRow` if it's flattened); use an `Option` for columns
+/// that can be null.
+#[proc_macro_derive(FromSqlRow, attributes(sql))]
+pub fn derive_from_sql_row(input: proc_macro::TokenStream) -> proc_macro::TokenStream {
+    // Parse the struct
+    let row_struct = match RowStruct::parse(input) {
+        Ok(row_struct) => row_struct,
+        Err(errors) => return errors,
+    };
+    let ident = &row_struct.ident;
+    let (impl_generics, ty_generics, where_clause) = row_struct.generics.split_for_impl();
+    // Read each field.  The reads are spanned to the field's type so that a type that can't be read
+    // is reported there.
+    let field_values = row_struct.fields().into_iter().map(|field| {
+        let name = field.ident.as_ref().expect("named field");
+        let ty = &field.ty;
+        if field.flatten.is_present() {
+            return quote_spanned! {ty.span()=>
+                #name: <#ty as ::sql_client::FromSqlRow>::from_row(reader)?
+            };
+        }
+        let column = field.rename.clone().unwrap_or_else(|| name.to_string());
+        quote_spanned! {ty.span()=>
+            #name: reader.get::<#ty>(reader.get_ordinal(#column)?)?
+        }
+    });
+    // Create the output
+    let tokens = quote! {
+        impl #impl_generics ::sql_client::FromSqlRow for #ident #ty_generics #where_clause {
+            fn from_row(
+                reader: &::sql_client::SqlDataReader<'_>,
+            ) -> ::std::result::Result<Self, ::sql_client::SqlClientError> {
+                ::std::result::Result::Ok(Self {
+                    #(#field_values,)*
+                })
+            }
+        }
+    };
+    // Return the tokens as a token stream
+    proc_macro::TokenStream::from(tokens)
+}
+
+/// Derives `sql_client::ToSqlRow`, sending each field as a column in the order they're declared.
+///
+/// Fields can be given `#[sql(flatten)]` to send them as several columns with their own
+/// `ToSqlRow`.  Each field's type must implement `ToSql` (or `ToSqlRow` if it's flattened).
+#[proc_macro_derive(ToSqlRow, attributes(sql))]
+pub fn derive_to_sql_row(input: proc_macro::TokenStream) -> proc_macro::TokenStream {
+    // Parse the struct
+    let row_struct = match RowStruct::parse(input) {
+        Ok(row_struct) => row_struct,
+        Err(errors) => return errors,
+    };
+    let ident = &row_struct.ident;
+    let (impl_generics, ty_generics, where_clause) = row_struct.generics.split_for_impl();
+    // Convert each field, spanned to the field's type as above.
+    let field_values = row_struct.fields().into_iter().map(|field| {
+        let name = field.ident.as_ref().expect("named field");
+        let ty = &field.ty;
+        if field.flatten.is_present() {
+            return quote_spanned! {ty.span()=>
+                row.extend(<#ty as ::sql_client::ToSqlRow>::to_row(&self.#name))
+            };
+        }
+        quote_spanned! {ty.span()=>
+            row.push(<#ty as ::sql_client::ToSql>::to_sql(&self.#name))
+        }
+    });
+    // Create the output
+    let tokens = quote! {
+        impl #impl_generics ::sql_client::ToSqlRow for #ident #ty_generics #where_clause {
+            fn to_row(&self) -> ::std::vec::Vec<::sql_client::SqlValue> {
+                let mut row = ::std::vec::Vec::new();
+                #(#field_values;)*
+                row
+            }
+        }
+    };
+    // Return the tokens as a token stream
+    proc_macro::TokenStream::from(tokens)
+}
@@ -0,0 +1,100 @@
+use crate::{SqlClientError, SqlDataReader, SqlValue, ToSqlRow};
+use std::future::Future;
+
+/// A source of rows for a [crate::SqlBulkCopy].
+///
+/// Rows are read one at a time as they're sent, so a source can stream more rows than would fit
+/// in memory.
+pub trait BulkCopySource: Send {
+    /// The names of the source's columns, used to map them to the destination's columns by name.
+    /// A source without names can return an empty list and be mapped by ordinal.
+    fn column_names(&self) -> Vec<String>;
+
+    /// Reads the next row, or returns `None` once there are no more rows.
+    fn next_row(
+        &mut self,
+    ) -> impl Future<Output = Result<Option<Vec<SqlValue>>, SqlClientError>> + Send;
+}
+
+/// Rows from an iterator of values that implement [ToSqlRow] (e.g. tuples, or structs that derive
+/// it).
+#[derive(Debug, Clone)]
+pub struct RowSource<I> {
+    /// The column names.
+    column_names: Vec<String>,
+    /// The rows.
+    rows: I,
+}
+
+impl<I, T> RowSource<I>
+where
+    I: Iterator<Item = T> + Send,
+    T: ToSqlRow,
+{
+    /// Creates a source from rows whose values are in the order of the given columns.
+    pub fn new(column_names: &[&str], rows: impl IntoIterator<IntoIter = I>) -> Self {
+        Self {
+            column_names: column_names.iter().map(|name| name.to_string()).collect(),
+            rows: rows.into_iter(),
+        }
+    }
+
+    /// Creates a source from rows whose columns have no names, so they can only be mapped by
+    /// ordinal.
+    pub fn unnamed(rows: impl IntoIterator<IntoIter = I>) -> Self {
+        Self::new(&[], rows)
+    }
+}
+
+impl<I, T> BulkCopySource for RowSource<I>
+where
+    I: Iterator<Item = T> + Send,
+    T: ToSqlRow,
+{
+    fn column_names(&self) -> Vec<String> {
+        self.column_names.clone()
+    }
+
+    async fn next_row(&mut self) -> Result<Option<Vec<SqlValue>>, SqlClientError> {
+        Ok(self.rows.next().map(|row| row.to_row()))
+    }
+}
+
+/// The remaining rows of a reader's current result set, e.g. to copy a query's results from one
+/// server to another.
+impl<'a> BulkCopySource for &mut SqlDataReader<'a> {
+    fn column_names(&self) -> Vec<String> {
+        self.columns()
+            .iter()
+            .map(|column| column.column_name.clone())
+            .collect()
+    }
+
+    async fn next_row(&mut self) -> Result<Option<Vec<SqlValue>>, SqlClientError> {
+        if !self.read().await? {
+            return Ok(None);
+        }
+        let mut row = Vec::with_capacity(self.field_count());
+        for i in 0..self.field_count() {
+            row.push(self.load_value(i).await?.clone());
+        }
+        Ok(Some(row))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_row_source() {
+        let mut source = RowSource::new(&["Id", "Name"], [(1, "a"), (2, "b")]);
+        assert_eq!(vec!["Id", "Name"], source.column_names());
+        assert_eq!(
+            Some(vec![SqlValue::Int(1), SqlValue::from("a")]),
+            source.next_row().await.unwrap()
+        );
+        assert!(source.next_row().await.unwrap().is_some());
+        assert_eq!(None, source.next_row().await.unwrap());
+    }
+}
@@ -0,0 +1,16 @@
+bitflags::bitflags! {
+    /// Specifies how a [crate::SqlDataReader] reads the results of a command.
+    pub struct CommandBehavior: u32 {
+        /// Each row is read into memory as the reader moves to it.
+        const DEFAULT = 0x00;
+        /// Each row's columns are read from the connection in order, as they're asked for, so that
+        /// large values can be read as streams rather than held in memory.
+        const SEQUENTIAL_ACCESS = 0x10;
+    }
+}
+
+impl Default for CommandBehavior {
+    fn default() -> Self {
+        CommandBehavior::DEFAULT
+    }
+}
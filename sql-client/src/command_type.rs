@@ -0,0 +1,36 @@
+use std::fmt::{Display, Formatter};
+
+/// Specifies how a command string is interpreted.
+#[derive(PartialEq, Debug, Clone, Copy, Default)]
+pub enum CommandType {
+    /// A SQL text command.
+    #[default]
+    Text = 1,
+    /// The name of a stored procedure.
+    StoredProcedure = 4,
+    /// The name of a table.
+    TableDirect = 512,
+}
+
+impl Display for CommandType {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            CommandType::Text => write!(f, "Text"),
+            CommandType::StoredProcedure => write!(f, "StoredProcedure"),
+            CommandType::TableDirect => write!(f, "TableDirect"),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[rstest::rstest]
+    #[case(CommandType::Text, "Text")]
+    #[case(CommandType::StoredProcedure, "StoredProcedure")]
+    #[case(CommandType::TableDirect, "TableDirect")]
+    fn test_to_string(#[case] value: CommandType, #[case] expected: &str) {
+        assert_eq!(expected, value.to_string());
+    }
+}
@@ -0,0 +1,257 @@
+use crate::{BulkCopySource, SqlBulkCopyColumn, SqlClientError, SqlDbType, SqlMetaData, SqlValue};
+use chrono::{DateTime, NaiveDate, NaiveDateTime, NaiveTime};
+use std::io::Read;
+use std::str::FromStr;
+use uuid::Uuid;
+
+/// Rows read from CSV, streamed a record at a time.
+///
+/// Each column of the schema is read from a CSV field and parsed as the column's type.  Empty
+/// fields are nulls.
+#[derive(Debug)]
+pub struct CsvSource<R> {
+    /// The CSV reader.
+    reader: csv::Reader<R>,
+    /// The columns of the rows.
+    schema: Vec<SqlMetaData>,
+    /// The index of the field each column is read from.
+    fields: Vec<usize>,
+    /// The current record.
+    record: csv::StringRecord,
+}
+
+impl<R: Read + Send> CsvSource<R> {
+    /// Creates a source whose columns are read from the fields with the same names (ignoring
+    /// case), or in the same positions if the CSV has no header row.
+    pub fn new(reader: csv::Reader<R>, schema: Vec<SqlMetaData>) -> Result<Self, SqlClientError> {
+        let has_headers = reader.has_headers();
+        let columns = schema
+            .into_iter()
+            .enumerate()
+            .map(|(i, column)| {
+                let field = if has_headers {
+                    SqlBulkCopyColumn::from(column.name())
+                } else {
+                    SqlBulkCopyColumn::from(i)
+                };
+                (field, column)
+            })
+            .collect();
+        Self::with_fields(reader, columns)
+    }
+
+    /// Creates a source whose columns are each read from the given field, by header name or
+    /// ordinal.
+    pub fn with_fields(
+        mut reader: csv::Reader<R>,
+        columns: Vec<(SqlBulkCopyColumn, SqlMetaData)>,
+    ) -> Result<Self, SqlClientError> {
+        // Without a header row, fields can only be found by ordinal.
+        let headers = if reader.has_headers() {
+            Some(reader.headers().map_err(csv_error)?.clone())
+        } else {
+            None
+        };
+        let mut schema = Vec::with_capacity(columns.len());
+        let mut fields = Vec::with_capacity(columns.len());
+        for (field, column) in columns {
+            let index = match (&headers, &field) {
+                (Some(headers), _) => field.find(headers.iter()),
+                (None, SqlBulkCopyColumn::Ordinal(ordinal)) => Some(*ordinal),
+                (None, SqlBulkCopyColumn::Name(_)) => None,
+            }
+            .ok_or_else(|| {
+                SqlClientError::BulkCopySource(format!(
+                    "The CSV has no field {:?} for column '{}'",
+                    field,
+                    column.name()
+                ))
+            })?;
+            schema.push(column);
+            fields.push(index);
+        }
+        Ok(Self {
+            reader,
+            schema,
+            fields,
+            record: csv::StringRecord::new(),
+        })
+    }
+}
+
+impl<R: Read + Send> BulkCopySource for CsvSource<R> {
+    fn column_names(&self) -> Vec<String> {
+        self.schema
+            .iter()
+            .map(|column| column.name().to_string())
+            .collect()
+    }
+
+    async fn next_row(&mut self) -> Result<Option<Vec<SqlValue>>, SqlClientError> {
+        if !self
+            .reader
+            .read_record(&mut self.record)
+            .map_err(csv_error)?
+        {
+            return Ok(None);
+        }
+        self.schema
+            .iter()
+            .zip(&self.fields)
+            .map(|(column, field)| match self.record.get(*field) {
+                Some(text) => parse_field(text, column),
+                None => Err(SqlClientError::BulkCopySource(format!(
+                    "Record {} has no field {} for column '{}'",
+                    self.reader.position().record(),
+                    field,
+                    column.name()
+                ))),
+            })
+            .collect::<Result<Vec<SqlValue>, SqlClientError>>()
+            .map(Some)
+    }
+}
+
+/// Reports a CSV error.
+fn csv_error(error: csv::Error) -> SqlClientError {
+    SqlClientError::BulkCopySource(error.to_string())
+}
+
+/// Parses a CSV field as a value of a column's type.
+fn parse_field(text: &str, column: &SqlMetaData) -> Result<SqlValue, SqlClientError> {
+    let invalid =
+        || SqlClientError::UnsupportedValue(column.sql_db_type().to_string(), text.to_string());
+    // Parses the text as a type.
+    fn parse<T: FromStr>(text: &str) -> Option<T> {
+        text.trim().parse().ok()
+    }
+    if text.is_empty() {
+        return Ok(SqlValue::Null);
+    }
+    let value = match column.sql_db_type() {
+        SqlDbType::Bit => match text.trim().to_ascii_lowercase().as_str() {
+            "1" | "true" => Some(SqlValue::Bit(true)),
+            "0" | "false" => Some(SqlValue::Bit(false)),
+            _ => None,
+        },
+        SqlDbType::TinyInt => parse(text).map(SqlValue::TinyInt),
+        SqlDbType::SmallInt => parse(text).map(SqlValue::SmallInt),
+        SqlDbType::Int => parse(text).map(SqlValue::Int),
+        SqlDbType::BigInt => parse(text).map(SqlValue::BigInt),
+        SqlDbType::Real => parse(text).map(SqlValue::Real),
+        SqlDbType::Float => parse(text).map(SqlValue::Float),
+        SqlDbType::Decimal => parse(text).map(SqlValue::Decimal),
+        SqlDbType::Money | SqlDbType::SmallMoney => parse(text).map(SqlValue::Money),
+        SqlDbType::UniqueIdentifier => parse::<Uuid>(text).map(SqlValue::Guid),
+        SqlDbType::Date => parse::<NaiveDate>(text).map(SqlValue::Date),
+        SqlDbType::Time => parse::<NaiveTime>(text).map(SqlValue::Time),
+        SqlDbType::DateTime | SqlDbType::DateTime2 | SqlDbType::SmallDateTime => {
+            parse::<NaiveDateTime>(text)
+                .or_else(|| NaiveDateTime::parse_from_str(text.trim(), "%Y-%m-%d %H:%M:%S%.f").ok())
+                .map(SqlValue::DateTime)
+        }
+        SqlDbType::DateTimeOffset => DateTime::parse_from_rfc3339(text.trim())
+            .or_else(|_| DateTime::parse_from_str(text.trim(), "%Y-%m-%d %H:%M:%S%.f %:z"))
+            .ok()
+            .map(SqlValue::DateTimeOffset),
+        SqlDbType::Binary | SqlDbType::VarBinary | SqlDbType::Image | SqlDbType::Timestamp => {
+            parse_hex(text.trim()).map(SqlValue::Binary)
+        }
+        _ => Some(SqlValue::String(text.to_string())),
+    };
+    value.ok_or_else(invalid)
+}
+
+/// Parses binary data written as hex digits, with or without a leading "0x".
+fn parse_hex(text: &str) -> Option<Vec<u8>> {
+    let digits = text
+        .strip_prefix("0x")
+        .or_else(|| text.strip_prefix("0X"))
+        .unwrap_or(text);
+    if !digits.len().is_multiple_of(2) {
+        return None;
+    }
+    (0..digits.len())
+        .step_by(2)
+        .map(|i| u8::from_str_radix(digits.get(i..i + 2)?, 16).ok())
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_csv_source() {
+        let csv = "name,id,joined,balance\nalice,1,2024-01-02,10.50\nbob,2,,\n";
+        let reader = csv::Reader::from_reader(csv.as_bytes());
+        let mut source = CsvSource::new(
+            reader,
+            vec![
+                SqlMetaData::new("Id", SqlDbType::Int),
+                SqlMetaData::new("Name", SqlDbType::NVarChar),
+                SqlMetaData::new("Joined", SqlDbType::Date),
+                SqlMetaData::new("Balance", SqlDbType::Decimal),
+            ],
+        )
+        .unwrap();
+        assert_eq!(
+            vec!["Id", "Name", "Joined", "Balance"],
+            source.column_names()
+        );
+        assert_eq!(
+            Some(vec![
+                SqlValue::Int(1),
+                SqlValue::from("alice"),
+                SqlValue::Date(NaiveDate::from_ymd_opt(2024, 1, 2).unwrap()),
+                SqlValue::Decimal("10.50".parse().unwrap()),
+            ]),
+            source.next_row().await.unwrap()
+        );
+        assert_eq!(
+            Some(vec![
+                SqlValue::Int(2),
+                SqlValue::from("bob"),
+                SqlValue::Null,
+                SqlValue::Null
+            ]),
+            source.next_row().await.unwrap()
+        );
+        assert_eq!(None, source.next_row().await.unwrap());
+    }
+
+    #[tokio::test]
+    async fn test_csv_source_without_headers() {
+        let csv = "0x0102,true\n";
+        let reader = csv::ReaderBuilder::new()
+            .has_headers(false)
+            .from_reader(std::io::Cursor::new(csv));
+        let mut source = CsvSource::with_fields(
+            reader,
+            vec![
+                (1.into(), SqlMetaData::new("Active", SqlDbType::Bit)),
+                (0.into(), SqlMetaData::new("Data", SqlDbType::VarBinary)),
+            ],
+        )
+        .unwrap();
+        assert_eq!(
+            Some(vec![SqlValue::Bit(true), SqlValue::Binary(vec![1, 2])]),
+            source.next_row().await.unwrap()
+        );
+    }
+
+    #[test]
+    fn test_csv_source_errors() {
+        let reader = csv::Reader::from_reader("id\n1\n".as_bytes());
+        let schema = vec![SqlMetaData::new("Name", SqlDbType::NVarChar)];
+        assert!(matches!(
+            CsvSource::new(reader, schema),
+            Err(SqlClientError::BulkCopySource(_))
+        ));
+        let column = SqlMetaData::new("Id", SqlDbType::Int);
+        assert!(matches!(
+            parse_field("x", &column),
+            Err(SqlClientError::UnsupportedValue(_, _))
+        ));
+    }
+}
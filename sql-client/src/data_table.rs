@@ -0,0 +1,181 @@
+use crate::sql_collation::SqlCollation;
+use crate::tds_type_info::TypeInfo;
+use crate::{DataTableReader, SqlClientError, SqlDataReader, SqlMetaData, SqlValue, ToSqlRow};
+
+/// Rows of values with the same columns, sent as a table-valued parameter (see
+/// [crate::SqlParameter::with_table]).
+///
+/// The columns must be in the same order, and of types that can be converted to the types, of the
+/// columns of the parameter's user-defined table type.
+#[derive(PartialEq, Debug, Clone, Default)]
+pub struct DataTable {
+    /// The columns.
+    columns: Vec<SqlMetaData>,
+    /// The rows, each with one value per column.
+    rows: Vec<Vec<SqlValue>>,
+}
+
+impl DataTable {
+    /// Creates an empty table with the given columns.
+    pub fn new(columns: Vec<SqlMetaData>) -> Self {
+        Self {
+            columns,
+            rows: Vec::new(),
+        }
+    }
+
+    /// Creates a table with the given columns from rows of values (e.g. single values, tuples, or
+    /// structs that derive [ToSqlRow]).
+    pub fn from_rows<T: ToSqlRow>(
+        columns: Vec<SqlMetaData>,
+        rows: impl IntoIterator<Item = T>,
+    ) -> Result<Self, SqlClientError> {
+        let mut table = Self::new(columns);
+        for row in rows {
+            table.add_row(row)?;
+        }
+        Ok(table)
+    }
+
+    /// Creates a table from the remaining rows of the reader's current result set, with the same
+    /// columns.
+    pub async fn from_reader(reader: &mut SqlDataReader<'_>) -> Result<Self, SqlClientError> {
+        let mut table = Self::new(
+            reader
+                .columns()
+                .iter()
+                .map(SqlMetaData::from_column)
+                .collect(),
+        );
+        while reader.read().await? {
+            let mut row = Vec::with_capacity(table.columns.len());
+            for i in 0..table.columns.len() {
+                row.push(reader.load_value(i).await?.clone());
+            }
+            table.rows.push(row);
+        }
+        Ok(table)
+    }
+
+    /// Adds a row.  It must have a value for each column.
+    pub fn add_row(&mut self, row: impl ToSqlRow) -> Result<(), SqlClientError> {
+        let row = row.to_row();
+        if row.len() != self.columns.len() {
+            return Err(SqlClientError::ArgumentOutOfRange(
+                "row".to_string(),
+                format!(
+                    "The row has {} values but the table has {} columns",
+                    row.len(),
+                    self.columns.len()
+                ),
+            ));
+        }
+        self.rows.push(row);
+        Ok(())
+    }
+
+    /// The columns.
+    pub fn columns(&self) -> &[SqlMetaData] {
+        &self.columns
+    }
+
+    /// The rows.
+    pub fn rows(&self) -> &[Vec<SqlValue>] {
+        &self.rows
+    }
+
+    /// The number of rows.
+    pub fn len(&self) -> usize {
+        self.rows.len()
+    }
+
+    /// Whether there are no rows.
+    pub fn is_empty(&self) -> bool {
+        self.rows.is_empty()
+    }
+
+    /// Creates a reader over the rows, e.g. to bulk copy them with [crate::SqlBulkCopy].
+    pub fn create_data_reader(&self) -> DataTableReader<'_> {
+        DataTableReader::new(self)
+    }
+
+    /// The TYPE_INFO each column is sent with.
+    pub(crate) fn type_infos(
+        &self,
+        collation: Option<SqlCollation>,
+    ) -> Result<Vec<TypeInfo>, SqlClientError> {
+        self.columns
+            .iter()
+            .enumerate()
+            .map(|(i, column)| column.type_info(self.rows.iter().map(|row| &row[i]), collation))
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db_connection_internal::DbConnectionInternal;
+    use crate::tds_enums::TdsEnums;
+    use crate::tds_parser::TdsParser;
+    use crate::tds_test_utils::TokenBuilder;
+    use crate::{SqlCommand, SqlConnection, SqlDbType};
+    use test_utils::MockStream;
+
+    #[test]
+    fn test_from_rows() {
+        let columns = vec![
+            SqlMetaData::new("Id", SqlDbType::BigInt),
+            SqlMetaData::new("Name", SqlDbType::NVarChar),
+        ];
+        let table = DataTable::from_rows(columns, [(1i64, "a"), (2i64, "b")]).unwrap();
+        assert_eq!(2, table.len());
+        assert_eq!(
+            vec![SqlValue::BigInt(2), SqlValue::from("b")],
+            table.rows()[1]
+        );
+    }
+
+    #[test]
+    fn test_wrong_number_of_values() {
+        let mut table = DataTable::new(vec![SqlMetaData::new("Id", SqlDbType::BigInt)]);
+        assert!(matches!(
+            table.add_row((1i64, 2i64)),
+            Err(SqlClientError::ArgumentOutOfRange(_, _))
+        ));
+        assert!(table.is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_from_reader() {
+        let tokens = TokenBuilder::new()
+            .col_metadata(&[("Id", SqlDbType::Int), ("Name", SqlDbType::NVarChar)])
+            .row(&[SqlValue::Int(1), SqlValue::from("a")])
+            .nbc_row(&[SqlValue::Int(2), SqlValue::Null])
+            .done(TdsEnums::SQLDONE, TdsEnums::DONE_COUNT, TdsEnums::SELECT, 2);
+        let mut connection = SqlConnection::new("Server=test").unwrap();
+        connection.attach(DbConnectionInternal::new(TdsParser::new(
+            Box::new(MockStream::new(tokens.packets())),
+            TdsEnums::DEFAULT_PACKET_SIZE,
+        )));
+        let mut command = SqlCommand::new("select ...");
+        let mut reader = command.execute_reader(&mut connection).await.unwrap();
+        let table = DataTable::from_reader(&mut reader).await.unwrap();
+        assert_eq!(
+            vec!["Id", "Name"],
+            table
+                .columns()
+                .iter()
+                .map(|column| column.name())
+                .collect::<Vec<_>>()
+        );
+        assert_eq!(SqlDbType::Int, table.columns()[0].sql_db_type());
+        assert_eq!(
+            vec![
+                vec![SqlValue::Int(1), SqlValue::from("a")],
+                vec![SqlValue::Int(2), SqlValue::Null],
+            ],
+            table.rows()
+        );
+    }
+}
@@ -0,0 +1,41 @@
+use crate::{BulkCopySource, DataTable, SqlClientError, SqlValue};
+
+/// Reads the rows of a [DataTable] in order, e.g. as the source of a [crate::SqlBulkCopy].
+#[derive(Debug, Clone)]
+pub struct DataTableReader<'a> {
+    /// The table.
+    table: &'a DataTable,
+    /// The index of the next row.
+    position: usize,
+}
+
+impl<'a> DataTableReader<'a> {
+    /// Creates a reader positioned before the table's first row.
+    pub(crate) fn new(table: &'a DataTable) -> Self {
+        Self { table, position: 0 }
+    }
+}
+
+impl<'a> Iterator for DataTableReader<'a> {
+    type Item = &'a [SqlValue];
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let row = self.table.rows().get(self.position)?;
+        self.position += 1;
+        Some(row)
+    }
+}
+
+impl<'a> BulkCopySource for DataTableReader<'a> {
+    fn column_names(&self) -> Vec<String> {
+        self.table
+            .columns()
+            .iter()
+            .map(|column| column.name().to_string())
+            .collect()
+    }
+
+    async fn next_row(&mut self) -> Result<Option<Vec<SqlValue>>, SqlClientError> {
+        Ok(self.next().map(|row| row.to_vec()))
+    }
+}
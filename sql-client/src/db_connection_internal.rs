@@ -1,4 +1,19 @@
 use crate::db_connection_pool::DbConnectionPool;
+use crate::db_connection_string_utils::parse_data_source;
+use crate::sni_tcp_handle::SniTcpHandle;
+use crate::sql_command::SqlCommand;
+use crate::sql_login::SqlLogin;
+use crate::tds_enums::TdsEnums;
+use crate::tds_parser::TdsParser;
+use crate::tds_parser_state_object::{TdsParserStateObject, TdsStream};
+use crate::tds_pre_login::TdsPreLogin;
+use crate::tds_token::TdsToken;
+use crate::SqlClientError;
+use std::sync::atomic::Ordering;
+use std::sync::Arc;
+use tokio::net::TcpStream;
+use tokio::sync::OwnedSemaphorePermit;
+use tokio::time::Instant;
 
 /// Internal connection properties and state.
 pub(crate) struct DbConnectionInternal {
@@ -8,12 +23,317 @@ pub(crate) struct DbConnectionInternal {
     hide_password: bool,
     /// The current connection state.
     connection_state: u8,
-    /// If a pooled connection, the pool that the connection came from.
-    connection_pool: Option<DbConnectionPool>,
+    /// If a pooled connection that's been lent out, the pool that the connection came from.
+    connection_pool: Option<Arc<DbConnectionPool>>,
+    /// If a pooled connection that's been lent out, its place among the pool's lent connections.
+    pool_permit: Option<OwnedSemaphorePermit>,
     /// True when the connection should no longer be used.
     is_connection_doomed: bool,
     /// True when the connection should not longer be pooled.
     cannot_be_pooled: bool,
     /// When the connection was created.
-    create_time: chrono::DateTime<chrono::Utc>,
+    create_time: Instant,
+    /// If a pooled connection, the generation of its pool that it was created in.
+    pool_generation: u64,
+    /// The parser for the connection's stream.
+    parser: TdsParser,
+}
+
+impl DbConnectionInternal {
+    /// Creates an internal connection around a parser for an open, logged-in stream.
+    pub fn new(parser: TdsParser) -> Self {
+        Self {
+            allow_set_connection_string: false,
+            hide_password: true,
+            connection_state: 0,
+            connection_pool: None,
+            pool_permit: None,
+            is_connection_doomed: false,
+            cannot_be_pooled: false,
+            create_time: Instant::now(),
+            pool_generation: 0,
+            parser,
+        }
+    }
+
+    /// Connects to the server over TCP and logs in.
+    pub async fn connect(login: &SqlLogin) -> Result<Self, SqlClientError> {
+        let (host_name, port) = parse_data_source(login.data_source())?;
+        log::debug!("connect - host: {}, port: {}", host_name, port);
+        let stream = TcpStream::connect((host_name.as_str(), port)).await?;
+        stream.set_nodelay(true)?;
+        Self::login(Box::new(stream), &host_name, login).await
+    }
+
+    /// Logs in over a connected stream.  The PRELOGIN handshake settles whether the connection
+    /// is encrypted: all of it if either side asks, otherwise just the login (unless the server
+    /// can't encrypt at all).
+    pub async fn login(
+        stream: Box<dyn TdsStream>,
+        host_name: &str,
+        login: &SqlLogin,
+    ) -> Result<Self, SqlClientError> {
+        // Agree on encryption
+        let client_encryption = match login.encrypt() {
+            true => TdsEnums::ENCRYPT_ON,
+            false => TdsEnums::ENCRYPT_OFF,
+        };
+        let mut state = TdsParserStateObject::new(stream, TdsEnums::DEFAULT_PACKET_SIZE);
+        state
+            .write_message(
+                TdsEnums::MT_PRELOGIN,
+                &TdsPreLogin::new(client_encryption).to_bytes(),
+            )
+            .await?;
+        let server_encryption = TdsPreLogin::from_bytes(&state.read_message().await?)?.encryption();
+        let handle = match server_encryption {
+            TdsEnums::ENCRYPT_NOT_SUP if login.encrypt() => {
+                return Err(SqlClientError::NotSupported(
+                    "Encryption, by the server (set Encrypt=False to connect without it)"
+                        .to_string(),
+                ))
+            }
+            TdsEnums::ENCRYPT_NOT_SUP => SniTcpHandle::new(state.into_stream()),
+            _ => {
+                SniTcpHandle::new_ssl(
+                    state.into_stream(),
+                    host_name,
+                    login.trust_server_certificate(),
+                )
+                .await?
+            }
+        };
+        let login_only = client_encryption == TdsEnums::ENCRYPT_OFF
+            && server_encryption == TdsEnums::ENCRYPT_OFF;
+        let ssl_enabled = handle.ssl_enabled();
+        // Log in
+        let mut parser = TdsParser::new(Box::new(handle), login.packet_size() as usize);
+        parser.tds_login(login).await?;
+        if login_only {
+            // The response, and everything after it, isn't encrypted.
+            ssl_enabled.store(false, Ordering::Relaxed);
+        }
+        let mut errors = Vec::new();
+        let mut logged_in = false;
+        while parser.has_pending_data() {
+            match parser.next_token().await? {
+                TdsToken::Error(error) => errors.push(error),
+                TdsToken::Other(TdsEnums::SQLLOGINACK) => logged_in = true,
+                _ => {}
+            }
+        }
+        SqlCommand::check_errors(errors, parser.client_connection_id())?;
+        if !logged_in {
+            return Err(SqlClientError::Protocol(
+                "The server didn't acknowledge the login".to_string(),
+            ));
+        }
+        Ok(Self::new(parser))
+    }
+
+    /// The parser for the connection's stream.
+    pub fn parser(&self) -> &TdsParser {
+        &self.parser
+    }
+
+    /// The parser for the connection's stream, for modification.
+    pub fn parser_mut(&mut self) -> &mut TdsParser {
+        &mut self.parser
+    }
+
+    /// Marks the connection as lent out by a pool.
+    pub fn lend(&mut self, connection_pool: Arc<DbConnectionPool>, permit: OwnedSemaphorePermit) {
+        self.connection_pool = Some(connection_pool);
+        self.pool_permit = Some(permit);
+    }
+
+    /// Whether the connection was lent out by a pool.
+    pub fn is_pooled(&self) -> bool {
+        self.connection_pool.is_some()
+    }
+
+    /// When the connection was created.
+    pub fn create_time(&self) -> Instant {
+        self.create_time
+    }
+
+    /// The generation of its pool that the connection was created in.
+    pub fn pool_generation(&self) -> u64 {
+        self.pool_generation
+    }
+
+    /// Sets the generation of its pool that the connection was created in.
+    pub fn set_pool_generation(&mut self, value: u64) {
+        self.pool_generation = value;
+    }
+
+    /// Whether the connection should no longer be used: it's been doomed, or it's failed.
+    pub fn is_connection_doomed(&self) -> bool {
+        self.is_connection_doomed || self.parser.is_broken()
+    }
+
+    /// Marks the connection as no longer usable, so that it's discarded rather than pooled.
+    pub fn doom(&mut self) {
+        self.is_connection_doomed = true;
+    }
+
+    /// Stops the connection from going back into its pool, although it can still be used.
+    pub fn do_not_pool(&mut self) {
+        self.cannot_be_pooled = true;
+    }
+
+    /// Whether the connection can go back into its pool to be used again.
+    pub fn can_be_pooled(&self) -> bool {
+        !self.is_connection_doomed() && !self.cannot_be_pooled && !self.parser.has_pending_data()
+    }
+
+    /// Asks the server to reset the session state (e.g. SET options and temporary tables) with
+    /// the next request, so that a pooled connection being reused starts afresh.
+    pub fn reset(&mut self) {
+        self.parser.state_mut().set_reset_connection(true);
+    }
+
+    /// Checks that the connection still works with a trivial request.
+    pub async fn ping(&mut self) -> Result<(), SqlClientError> {
+        self.parser.tds_execute_sql_batch("SELECT 1").await?;
+        let mut errors = Vec::new();
+        while self.parser.has_pending_data() {
+            match self.parser.next_token().await? {
+                TdsToken::Row => self.parser.skip_row(false).await?,
+                TdsToken::NbcRow => self.parser.skip_row(true).await?,
+                TdsToken::Error(error) => errors.push(error),
+                _ => {}
+            }
+        }
+        SqlCommand::check_errors(errors, self.parser.client_connection_id())
+    }
+
+    /// Closes the connection, returning it to its pool if it came from one.
+    pub fn close(mut self) {
+        let (Some(connection_pool), permit) =
+            (self.connection_pool.take(), self.pool_permit.take())
+        else {
+            return;
+        };
+        if let Some(discarded) = connection_pool.return_connection(self) {
+            connection_pool.remove_connection();
+            drop(discarded);
+        }
+        // Let the next waiter borrow a connection once this one's back in the pool.
+        drop(permit);
+    }
+}
+
+impl Drop for DbConnectionInternal {
+    /// Stops a pool counting a lent connection that's discarded rather than returned.
+    fn drop(&mut self) {
+        if let Some(connection_pool) = self.connection_pool.take() {
+            connection_pool.remove_connection();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::sql_connection_string::SqlConnectionString;
+    use crate::ssl_over_tds_stream::SslOverTdsStream;
+    use crate::tds_parser_state_object::decode_utf16;
+    use crate::tds_test_utils::{serve_login, serve_pre_login, tls_acceptor, TokenBuilder};
+
+    /// The login details for a connection string.
+    fn sql_login(connection_string: &str) -> SqlLogin {
+        let options = SqlConnectionString::try_from(connection_string).unwrap();
+        SqlLogin::new(&options, options.sql_credential().unwrap().as_ref()).unwrap()
+    }
+
+    /// Logs in to a server that plays its side with the given encryption option and response.
+    async fn login_to(
+        connection_string: &str,
+        server_encryption: u8,
+        response: TokenBuilder,
+    ) -> (Result<DbConnectionInternal, SqlClientError>, Vec<u8>) {
+        let (client, server) = tokio::io::duplex(64 * 1024);
+        let acceptor = (server_encryption != TdsEnums::ENCRYPT_NOT_SUP).then(tls_acceptor);
+        let server = tokio::spawn(async move {
+            serve_login(server, server_encryption, acceptor, &response).await
+        });
+        let login = sql_login(connection_string);
+        let result = DbConnectionInternal::login(Box::new(client), "localhost", &login).await;
+        (result, server.await.unwrap())
+    }
+
+    #[rstest::rstest]
+    #[case::not_supported(false, TdsEnums::ENCRYPT_NOT_SUP)]
+    #[case::login_only(false, TdsEnums::ENCRYPT_OFF)]
+    #[case::client_encrypts(true, TdsEnums::ENCRYPT_ON)]
+    #[case::server_requires(false, TdsEnums::ENCRYPT_REQ)]
+    #[tokio::test]
+    async fn test_login(#[case] encrypt: bool, #[case] server_encryption: u8) {
+        let connection_string = format!(
+            "Server=localhost;User ID=me;Password=secret;Encrypt={};TrustServerCertificate=true",
+            encrypt
+        );
+        let response = TokenBuilder::new()
+            .login_ack()
+            .done(TdsEnums::SQLDONE, 0, 0, 0);
+        let (result, login7) = login_to(&connection_string, server_encryption, response).await;
+        let connection = result.unwrap();
+        assert!(!connection.parser().has_pending_data());
+        // The server got the login (the user name is the second variable-length field)
+        let offset = u16::from_le_bytes([login7[40], login7[41]]) as usize;
+        let length = u16::from_le_bytes([login7[42], login7[43]]) as usize;
+        assert_eq!(
+            "me",
+            decode_utf16(&login7[offset..offset + length * 2]).unwrap()
+        );
+    }
+
+    #[tokio::test]
+    async fn test_login_failed() {
+        let response = TokenBuilder::new()
+            .error(TdsEnums::SQLERROR, 18456, 14, "Login failed for user 'me'.")
+            .done(TdsEnums::SQLDONE, TdsEnums::DONE_ERROR, 0, 0);
+        let (result, _) = login_to(
+            "Server=localhost;User ID=me;Password=wrong;Encrypt=False",
+            TdsEnums::ENCRYPT_NOT_SUP,
+            response,
+        )
+        .await;
+        match result {
+            Err(SqlClientError::Server(exception)) => assert_eq!(18456, exception.number()),
+            _ => panic!("Expected a server error"),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_encryption_not_supported() {
+        let (client, mut server) = tokio::io::duplex(64 * 1024);
+        let server = tokio::spawn(async move {
+            serve_pre_login(&mut server, TdsEnums::ENCRYPT_NOT_SUP).await;
+            server
+        });
+        let login = sql_login("Server=localhost;User ID=me;Password=secret;Encrypt=True");
+        let result = DbConnectionInternal::login(Box::new(client), "localhost", &login).await;
+        assert!(matches!(result, Err(SqlClientError::NotSupported(_))));
+        server.await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_untrusted_certificate() {
+        let (client, mut server) = tokio::io::duplex(64 * 1024);
+        let server = tokio::spawn(async move {
+            serve_pre_login(&mut server, TdsEnums::ENCRYPT_ON).await;
+            // The client rejects the self-signed certificate
+            let result = tls_acceptor().accept(SslOverTdsStream::new(server)).await;
+            assert!(result.is_err());
+        });
+        let login = sql_login("Server=localhost;User ID=me;Password=secret;Encrypt=True");
+        let result = DbConnectionInternal::login(Box::new(client), "localhost", &login).await;
+        // A rejected certificate isn't worth retrying.
+        let error = result.err().unwrap();
+        assert!(matches!(error, SqlClientError::Io(_, _)));
+        assert!(!error.is_transient());
+        server.await.unwrap();
+    }
 }
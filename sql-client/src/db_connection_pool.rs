@@ -1,2 +1,939 @@
-/// A connection pool (not implemented) TODO
-pub(crate) struct DbConnectionPool;
+use crate::db_connection_internal::DbConnectionInternal;
+use crate::db_connection_pool_key::DbConnectionPoolKey;
+use crate::sql_connection_string::SqlConnectionString;
+use crate::{SqlClientError, SqlConnectionPoolStatistics};
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex, MutexGuard, OnceLock};
+use std::time::Duration;
+use tokio::sync::{Notify, OwnedSemaphorePermit, Semaphore};
+use tokio::time::Instant;
+
+/// The process's pools.
+static POOLS: OnceLock<Mutex<DbConnectionPools>> = OnceLock::new();
+
+/// How long new connection attempts are blocked after the first failure.
+const ERROR_WAIT_DEFAULT: Duration = Duration::from_secs(5);
+/// The longest that new connection attempts are blocked after repeated failures.
+const ERROR_WAIT_MAX: Duration = Duration::from_secs(60);
+
+/// A pool of open connections that share a connection string and credentials.
+///
+/// Opening a connection borrows one of the pool's idle connections (or creates a new one), and
+/// closing it returns it.  At most Max Pool Size connections are borrowed at once; further opens
+/// wait their turn, first come first served, for up to the Connect Timeout.  Connections that have
+/// failed, or that are older than the Load Balance Timeout, aren't used again.  Opening a
+/// connection also creates connections in the background until the pool has Min Pool Size of
+/// them, and connections aren't discarded for their age when that would leave fewer.
+///
+/// Unless the Pool Blocking Period says otherwise, a failure to connect puts the pool in an error
+/// state: for the next 5 seconds (doubling with each further failure, up to a minute) attempts to
+/// connect fail fast with the same error.
+///
+/// Clearing the pool discards its idle connections, and its borrowed connections when they're
+/// returned.  Draining it also stops it lending connections, and waits for the borrowed ones to be
+/// returned.
+pub(crate) struct DbConnectionPool {
+    /// The connection string and credentials the pool is for.
+    key: DbConnectionPoolKey,
+    /// The most connections that the pool can hold.
+    max_pool_size: usize,
+    /// The fewest connections that the pool keeps.
+    min_pool_size: usize,
+    /// Whether connection attempts are blocked after a failure.
+    blocking_period_enabled: bool,
+    /// How long a connection may be pooled for after it was created (or zero for no limit).
+    load_balance_timeout: Duration,
+    /// Hands out the right to borrow a connection, to waiters in the order they arrived.
+    permits: Arc<Semaphore>,
+    /// The pool's connections.
+    state: Mutex<PoolState>,
+    /// Wakes a drain when a connection is discarded.
+    discarded: Notify,
+}
+
+/// The connections of a pool.
+struct PoolState {
+    /// The connections that aren't borrowed, most recently returned last.
+    idle: Vec<DbConnectionInternal>,
+    /// The number of open connections, borrowed or idle.
+    count: usize,
+    /// The error that the last connection attempt failed with, while the pool is blocked.
+    error: Option<SqlClientError>,
+    /// When the pool stops blocking connection attempts.
+    blocked_until: Instant,
+    /// How long to block connection attempts after the next failure.
+    error_wait: Duration,
+    /// The generation of the pool, which increases each time it's cleared.  Connections created in
+    /// earlier generations are discarded.
+    generation: u64,
+    /// Whether the pool has stopped lending connections.
+    draining: bool,
+    /// The number of opens waiting for a connection.
+    pending: usize,
+    /// The counts of connections created and discarded, and of wait times.
+    statistics: SqlConnectionPoolStatistics,
+}
+
+/// Counts an open waiting for a connection for as long as it's alive.
+struct Pending<'a>(&'a Mutex<PoolState>);
+
+impl<'a> Pending<'a> {
+    /// Starts counting an open waiting for a connection.
+    fn new(state: &'a Mutex<PoolState>) -> Self {
+        state.lock().unwrap().pending += 1;
+        Self(state)
+    }
+}
+
+impl Drop for Pending<'_> {
+    fn drop(&mut self) {
+        self.0.lock().unwrap().pending -= 1;
+    }
+}
+
+/// A set of pools, by the connection string and credentials they're for.
+#[derive(Default)]
+struct DbConnectionPools {
+    /// The pools.
+    pools: HashMap<DbConnectionPoolKey, Arc<DbConnectionPool>>,
+    /// Whether the pools have stopped lending connections, so that no more may be created.
+    draining: bool,
+}
+
+impl DbConnectionPools {
+    /// Gets the pool for a connection string and credentials, creating it if needed.
+    fn get(
+        &mut self,
+        key: &DbConnectionPoolKey,
+        options: &SqlConnectionString,
+    ) -> Result<Arc<DbConnectionPool>, SqlClientError> {
+        if let Some(pool) = self.pools.get(key) {
+            return Ok(pool.clone());
+        }
+        if self.draining {
+            return Err(SqlClientError::InvalidOperation(
+                "The connection pool has been drained.".to_string(),
+            ));
+        }
+        let pool = Arc::new(DbConnectionPool::new(key.clone(), options)?);
+        self.pools.insert(key.clone(), pool.clone());
+        Ok(pool)
+    }
+
+    /// Stops all the pools lending connections (including pools that would be created later), and
+    /// returns them.
+    fn stop_lending(&mut self) -> Vec<Arc<DbConnectionPool>> {
+        self.draining = true;
+        let pools: Vec<_> = self.pools.values().cloned().collect();
+        for pool in &pools {
+            pool.stop_lending();
+        }
+        pools
+    }
+}
+
+impl DbConnectionPool {
+    /// The process's pools.
+    fn pools() -> MutexGuard<'static, DbConnectionPools> {
+        POOLS.get_or_init(Default::default).lock().unwrap()
+    }
+
+    /// Gets the pool for a connection string and credentials, creating it if needed.
+    pub fn get(
+        key: &DbConnectionPoolKey,
+        options: &SqlConnectionString,
+    ) -> Result<Arc<Self>, SqlClientError> {
+        Self::pools().get(key, options)
+    }
+
+    /// Finds the pool for a connection string and credentials, if it's been created.
+    pub fn find(key: &DbConnectionPoolKey) -> Option<Arc<Self>> {
+        Self::pools().pools.get(key).cloned()
+    }
+
+    /// All the pools.
+    pub fn all() -> Vec<Arc<Self>> {
+        Self::pools().pools.values().cloned().collect()
+    }
+
+    /// Stops all the pools lending connections, including pools that would be created later (so
+    /// that opening a pooled connection fails from now on), and returns them.
+    pub fn stop_lending_all() -> Vec<Arc<Self>> {
+        Self::pools().stop_lending()
+    }
+
+    /// Creates an empty pool.
+    fn new(
+        key: DbConnectionPoolKey,
+        options: &SqlConnectionString,
+    ) -> Result<Self, SqlClientError> {
+        let max_pool_size = options.max_pool_size() as usize;
+        let min_pool_size = options.min_pool_size() as usize;
+        if max_pool_size == 0 {
+            return Err(SqlClientError::ArgumentOutOfRange(
+                "Max Pool Size".to_string(),
+                "Max Pool Size must be at least 1".to_string(),
+            ));
+        }
+        if min_pool_size > max_pool_size {
+            return Err(SqlClientError::ArgumentOutOfRange(
+                "Min Pool Size".to_string(),
+                "Min Pool Size can't be greater than Max Pool Size".to_string(),
+            ));
+        }
+        Ok(Self {
+            key,
+            max_pool_size,
+            min_pool_size,
+            blocking_period_enabled: options
+                .pool_blocking_period()
+                .is_enabled(&options.data_source().unwrap_or_default()),
+            load_balance_timeout: Duration::from_secs(options.load_balance_timeout() as u64),
+            permits: Arc::new(Semaphore::new(max_pool_size)),
+            state: Mutex::new(PoolState {
+                idle: Vec::new(),
+                count: 0,
+                error: None,
+                blocked_until: Instant::now(),
+                error_wait: ERROR_WAIT_DEFAULT,
+                generation: 0,
+                draining: false,
+                pending: 0,
+                statistics: SqlConnectionPoolStatistics::new(),
+            }),
+            discarded: Notify::new(),
+        })
+    }
+
+    /// The connection string and credentials the pool is for.
+    pub fn key(&self) -> &DbConnectionPoolKey {
+        &self.key
+    }
+
+    /// Waits for the right to borrow a connection, for up to the given time (or forever if None).
+    pub async fn acquire(
+        &self,
+        timeout: Option<Duration>,
+    ) -> Result<OwnedSemaphorePermit, SqlClientError> {
+        let started = Instant::now();
+        let pending = Pending::new(&self.state);
+        let acquire = self.permits.clone().acquire_owned();
+        let permit = match timeout {
+            Some(timeout) => tokio::time::timeout(timeout, acquire).await.map_err(|_| {
+                SqlClientError::Timeout(
+                    "The timeout period elapsed prior to obtaining a connection from the pool.  \
+                    This may have occurred because all pooled connections were in use and max \
+                    pool size was reached."
+                        .to_string(),
+                )
+            })?,
+            None => acquire.await,
+        };
+        drop(pending);
+        // The semaphore is closed when the pool is drained.
+        let permit = permit.map_err(|_| {
+            SqlClientError::InvalidOperation("The connection pool has been drained.".to_string())
+        })?;
+        self.state
+            .lock()
+            .unwrap()
+            .statistics
+            .record_wait(started.elapsed());
+        Ok(permit)
+    }
+
+    /// Takes the most recently returned idle connection, if there is one, discarding any that
+    /// have expired.
+    pub fn take_idle(&self) -> Option<DbConnectionInternal> {
+        let mut state = self.state.lock().unwrap();
+        while let Some(inner_connection) = state.idle.pop() {
+            if !self.is_expired(&inner_connection, state.count) {
+                return Some(inner_connection);
+            }
+            log::debug!("connection pool discarding an expired connection");
+            state.count = state.count.saturating_sub(1);
+            state.statistics.record_destroyed(1);
+        }
+        None
+    }
+
+    /// Whether a connection has outlived the Load Balance Timeout, and can be discarded without
+    /// taking the pool (of the given number of connections) below the Min Pool Size.
+    fn is_expired(&self, inner_connection: &DbConnectionInternal, count: usize) -> bool {
+        !self.load_balance_timeout.is_zero()
+            && count > self.min_pool_size
+            && inner_connection.create_time().elapsed() >= self.load_balance_timeout
+    }
+
+    /// Fails with the cached error if the pool is blocking connection attempts after a failure.
+    pub fn check_error(&self) -> Result<(), SqlClientError> {
+        let state = self.state.lock().unwrap();
+        match &state.error {
+            Some(error) if Instant::now() < state.blocked_until => Err(error.clone()),
+            _ => Ok(()),
+        }
+    }
+
+    /// Records a failed connection attempt, blocking further attempts for a while (twice as long
+    /// as last time, up to a limit).
+    pub fn record_error(&self, error: &SqlClientError) {
+        if !self.blocking_period_enabled {
+            return;
+        }
+        let mut state = self.state.lock().unwrap();
+        log::debug!(
+            "connection pool blocking connection attempts for {:?}",
+            state.error_wait
+        );
+        state.error = Some(error.clone());
+        state.blocked_until = Instant::now() + state.error_wait;
+        state.error_wait = (state.error_wait * 2).min(ERROR_WAIT_MAX);
+    }
+
+    /// Records a successful connection attempt, leaving the error state.
+    pub fn record_success(&self) {
+        let mut state = self.state.lock().unwrap();
+        state.error = None;
+        state.error_wait = ERROR_WAIT_DEFAULT;
+    }
+
+    /// Counts a connection that's about to be created to keep the Min Pool Size, unless the pool
+    /// has enough (or has stopped lending).  Returns whether it should be created.
+    pub fn reserve_connection(&self) -> bool {
+        let mut state = self.state.lock().unwrap();
+        if state.draining || state.count >= self.min_pool_size {
+            return false;
+        }
+        state.count += 1;
+        true
+    }
+
+    /// Stops counting a reserved connection that couldn't be created.
+    pub fn cancel_reservation(&self) {
+        let mut state = self.state.lock().unwrap();
+        state.count = state.count.saturating_sub(1);
+        self.discarded.notify_waiters();
+    }
+
+    /// Adds a reserved connection to the idle connections, to be lent after the ones that have
+    /// been returned.
+    pub fn add_reserved(&self, mut inner_connection: DbConnectionInternal) {
+        let mut state = self.state.lock().unwrap();
+        if state.draining {
+            drop(state);
+            self.cancel_reservation();
+            return;
+        }
+        state.statistics.record_created();
+        inner_connection.set_pool_generation(state.generation);
+        state.idle.insert(0, inner_connection);
+    }
+
+    /// Counts a new connection that's about to be lent out.
+    pub fn add_connection(&self, inner_connection: &mut DbConnectionInternal) {
+        let mut state = self.state.lock().unwrap();
+        state.count += 1;
+        state.statistics.record_created();
+        inner_connection.set_pool_generation(state.generation);
+    }
+
+    /// Stops counting a lent connection that's been discarded.
+    pub fn remove_connection(&self) {
+        let mut state = self.state.lock().unwrap();
+        state.count = state.count.saturating_sub(1);
+        state.statistics.record_destroyed(1);
+        self.discarded.notify_waiters();
+    }
+
+    /// Takes back a lent connection, keeping it if it can be used again (i.e. it hasn't been
+    /// doomed or expired).  Returns the connection if it can't, to be discarded.
+    pub fn return_connection(
+        &self,
+        mut inner_connection: DbConnectionInternal,
+    ) -> Option<DbConnectionInternal> {
+        let mut state = self.state.lock().unwrap();
+        if self.is_expired(&inner_connection, state.count)
+            || inner_connection.pool_generation() != state.generation
+            || state.draining
+        {
+            inner_connection.do_not_pool();
+        }
+        if !inner_connection.can_be_pooled() {
+            return Some(inner_connection);
+        }
+        state.idle.push(inner_connection);
+        None
+    }
+
+    /// Discards the idle connections, and the lent connections when they're returned.
+    pub fn clear(&self) {
+        let idle = {
+            let mut state = self.state.lock().unwrap();
+            let idle = std::mem::take(&mut state.idle);
+            state.generation += 1;
+            state.count = state.count.saturating_sub(idle.len());
+            state.statistics.record_destroyed(idle.len());
+            idle
+        };
+        self.discarded.notify_waiters();
+        // Close the connections outside the lock.
+        drop(idle);
+    }
+
+    /// Stops lending connections (failing the opens that are waiting for one), and discards the
+    /// idle connections.
+    pub fn stop_lending(&self) {
+        self.state.lock().unwrap().draining = true;
+        self.permits.close();
+        self.clear();
+    }
+
+    /// Stops lending connections, and waits until the lent connections have been returned and
+    /// discarded (or until the deadline, if there is one).
+    pub async fn drain(&self, deadline: Option<Instant>) -> Result<(), SqlClientError> {
+        self.stop_lending();
+        loop {
+            // Listen before checking, so that a discard in between isn't missed.
+            let discarded = self.discarded.notified();
+            if self.count() == 0 {
+                return Ok(());
+            }
+            match deadline {
+                Some(deadline) => {
+                    tokio::time::timeout_at(deadline, discarded)
+                        .await
+                        .map_err(|_| {
+                            SqlClientError::Timeout(format!(
+                                "{} pooled connections weren't closed before the pool was drained",
+                                self.count()
+                            ))
+                        })?
+                }
+                None => discarded.await,
+            }
+        }
+    }
+
+    /// A snapshot of the pool's counts.
+    pub fn statistics(&self) -> SqlConnectionPoolStatistics {
+        let state = self.state.lock().unwrap();
+        let idle_count = state.idle.len();
+        state.statistics.snapshot(
+            idle_count,
+            state.count.saturating_sub(idle_count),
+            state.pending,
+        )
+    }
+
+    /// The number of open connections, borrowed or idle.
+    pub fn count(&self) -> usize {
+        self.state.lock().unwrap().count
+    }
+
+    /// The number of idle connections.
+    pub fn idle_count(&self) -> usize {
+        self.state.lock().unwrap().idle.len()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tds_enums::TdsEnums;
+    use crate::tds_parser::TdsParser;
+    use crate::tds_test_utils::TokenBuilder;
+    use crate::tds_token::TdsToken;
+    use crate::SqlConnection;
+    use std::cell::{Cell, RefCell};
+    use std::io;
+    use std::sync::atomic::{AtomicU32, Ordering};
+    use test_utils::MockStream;
+
+    /// Opens a connection, counting the new connections made.
+    async fn open(
+        connection: &mut SqlConnection,
+        connects: &Cell<u32>,
+    ) -> Result<(), SqlClientError> {
+        open_or_fail(connection, connects, true).await
+    }
+
+    /// Opens a connection, counting the attempts to connect, which succeed or fail as given.
+    async fn open_or_fail(
+        connection: &mut SqlConnection,
+        connects: &Cell<u32>,
+        succeed: bool,
+    ) -> Result<(), SqlClientError> {
+        connection
+            .open_with(|| {
+                connects.set(connects.get() + 1);
+                async move {
+                    match succeed {
+                        true => Ok(DbConnectionInternal::new(TdsParser::new(
+                            Box::new(MockStream::new(Vec::new())),
+                            TdsEnums::DEFAULT_PACKET_SIZE,
+                        ))),
+                        false => Err(SqlClientError::Io(
+                            io::ErrorKind::ConnectionRefused,
+                            "connection refused".to_string(),
+                        )),
+                    }
+                }
+            })
+            .await
+    }
+
+    /// Opens a connection over a stream, returning whether it connected rather than borrowing a
+    /// pooled connection.
+    async fn open_over(connection: &mut SqlConnection, stream: MockStream) -> bool {
+        let mut stream = Some(stream);
+        connection
+            .open_with(|| {
+                let stream = stream.take().unwrap();
+                async move {
+                    Ok(DbConnectionInternal::new(TdsParser::new(
+                        Box::new(stream),
+                        TdsEnums::DEFAULT_PACKET_SIZE,
+                    )))
+                }
+            })
+            .await
+            .unwrap();
+        stream.is_none()
+    }
+
+    /// The pool for a connection string.
+    fn pool(connection_string: &str) -> Arc<DbConnectionPool> {
+        let key = DbConnectionPoolKey::new(connection_string, None).unwrap();
+        DbConnectionPool::get(&key, &connection_string.try_into().unwrap()).unwrap()
+    }
+
+    #[rstest::rstest]
+    #[case::closed("Server=pool_close", true)]
+    #[case::dropped("Server=pool_drop", false)]
+    #[tokio::test]
+    async fn test_reuse(#[case] connection_string: &str, #[case] close: bool) {
+        let connects = Cell::new(0);
+        let mut connection = SqlConnection::new(connection_string).unwrap();
+        open(&mut connection, &connects).await.unwrap();
+        let client_connection_id = connection.client_connection_id();
+        if close {
+            connection.close();
+        } else {
+            drop(connection);
+            connection = SqlConnection::new(connection_string).unwrap();
+        }
+        let pool = pool(connection_string);
+        assert_eq!((1, 1), (pool.count(), pool.idle_count()));
+        // The connection is borrowed again rather than connecting again.
+        open(&mut connection, &connects).await.unwrap();
+        assert_eq!(1, connects.get());
+        assert_eq!(client_connection_id, connection.client_connection_id());
+        assert_eq!((1, 0), (pool.count(), pool.idle_count()));
+    }
+
+    #[tokio::test]
+    async fn test_discard() {
+        let connection_string = "Server=pool_discard";
+        let connects = Cell::new(0);
+        let mut connection = SqlConnection::new(connection_string).unwrap();
+        open(&mut connection, &connects).await.unwrap();
+        // A connection that's been left part way through a message isn't returned.
+        connection.detach();
+        let pool = pool(connection_string);
+        assert_eq!((0, 0), (pool.count(), pool.idle_count()));
+        open(&mut connection, &connects).await.unwrap();
+        assert_eq!(2, connects.get());
+    }
+
+    #[tokio::test]
+    async fn test_no_pooling() {
+        let connection_string = "Server=pool_none;Pooling=false";
+        let connects = Cell::new(0);
+        let mut connection = SqlConnection::new(connection_string).unwrap();
+        open(&mut connection, &connects).await.unwrap();
+        connection.close();
+        open(&mut connection, &connects).await.unwrap();
+        assert_eq!(2, connects.get());
+        assert_eq!(0, pool(connection_string).count());
+    }
+
+    #[tokio::test]
+    async fn test_waiters_are_fair() {
+        let connection_string = "Server=pool_fair;Max Pool Size=1";
+        let connects = Cell::new(0);
+        let order = RefCell::new(Vec::new());
+        let mut first = SqlConnection::new(connection_string).unwrap();
+        open(&mut first, &connects).await.unwrap();
+        // Each waiter borrows the connection in turn, then closes it.
+        let waiter = |name: &'static str| {
+            let (connects, order) = (&connects, &order);
+            async move {
+                let mut connection = SqlConnection::new(connection_string).unwrap();
+                open(&mut connection, connects).await.unwrap();
+                order.borrow_mut().push(name);
+                connection.close();
+            }
+        };
+        let close_first = async {
+            tokio::task::yield_now().await;
+            first.close();
+        };
+        tokio::join!(waiter("second"), waiter("third"), close_first);
+        assert_eq!(vec!["second", "third"], *order.borrow());
+        assert_eq!(1, connects.get());
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_wait_times_out() {
+        let connection_string = "Server=pool_timeout;Max Pool Size=1;Connect Timeout=2";
+        let connects = Cell::new(0);
+        let mut first = SqlConnection::new(connection_string).unwrap();
+        open(&mut first, &connects).await.unwrap();
+        let mut second = SqlConnection::new(connection_string).unwrap();
+        assert!(matches!(
+            open(&mut second, &connects).await,
+            Err(SqlClientError::Timeout(_))
+        ));
+        assert_eq!(1, connects.get());
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_blocking_period() {
+        /// Opens and closes a connection.
+        async fn attempt(
+            connection: &mut SqlConnection,
+            connects: &Cell<u32>,
+            succeed: bool,
+        ) -> Result<(), SqlClientError> {
+            let result = open_or_fail(connection, connects, succeed).await;
+            connection.close();
+            result
+        }
+        let connects = Cell::new(0);
+        let mut connection = SqlConnection::new("Server=pool_block").unwrap();
+        assert!(attempt(&mut connection, &connects, false).await.is_err());
+        assert_eq!(1, connects.get());
+        // Attempts fail fast with the same error for 5 seconds.
+        let error = attempt(&mut connection, &connects, true).await.unwrap_err();
+        assert_eq!(
+            "An I/O error occurred: connection refused",
+            error.to_string()
+        );
+        assert_eq!(1, connects.get());
+        tokio::time::advance(Duration::from_secs(5)).await;
+        // Another failure blocks attempts for twice as long.
+        assert!(attempt(&mut connection, &connects, false).await.is_err());
+        assert_eq!(2, connects.get());
+        tokio::time::advance(Duration::from_secs(9)).await;
+        assert!(attempt(&mut connection, &connects, true).await.is_err());
+        assert_eq!(2, connects.get());
+        tokio::time::advance(Duration::from_secs(1)).await;
+        attempt(&mut connection, &connects, true).await.unwrap();
+        assert_eq!(3, connects.get());
+        // A success resets the blocking period.  (The idle connection is discarded, so that the
+        // next attempt connects.)
+        let pool = pool("Server=pool_block");
+        pool.take_idle();
+        pool.remove_connection();
+        assert!(attempt(&mut connection, &connects, false).await.is_err());
+        tokio::time::advance(Duration::from_secs(5)).await;
+        attempt(&mut connection, &connects, true).await.unwrap();
+        assert_eq!(5, connects.get());
+    }
+
+    #[rstest::rstest]
+    #[case::never_block("Server=pool_never_block;Pool Blocking Period=NeverBlock")]
+    #[case::azure("Server=pool.database.windows.net;Pool Blocking Period=Auto")]
+    #[tokio::test]
+    async fn test_no_blocking_period(#[case] connection_string: &str) {
+        let connects = Cell::new(0);
+        let mut connection = SqlConnection::new(connection_string).unwrap();
+        assert!(open_or_fail(&mut connection, &connects, false)
+            .await
+            .is_err());
+        open(&mut connection, &connects).await.unwrap();
+        assert_eq!(2, connects.get());
+    }
+
+    #[rstest::rstest]
+    #[case::no_ping("Server=pool_reset", false)]
+    #[case::ping("Server=pool_ping", true)]
+    #[tokio::test]
+    async fn test_reset_on_reuse(#[case] connection_string: &str, #[case] ping: bool) {
+        let mut connection = SqlConnection::new(connection_string).unwrap();
+        connection.set_ping_on_checkout(ping);
+        let stream = MockStream::new(
+            TokenBuilder::new()
+                .done(TdsEnums::SQLDONE, 0, 0, 1)
+                .packets(),
+        );
+        let written = stream.written();
+        assert!(open_over(&mut connection, stream).await);
+        connection.close();
+        assert!(!open_over(&mut connection, MockStream::new(Vec::new())).await);
+        // The first request after reuse (the ping, if there is one) resets the connection.
+        if !ping {
+            connection
+                .parser_mut()
+                .unwrap()
+                .tds_execute_sql_batch("SELECT 1")
+                .await
+                .unwrap();
+        }
+        let written = written.lock().unwrap().clone();
+        assert_eq!(TdsEnums::MT_SQL, written[0]);
+        assert_eq!(TdsEnums::ST_EOM | TdsEnums::ST_RESET_CONNECTION, written[1]);
+        assert_eq!(!ping, connection.parser_mut().unwrap().has_pending_data());
+    }
+
+    #[tokio::test]
+    async fn test_failed_ping_discards() {
+        let connection_string = "Server=pool_ping_failure";
+        let mut connection = SqlConnection::new(connection_string).unwrap();
+        connection.set_ping_on_checkout(true);
+        // The server has dropped the pooled connection, so the ping fails.
+        assert!(open_over(&mut connection, MockStream::new(Vec::new())).await);
+        connection.close();
+        assert!(open_over(&mut connection, MockStream::new(Vec::new())).await);
+        let pool = pool(connection_string);
+        assert_eq!((1, 0), (pool.count(), pool.idle_count()));
+    }
+
+    #[tokio::test]
+    async fn test_doomed_not_returned() {
+        let connection_string = "Server=pool_doomed";
+        let mut connection = SqlConnection::new(connection_string).unwrap();
+        let stream = MockStream::new(
+            TokenBuilder::new()
+                .error(TdsEnums::SQLERROR, 596, 21, "Cannot continue the execution")
+                .done(TdsEnums::SQLDONE, TdsEnums::DONE_ERROR, 0, 0)
+                .packets(),
+        );
+        open_over(&mut connection, stream).await;
+        let parser = connection.parser_mut().unwrap();
+        assert!(matches!(
+            parser.next_token().await.unwrap(),
+            TdsToken::Error(_)
+        ));
+        parser.next_token().await.unwrap();
+        connection.close();
+        let pool = pool(connection_string);
+        assert_eq!((0, 0), (pool.count(), pool.idle_count()));
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_load_balance_timeout() {
+        let connection_string = "Server=pool_lifetime;Load Balance Timeout=10";
+        let pool = pool(connection_string);
+        let mut connection = SqlConnection::new(connection_string).unwrap();
+        // An idle connection that expires is discarded when it would be borrowed.
+        assert!(open_over(&mut connection, MockStream::new(Vec::new())).await);
+        connection.close();
+        tokio::time::advance(Duration::from_secs(9)).await;
+        assert!(!open_over(&mut connection, MockStream::new(Vec::new())).await);
+        connection.close();
+        tokio::time::advance(Duration::from_secs(1)).await;
+        assert!(open_over(&mut connection, MockStream::new(Vec::new())).await);
+        assert_eq!((1, 0), (pool.count(), pool.idle_count()));
+        // A lent connection that expires is discarded when it's returned.
+        tokio::time::advance(Duration::from_secs(10)).await;
+        connection.close();
+        assert_eq!((0, 0), (pool.count(), pool.idle_count()));
+    }
+
+    /// Fills a pool, returning how many connections it tried to create, which succeed or fail as
+    /// given.
+    async fn fill(pool: &Arc<DbConnectionPool>, succeed: bool) -> u32 {
+        let connects = Arc::new(AtomicU32::new(0));
+        let counter = connects.clone();
+        let task = SqlConnection::fill_pool(pool.clone(), 15, move || {
+            counter.fetch_add(1, Ordering::Relaxed);
+            async move {
+                match succeed {
+                    true => Ok(DbConnectionInternal::new(TdsParser::new(
+                        Box::new(MockStream::new(Vec::new())),
+                        TdsEnums::DEFAULT_PACKET_SIZE,
+                    ))),
+                    false => Err(SqlClientError::Io(
+                        io::ErrorKind::ConnectionRefused,
+                        "connection refused".to_string(),
+                    )),
+                }
+            }
+        });
+        if let Some(task) = task {
+            task.await.unwrap();
+        }
+        connects.load(Ordering::Relaxed)
+    }
+
+    #[tokio::test]
+    async fn test_min_pool_size() {
+        let connection_string = "Server=pool_min;Min Pool Size=3";
+        let pool = pool(connection_string);
+        let connects = Cell::new(0);
+        let mut connection = SqlConnection::new(connection_string).unwrap();
+        // Filling the pool makes up the connections the open didn't.
+        open(&mut connection, &connects).await.unwrap();
+        assert_eq!((1, 0), (pool.count(), pool.idle_count()));
+        assert_eq!(2, fill(&pool, true).await);
+        assert_eq!((3, 2), (pool.count(), pool.idle_count()));
+        assert_eq!(0, fill(&pool, true).await);
+        // A failure to fill the pool blocks it, for a later open to report.
+        connection.close();
+        pool.clear();
+        open(&mut connection, &connects).await.unwrap();
+        assert_eq!(1, fill(&pool, false).await);
+        assert_eq!((1, 0), (pool.count(), pool.idle_count()));
+        assert!(pool.check_error().is_err());
+        connection.close();
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_min_pool_size_kept_after_load_balance_timeout() {
+        let connection_string = "Server=pool_min_lifetime;Min Pool Size=1;Load Balance Timeout=10";
+        let pool = pool(connection_string);
+        let mut connection = SqlConnection::new(connection_string).unwrap();
+        assert!(open_over(&mut connection, MockStream::new(Vec::new())).await);
+        connection.close();
+        // The connection has expired, but is kept as the pool's only connection.
+        tokio::time::advance(Duration::from_secs(10)).await;
+        assert!(!open_over(&mut connection, MockStream::new(Vec::new())).await);
+        connection.close();
+        assert_eq!((1, 1), (pool.count(), pool.idle_count()));
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_statistics() {
+        let connection_string = "Server=pool_statistics;Max Pool Size=1";
+        let connects = Cell::new(0);
+        let mut first = SqlConnection::new(connection_string).unwrap();
+        let mut second = SqlConnection::new(connection_string).unwrap();
+        assert_eq!(None, first.pool_statistics());
+        open(&mut first, &connects).await.unwrap();
+        // The second open waits 50ms for the first connection to be returned.
+        let wait = async {
+            tokio::task::yield_now().await;
+            let statistics = first.pool_statistics().unwrap();
+            assert_eq!(
+                (0, 1, 1),
+                (
+                    statistics.idle_count(),
+                    statistics.active_count(),
+                    statistics.pending_count()
+                )
+            );
+            tokio::time::advance(Duration::from_millis(50)).await;
+            first.close();
+        };
+        let (result, _) = tokio::join!(open(&mut second, &connects), wait);
+        result.unwrap();
+        second.close();
+        SqlConnection::clear_pool(&second);
+        let statistics = second.pool_statistics().unwrap();
+        assert_eq!(
+            (0, 0, 0, 1, 1),
+            (
+                statistics.idle_count(),
+                statistics.active_count(),
+                statistics.pending_count(),
+                statistics.created_count(),
+                statistics.destroyed_count()
+            )
+        );
+        let histogram = statistics.wait_time_histogram();
+        assert_eq!((Duration::from_millis(1), 1), histogram[0]);
+        assert_eq!((Duration::from_millis(100), 1), histogram[2]);
+    }
+
+    #[tokio::test]
+    async fn test_clear() {
+        let connection_string = "Server=pool_clear";
+        let connects = Cell::new(0);
+        let mut first = SqlConnection::new(connection_string).unwrap();
+        let mut second = SqlConnection::new(connection_string).unwrap();
+        open(&mut first, &connects).await.unwrap();
+        open(&mut second, &connects).await.unwrap();
+        first.close();
+        // The idle connection is discarded straight away, and the borrowed one when it's returned.
+        SqlConnection::clear_pool(&second);
+        let pool = pool(connection_string);
+        assert_eq!((1, 0), (pool.count(), pool.idle_count()));
+        second.close();
+        assert_eq!((0, 0), (pool.count(), pool.idle_count()));
+        // Connections made after clearing are pooled as usual.
+        open(&mut first, &connects).await.unwrap();
+        first.close();
+        assert_eq!((1, 1), (pool.count(), pool.idle_count()));
+        assert_eq!(3, connects.get());
+    }
+
+    #[test]
+    fn test_no_new_pools_after_draining() {
+        let mut pools = DbConnectionPools::default();
+        let options = "Server=pool_drained".try_into().unwrap();
+        let key = DbConnectionPoolKey::new("Server=pool_drained", None).unwrap();
+        let pool = pools.get(&key, &options).unwrap();
+        assert_eq!(1, pools.stop_lending().len());
+        // The existing pool is returned (and won't lend), but a new connection string can't
+        // create another pool.
+        assert!(Arc::ptr_eq(&pool, &pools.get(&key, &options).unwrap()));
+        let new_options = "Server=pool_drained_new".try_into().unwrap();
+        let new_key = DbConnectionPoolKey::new("Server=pool_drained_new", None).unwrap();
+        assert!(matches!(
+            pools.get(&new_key, &new_options),
+            Err(SqlClientError::InvalidOperation(_))
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_drain() {
+        let connection_string = "Server=pool_drain;Max Pool Size=1";
+        let connects = Cell::new(0);
+        let mut first = SqlConnection::new(connection_string).unwrap();
+        let mut second = SqlConnection::new(connection_string).unwrap();
+        open(&mut first, &connects).await.unwrap();
+        let pool = pool(connection_string);
+        // The waiting open fails, and the drain finishes once the borrowed connection is closed.
+        let close_first = async {
+            tokio::task::yield_now().await;
+            tokio::task::yield_now().await;
+            assert_eq!(1, pool.count());
+            first.close();
+        };
+        let (waiting, drained, _) =
+            tokio::join!(open(&mut second, &connects), pool.drain(None), close_first);
+        assert!(matches!(waiting, Err(SqlClientError::InvalidOperation(_))));
+        drained.unwrap();
+        assert_eq!((0, 0), (pool.count(), pool.idle_count()));
+        assert!(matches!(
+            open(&mut second, &connects).await,
+            Err(SqlClientError::InvalidOperation(_))
+        ));
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_drain_times_out() {
+        let connection_string = "Server=pool_drain_timeout";
+        let connects = Cell::new(0);
+        let mut connection = SqlConnection::new(connection_string).unwrap();
+        open(&mut connection, &connects).await.unwrap();
+        let deadline = Instant::now() + Duration::from_secs(1);
+        assert!(matches!(
+            pool(connection_string).drain(Some(deadline)).await,
+            Err(SqlClientError::Timeout(_))
+        ));
+    }
+
+    #[test]
+    fn test_invalid_sizes() {
+        for connection_string in [
+            "Server=pool_invalid;Max Pool Size=0",
+            "Server=pool_invalid;Min Pool Size=5;Max Pool Size=2",
+        ] {
+            let key = DbConnectionPoolKey::new(connection_string, None).unwrap();
+            let options = connection_string.try_into().unwrap();
+            assert!(DbConnectionPool::get(&key, &options).is_err());
+        }
+    }
+}
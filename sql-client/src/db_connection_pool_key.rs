@@ -0,0 +1,91 @@
+use crate::sql_credential::SqlCredential;
+use crate::{SqlClientError, SqlConnectionStringBuilder};
+use secstr::SecStr;
+use std::collections::BTreeMap;
+use std::hash::{Hash, Hasher};
+
+/// Identifies a connection pool: connections are only shared by connections with equivalent
+/// connection strings and the same credentials.
+#[derive(PartialEq, Eq, Clone)]
+pub(crate) struct DbConnectionPoolKey {
+    /// The connection string, in canonical form.
+    connection_string: String,
+    /// The user ID and password of the credentials supplied alongside the connection string.
+    credential: Option<(String, SecStr)>,
+}
+
+impl DbConnectionPoolKey {
+    /// Creates the key for a connection string and credentials.
+    pub fn new(
+        connection_string: &str,
+        sql_credential: Option<&SqlCredential>,
+    ) -> Result<Self, SqlClientError> {
+        Ok(Self {
+            connection_string: canonical_connection_string(connection_string)?,
+            credential: sql_credential
+                .map(|sql_credential| (sql_credential.user_id(), sql_credential.password())),
+        })
+    }
+
+    /// The connection string, in canonical form.
+    pub fn connection_string(&self) -> &str {
+        &self.connection_string
+    }
+}
+
+impl Hash for DbConnectionPoolKey {
+    /// Hashes all but the password, which is only compared.
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.connection_string.hash(state);
+        self.credential
+            .as_ref()
+            .map(|(user_id, _)| user_id)
+            .hash(state);
+    }
+}
+
+/// Puts a connection string in a canonical form, so that connection strings that only differ in
+/// the order, case or synonyms of their keywords, in how their values are written (e.g. "Yes" for
+/// "True"), or in spacing, share a pool.
+fn canonical_connection_string(connection_string: &str) -> Result<String, SqlClientError> {
+    // Parse the connection string, which names each keyword and formats each value the same way.
+    let connection_string =
+        SqlConnectionStringBuilder::try_from(connection_string)?.connection_string();
+    // Sort the keywords, keeping the last value of any repeated keyword.
+    let pairs: BTreeMap<&str, &str> = connection_string
+        .split(';')
+        .filter_map(|key_value_pair| key_value_pair.split_once('='))
+        .collect();
+    Ok(pairs
+        .into_iter()
+        .map(|(keyword, value)| format!("{}={}", keyword, value))
+        .collect::<Vec<_>>()
+        .join(";"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[rstest::rstest]
+    #[case("Server=test;Database=db", "database=db; Server=test")]
+    #[case("Server=test;Encrypt=Yes", "server = test;ENCRYPT=Yes")]
+    #[case("Server=other;Server=test", "Server=test")]
+    #[case("Server=test;Database=db", "Data Source=test;Initial Catalog=db")]
+    #[case("Address=test;Encrypt=Yes", "Server=test;Encrypt=true")]
+    #[case("Server=test;Pooling=True", "Server=test;Pooling=yes")]
+    fn test_equivalent(#[case] a: &str, #[case] b: &str) {
+        assert!(
+            DbConnectionPoolKey::new(a, None).unwrap()
+                == DbConnectionPoolKey::new(b, None).unwrap()
+        );
+    }
+
+    #[test]
+    fn test_different() {
+        let credential = SqlCredential::new("user".to_string(), SecStr::from("password")).unwrap();
+        let key = DbConnectionPoolKey::new("Server=test", None).unwrap();
+        assert!(key != DbConnectionPoolKey::new("Server=other", None).unwrap());
+        assert!(key != DbConnectionPoolKey::new("Server=test", Some(&credential)).unwrap());
+    }
+}
@@ -1,9 +1,11 @@
+use crate::retry_enumerators::SqlRetryMethod;
 use crate::{
     ApplicationIntent, PoolBlockingPeriod, SqlAuthenticationMethod,
     SqlConnectionAttestationProtocol, SqlConnectionColumnEncryptionSetting,
     SqlConnectionIpAddressPreference, TransactionBinding, TypeSystem, TypeSystemVersion,
 };
 use secstr::SecStr;
+use std::time::Duration;
 pub(crate) struct DbConnectionStringDefaults;
 impl DbConnectionStringDefaults {
     pub const APPLICATION_INTENT: ApplicationIntent = ApplicationIntent::ReadWrite;
@@ -40,6 +42,12 @@ impl DbConnectionStringDefaults {
     pub const POOLING: bool = true;
     pub const POOL_BLOCKING_PERIOD: PoolBlockingPeriod = PoolBlockingPeriod::Auto;
     pub const REPLICATION: bool = false;
+    pub const RETRY_DELTA_TIME: Duration = Duration::from_secs(1);
+    pub const RETRY_MAX_TIME_INTERVAL: Duration = Duration::from_secs(30);
+    pub const RETRY_METHOD: Option<SqlRetryMethod> = None;
+    pub const RETRY_MIN_TIME_INTERVAL: Duration = Duration::ZERO;
+    pub const RETRY_NUMBER_OF_TRIES: u32 = 3;
+    pub const RETRY_TRANSIENT_ERRORS: Option<Vec<i32>> = None;
     pub const TRANSACTION_BINDING_STR: &'static str = "Implicit Unbind";
     pub const TRANSACTION_BINDING: TransactionBinding = TransactionBinding::ImplicitUnbind;
     pub const TRUST_SERVER_CERTIFICATE: bool = false;
@@ -32,6 +32,12 @@ impl DbConnectionStringKeywords {
     pub const POOL_BLOCKING_PERIOD: &'static str = "Pool Blocking Period";
     pub const POOLING: &'static str = "Pooling";
     pub const REPLICATION: &'static str = "Replication";
+    pub const RETRY_DELTA_TIME: &'static str = "Retry Delta Time";
+    pub const RETRY_MAX_TIME_INTERVAL: &'static str = "Retry Max Time Interval";
+    pub const RETRY_METHOD: &'static str = "Retry Method";
+    pub const RETRY_MIN_TIME_INTERVAL: &'static str = "Retry Min Time Interval";
+    pub const RETRY_NUMBER_OF_TRIES: &'static str = "Retry Number Of Tries";
+    pub const RETRY_TRANSIENT_ERRORS: &'static str = "Retry Transient Errors";
     pub const TRANSACTION_BINDING: &'static str = "Transaction Binding";
     pub const TRUST_SERVER_CERTIFICATE: &'static str = "Trust Server Certificate";
     pub const TYPE_SYSTEM_VERSION: &'static str = "Type System Version";
@@ -95,6 +101,18 @@ impl DbConnectionStringKeywordsLower {
     pub const POOL_BLOCKING_PERIOD_ALT: &'static str = "poolblockingperiod";
     pub const POOLING: &'static str = "pooling";
     pub const REPLICATION: &'static str = "replication";
+    pub const RETRY_DELTA_TIME: &'static str = "retry delta time";
+    pub const RETRY_DELTA_TIME_ALT: &'static str = "retrydeltatime";
+    pub const RETRY_MAX_TIME_INTERVAL: &'static str = "retry max time interval";
+    pub const RETRY_MAX_TIME_INTERVAL_ALT: &'static str = "retrymaxtimeinterval";
+    pub const RETRY_METHOD: &'static str = "retry method";
+    pub const RETRY_METHOD_ALT: &'static str = "retrymethod";
+    pub const RETRY_MIN_TIME_INTERVAL: &'static str = "retry min time interval";
+    pub const RETRY_MIN_TIME_INTERVAL_ALT: &'static str = "retrymintimeinterval";
+    pub const RETRY_NUMBER_OF_TRIES: &'static str = "retry number of tries";
+    pub const RETRY_NUMBER_OF_TRIES_ALT: &'static str = "retrynumberoftries";
+    pub const RETRY_TRANSIENT_ERRORS: &'static str = "retry transient errors";
+    pub const RETRY_TRANSIENT_ERRORS_ALT: &'static str = "retrytransienterrors";
     pub const TRANSACTION_BINDING: &'static str = "transaction binding";
     pub const TYPE_SYSTEM_VERSION: &'static str = "type system version";
     pub const TRUST_SERVER_CERTIFICATE: &'static str = "trust server certificate";
@@ -1,4 +1,6 @@
+use crate::tds_enums::TdsEnums;
 use crate::SqlClientError;
+use std::time::Duration;
 
 /// Converts a (true/yes,false/no) string to a boolean.
 pub(crate) fn convert_to_boolean(value: &str) -> Result<bool, SqlClientError> {
@@ -24,32 +26,131 @@ pub(crate) fn convert_to_integrated_security(value: &str) -> Result<bool, SqlCli
     }
 }
 
-const LOCAL_DB_PREFIX: &'static str = "(localdb)\\";
-const LOCAL_DB_PREFIX_NP: &'static str = "np:\\\\.\\pipe\\LOCALDB#";
+/// Converts a number of seconds (e.g. "1.5") to a duration.
+pub(crate) fn convert_to_duration(value: &str) -> Result<Duration, SqlClientError> {
+    value
+        .trim()
+        .parse::<f64>()
+        .ok()
+        .and_then(|seconds| Duration::try_from_secs_f64(seconds).ok())
+        .ok_or_else(|| SqlClientError::UnsupportedValue("seconds".to_string(), value.to_string()))
+}
+
+/// Formats a duration as a number of seconds.
+pub(crate) fn format_duration(value: &Duration) -> String {
+    value.as_secs_f64().to_string()
+}
+
+/// Converts a comma-separated list (e.g. "1205, 4060") to error numbers.
+pub(crate) fn convert_to_error_numbers(value: &str) -> Result<Vec<i32>, SqlClientError> {
+    value
+        .split(',')
+        .map(|number| {
+            number.trim().parse().map_err(|_| {
+                SqlClientError::UnsupportedValue("error numbers".to_string(), value.to_string())
+            })
+        })
+        .collect()
+}
+
+/// Formats error numbers as a comma-separated list.
+pub(crate) fn format_error_numbers(value: &[i32]) -> String {
+    value
+        .iter()
+        .map(|number| number.to_string())
+        .collect::<Vec<_>>()
+        .join(",")
+}
+
+const LOCAL_DB_PREFIX: &str = "(localdb)\\";
+const LOCAL_DB_PREFIX_NP: &str = "np:\\\\.\\pipe\\LOCALDB#";
 
 //
 pub(crate) fn get_local_db_instance_name_from_server_name(server_name: &str) -> Option<String> {
-    // If the server starts with the regular prefix...
-    if server_name.starts_with(LOCAL_DB_PREFIX) {
-        // Pull the name off the end
-        let instance_name = &server_name[LOCAL_DB_PREFIX.len()..];
-        Some(instance_name.to_string())
-    }
-    // If, instead, the server name starts with the NP prefix
-    else if server_name.starts_with(LOCAL_DB_PREFIX_NP) {
-        // Pull the name off the end
-        let instance_name = &server_name[LOCAL_DB_PREFIX_NP.len()..];
-        Some(instance_name.to_string())
-    } else {
-        None
-    }
+    // If the server starts with the regular prefix, pull the name off the end.
+    server_name
+        .strip_prefix(LOCAL_DB_PREFIX)
+        // If, instead, the server name starts with the NP prefix, pull the name off the end of that.
+        .or_else(|| server_name.strip_prefix(LOCAL_DB_PREFIX_NP))
+        .map(|instance_name| instance_name.to_string())
+}
+
+const AZURE_SQL_SERVER_ENDPOINTS: [&str; 5] = [
+    ".database.windows.net",
+    ".database.cloudapi.de",
+    ".database.usgovcloudapi.net",
+    ".database.chinacloudapi.cn",
+    ".database.fabric.microsoft.com",
+];
+
+/// Whether a data source (e.g. "tcp:server.database.windows.net,1433") is an Azure SQL endpoint.
+pub(crate) fn is_azure_sql_server_endpoint(data_source: &str) -> bool {
+    // Remove the port and the instance name.
+    let server_name = match data_source.rsplit_once(',') {
+        Some((server_name, _)) => server_name,
+        None => data_source,
+    };
+    let server_name = match server_name.rsplit_once('\\') {
+        Some((server_name, _)) if !server_name.is_empty() => server_name,
+        _ => server_name,
+    };
+    let server_name = server_name.trim_end().to_lowercase();
+    AZURE_SQL_SERVER_ENDPOINTS
+        .iter()
+        .any(|endpoint| server_name.ends_with(endpoint))
+}
+
+/// Splits a data source (e.g. "tcp:myserver,1433") into the host name and the TCP port to connect
+/// to.  Only TCP is supported, and a named instance needs a port, since the SQL Server Browser
+/// isn't used to look one up.
+pub(crate) fn parse_data_source(data_source: &str) -> Result<(String, u16), SqlClientError> {
+    let data_source = data_source.trim();
+    // Remove the protocol
+    let server_name = match data_source.split_once(':') {
+        Some((protocol, server_name)) if protocol.eq_ignore_ascii_case("tcp") => server_name,
+        Some((protocol, _)) if !protocol.contains(['\\', ',']) => {
+            return Err(SqlClientError::NotSupported(format!(
+                "The '{}' protocol (only TCP is supported)",
+                protocol
+            )))
+        }
+        _ => data_source,
+    };
+    // Remove the port
+    let (server_name, port) = match server_name.rsplit_once(',') {
+        Some((server_name, port)) => {
+            let port = port.trim().parse::<u16>().map_err(|_| {
+                SqlClientError::UnsupportedValue("port".to_string(), port.to_string())
+            })?;
+            (server_name, Some(port))
+        }
+        None => (server_name, None),
+    };
+    // Remove the instance name
+    let server_name = match server_name.split_once('\\') {
+        Some((server_name, instance_name)) if port.is_none() => {
+            return Err(SqlClientError::NotSupported(format!(
+                "Connecting to the named instance '{}' without a port",
+                instance_name
+            )))
+        }
+        Some((server_name, _)) => server_name,
+        None => server_name,
+    };
+    let server_name = match server_name.trim() {
+        "" | "." | "(local)" => "localhost",
+        server_name => server_name,
+    };
+    Ok((
+        server_name.to_string(),
+        port.unwrap_or(TdsEnums::DEFAULT_PORT),
+    ))
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
     use crate::ApplicationIntent;
-    use rstest;
 
     #[rstest::rstest]
     #[case("yes", true)]
@@ -69,7 +170,7 @@ mod tests {
     fn test_convert_to_boolean(#[case] value: &str, #[case] expected: bool) {
         match convert_to_boolean(value) {
             Ok(actual) => assert_eq!(expected, actual),
-            Err(e) => assert!(false, "Expected: Ok, Actual: Err"),
+            Err(e) => panic!("Expected: Ok, Actual: Err"),
         }
     }
 
@@ -94,7 +195,38 @@ mod tests {
     fn test_convert_to_integrated_security(#[case] value: &str, #[case] expected: bool) {
         match convert_to_integrated_security(value) {
             Ok(actual) => assert_eq!(expected, actual),
-            Err(e) => assert!(false, "Expected: Ok, Actual: Err"),
+            Err(e) => panic!("Expected: Ok, Actual: Err"),
+        }
+    }
+
+    #[rstest::rstest]
+    #[case("1", Some(Duration::from_secs(1)))]
+    #[case(" 0.25 ", Some(Duration::from_millis(250)))]
+    #[case("0", Some(Duration::ZERO))]
+    #[case("-1", None)]
+    #[case("soon", None)]
+    fn test_convert_to_duration(#[case] value: &str, #[case] expected: Option<Duration>) {
+        assert_eq!(expected, convert_to_duration(value).ok());
+        if let Some(expected) = expected {
+            assert_eq!(
+                expected,
+                convert_to_duration(&format_duration(&expected)).unwrap()
+            );
+        }
+    }
+
+    #[rstest::rstest]
+    #[case("1205", Some(vec![1205]))]
+    #[case("1205, 4060,40613", Some(vec![1205, 4060, 40613]))]
+    #[case("1205,", None)]
+    #[case("deadlock", None)]
+    fn test_convert_to_error_numbers(#[case] value: &str, #[case] expected: Option<Vec<i32>>) {
+        assert_eq!(expected, convert_to_error_numbers(value).ok());
+        if let Some(expected) = expected {
+            assert_eq!(
+                expected,
+                convert_to_error_numbers(&format_error_numbers(&expected)).unwrap()
+            );
         }
     }
 
@@ -109,4 +241,32 @@ mod tests {
         let actual = get_local_db_instance_name_from_server_name(value);
         assert_eq!(expected.map(|e| e.to_string()), actual);
     }
+
+    #[rstest::rstest]
+    #[case("myserver.database.windows.net", true)]
+    #[case("tcp:MyServer.Database.Windows.Net,1433", true)]
+    #[case("myserver.database.chinacloudapi.cn\\instance", true)]
+    #[case("myserver.database.fabric.microsoft.com ", true)]
+    #[case("localhost", false)]
+    #[case("database.windows.net.example.com", false)]
+    fn test_is_azure_sql_server_endpoint(#[case] value: &str, #[case] expected: bool) {
+        assert_eq!(expected, is_azure_sql_server_endpoint(value));
+    }
+
+    #[rstest::rstest]
+    #[case("myserver", Some(("myserver", 1433)))]
+    #[case(" tcp:MyServer , 1434 ", Some(("MyServer", 1434)))]
+    #[case("TCP:myserver\\instance,1435", Some(("myserver", 1435)))]
+    #[case("(local)", Some(("localhost", 1433)))]
+    #[case(".,1436", Some(("localhost", 1436)))]
+    #[case("myserver\\instance", None)]
+    #[case("np:\\\\.\\pipe\\sql\\query", None)]
+    #[case("myserver,port", None)]
+    fn test_parse_data_source(#[case] value: &str, #[case] expected: Option<(&str, u16)>) {
+        let actual = parse_data_source(value).ok();
+        assert_eq!(
+            expected.map(|(host, port)| (host.to_string(), port)),
+            actual
+        );
+    }
 }
@@ -0,0 +1,154 @@
+use crate::{SqlClientError, SqlDecimal, SqlMoney, SqlValue};
+use chrono::{DateTime, FixedOffset, NaiveDate, NaiveDateTime, NaiveTime};
+use secstr::SecStr;
+use uuid::Uuid;
+
+/// A type that can be read from a column value (e.g. with [crate::SqlDataReader::get]).
+///
+/// Applications can implement this for their own types, such as newtypes or enums stored as
+/// strings, by converting from one of the built-in types:
+///
+/// ```
+/// use sql_client::{FromSql, SqlClientError, SqlValue};
+///
+/// struct CustomerId(i64);
+///
+/// impl FromSql for CustomerId {
+///     fn from_sql(value: &SqlValue) -> Result<Self, SqlClientError> {
+///         i64::from_sql(value).map(CustomerId)
+///     }
+/// }
+/// ```
+pub trait FromSql: Sized {
+    /// Converts a non-null value.
+    fn from_sql(value: &SqlValue) -> Result<Self, SqlClientError>;
+
+    /// The value to use for a null, or None if the type can't hold a null (the default).
+    fn from_sql_null() -> Option<Self> {
+        None
+    }
+}
+
+/// The error for a value that can't be converted to a type.
+pub(crate) fn unsupported(type_name: &str, value: &SqlValue) -> SqlClientError {
+    SqlClientError::UnsupportedValue(type_name.to_string(), format!("{:?}", value))
+}
+
+/// Implements [FromSql] for a type held by a single [SqlValue] variant.
+macro_rules! from_sql_variant {
+    ($type:ty, $variant:ident) => {
+        impl FromSql for $type {
+            fn from_sql(value: &SqlValue) -> Result<Self, SqlClientError> {
+                match value {
+                    SqlValue::$variant(value) => Ok(value.clone()),
+                    value => Err(unsupported(stringify!($type), value)),
+                }
+            }
+        }
+    };
+}
+
+from_sql_variant!(bool, Bit);
+from_sql_variant!(u8, TinyInt);
+from_sql_variant!(i16, SmallInt);
+from_sql_variant!(i32, Int);
+from_sql_variant!(i64, BigInt);
+from_sql_variant!(f32, Real);
+from_sql_variant!(f64, Float);
+from_sql_variant!(SqlMoney, Money);
+from_sql_variant!(String, String);
+from_sql_variant!(Vec<u8>, Binary);
+from_sql_variant!(NaiveDate, Date);
+from_sql_variant!(NaiveTime, Time);
+from_sql_variant!(NaiveDateTime, DateTime);
+from_sql_variant!(DateTime<FixedOffset>, DateTimeOffset);
+from_sql_variant!(Uuid, Guid);
+
+impl FromSql for SqlDecimal {
+    fn from_sql(value: &SqlValue) -> Result<Self, SqlClientError> {
+        match value {
+            SqlValue::Decimal(value) => Ok(*value),
+            // Money is an exact decimal too
+            SqlValue::Money(value) => Ok(SqlDecimal::from(*value)),
+            value => Err(unsupported("SqlDecimal", value)),
+        }
+    }
+}
+
+#[cfg(feature = "rust_decimal")]
+impl FromSql for rust_decimal::Decimal {
+    fn from_sql(value: &SqlValue) -> Result<Self, SqlClientError> {
+        SqlDecimal::from_sql(value)?.try_into()
+    }
+}
+
+#[cfg(feature = "bigdecimal")]
+impl FromSql for bigdecimal::BigDecimal {
+    fn from_sql(value: &SqlValue) -> Result<Self, SqlClientError> {
+        SqlDecimal::from_sql(value).map(Into::into)
+    }
+}
+
+impl FromSql for SecStr {
+    fn from_sql(value: &SqlValue) -> Result<Self, SqlClientError> {
+        match value {
+            SqlValue::String(value) => Ok(SecStr::from(value.as_str())),
+            SqlValue::Binary(value) => Ok(SecStr::new(value.clone())),
+            value => Err(unsupported("SecStr", value)),
+        }
+    }
+}
+
+impl FromSql for SqlValue {
+    fn from_sql(value: &SqlValue) -> Result<Self, SqlClientError> {
+        Ok(value.clone())
+    }
+
+    fn from_sql_null() -> Option<Self> {
+        Some(SqlValue::Null)
+    }
+}
+
+impl<T: FromSql> FromSql for Option<T> {
+    fn from_sql(value: &SqlValue) -> Result<Self, SqlClientError> {
+        T::from_sql(value).map(Some)
+    }
+
+    fn from_sql_null() -> Option<Self> {
+        Some(None)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_from_sql() {
+        assert_eq!(42, i32::from_sql(&SqlValue::Int(42)).unwrap());
+        assert_eq!("abc", String::from_sql(&SqlValue::from("abc")).unwrap());
+        assert_eq!(
+            Some(true),
+            Option::<bool>::from_sql(&SqlValue::Bit(true)).unwrap()
+        );
+        assert!(matches!(
+            i32::from_sql(&SqlValue::from("abc")),
+            Err(SqlClientError::UnsupportedValue(_, _))
+        ));
+        assert_eq!(
+            "1.2300".parse::<SqlDecimal>().unwrap(),
+            SqlDecimal::from_sql(&SqlValue::Money("1.23".parse().unwrap())).unwrap()
+        );
+        assert_eq!(
+            SecStr::from("secret"),
+            SecStr::from_sql(&SqlValue::from("secret")).unwrap()
+        );
+    }
+
+    #[test]
+    fn test_from_sql_null() {
+        assert_eq!(None, i32::from_sql_null());
+        assert_eq!(Some(None), Option::<i32>::from_sql_null());
+        assert_eq!(Some(SqlValue::Null), SqlValue::from_sql_null());
+    }
+}
@@ -0,0 +1,161 @@
+use crate::{SqlClientError, SqlDataReader};
+
+/// A type that can be built from the current row of a [SqlDataReader] (e.g. with
+/// [SqlDataReader::get_row]).
+///
+/// This is usually derived, which maps each field to the column of the same name:
+///
+/// ```ignore
+/// #[derive(FromSqlRow)]
+/// struct Customer {
+///     id: i64,
+///     // Read from the "customer_name" column
+///     #[sql(rename = "customer_name")]
+///     name: String,
+///     // Null if the column is null
+///     email: Option<String>,
+///     // Built from the same row
+///     #[sql(flatten)]
+///     address: Address,
+/// }
+/// ```
+///
+/// Each field's type must implement [crate::FromSql] (or [FromSqlRow] if it's flattened).
+pub trait FromSqlRow: Sized {
+    /// Builds a value from the current row.
+    fn from_row(reader: &SqlDataReader<'_>) -> Result<Self, SqlClientError>;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db_connection_internal::DbConnectionInternal;
+    use crate::tds_enums::TdsEnums;
+    use crate::tds_parser::TdsParser;
+    use crate::tds_test_utils::TokenBuilder;
+    use crate::{FromSqlRow, SqlCommand, SqlConnection, SqlDbType, SqlValue};
+    use test_utils::MockStream;
+
+    #[derive(FromSqlRow, PartialEq, Debug)]
+    struct Address {
+        city: String,
+        #[sql(rename = "Postcode")]
+        postal_code: Option<String>,
+    }
+
+    #[derive(FromSqlRow, PartialEq, Debug)]
+    struct Customer {
+        id: i64,
+        #[sql(rename = "CustomerName")]
+        name: String,
+        email: Option<String>,
+        #[sql(flatten)]
+        address: Address,
+    }
+
+    /// Creates an open connection that will read the given response.
+    fn connection(tokens: &TokenBuilder) -> SqlConnection {
+        let mut connection = SqlConnection::new("Server=test").unwrap();
+        connection.attach(DbConnectionInternal::new(TdsParser::new(
+            Box::new(MockStream::new(tokens.packets())),
+            TdsEnums::DEFAULT_PACKET_SIZE,
+        )));
+        connection
+    }
+
+    #[tokio::test]
+    async fn test_derive() {
+        let tokens = TokenBuilder::new()
+            .col_metadata(&[
+                ("Id", SqlDbType::BigInt),
+                ("CustomerName", SqlDbType::NVarChar),
+                ("Email", SqlDbType::NVarChar),
+                ("City", SqlDbType::NVarChar),
+                ("Postcode", SqlDbType::NVarChar),
+            ])
+            .row(&[
+                SqlValue::BigInt(1),
+                SqlValue::from("Ann"),
+                SqlValue::from("ann@example.com"),
+                SqlValue::from("Leeds"),
+                SqlValue::from("LS1"),
+            ])
+            .nbc_row(&[
+                SqlValue::BigInt(2),
+                SqlValue::from("Bob"),
+                SqlValue::Null,
+                SqlValue::from("York"),
+                SqlValue::Null,
+            ])
+            .done(TdsEnums::SQLDONE, TdsEnums::DONE_COUNT, TdsEnums::SELECT, 2);
+        let mut connection = connection(&tokens);
+        let mut command = SqlCommand::new("select ...");
+        let mut reader = command.execute_reader(&mut connection).await.unwrap();
+        let mut customers: Vec<Customer> = Vec::new();
+        while reader.read().await.unwrap() {
+            customers.push(reader.get_row().unwrap());
+        }
+        assert_eq!(
+            vec![
+                Customer {
+                    id: 1,
+                    name: "Ann".to_string(),
+                    email: Some("ann@example.com".to_string()),
+                    address: Address {
+                        city: "Leeds".to_string(),
+                        postal_code: Some("LS1".to_string()),
+                    },
+                },
+                Customer {
+                    id: 2,
+                    name: "Bob".to_string(),
+                    email: None,
+                    address: Address {
+                        city: "York".to_string(),
+                        postal_code: None,
+                    },
+                },
+            ],
+            customers
+        );
+    }
+
+    #[rstest::rstest]
+    // A null in a field that isn't an Option
+    #[case(SqlValue::Null, SqlValue::from("Ann"))]
+    // The wrong type
+    #[case(SqlValue::Int(1), SqlValue::from("Ann"))]
+    #[tokio::test]
+    async fn test_derive_errors(#[case] city: SqlValue, #[case] name: SqlValue) {
+        let tokens = TokenBuilder::new()
+            .col_metadata(&[("City", SqlDbType::Int), ("Name", SqlDbType::NVarChar)])
+            .nbc_row(&[city, name])
+            .done(TdsEnums::SQLDONE, TdsEnums::DONE_COUNT, TdsEnums::SELECT, 1);
+        let mut connection = connection(&tokens);
+        let mut command = SqlCommand::new("select ...");
+        let mut reader = command.execute_reader(&mut connection).await.unwrap();
+        assert!(reader.read().await.unwrap());
+        // The postcode column is missing too, but the city is read first.
+        let error = reader.get_row::<Address>().unwrap_err();
+        assert!(matches!(
+            error,
+            SqlClientError::NullValue(_) | SqlClientError::UnsupportedValue(_, _)
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_derive_missing_column() {
+        let tokens = TokenBuilder::new()
+            .col_metadata(&[("City", SqlDbType::NVarChar)])
+            .row(&[SqlValue::from("Leeds")])
+            .done(TdsEnums::SQLDONE, TdsEnums::DONE_COUNT, TdsEnums::SELECT, 1);
+        let mut connection = connection(&tokens);
+        let mut command = SqlCommand::new("select ...");
+        let mut reader = command.execute_reader(&mut connection).await.unwrap();
+        assert!(reader.read().await.unwrap());
+        assert!(matches!(
+            reader.get_row::<Address>(),
+            Err(SqlClientError::ArgumentOutOfRange(_, _))
+        ));
+    }
+}
@@ -2,27 +2,82 @@
 #![allow(unused_variables)]
 #![allow(unused_imports)]
 
+// Lets the derive macros' "::sql_client" paths work inside this crate.
+extern crate self as sql_client;
+
 pub mod application_intent;
+pub mod bulk_copy_source;
+pub mod command_behavior;
+pub mod command_type;
 pub(crate) mod connection_state;
+#[cfg(feature = "csv")]
+pub mod csv_source;
+pub mod data_table;
+pub mod data_table_reader;
 pub(crate) mod db_connection_internal;
 pub(crate) mod db_connection_pool;
+pub(crate) mod db_connection_pool_key;
 pub(crate) mod db_connection_string_defaults;
 pub(crate) mod db_connection_string_keywords;
 pub(crate) mod db_connection_string_utils;
+pub mod from_sql;
+pub mod from_sql_row;
+pub mod parameter_direction;
 pub mod pool_blocking_period;
-mod retry_enumerators;
-mod retry_enumerators2;
+#[cfg(feature = "arrow")]
+pub mod record_batch_source;
+pub mod retry_enumerators;
+mod sni_tcp_handle;
 pub mod sql_authentication_method;
+pub mod sql_bulk_copy;
+pub mod sql_bulk_copy_column_mapping;
+pub mod sql_bulk_copy_options;
+pub mod sql_circuit_breaker;
+pub mod sql_circuit_breaker_option;
+pub mod sql_circuit_breaker_state;
 pub mod sql_client_error;
+mod sql_collation;
 pub mod sql_column_encryption_setting;
+pub mod sql_command;
 pub mod sql_connection;
 mod sql_connection_attestation_protocol;
 pub mod sql_connection_ip_address_preference;
+pub mod sql_connection_pool_statistics;
 mod sql_connection_string;
 pub mod sql_connection_string_builder;
 pub mod sql_credential;
+pub mod sql_data_reader;
+pub mod sql_db_type;
+pub mod sql_decimal;
+pub mod sql_error;
+pub mod sql_exception;
+pub mod sql_info_message_event_args;
+mod sql_login;
+pub mod sql_meta_data;
+pub mod sql_money;
+pub mod sql_parameter;
+pub mod sql_parameter_collection;
+pub mod sql_retry_exception;
+pub mod sql_retry_logic_option;
+pub mod sql_retry_logic_provider;
+pub mod sql_retrying_event_args;
+pub mod sql_stream;
+pub mod sql_text_reader;
+pub mod sql_transient_errors;
+pub mod sql_value;
+mod ssl_over_tds_stream;
 pub(crate) mod tds_enums;
+mod tds_parser;
+mod tds_parser_state_object;
+mod tds_pre_login;
+#[cfg(test)]
+mod tds_test_utils;
+mod tds_token;
+mod tds_type_info;
+mod tds_value;
 mod test_init;
+pub mod to_sql;
+pub mod to_sql_row;
 mod transaction;
 mod transaction_binding;
 mod type_system;
@@ -31,24 +86,106 @@ mod type_system;
 #[doc(inline)]
 pub use application_intent::ApplicationIntent;
 #[doc(inline)]
+pub use bulk_copy_source::{BulkCopySource, RowSource};
+#[doc(inline)]
+pub use command_behavior::CommandBehavior;
+#[doc(inline)]
+pub use command_type::CommandType;
+#[cfg(feature = "csv")]
+#[doc(inline)]
+pub use csv_source::CsvSource;
+#[doc(inline)]
+pub use data_table::DataTable;
+#[doc(inline)]
+pub use data_table_reader::DataTableReader;
+#[doc(inline)]
+pub use from_sql::FromSql;
+#[doc(inline)]
+pub use from_sql_row::FromSqlRow;
+#[doc(inline)]
+pub use parameter_direction::ParameterDirection;
+#[doc(inline)]
 pub use pool_blocking_period::PoolBlockingPeriod;
+#[cfg(feature = "arrow")]
+#[doc(inline)]
+pub use record_batch_source::RecordBatchSource;
+#[doc(inline)]
+pub use retry_enumerators::{SqlRetryInterval, SqlRetryIntervalBuilder, SqlRetryMethod};
 #[doc(inline)]
 pub use sql_authentication_method::SqlAuthenticationMethod;
 #[doc(inline)]
+pub use sql_bulk_copy::SqlBulkCopy;
+#[doc(inline)]
+pub use sql_bulk_copy_column_mapping::{SqlBulkCopyColumn, SqlBulkCopyColumnMapping};
+#[doc(inline)]
+pub use sql_bulk_copy_options::SqlBulkCopyOptions;
+#[doc(inline)]
+pub use sql_circuit_breaker::SqlCircuitBreaker;
+#[doc(inline)]
+pub use sql_circuit_breaker_option::SqlCircuitBreakerOption;
+#[doc(inline)]
+pub use sql_circuit_breaker_state::SqlCircuitBreakerState;
+#[doc(inline)]
+pub use sql_client_derive::{FromSqlRow, ToSqlRow};
+#[doc(inline)]
 pub use sql_client_error::SqlClientError;
 #[doc(inline)]
 pub use sql_column_encryption_setting::SqlConnectionColumnEncryptionSetting;
 #[doc(inline)]
+pub use sql_command::SqlCommand;
+#[doc(inline)]
 pub use sql_connection::SqlConnection;
 #[doc(inline)]
 pub(crate) use sql_connection_attestation_protocol::SqlConnectionAttestationProtocol;
 #[doc(inline)]
 pub use sql_connection_ip_address_preference::SqlConnectionIpAddressPreference;
 #[doc(inline)]
+pub use sql_connection_pool_statistics::SqlConnectionPoolStatistics;
+#[doc(inline)]
 pub use sql_connection_string_builder::SqlConnectionStringBuilder;
 #[doc(inline)]
 pub use sql_credential::SqlCredential;
 #[doc(inline)]
+pub use sql_data_reader::SqlDataReader;
+#[doc(inline)]
+pub use sql_db_type::SqlDbType;
+#[doc(inline)]
+pub use sql_decimal::SqlDecimal;
+#[doc(inline)]
+pub use sql_error::SqlError;
+#[doc(inline)]
+pub use sql_exception::SqlException;
+#[doc(inline)]
+pub use sql_info_message_event_args::SqlInfoMessageEventArgs;
+#[doc(inline)]
+pub use sql_meta_data::SqlMetaData;
+#[doc(inline)]
+pub use sql_money::SqlMoney;
+#[doc(inline)]
+pub use sql_parameter::SqlParameter;
+#[doc(inline)]
+pub use sql_parameter_collection::SqlParameterCollection;
+#[doc(inline)]
+pub use sql_retry_exception::{SqlRetryAttempt, SqlRetryException};
+#[doc(inline)]
+pub use sql_retry_logic_option::SqlRetryLogicOption;
+#[doc(inline)]
+pub use sql_retry_logic_provider::SqlRetryLogicProvider;
+#[doc(inline)]
+pub use sql_retrying_event_args::SqlRetryingEventArgs;
+#[doc(inline)]
+pub use sql_stream::SqlStream;
+#[doc(inline)]
+pub use sql_text_reader::SqlTextReader;
+#[doc(inline)]
+pub use sql_transient_errors::SqlTransientErrors;
+#[doc(inline)]
+pub use sql_value::SqlValue;
+#[doc(inline)]
+pub use to_sql::ToSql;
+#[doc(inline)]
+pub use to_sql_row::ToSqlRow;
+#[doc(inline)]
 pub(crate) use transaction_binding::{TransactionBinding, TransactionBindingKeywords};
 #[doc(inline)]
 pub(crate) use type_system::{TypeSystem, TypeSystemVersion};
@@ -0,0 +1,72 @@
+use std::fmt::{Display, Formatter};
+
+/// Specifies the type of a parameter within a query relative to the command.
+#[derive(PartialEq, Debug, Clone, Copy, Default)]
+pub enum ParameterDirection {
+    /// The parameter is an input parameter.
+    #[default]
+    Input = 1,
+    /// The parameter is an output parameter.
+    Output = 2,
+    /// The parameter is capable of both input and output.
+    InputOutput = 3,
+    /// The parameter represents a return value from a stored procedure.
+    ReturnValue = 6,
+}
+
+impl ParameterDirection {
+    /// Whether the parameter's value is sent to the server.
+    pub(crate) fn is_input(&self) -> bool {
+        matches!(
+            self,
+            ParameterDirection::Input | ParameterDirection::InputOutput
+        )
+    }
+
+    /// Whether the parameter's value is returned by the server in a RETURNVALUE token.
+    pub(crate) fn is_output(&self) -> bool {
+        matches!(
+            self,
+            ParameterDirection::Output | ParameterDirection::InputOutput
+        )
+    }
+}
+
+impl Display for ParameterDirection {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ParameterDirection::Input => write!(f, "Input"),
+            ParameterDirection::Output => write!(f, "Output"),
+            ParameterDirection::InputOutput => write!(f, "InputOutput"),
+            ParameterDirection::ReturnValue => write!(f, "ReturnValue"),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[rstest::rstest]
+    #[case(ParameterDirection::Input, "Input")]
+    #[case(ParameterDirection::Output, "Output")]
+    #[case(ParameterDirection::InputOutput, "InputOutput")]
+    #[case(ParameterDirection::ReturnValue, "ReturnValue")]
+    fn test_to_string(#[case] value: ParameterDirection, #[case] expected: &str) {
+        assert_eq!(expected, value.to_string());
+    }
+
+    #[rstest::rstest]
+    #[case(ParameterDirection::Input, true, false)]
+    #[case(ParameterDirection::Output, false, true)]
+    #[case(ParameterDirection::InputOutput, true, true)]
+    #[case(ParameterDirection::ReturnValue, false, false)]
+    fn test_is_input_output(
+        #[case] value: ParameterDirection,
+        #[case] is_input: bool,
+        #[case] is_output: bool,
+    ) {
+        assert_eq!(is_input, value.is_input());
+        assert_eq!(is_output, value.is_output());
+    }
+}
@@ -1,7 +1,8 @@
+use crate::db_connection_string_utils::is_azure_sql_server_endpoint;
 use crate::sql_client_error::SqlClientError;
 use std::fmt::{Display, Formatter};
 
-/// ?
+/// Whether a connection pool blocks new connection attempts for a while after one fails.
 #[derive(PartialEq, Debug, Clone, Copy)]
 pub enum PoolBlockingPeriod {
     // Blocking period OFF for Azure SQL servers, but ON for all other SQL servers.
@@ -11,6 +12,17 @@ pub enum PoolBlockingPeriod {
     // Blocking period OFF for all SQL servers including Azure SQL servers.
     NeverBlock = 2,
 }
+impl PoolBlockingPeriod {
+    /// Whether connection attempts to the data source are blocked after a failure.
+    pub(crate) fn is_enabled(&self, data_source: &str) -> bool {
+        match self {
+            PoolBlockingPeriod::Auto => !is_azure_sql_server_endpoint(data_source),
+            PoolBlockingPeriod::AlwaysBlock => true,
+            PoolBlockingPeriod::NeverBlock => false,
+        }
+    }
+}
+
 impl TryFrom<&str> for PoolBlockingPeriod {
     type Error = SqlClientError;
 
@@ -60,4 +72,17 @@ mod tests {
     fn test_to_string(#[case] value: PoolBlockingPeriod, #[case] expected: &str) {
         assert_eq!(expected, value.to_string());
     }
+
+    #[rstest::rstest]
+    #[case(PoolBlockingPeriod::Auto, "localhost", true)]
+    #[case(PoolBlockingPeriod::Auto, "tcp:test.database.windows.net,1433", false)]
+    #[case(PoolBlockingPeriod::AlwaysBlock, "test.database.windows.net", true)]
+    #[case(PoolBlockingPeriod::NeverBlock, "localhost", false)]
+    fn test_is_enabled(
+        #[case] value: PoolBlockingPeriod,
+        #[case] data_source: &str,
+        #[case] expected: bool,
+    ) {
+        assert_eq!(expected, value.is_enabled(data_source));
+    }
 }
@@ -0,0 +1,276 @@
+use crate::{BulkCopySource, SqlClientError, SqlDecimal, SqlValue};
+use arrow_array::cast::AsArray;
+use arrow_array::types::{
+    ArrowTemporalType, Date32Type, Date64Type, Decimal128Type, Float32Type, Float64Type, Int16Type,
+    Int32Type, Int64Type, Int8Type, Time32MillisecondType, Time32SecondType, Time64MicrosecondType,
+    Time64NanosecondType, TimestampMicrosecondType, TimestampMillisecondType,
+    TimestampNanosecondType, TimestampSecondType, UInt16Type, UInt32Type, UInt64Type, UInt8Type,
+};
+use arrow_array::{Array, RecordBatch, RecordBatchReader};
+use arrow_schema::{ArrowError, DataType, SchemaRef, TimeUnit};
+use chrono::{NaiveDateTime, NaiveTime};
+
+/// Rows from a stream of Arrow record batches, read a batch at a time.
+///
+/// Each Arrow type is sent as the closest SQL type (e.g. Utf8 as nvarchar, Timestamp as datetime2,
+/// Decimal128 as decimal).  A timestamp with a time zone is sent as a datetimeoffset in UTC.
+#[derive(Debug)]
+pub struct RecordBatchSource<I> {
+    /// The schema of the batches.
+    schema: SchemaRef,
+    /// The batches.
+    batches: I,
+    /// The current batch.
+    batch: Option<RecordBatch>,
+    /// The index of the next row of the current batch.
+    row: usize,
+}
+
+impl<I> RecordBatchSource<I>
+where
+    I: Iterator<Item = Result<RecordBatch, ArrowError>> + Send,
+{
+    /// Creates a source from batches with the given schema.
+    pub fn new(schema: SchemaRef, batches: impl IntoIterator<IntoIter = I>) -> Self {
+        Self {
+            schema,
+            batches: batches.into_iter(),
+            batch: None,
+            row: 0,
+        }
+    }
+}
+
+impl<R> RecordBatchSource<R>
+where
+    R: RecordBatchReader + Send,
+{
+    /// Creates a source from a record batch reader (e.g. an Arrow IPC or Parquet reader).
+    pub fn from_reader(reader: R) -> Self {
+        Self::new(reader.schema(), reader)
+    }
+}
+
+impl<I> BulkCopySource for RecordBatchSource<I>
+where
+    I: Iterator<Item = Result<RecordBatch, ArrowError>> + Send,
+{
+    fn column_names(&self) -> Vec<String> {
+        self.schema
+            .fields()
+            .iter()
+            .map(|field| field.name().clone())
+            .collect()
+    }
+
+    async fn next_row(&mut self) -> Result<Option<Vec<SqlValue>>, SqlClientError> {
+        // Move on to the next non-empty batch once the current one has been read.
+        while self
+            .batch
+            .as_ref()
+            .is_none_or(|batch| self.row >= batch.num_rows())
+        {
+            match self.batches.next() {
+                Some(batch) => {
+                    self.batch = Some(batch.map_err(arrow_error)?);
+                    self.row = 0;
+                }
+                None => return Ok(None),
+            }
+        }
+        let batch = self.batch.as_ref().unwrap();
+        let row = batch
+            .columns()
+            .iter()
+            .map(|column| to_sql_value(column.as_ref(), self.row))
+            .collect::<Result<Vec<SqlValue>, SqlClientError>>()?;
+        self.row += 1;
+        Ok(Some(row))
+    }
+}
+
+/// Reports an Arrow error.
+fn arrow_error(error: ArrowError) -> SqlClientError {
+    SqlClientError::BulkCopySource(error.to_string())
+}
+
+/// Gets a value of an array.
+fn to_sql_value(array: &dyn Array, row: usize) -> Result<SqlValue, SqlClientError> {
+    if array.is_null(row) {
+        return Ok(SqlValue::Null);
+    }
+    let out_of_range = || {
+        SqlClientError::UnsupportedValue(
+            array.data_type().to_string(),
+            format!("the value at row {}", row),
+        )
+    };
+    let value = match array.data_type() {
+        DataType::Boolean => SqlValue::Bit(array.as_boolean().value(row)),
+        DataType::Int8 => SqlValue::SmallInt(array.as_primitive::<Int8Type>().value(row) as i16),
+        DataType::Int16 => SqlValue::SmallInt(array.as_primitive::<Int16Type>().value(row)),
+        DataType::Int32 => SqlValue::Int(array.as_primitive::<Int32Type>().value(row)),
+        DataType::Int64 => SqlValue::BigInt(array.as_primitive::<Int64Type>().value(row)),
+        DataType::UInt8 => SqlValue::TinyInt(array.as_primitive::<UInt8Type>().value(row)),
+        DataType::UInt16 => SqlValue::Int(array.as_primitive::<UInt16Type>().value(row) as i32),
+        DataType::UInt32 => SqlValue::BigInt(array.as_primitive::<UInt32Type>().value(row) as i64),
+        DataType::UInt64 => SqlValue::BigInt(
+            i64::try_from(array.as_primitive::<UInt64Type>().value(row))
+                .map_err(|_| out_of_range())?,
+        ),
+        DataType::Float32 => SqlValue::Real(array.as_primitive::<Float32Type>().value(row)),
+        DataType::Float64 => SqlValue::Float(array.as_primitive::<Float64Type>().value(row)),
+        DataType::Decimal128(_, scale) => {
+            let value = array.as_primitive::<Decimal128Type>().value(row);
+            // A negative scale multiplies the value by a power of ten.
+            let decimal = if *scale < 0 {
+                10i128
+                    .checked_pow(scale.unsigned_abs() as u32)
+                    .and_then(|factor| value.checked_mul(factor))
+                    .ok_or_else(out_of_range)
+                    .and_then(|value| SqlDecimal::from_unscaled(value, 0))?
+            } else {
+                SqlDecimal::from_unscaled(value, *scale as u8)?
+            };
+            SqlValue::Decimal(decimal)
+        }
+        DataType::Utf8 => SqlValue::String(array.as_string::<i32>().value(row).to_string()),
+        DataType::LargeUtf8 => SqlValue::String(array.as_string::<i64>().value(row).to_string()),
+        DataType::Utf8View => SqlValue::String(array.as_string_view().value(row).to_string()),
+        DataType::Binary => SqlValue::Binary(array.as_binary::<i32>().value(row).to_vec()),
+        DataType::LargeBinary => SqlValue::Binary(array.as_binary::<i64>().value(row).to_vec()),
+        DataType::FixedSizeBinary(_) => {
+            SqlValue::Binary(array.as_fixed_size_binary().value(row).to_vec())
+        }
+        DataType::Date32 => SqlValue::Date(
+            datetime::<Date32Type>(array, row)
+                .ok_or_else(out_of_range)?
+                .date(),
+        ),
+        DataType::Date64 => SqlValue::Date(
+            datetime::<Date64Type>(array, row)
+                .ok_or_else(out_of_range)?
+                .date(),
+        ),
+        DataType::Time32(TimeUnit::Second) => {
+            SqlValue::Time(time::<Time32SecondType>(array, row).ok_or_else(out_of_range)?)
+        }
+        DataType::Time32(_) => {
+            SqlValue::Time(time::<Time32MillisecondType>(array, row).ok_or_else(out_of_range)?)
+        }
+        DataType::Time64(TimeUnit::Microsecond) => {
+            SqlValue::Time(time::<Time64MicrosecondType>(array, row).ok_or_else(out_of_range)?)
+        }
+        DataType::Time64(_) => {
+            SqlValue::Time(time::<Time64NanosecondType>(array, row).ok_or_else(out_of_range)?)
+        }
+        DataType::Timestamp(unit, time_zone) => {
+            let datetime = match unit {
+                TimeUnit::Second => datetime::<TimestampSecondType>(array, row),
+                TimeUnit::Millisecond => datetime::<TimestampMillisecondType>(array, row),
+                TimeUnit::Microsecond => datetime::<TimestampMicrosecondType>(array, row),
+                TimeUnit::Nanosecond => datetime::<TimestampNanosecondType>(array, row),
+            }
+            .ok_or_else(out_of_range)?;
+            // A timestamp with a time zone is stored in UTC.
+            match time_zone {
+                Some(_) => SqlValue::DateTimeOffset(datetime.and_utc().fixed_offset()),
+                None => SqlValue::DateTime(datetime),
+            }
+        }
+        data_type => {
+            return Err(SqlClientError::NotSupported(format!(
+                "Bulk copying the Arrow type {}",
+                data_type
+            )))
+        }
+    };
+    Ok(value)
+}
+
+/// Gets a date or timestamp value as a date and time.
+fn datetime<T: ArrowTemporalType>(array: &dyn Array, row: usize) -> Option<NaiveDateTime>
+where
+    i64: From<T::Native>,
+{
+    array.as_primitive::<T>().value_as_datetime(row)
+}
+
+/// Gets a time value.
+fn time<T: ArrowTemporalType>(array: &dyn Array, row: usize) -> Option<NaiveTime>
+where
+    i64: From<T::Native>,
+{
+    array.as_primitive::<T>().value_as_time(row)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use arrow_array::{
+        ArrayRef, Decimal128Array, Int32Array, RecordBatchIterator, StringArray,
+        TimestampMillisecondArray,
+    };
+    use arrow_schema::{Field, Schema};
+    use chrono::NaiveDate;
+    use std::sync::Arc;
+
+    #[tokio::test]
+    async fn test_record_batch_source() {
+        let schema = Arc::new(Schema::new(vec![
+            Field::new("Id", DataType::Int32, false),
+            Field::new("Name", DataType::Utf8, true),
+            Field::new("Amount", DataType::Decimal128(10, 2), true),
+            Field::new(
+                "Created",
+                DataType::Timestamp(TimeUnit::Millisecond, None),
+                true,
+            ),
+        ]));
+        let batch = |ids: Vec<i32>, names: Vec<Option<&str>>| {
+            let rows = ids.len();
+            RecordBatch::try_new(
+                schema.clone(),
+                vec![
+                    Arc::new(Int32Array::from(ids)) as ArrayRef,
+                    Arc::new(StringArray::from(names)),
+                    Arc::new(
+                        Decimal128Array::from(vec![12345; rows])
+                            .with_precision_and_scale(10, 2)
+                            .unwrap(),
+                    ),
+                    Arc::new(TimestampMillisecondArray::from(vec![1_000; rows])),
+                ],
+            )
+        };
+        let batches = vec![
+            batch(vec![1], vec![Some("a")]),
+            batch(vec![], vec![]),
+            batch(vec![2], vec![None]),
+        ];
+        let mut source =
+            RecordBatchSource::from_reader(RecordBatchIterator::new(batches, schema.clone()));
+        assert_eq!(
+            vec!["Id", "Name", "Amount", "Created"],
+            source.column_names()
+        );
+        let created = NaiveDate::from_ymd_opt(1970, 1, 1)
+            .unwrap()
+            .and_hms_opt(0, 0, 1)
+            .unwrap();
+        assert_eq!(
+            Some(vec![
+                SqlValue::Int(1),
+                SqlValue::from("a"),
+                SqlValue::Decimal("123.45".parse().unwrap()),
+                SqlValue::DateTime(created),
+            ]),
+            source.next_row().await.unwrap()
+        );
+        assert_eq!(
+            Some(SqlValue::Null),
+            source.next_row().await.unwrap().map(|row| row[1].clone())
+        );
+        assert_eq!(None, source.next_row().await.unwrap());
+    }
+}
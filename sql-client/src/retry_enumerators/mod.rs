@@ -1,21 +1,47 @@
+//! Strategies for the intervals between the attempts of a retried operation.
+mod sql_decorrelated_jitter_interval_enumerator;
 mod sql_exponential_interval_enumerator;
+mod sql_fibonacci_interval_enumerator;
 mod sql_fixed_interval_enumerator;
+mod sql_full_jitter_interval_enumerator;
 mod sql_incremental_interval_enumerator;
 mod sql_none_interval_enumerator;
+mod sql_retry_interval_builder;
+mod sql_retry_method;
 
-use crate::SqlClientError;
+pub use sql_decorrelated_jitter_interval_enumerator::SqlDecorrelatedJitterIntervalEnumerator;
+pub use sql_exponential_interval_enumerator::SqlExponentialIntervalEnumerator;
+pub use sql_fibonacci_interval_enumerator::SqlFibonacciIntervalEnumerator;
+pub use sql_fixed_interval_enumerator::SqlFixedIntervalEnumerator;
+pub use sql_full_jitter_interval_enumerator::SqlFullJitterIntervalEnumerator;
+pub use sql_incremental_interval_enumerator::SqlIncrementalIntervalEnumerator;
+pub use sql_none_interval_enumerator::SqlNoneIntervalEnumerator;
+pub use sql_retry_interval_builder::SqlRetryIntervalBuilder;
+pub use sql_retry_method::SqlRetryMethod;
+
+use rand::rngs::StdRng;
 use rand::Rng;
-use std::cmp::min;
-use std::ops::Deref;
 use std::time::Duration;
 
-/// The minimum allowable min, max, or gap interval.
+/// The minimum allowable min, max, or delta interval.
 const MIN_DURATION: Duration = Duration::new(0, 0);
-/// The maximum allowable min, max, or gap interval.
+/// The maximum allowable min, max, or delta interval.
 const MAX_DURATION: Duration = Duration::from_secs(120);
 
+/// A SQL retry interval.
+///
+/// Generates the intervals to wait between the attempts of a retried operation.  The iterator ends
+/// when the strategy has no more intervals to give (e.g. once they would exceed the maximum).
+pub trait SqlRetryInterval: Iterator<Item = Duration> {
+    /// The interval last returned by the iterator (zero before the first).
+    fn current(&self) -> Duration;
+    /// Starts the intervals again from the beginning.
+    fn reset(&mut self);
+}
+
 /// Ensures an interval is between the max and min
 fn ensure_in_range(
+    rng: &mut StdRng,
     min_time_interval: &Duration,
     max_time_interval: &Duration,
     time_interval: Duration,
@@ -35,22 +61,22 @@ fn ensure_in_range(
         }
         // Otherwise, fuzz it by a random amount
         else {
-            let millis = rand::random::<f64>() * (max_time_interval.as_millis() as f64 * 0.2) + (max_time_interval.as_millis() as f64 * 0.8);
+            let millis = rng.gen::<f64>() * (max_time_interval.as_millis() as f64 * 0.2) + (max_time_interval.as_millis() as f64 * 0.8);
             Duration::from_millis(millis as u64)
         };
     // Return the value guarded against the min
     if &guarded_max < min_time_interval {
-        min_time_interval.clone()
+        *min_time_interval
     } else {
         guarded_max
     }
 }
 
-/// Gets a range of values on either side of the gap time interval
-fn get_random_interval(gap_time_interval: &Duration, randomness: f64) -> (u64, u64) {
+/// Gets a range of values on either side of the delta time interval
+fn get_random_interval(delta_time: &Duration, randomness: f64) -> (u64, u64) {
     // Figure out the randoms
-    let temp_max = (gap_time_interval.as_millis() as f64) * (1.0 + randomness);
-    let temp_min = (gap_time_interval.as_millis() as f64) * (1.0 - randomness);
+    let temp_max = (delta_time.as_millis() as f64) * (1.0 + randomness);
+    let temp_min = (delta_time.as_millis() as f64) * (1.0 - randomness);
     log::debug!(" - temp_max = {:?}, temp_min = {:?}", temp_max, temp_min);
     let u64_max = u64::MAX as f64;
     let max_random = if temp_max > u64_max {
@@ -75,80 +101,21 @@ fn get_random_interval(gap_time_interval: &Duration, randomness: f64) -> (u64, u
 }
 
 // Gets a random value in the range.
-fn get_random(min_random: u64, max_random: u64) -> u64 {
+fn get_random(rng: &mut StdRng, min_random: u64, max_random: u64) -> u64 {
     // If we've eliminated randomness (as in unit testing)...
     if max_random == min_random {
         // Return either value.
         min_random
     } else {
-        // Create a random number generator
-        let mut rng = rand::thread_rng();
-        log::debug!(" - created rng");
         // Get an amount of randomness within the range
         rng.gen_range(min_random..max_random)
     }
 }
-/// Validates the intervals.
-fn validate(
-    gap_time_interval: &Duration,
-    max_time_interval: &Duration,
-    min_time_interval: &Duration,
-) -> Result<(), SqlClientError> {
-    if min_time_interval < &MIN_DURATION || max_time_interval > &MAX_DURATION {
-        Err(SqlClientError::ArgumentOutOfRange(
-            "min_time_interval".to_string(),
-            format!(
-                "min_time_interval must be between {:?} and {:?}",
-                MIN_DURATION, MAX_DURATION
-            )
-            .to_string(),
-        ))
-    } else if max_time_interval < &MIN_DURATION || max_time_interval > &MAX_DURATION {
-        Err(SqlClientError::ArgumentOutOfRange(
-            "max_time_interval".to_string(),
-            format!(
-                "max_time_interval must be between {:?} and {:?}",
-                MIN_DURATION, MAX_DURATION
-            )
-            .to_string(),
-        ))
-    } else if gap_time_interval < &MIN_DURATION || gap_time_interval > &MAX_DURATION {
-        Err(SqlClientError::ArgumentOutOfRange(
-            "gap_time_interval".to_string(),
-            format!(
-                "gap_time_interval must be between {:?} and {:?}",
-                MIN_DURATION, MAX_DURATION
-            )
-            .to_string(),
-        ))
-    } else if max_time_interval < min_time_interval {
-        Err(SqlClientError::ArgumentOutOfRange(
-            "max_time_interval".to_string(),
-            "max_time_interval must be greater than min_time_interval".to_string(),
-        ))
-    } else {
-        // Validation succeeded.
-        Ok(())
-    }
-}
-
-/// A SQL retry interval.
-///
-/// Generates retry intervals for use when retrying a connection.
-pub(crate) trait SqlRetryInterval: Clone {
-    /// The current retry interval.
-    fn current(&self) -> Duration;
-    /// Moved "current" to the next retry interval.
-    fn move_next(&mut self) -> bool;
-    /// Resets the retry interval.
-    fn reset(&mut self);
-}
 
 #[cfg(test)]
 mod tests {
     use super::*;
-    use crate::retry_enumerators::SqlRetryInterval;
-    use std::cmp::min;
+    use rand::SeedableRng;
 
     // Test "ensure in range" for <= max values
     #[rstest::rstest]
@@ -164,10 +131,12 @@ mod tests {
         #[case] expected: u64,
         #[case] exact: bool,
     ) {
+        let mut rng = StdRng::seed_from_u64(1);
         let min_duration = Duration::from_secs(min);
         let max_duration = Duration::from_secs(max);
         let input_duration = Duration::from_secs(value);
-        let actual_duration = ensure_in_range(&min_duration, &max_duration, input_duration);
+        let actual_duration =
+            ensure_in_range(&mut rng, &min_duration, &max_duration, input_duration);
         // If we're looking for an exact match (which we do when the value is less than or equal the maximum)...
         if exact {
             assert_eq!(expected, actual_duration.as_secs());
@@ -0,0 +1,102 @@
+use super::{get_random, SqlRetryInterval};
+use rand::rngs::StdRng;
+use std::time::Duration;
+
+/// "Decorrelated jitter" intervals: each is drawn at random from the delta time up to three times
+/// the previous interval, capped at the maximum.
+///
+/// The intervals grow on average like exponential backoff, but clients that failed together
+/// quickly drift apart.
+#[derive(Debug, Clone)]
+pub struct SqlDecorrelatedJitterIntervalEnumerator {
+    delta_time: Duration,
+    min_time_interval: Duration,
+    max_time_interval: Duration,
+    current: Duration,
+    rng: StdRng,
+}
+impl SqlDecorrelatedJitterIntervalEnumerator {
+    /// Creates a new enumerator.
+    pub(super) fn new(
+        delta_time: Duration,
+        max_time_interval: Duration,
+        min_time_interval: Duration,
+        rng: StdRng,
+    ) -> Self {
+        Self {
+            delta_time,
+            min_time_interval,
+            max_time_interval,
+            current: Duration::new(0, 0),
+            rng,
+        }
+    }
+}
+
+impl Iterator for SqlDecorrelatedJitterIntervalEnumerator {
+    type Item = Duration;
+
+    /// Moves to the next retry interval.
+    fn next(&mut self) -> Option<Duration> {
+        let delta = self.delta_time.as_millis() as u64;
+        let min = self.min_time_interval.as_millis() as u64;
+        let max = self.max_time_interval.as_millis() as u64;
+        // The first interval grows from the delta time.
+        let previous = (self.current.as_millis() as u64).max(delta);
+        let min_random = delta.clamp(min, max);
+        let max_random = previous.saturating_mul(3).clamp(min_random, max);
+        let random = get_random(&mut self.rng, min_random, max_random.saturating_add(1));
+        self.current = Duration::from_millis(random.min(max_random));
+        // This enumerator is always able to get a new value.
+        Some(self.current)
+    }
+}
+
+impl SqlRetryInterval for SqlDecorrelatedJitterIntervalEnumerator {
+    /// Gets the current value of the retry interval.
+    fn current(&self) -> Duration {
+        self.current
+    }
+
+    /// Resets the retry interval.
+    fn reset(&mut self) {
+        self.current = Duration::new(0, 0);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::retry_enumerators::{SqlRetryInterval, SqlRetryIntervalBuilder};
+    use std::time::Duration;
+
+    #[test]
+    pub fn test_decorrelated_jitter() {
+        let delta = Duration::from_millis(100);
+        let max = Duration::from_secs(10);
+        let mut subject = SqlRetryIntervalBuilder::new(delta)
+            .max_time_interval(max)
+            .seed(3)
+            .decorrelated_jitter()
+            .unwrap();
+        let mut previous = delta;
+        let mut samples = Vec::new();
+        for _ in 0..10_000 {
+            let interval = subject.next().unwrap();
+            // Each interval is between the delta time and three times the last, up to the maximum.
+            assert!(interval >= delta && interval <= max);
+            assert!(interval <= previous * 3);
+            previous = interval;
+            samples.push(interval.as_secs_f64());
+        }
+        // The intervals wander over the whole range rather than settling.
+        assert!(samples.iter().any(|sample| *sample < 1.0));
+        assert!(samples.iter().any(|sample| *sample > 9.0));
+        let mean = samples.iter().sum::<f64>() / samples.len() as f64;
+        assert!(mean > 2.0 && mean < 8.0, "mean {}", mean);
+        // Successive intervals aren't strongly tied to each other.
+        let pairs = samples.windows(2).filter(|pair| pair[1] > pair[0]).count();
+        assert!((3500..=6500).contains(&pairs), "increases {}", pairs);
+        subject.reset();
+        assert!(subject.next().unwrap() <= delta * 3);
+    }
+}
@@ -1,72 +1,56 @@
-use super::validate;
-use crate::retry_enumerators::{get_random, get_random_interval};
-use crate::SqlClientError;
-use rand::Rng;
-use std::cmp::min;
-use std::ops::Deref;
+use super::{get_random, get_random_interval, SqlRetryInterval};
+use rand::rngs::StdRng;
 use std::time::Duration;
 
-#[derive(Clone)]
-pub(crate) struct SqlExponentialIntervalEnumerator {
+/// Intervals that grow exponentially from the minimum, ending once they would exceed the maximum.
+#[derive(Debug, Clone)]
+pub struct SqlExponentialIntervalEnumerator {
     internal_counter: u64,
     max_random: u64,
     min_random: u64,
     min_time_interval: Duration,
     max_time_interval: Duration,
     current: Duration,
+    rng: StdRng,
 }
 impl SqlExponentialIntervalEnumerator {
     /// Creates a new enumerator with a specified amount of randomness.
-    fn new_random(
+    pub(super) fn new(
         delta_backoff_time: Duration,
         max_time_interval: Duration,
         min_time_interval: Duration,
         randomness: f64,
-    ) -> Result<Self, SqlClientError> {
-        // Validate the intervals.
-        validate(&delta_backoff_time, &max_time_interval, &min_time_interval)?;
+        rng: StdRng,
+    ) -> Self {
         // Get the random range
         let (min_random, max_random) = get_random_interval(&delta_backoff_time, randomness);
         // Return the value
-        Ok(Self {
+        Self {
             internal_counter: 1,
             max_random,
             min_random,
             max_time_interval,
             min_time_interval,
             current: Duration::new(0, 0),
-        })
-    }
-
-    /// Creates a new enumerator.
-    pub fn new(
-        delta_backoff_time: Duration,
-        max_time_interval: Duration,
-        min_time_interval: Duration,
-    ) -> Result<Self, SqlClientError> {
-        Self::new_random(
-            delta_backoff_time,
-            max_time_interval,
-            min_time_interval,
-            0.2,
-        )
+            rng,
+        }
     }
 
     /// Gets the next interval.
     fn next_interval(&mut self) -> Duration {
         log::debug!("next_interval");
         // Get the exponent
-        let exponent = &self.internal_counter.pow(2) - 1;
+        let exponent = self.internal_counter.pow(2) - 1;
         log::debug!(
             " - internal_counter = {:?}, exponent = {:?}",
             &self.internal_counter,
             exponent
         );
         // Get an amount of randomness in the range
-        let random = get_random(self.min_random, self.max_random);
+        let random = get_random(&mut self.rng, self.min_random, self.max_random);
         log::debug!(" - random = {:?}", random);
         // Get the delta, using an exponent of our counter times the mildly randomized gap.
-        let delta = exponent * random;
+        let delta = exponent.saturating_mul(random);
         log::debug!(" - delta = {:?}", delta);
         // Get the new time
         let new_time = self.min_time_interval + Duration::from_millis(delta);
@@ -78,32 +62,30 @@ impl SqlExponentialIntervalEnumerator {
     }
 }
 
-impl super::SqlRetryInterval for SqlExponentialIntervalEnumerator {
-    /// Gets the current value of the retry interval.
-    fn current(&self) -> Duration {
-        self.current.clone()
-    }
+impl Iterator for SqlExponentialIntervalEnumerator {
+    type Item = Duration;
 
     /// Moves to the next retry interval.
-    fn move_next(&mut self) -> bool {
-        // If the current value is less than the maximum...
-        if &self.current < &self.max_time_interval {
-            // Get the next interval
-            let next = self.next_interval();
-            // If the next value is also less than the maximum...
-            if &next <= &self.max_time_interval {
-                // Save it as the new "current"
-                self.current = next;
-                // Return that we were able to get a new value
-                true
-            } else {
-                // Return that we were not able to get a new value
-                false
-            }
-        } else {
-            // We're already over the maximum.  We can't go next.
-            false
+    fn next(&mut self) -> Option<Duration> {
+        // If we're already at the maximum, we can't go next.
+        if self.current >= self.max_time_interval {
+            return None;
+        }
+        // Get the next interval, as long as it's not over the maximum.
+        let next = self.next_interval();
+        if next > self.max_time_interval {
+            return None;
         }
+        // Save it as the new "current"
+        self.current = next;
+        Some(next)
+    }
+}
+
+impl SqlRetryInterval for SqlExponentialIntervalEnumerator {
+    /// Gets the current value of the retry interval.
+    fn current(&self) -> Duration {
+        self.current
     }
 
     /// Resets the retry interval.
@@ -117,48 +99,27 @@ impl super::SqlRetryInterval for SqlExponentialIntervalEnumerator {
 
 #[cfg(test)]
 mod tests {
-    use super::*;
-    use crate::retry_enumerators::SqlRetryInterval;
-    use std::cmp::min;
+    use crate::retry_enumerators::{SqlRetryInterval, SqlRetryIntervalBuilder};
+    use std::time::Duration;
 
     #[test]
     pub fn test_exponential_interval_enumerator() {
-        let gap_threshold = Duration::from_secs(1);
-        let min_interval = Duration::from_secs(5);
-        let max_interval = Duration::from_secs(20);
         // Create the enumerator, eliminating the randomness so that our tests are repeatable.
-        let mut subject = SqlExponentialIntervalEnumerator::new_random(
-            gap_threshold,
-            max_interval,
-            min_interval,
-            0.0,
-        )
-        .unwrap();
-        // Move to the next interval, which should return "true".
-        assert!(subject.move_next());
-        // The current value should be the minimum
-        assert_eq!(5, subject.current().as_secs());
-        // Move to the next interval, which should return "true".
-        assert!(subject.move_next());
-        // The current value should have advanced by the next exponent of the gap
-        assert_eq!(8, subject.current().as_secs());
-        // Move to the next interval, which should return "true".
-        assert!(subject.move_next());
-        // The current value should have advanced by the next exponent of the gap
-        assert_eq!(13, subject.current().as_secs());
-        // Move to the next interval, which should return "true".
-        assert!(subject.move_next());
-        // The current value should have advanced by the next exponent of the gap
-        assert_eq!(20, subject.current().as_secs());
-        // Move to the next interval, which should return "false".
-        assert!(!subject.move_next());
-        // The current value should NOT have advanced
+        let mut subject = SqlRetryIntervalBuilder::new(Duration::from_secs(1))
+            .min_time_interval(Duration::from_secs(5))
+            .max_time_interval(Duration::from_secs(20))
+            .randomness(0.0)
+            .exponential()
+            .unwrap();
+        // The first interval should be the minimum, then each should advance by the next exponent
+        // of the gap until the maximum.
+        let intervals: Vec<u64> = subject.by_ref().map(|d| d.as_secs()).collect();
+        assert_eq!(vec![5, 8, 13, 20], intervals);
+        // The current value should NOT have advanced past the maximum
         assert_eq!(20, subject.current().as_secs());
         // Reset the intervals
         subject.reset();
-        // Move to the next interval, which should return "true".
-        assert!(subject.move_next());
-        // The current value should be the minimum
-        assert_eq!(5, subject.current().as_secs());
+        // The next value should be the minimum
+        assert_eq!(Some(Duration::from_secs(5)), subject.next());
     }
 }
@@ -0,0 +1,113 @@
+use super::{get_random, get_random_interval, SqlRetryInterval};
+use rand::rngs::StdRng;
+use std::time::Duration;
+
+/// Intervals that grow from the minimum by the Fibonacci sequence times about the delta time
+/// (1, 1, 2, 3, 5, 8, ...), ending once they would exceed the maximum.
+///
+/// They grow more gently than exponential intervals.
+#[derive(Debug, Clone)]
+pub struct SqlFibonacciIntervalEnumerator {
+    /// The current and next Fibonacci numbers.
+    fibonacci: (u64, u64),
+    max_random: u64,
+    min_random: u64,
+    min_time_interval: Duration,
+    max_time_interval: Duration,
+    current: Duration,
+    rng: StdRng,
+}
+impl SqlFibonacciIntervalEnumerator {
+    /// Creates a new enumerator with a specified amount of randomness.
+    pub(super) fn new(
+        delta_time: Duration,
+        max_time_interval: Duration,
+        min_time_interval: Duration,
+        randomness: f64,
+        rng: StdRng,
+    ) -> Self {
+        // Get the random range
+        let (min_random, max_random) = get_random_interval(&delta_time, randomness);
+        Self {
+            fibonacci: (1, 1),
+            max_random,
+            min_random,
+            min_time_interval,
+            max_time_interval,
+            current: Duration::new(0, 0),
+            rng,
+        }
+    }
+}
+
+impl Iterator for SqlFibonacciIntervalEnumerator {
+    type Item = Duration;
+
+    /// Moves to the next retry interval.
+    fn next(&mut self) -> Option<Duration> {
+        let (factor, next_factor) = self.fibonacci;
+        let random = get_random(&mut self.rng, self.min_random, self.max_random);
+        let next = self.min_time_interval + Duration::from_millis(factor.saturating_mul(random));
+        // Stop once the intervals would exceed the maximum.
+        if next > self.max_time_interval {
+            return None;
+        }
+        self.fibonacci = (next_factor, factor.saturating_add(next_factor));
+        self.current = next;
+        Some(next)
+    }
+}
+
+impl SqlRetryInterval for SqlFibonacciIntervalEnumerator {
+    /// Gets the current value of the retry interval.
+    fn current(&self) -> Duration {
+        self.current
+    }
+
+    /// Resets the retry interval.
+    fn reset(&mut self) {
+        self.current = Duration::new(0, 0);
+        self.fibonacci = (1, 1);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::retry_enumerators::{SqlRetryInterval, SqlRetryIntervalBuilder};
+    use std::time::Duration;
+
+    #[test]
+    pub fn test_fibonacci_interval_enumerator() {
+        // Create the enumerator, eliminating the randomness so that our tests are repeatable.
+        let mut subject = SqlRetryIntervalBuilder::new(Duration::from_secs(1))
+            .min_time_interval(Duration::from_secs(2))
+            .max_time_interval(Duration::from_secs(20))
+            .randomness(0.0)
+            .fibonacci()
+            .unwrap();
+        let intervals: Vec<u64> = subject.by_ref().map(|d| d.as_secs()).collect();
+        assert_eq!(vec![3, 3, 4, 5, 7, 10, 15], intervals);
+        assert_eq!(15, subject.current().as_secs());
+        subject.reset();
+        assert_eq!(Some(Duration::from_secs(3)), subject.next());
+    }
+
+    #[test]
+    pub fn test_fibonacci_randomness() {
+        // The first interval varies evenly within 20% of the delta time.
+        let samples: Vec<f64> = (0..10_000)
+            .map(|seed| {
+                SqlRetryIntervalBuilder::new(Duration::from_secs(1))
+                    .seed(seed)
+                    .fibonacci()
+                    .unwrap()
+                    .next()
+                    .unwrap()
+                    .as_secs_f64()
+            })
+            .collect();
+        assert!(samples.iter().all(|sample| (0.8..=1.2).contains(sample)));
+        let mean = samples.iter().sum::<f64>() / samples.len() as f64;
+        assert!((mean - 1.0).abs() < 0.01, "mean {}", mean);
+    }
+}
@@ -0,0 +1,130 @@
+use super::{get_random, SqlRetryInterval};
+use rand::rngs::StdRng;
+use std::time::Duration;
+
+/// "Full jitter" intervals: each is drawn at random from the minimum up to a ceiling that doubles
+/// from the delta time until it reaches the maximum.
+///
+/// Spreading the whole interval at random stops clients that failed together (e.g. after a
+/// failover) from retrying together.
+#[derive(Debug, Clone)]
+pub struct SqlFullJitterIntervalEnumerator {
+    /// The number of intervals given so far.
+    attempt: u32,
+    delta_time: Duration,
+    min_time_interval: Duration,
+    max_time_interval: Duration,
+    current: Duration,
+    rng: StdRng,
+}
+impl SqlFullJitterIntervalEnumerator {
+    /// Creates a new enumerator.
+    pub(super) fn new(
+        delta_time: Duration,
+        max_time_interval: Duration,
+        min_time_interval: Duration,
+        rng: StdRng,
+    ) -> Self {
+        Self {
+            attempt: 0,
+            delta_time,
+            min_time_interval,
+            max_time_interval,
+            current: Duration::new(0, 0),
+            rng,
+        }
+    }
+
+    /// The ceiling of the next interval: the delta time doubled for each attempt, up to the
+    /// maximum.
+    fn ceiling(&self) -> u64 {
+        let delta = self.delta_time.as_millis() as u64;
+        delta
+            .saturating_mul(2u64.saturating_pow(self.attempt))
+            .min(self.max_time_interval.as_millis() as u64)
+    }
+}
+
+impl Iterator for SqlFullJitterIntervalEnumerator {
+    type Item = Duration;
+
+    /// Moves to the next retry interval.
+    fn next(&mut self) -> Option<Duration> {
+        let min_random = self.min_time_interval.as_millis() as u64;
+        let max_random = self.ceiling().max(min_random);
+        // Pick anywhere from the minimum up to (and including) the ceiling.
+        let random = get_random(&mut self.rng, min_random, max_random.saturating_add(1));
+        self.current = Duration::from_millis(random.min(max_random));
+        self.attempt = self.attempt.saturating_add(1);
+        // This enumerator is always able to get a new value.
+        Some(self.current)
+    }
+}
+
+impl SqlRetryInterval for SqlFullJitterIntervalEnumerator {
+    /// Gets the current value of the retry interval.
+    fn current(&self) -> Duration {
+        self.current
+    }
+
+    /// Resets the retry interval.
+    fn reset(&mut self) {
+        self.current = Duration::new(0, 0);
+        self.attempt = 0;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::retry_enumerators::{SqlRetryInterval, SqlRetryIntervalBuilder};
+    use std::time::Duration;
+
+    #[test]
+    pub fn test_full_jitter_ceiling() {
+        let mut subject = SqlRetryIntervalBuilder::new(Duration::from_secs(1))
+            .max_time_interval(Duration::from_secs(8))
+            .seed(1)
+            .full_jitter()
+            .unwrap();
+        // Each interval is below a ceiling that doubles up to the maximum.
+        for ceiling in [1, 2, 4, 8, 8, 8] {
+            let interval = subject.next().unwrap();
+            assert!(interval <= Duration::from_secs(ceiling));
+            assert_eq!(interval, subject.current());
+        }
+        subject.reset();
+        assert!(subject.next().unwrap() <= Duration::from_secs(1));
+    }
+
+    #[test]
+    pub fn test_full_jitter_is_uniform() {
+        // Once at the maximum, the intervals are spread evenly between the minimum and maximum.
+        let samples: Vec<f64> = SqlRetryIntervalBuilder::new(Duration::from_secs(1))
+            .min_time_interval(Duration::from_secs(2))
+            .max_time_interval(Duration::from_secs(10))
+            .seed(42)
+            .full_jitter()
+            .unwrap()
+            .skip(4)
+            .take(10_000)
+            .map(|interval| interval.as_secs_f64())
+            .collect();
+        assert!(samples.iter().all(|sample| (2.0..=10.0).contains(sample)));
+        let mean = samples.iter().sum::<f64>() / samples.len() as f64;
+        assert!((mean - 6.0).abs() < 0.1, "mean {}", mean);
+        // Each quarter of the range gets about a quarter of the samples.
+        for quarter in 0..4 {
+            let low = 2.0 + 2.0 * quarter as f64;
+            let count = samples
+                .iter()
+                .filter(|sample| **sample >= low && **sample < low + 2.0)
+                .count();
+            assert!(
+                (2250..=2750).contains(&count),
+                "quarter {}: {}",
+                quarter,
+                count
+            );
+        }
+    }
+}
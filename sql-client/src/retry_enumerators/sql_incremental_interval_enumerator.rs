@@ -1,53 +1,38 @@
-use super::validate;
-use crate::retry_enumerators::{get_random, get_random_interval};
-use crate::SqlClientError;
-use rand::Rng;
-use std::cmp::min;
-use std::ops::Deref;
+use super::{get_random, get_random_interval, SqlRetryInterval};
+use rand::rngs::StdRng;
 use std::time::Duration;
 
-#[derive(Clone)]
-pub(crate) struct SqlIncrementalIntervalEnumerator {
+/// Intervals that start at the minimum and grow by about the delta time, ending once they would
+/// exceed the maximum.
+#[derive(Debug, Clone)]
+pub struct SqlIncrementalIntervalEnumerator {
     max_random: u64,
     min_random: u64,
     min_time_interval: Duration,
     max_time_interval: Duration,
     current: Duration,
+    rng: StdRng,
 }
 impl SqlIncrementalIntervalEnumerator {
     /// Creates a new enumerator with a specified amount of randomness.
-    fn new_random(
+    pub(super) fn new(
         delta_backoff_time: Duration,
         max_time_interval: Duration,
         min_time_interval: Duration,
         randomness: f64,
-    ) -> Result<Self, SqlClientError> {
-        // Validate the intervals.
-        validate(&delta_backoff_time, &max_time_interval, &min_time_interval)?;
+        rng: StdRng,
+    ) -> Self {
         // Get the random range
         let (min_random, max_random) = get_random_interval(&delta_backoff_time, randomness);
         // Return the value
-        Ok(Self {
+        Self {
             max_random,
             min_random,
             max_time_interval,
             min_time_interval,
             current: Duration::new(0, 0),
-        })
-    }
-
-    /// Creates a new enumerator.
-    pub fn new(
-        delta_backoff_time: Duration,
-        max_time_interval: Duration,
-        min_time_interval: Duration,
-    ) -> Result<Self, SqlClientError> {
-        Self::new_random(
-            delta_backoff_time,
-            max_time_interval,
-            min_time_interval,
-            0.2,
-        )
+            rng,
+        }
     }
 
     /// Gets the next interval.
@@ -61,7 +46,7 @@ impl SqlIncrementalIntervalEnumerator {
         // If the current is not less than the minimum...
         else {
             // Get an amount of randomness in the range
-            let random = get_random(self.min_random, self.max_random);
+            let random = get_random(&mut self.rng, self.min_random, self.max_random);
             log::debug!(" - random = {:?}", random);
             // Get the new time
             let new_time = self.current + Duration::from_millis(random);
@@ -72,32 +57,30 @@ impl SqlIncrementalIntervalEnumerator {
     }
 }
 
-impl super::SqlRetryInterval for SqlIncrementalIntervalEnumerator {
-    /// Gets the current value of the retry interval.
-    fn current(&self) -> Duration {
-        self.current.clone()
-    }
+impl Iterator for SqlIncrementalIntervalEnumerator {
+    type Item = Duration;
 
     /// Moves to the next retry interval.
-    fn move_next(&mut self) -> bool {
-        // If the current value is less than the maximum...
-        if &self.current < &self.max_time_interval {
-            // Get the next interval
-            let next = self.next_interval();
-            // If the next value is also less than the maximum...
-            if &next <= &self.max_time_interval {
-                // Save it as the new "current"
-                self.current = next;
-                // Return that we were able to get a new value
-                true
-            } else {
-                // Return that we were not able to get a new value
-                false
-            }
-        } else {
-            // We're already over the maximum.  We can't go next.
-            false
+    fn next(&mut self) -> Option<Duration> {
+        // If we're already at the maximum, we can't go next.
+        if self.current >= self.max_time_interval {
+            return None;
+        }
+        // Get the next interval, as long as it's not over the maximum.
+        let next = self.next_interval();
+        if next > self.max_time_interval {
+            return None;
         }
+        // Save it as the new "current"
+        self.current = next;
+        Some(next)
+    }
+}
+
+impl SqlRetryInterval for SqlIncrementalIntervalEnumerator {
+    /// Gets the current value of the retry interval.
+    fn current(&self) -> Duration {
+        self.current
     }
 
     /// Resets the retry interval.
@@ -109,56 +92,27 @@ impl super::SqlRetryInterval for SqlIncrementalIntervalEnumerator {
 
 #[cfg(test)]
 mod tests {
-    use super::*;
-    use crate::retry_enumerators::SqlRetryInterval;
-    use std::cmp::min;
+    use crate::retry_enumerators::{SqlRetryInterval, SqlRetryIntervalBuilder};
+    use std::time::Duration;
 
     #[test]
     pub fn test_incremental_interval_enumerator() {
-        let gap_threshold = Duration::from_secs(1);
-        let min_interval = Duration::from_secs(5);
-        let max_interval = Duration::from_secs(10);
         // Create the enumerator, eliminating the randomness so that our tests are repeatable.
-        let mut subject = SqlIncrementalIntervalEnumerator::new_random(
-            gap_threshold,
-            max_interval,
-            min_interval,
-            0.0,
-        )
-        .unwrap();
-        // Move to the next interval, which should return "true".
-        assert!(subject.move_next());
-        // The current value should be the minimum
-        assert_eq!(5, subject.current().as_secs());
-        // Move to the next interval, which should return "true".
-        assert!(subject.move_next());
-        // The current value should have advanced by the next exponent of the gap
-        assert_eq!(6, subject.current().as_secs());
-        // Move to the next interval, which should return "true".
-        assert!(subject.move_next());
-        // The current value should have advanced by the next exponent of the gap
-        assert_eq!(7, subject.current().as_secs());
-        // Move to the next interval, which should return "true".
-        assert!(subject.move_next());
-        // The current value should have advanced by the next exponent of the gap
-        assert_eq!(8, subject.current().as_secs());
-        // Move to the next interval, which should return "true".
-        assert!(subject.move_next());
-        // The current value should have advanced by the next exponent of the gap
-        assert_eq!(9, subject.current().as_secs());
-        // Move to the next interval, which should return "true".
-        assert!(subject.move_next());
-        // The current value should have advanced by the next exponent of the gap
-        assert_eq!(10, subject.current().as_secs());
-        // Move to the next interval, which should return "false".
-        assert!(!subject.move_next());
-        // The current value should NOT have advanced
+        let mut subject = SqlRetryIntervalBuilder::new(Duration::from_secs(1))
+            .min_time_interval(Duration::from_secs(5))
+            .max_time_interval(Duration::from_secs(10))
+            .randomness(0.0)
+            .incremental()
+            .unwrap();
+        // The first interval should be the minimum, then each should advance by the gap until the
+        // maximum.
+        let intervals: Vec<u64> = subject.by_ref().map(|d| d.as_secs()).collect();
+        assert_eq!(vec![5, 6, 7, 8, 9, 10], intervals);
+        // The current value should NOT have advanced past the maximum
         assert_eq!(10, subject.current().as_secs());
         // Reset the intervals
         subject.reset();
-        // Move to the next interval, which should return "true".
-        assert!(subject.move_next());
-        // The current value should be the minimum
-        assert_eq!(5, subject.current().as_secs());
+        // The next value should be the minimum
+        assert_eq!(Some(Duration::from_secs(5)), subject.next());
     }
 }
@@ -1,30 +1,30 @@
-use super::validate;
-use crate::retry_enumerators::{get_random, get_random_interval};
-use crate::SqlClientError;
-use rand::Rng;
-use std::cmp::min;
-use std::ops::Deref;
+use super::SqlRetryInterval;
 use std::time::Duration;
 
-#[derive(Clone)]
-pub(crate) struct SqlNoneIntervalEnumerator;
+/// Intervals of zero, without end.
+#[derive(Debug, Clone)]
+pub struct SqlNoneIntervalEnumerator;
 impl SqlNoneIntervalEnumerator {
     /// Creates a new enumerator.
-    pub fn new() -> Result<Self, SqlClientError> {
-        Ok(Self)
+    pub(super) fn new() -> Self {
+        Self
     }
 }
 
-impl super::SqlRetryInterval for SqlNoneIntervalEnumerator {
-    /// Gets the current value of the retry interval.
-    fn current(&self) -> Duration {
-        Duration::new(0, 0)
-    }
+impl Iterator for SqlNoneIntervalEnumerator {
+    type Item = Duration;
 
     /// Moves to the next retry interval.
-    fn move_next(&mut self) -> bool {
+    fn next(&mut self) -> Option<Duration> {
         // This enumerator is always able to get a new value.
-        true
+        Some(Duration::new(0, 0))
+    }
+}
+
+impl SqlRetryInterval for SqlNoneIntervalEnumerator {
+    /// Gets the current value of the retry interval.
+    fn current(&self) -> Duration {
+        Duration::new(0, 0)
     }
 
     /// Resets the retry interval.
@@ -33,17 +33,16 @@ impl super::SqlRetryInterval for SqlNoneIntervalEnumerator {
 
 #[cfg(test)]
 mod tests {
-    use super::*;
-    use crate::retry_enumerators::SqlRetryInterval;
-    use std::cmp::min;
+    use crate::retry_enumerators::{SqlRetryInterval, SqlRetryIntervalBuilder};
+    use std::time::Duration;
 
     #[test]
     pub fn test_none_interval_enumerator() {
-        // Create the enumerator, eliminating the randomness so that our tests are repeatable.
-        let mut subject = SqlNoneIntervalEnumerator::new().unwrap();
-        // Move to the next interval, which should return "true".
-        assert!(subject.move_next());
-        // The current value should be the 0
+        let mut subject = SqlRetryIntervalBuilder::new(Duration::from_secs(1))
+            .none()
+            .unwrap();
+        // The next value should be 0
+        assert_eq!(Some(Duration::ZERO), subject.next());
         assert_eq!(0, subject.current().as_secs());
     }
 }
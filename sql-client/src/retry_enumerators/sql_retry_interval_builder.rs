@@ -0,0 +1,273 @@
+use super::{
+    SqlDecorrelatedJitterIntervalEnumerator, SqlExponentialIntervalEnumerator,
+    SqlFibonacciIntervalEnumerator, SqlFixedIntervalEnumerator, SqlFullJitterIntervalEnumerator,
+    SqlIncrementalIntervalEnumerator, SqlNoneIntervalEnumerator, SqlRetryInterval, SqlRetryMethod,
+    MAX_DURATION, MIN_DURATION,
+};
+use crate::SqlClientError;
+use rand::rngs::StdRng;
+use rand::SeedableRng;
+use std::time::Duration;
+
+/// Builds retry interval enumerators, validating their settings.
+///
+/// ```
+/// # use sql_client::retry_enumerators::{SqlRetryIntervalBuilder, SqlRetryInterval};
+/// # use std::time::Duration;
+/// let intervals = SqlRetryIntervalBuilder::new(Duration::from_secs(1))
+///     .max_time_interval(Duration::from_secs(20))
+///     .seed(42)
+///     .exponential()
+///     .unwrap();
+/// assert!(intervals.take(3).all(|interval| interval <= Duration::from_secs(20)));
+/// ```
+#[derive(Debug, Clone)]
+pub struct SqlRetryIntervalBuilder {
+    /// The gap that the intervals are based on.
+    delta_time: Duration,
+    /// The shortest interval.
+    min_time_interval: Duration,
+    /// The longest interval.
+    max_time_interval: Duration,
+    /// How far each gap may vary from the delta time, as a fraction of it.
+    randomness: f64,
+    /// The random number generator the gaps are varied with.  If not set, each enumerator gets
+    /// its own, seeded from the operating system.
+    rng: Option<StdRng>,
+}
+
+impl SqlRetryIntervalBuilder {
+    /// The default fraction by which the gaps vary, as in .NET.
+    pub const DEFAULT_RANDOMNESS: f64 = 0.2;
+
+    /// Creates a builder for intervals based on the given gap, from zero up to the largest
+    /// interval allowed.
+    pub fn new(delta_time: Duration) -> Self {
+        Self {
+            delta_time,
+            min_time_interval: MIN_DURATION,
+            max_time_interval: MAX_DURATION,
+            randomness: Self::DEFAULT_RANDOMNESS,
+            rng: None,
+        }
+    }
+
+    /// Sets the shortest interval.
+    pub fn min_time_interval(mut self, value: Duration) -> Self {
+        self.min_time_interval = value;
+        self
+    }
+
+    /// Sets the longest interval (at most 120 seconds).
+    pub fn max_time_interval(mut self, value: Duration) -> Self {
+        self.max_time_interval = value;
+        self
+    }
+
+    /// Sets how far each gap may vary from the delta time, as a fraction of it (between 0 and 1).
+    pub fn randomness(mut self, value: f64) -> Self {
+        self.randomness = value;
+        self
+    }
+
+    /// Sets the random number generator that the gaps are varied with.  Each enumerator built
+    /// starts from a copy of it, so they all give the same intervals.
+    pub fn rng(mut self, value: StdRng) -> Self {
+        self.rng = Some(value);
+        self
+    }
+
+    /// Seeds the random number generator that the gaps are varied with, e.g. to make tests
+    /// repeatable.
+    pub fn seed(self, value: u64) -> Self {
+        self.rng(StdRng::seed_from_u64(value))
+    }
+
+    /// Builds intervals that grow exponentially from the minimum.
+    pub fn exponential(&self) -> Result<SqlExponentialIntervalEnumerator, SqlClientError> {
+        self.validate()?;
+        Ok(SqlExponentialIntervalEnumerator::new(
+            self.delta_time,
+            self.max_time_interval,
+            self.min_time_interval,
+            self.randomness,
+            self.new_rng(),
+        ))
+    }
+
+    /// Builds intervals that grow by about the delta time from the minimum.
+    pub fn incremental(&self) -> Result<SqlIncrementalIntervalEnumerator, SqlClientError> {
+        self.validate()?;
+        Ok(SqlIncrementalIntervalEnumerator::new(
+            self.delta_time,
+            self.max_time_interval,
+            self.min_time_interval,
+            self.randomness,
+            self.new_rng(),
+        ))
+    }
+
+    /// Builds intervals of about the delta time.
+    pub fn fixed(&self) -> Result<SqlFixedIntervalEnumerator, SqlClientError> {
+        self.validate()?;
+        Ok(SqlFixedIntervalEnumerator::new(
+            self.delta_time,
+            self.randomness,
+            self.new_rng(),
+        ))
+    }
+
+    /// Builds intervals of zero.
+    pub fn none(&self) -> Result<SqlNoneIntervalEnumerator, SqlClientError> {
+        self.validate()?;
+        Ok(SqlNoneIntervalEnumerator::new())
+    }
+
+    /// Builds "full jitter" intervals, drawn at random up to a ceiling that doubles from the delta
+    /// time.  The randomness setting doesn't apply.
+    pub fn full_jitter(&self) -> Result<SqlFullJitterIntervalEnumerator, SqlClientError> {
+        self.validate()?;
+        Ok(SqlFullJitterIntervalEnumerator::new(
+            self.delta_time,
+            self.max_time_interval,
+            self.min_time_interval,
+            self.new_rng(),
+        ))
+    }
+
+    /// Builds "decorrelated jitter" intervals, drawn at random from the delta time up to three
+    /// times the previous interval.  The randomness setting doesn't apply.
+    pub fn decorrelated_jitter(
+        &self,
+    ) -> Result<SqlDecorrelatedJitterIntervalEnumerator, SqlClientError> {
+        self.validate()?;
+        Ok(SqlDecorrelatedJitterIntervalEnumerator::new(
+            self.delta_time,
+            self.max_time_interval,
+            self.min_time_interval,
+            self.new_rng(),
+        ))
+    }
+
+    /// Builds intervals that grow from the minimum by the Fibonacci sequence times about the delta
+    /// time.
+    pub fn fibonacci(&self) -> Result<SqlFibonacciIntervalEnumerator, SqlClientError> {
+        self.validate()?;
+        Ok(SqlFibonacciIntervalEnumerator::new(
+            self.delta_time,
+            self.max_time_interval,
+            self.min_time_interval,
+            self.randomness,
+            self.new_rng(),
+        ))
+    }
+
+    /// Builds the intervals of a retry method.
+    pub fn build(
+        &self,
+        method: SqlRetryMethod,
+    ) -> Result<Box<dyn SqlRetryInterval + Send + Sync>, SqlClientError> {
+        Ok(match method {
+            SqlRetryMethod::None => Box::new(self.none()?),
+            SqlRetryMethod::Fixed => Box::new(self.fixed()?),
+            SqlRetryMethod::Incremental => Box::new(self.incremental()?),
+            SqlRetryMethod::Exponential => Box::new(self.exponential()?),
+            SqlRetryMethod::FullJitter => Box::new(self.full_jitter()?),
+            SqlRetryMethod::DecorrelatedJitter => Box::new(self.decorrelated_jitter()?),
+            SqlRetryMethod::Fibonacci => Box::new(self.fibonacci()?),
+        })
+    }
+
+    /// Gets the random number generator for a new enumerator.
+    fn new_rng(&self) -> StdRng {
+        match &self.rng {
+            Some(rng) => rng.clone(),
+            None => StdRng::from_entropy(),
+        }
+    }
+
+    /// Validates the settings.
+    pub fn validate(&self) -> Result<(), SqlClientError> {
+        // Checks that an interval is in the allowed range.
+        fn check_range(name: &str, value: Duration) -> Result<(), SqlClientError> {
+            if value < MIN_DURATION || value > MAX_DURATION {
+                Err(SqlClientError::ArgumentOutOfRange(
+                    name.to_string(),
+                    format!(
+                        "{} must be between {:?} and {:?}",
+                        name, MIN_DURATION, MAX_DURATION
+                    ),
+                ))
+            } else {
+                Ok(())
+            }
+        }
+        check_range("min_time_interval", self.min_time_interval)?;
+        check_range("max_time_interval", self.max_time_interval)?;
+        check_range("delta_time", self.delta_time)?;
+        if self.max_time_interval < self.min_time_interval {
+            return Err(SqlClientError::ArgumentOutOfRange(
+                "max_time_interval".to_string(),
+                "max_time_interval must be greater than min_time_interval".to_string(),
+            ));
+        }
+        if !(0.0..=1.0).contains(&self.randomness) {
+            return Err(SqlClientError::ArgumentOutOfRange(
+                "randomness".to_string(),
+                "randomness must be between 0 and 1".to_string(),
+            ));
+        }
+        // Validation succeeded.
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[rstest::rstest]
+    #[case::min_too_large(121, 120, 1, 0.2, Some("min_time_interval"))]
+    #[case::max_too_large(0, 121, 1, 0.2, Some("max_time_interval"))]
+    #[case::delta_too_large(0, 120, 121, 0.2, Some("delta_time"))]
+    #[case::max_below_min(10, 5, 1, 0.2, Some("max_time_interval"))]
+    #[case::randomness_too_large(0, 10, 1, 1.5, Some("randomness"))]
+    #[case::valid(5, 10, 1, 0.2, None)]
+    fn test_validate(
+        #[case] min: u64,
+        #[case] max: u64,
+        #[case] delta: u64,
+        #[case] randomness: f64,
+        #[case] expected: Option<&str>,
+    ) {
+        let builder = SqlRetryIntervalBuilder::new(Duration::from_secs(delta))
+            .min_time_interval(Duration::from_secs(min))
+            .max_time_interval(Duration::from_secs(max))
+            .randomness(randomness);
+        match (builder.validate(), expected) {
+            (Ok(()), None) => {}
+            (Err(SqlClientError::ArgumentOutOfRange(name, _)), Some(expected)) => {
+                assert_eq!(expected, name)
+            }
+            (result, _) => panic!("unexpected result {:?}", result),
+        }
+    }
+
+    #[rstest::rstest]
+    #[case(SqlRetryMethod::None)]
+    #[case(SqlRetryMethod::Fixed)]
+    #[case(SqlRetryMethod::Incremental)]
+    #[case(SqlRetryMethod::Exponential)]
+    #[case(SqlRetryMethod::FullJitter)]
+    #[case(SqlRetryMethod::DecorrelatedJitter)]
+    #[case(SqlRetryMethod::Fibonacci)]
+    fn test_seeded_intervals_repeat(#[case] method: SqlRetryMethod) {
+        let builder = SqlRetryIntervalBuilder::new(Duration::from_millis(100))
+            .max_time_interval(Duration::from_secs(100))
+            .seed(7);
+        let first: Vec<Duration> = builder.build(method).unwrap().take(5).collect();
+        let second: Vec<Duration> = builder.build(method).unwrap().take(5).collect();
+        assert_eq!(5, first.len());
+        assert_eq!(first, second);
+    }
+}
@@ -0,0 +1,81 @@
+use crate::sql_client_error::SqlClientError;
+use std::fmt::{Display, Formatter};
+
+/// How the intervals between the attempts of a retried operation grow.
+#[derive(PartialEq, Debug, Clone, Copy, Default)]
+pub enum SqlRetryMethod {
+    /// Retry immediately.
+    None = 0,
+    /// Wait for about the delta time between attempts.
+    Fixed = 1,
+    /// Wait longer by about the delta time after each attempt.
+    Incremental = 2,
+    /// Wait exponentially longer after each attempt.
+    #[default]
+    Exponential = 3,
+    /// Wait for a random time up to a ceiling that doubles after each attempt.
+    FullJitter = 4,
+    /// Wait for a random time up to three times the previous wait.
+    DecorrelatedJitter = 5,
+    /// Wait longer by the Fibonacci sequence after each attempt.
+    Fibonacci = 6,
+}
+impl TryFrom<&str> for SqlRetryMethod {
+    type Error = SqlClientError;
+
+    fn try_from(value: &str) -> Result<Self, Self::Error> {
+        match value.trim().to_lowercase().as_str() {
+            "none" => Ok(SqlRetryMethod::None),
+            "fixed" => Ok(SqlRetryMethod::Fixed),
+            "incremental" => Ok(SqlRetryMethod::Incremental),
+            "exponential" => Ok(SqlRetryMethod::Exponential),
+            "fulljitter" => Ok(SqlRetryMethod::FullJitter),
+            "decorrelatedjitter" => Ok(SqlRetryMethod::DecorrelatedJitter),
+            "fibonacci" => Ok(SqlRetryMethod::Fibonacci),
+            _ => Err(SqlClientError::UnsupportedValue(
+                "Retry method".to_string(),
+                value.to_string(),
+            )),
+        }
+    }
+}
+
+impl Display for SqlRetryMethod {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            SqlRetryMethod::None => write!(f, "None"),
+            SqlRetryMethod::Fixed => write!(f, "Fixed"),
+            SqlRetryMethod::Incremental => write!(f, "Incremental"),
+            SqlRetryMethod::Exponential => write!(f, "Exponential"),
+            SqlRetryMethod::FullJitter => write!(f, "FullJitter"),
+            SqlRetryMethod::DecorrelatedJitter => write!(f, "DecorrelatedJitter"),
+            SqlRetryMethod::Fibonacci => write!(f, "Fibonacci"),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[rstest::rstest]
+    #[case("None", SqlRetryMethod::None)]
+    #[case("fixed", SqlRetryMethod::Fixed)]
+    #[case("Incremental", SqlRetryMethod::Incremental)]
+    #[case(" exponential ", SqlRetryMethod::Exponential)]
+    #[case("FullJitter", SqlRetryMethod::FullJitter)]
+    #[case("decorrelatedjitter", SqlRetryMethod::DecorrelatedJitter)]
+    #[case("Fibonacci", SqlRetryMethod::Fibonacci)]
+    fn test_try_from(#[case] value: &str, #[case] expected: SqlRetryMethod) {
+        assert_eq!(expected, SqlRetryMethod::try_from(value).unwrap());
+        assert_eq!(
+            expected,
+            SqlRetryMethod::try_from(expected.to_string().as_str()).unwrap()
+        );
+    }
+
+    #[test]
+    fn test_try_from_invalid() {
+        assert!(SqlRetryMethod::try_from("linear").is_err());
+    }
+}
@@ -0,0 +1,178 @@
+use crate::ssl_over_tds_stream::SslOverTdsStream;
+use crate::tds_parser_state_object::TdsStream;
+use crate::SqlClientError;
+use std::io;
+use std::pin::Pin;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::task::{Context, Poll};
+use tokio::io::{AsyncRead, AsyncWrite, ReadBuf};
+use tokio_rustls::client::TlsStream;
+use tokio_rustls::rustls::client::danger::{
+    HandshakeSignatureValid, ServerCertVerified, ServerCertVerifier,
+};
+use tokio_rustls::rustls::crypto::{self, CryptoProvider};
+use tokio_rustls::rustls::pki_types::{CertificateDer, ServerName, UnixTime};
+use tokio_rustls::rustls::{ClientConfig, DigitallySignedStruct, RootCertStore, SignatureScheme};
+use tokio_rustls::TlsConnector;
+
+/// How a connection's traffic is carried.
+enum SniStream {
+    /// As it is.
+    Plain(Box<dyn TdsStream>),
+    /// Encrypted with TLS (while it's enabled).
+    Ssl(Box<TlsStream<SslOverTdsStream<Box<dyn TdsStream>>>>),
+}
+
+/// The stream that a connection's packets are sent over, which may be encrypted with TLS for the
+/// whole connection or just for the login.
+pub(crate) struct SniTcpHandle {
+    /// The stream.
+    stream: SniStream,
+    /// Whether traffic currently goes through TLS.
+    ssl_enabled: Arc<AtomicBool>,
+}
+
+impl SniTcpHandle {
+    /// Sends traffic over a stream as it is.
+    pub fn new(stream: Box<dyn TdsStream>) -> Self {
+        Self {
+            stream: SniStream::Plain(stream),
+            ssl_enabled: Arc::new(AtomicBool::new(false)),
+        }
+    }
+
+    /// Does the TLS handshake over a stream and then encrypts traffic over it.  The server's
+    /// certificate is checked against the host name unless `trust_server_certificate` is set.
+    pub async fn new_ssl(
+        stream: Box<dyn TdsStream>,
+        host_name: &str,
+        trust_server_certificate: bool,
+    ) -> Result<Self, SqlClientError> {
+        log::debug!("new_ssl - host: {}", host_name);
+        let server_name = ServerName::try_from(host_name.to_string()).map_err(|_| {
+            SqlClientError::UnsupportedValue("host name".to_string(), host_name.to_string())
+        })?;
+        let connector = TlsConnector::from(client_config(trust_server_certificate)?);
+        let mut tls = connector
+            .connect(server_name, SslOverTdsStream::new(stream))
+            .await?;
+        tls.get_mut().0.finish_handshake();
+        Ok(Self {
+            stream: SniStream::Ssl(Box::new(tls)),
+            ssl_enabled: Arc::new(AtomicBool::new(true)),
+        })
+    }
+
+    /// The switch for whether traffic goes through TLS, so that it can be turned off after the
+    /// login when only the login is encrypted.
+    pub fn ssl_enabled(&self) -> Arc<AtomicBool> {
+        self.ssl_enabled.clone()
+    }
+
+    /// The stream that traffic currently goes over.
+    fn current(&mut self) -> Pin<&mut dyn TdsStream> {
+        let ssl_enabled = self.ssl_enabled.load(Ordering::Relaxed);
+        match (&mut self.stream, ssl_enabled) {
+            (SniStream::Plain(stream), _) => Pin::new(stream.as_mut()),
+            (SniStream::Ssl(tls), true) => Pin::new(tls.as_mut()),
+            (SniStream::Ssl(tls), false) => Pin::new(tls.get_mut().0.get_mut().as_mut()),
+        }
+    }
+}
+
+/// Builds the TLS configuration.
+fn client_config(trust_server_certificate: bool) -> Result<Arc<ClientConfig>, SqlClientError> {
+    let provider = Arc::new(crypto::ring::default_provider());
+    let builder = ClientConfig::builder_with_provider(provider.clone())
+        .with_safe_default_protocol_versions()
+        .map_err(|e| SqlClientError::Io(io::ErrorKind::Other, e.to_string()))?;
+    let config = if trust_server_certificate {
+        builder
+            .dangerous()
+            .with_custom_certificate_verifier(Arc::new(TrustServerCertificate(provider)))
+            .with_no_client_auth()
+    } else {
+        let roots = RootCertStore::from_iter(webpki_roots::TLS_SERVER_ROOTS.iter().cloned());
+        builder.with_root_certificates(roots).with_no_client_auth()
+    };
+    Ok(Arc::new(config))
+}
+
+/// Accepts any server certificate (for TrustServerCertificate=true), while still checking that the
+/// server holds its key.
+#[derive(Debug)]
+struct TrustServerCertificate(Arc<CryptoProvider>);
+
+impl ServerCertVerifier for TrustServerCertificate {
+    fn verify_server_cert(
+        &self,
+        _end_entity: &CertificateDer<'_>,
+        _intermediates: &[CertificateDer<'_>],
+        _server_name: &ServerName<'_>,
+        _ocsp_response: &[u8],
+        _now: UnixTime,
+    ) -> Result<ServerCertVerified, tokio_rustls::rustls::Error> {
+        Ok(ServerCertVerified::assertion())
+    }
+
+    fn verify_tls12_signature(
+        &self,
+        message: &[u8],
+        cert: &CertificateDer<'_>,
+        dss: &DigitallySignedStruct,
+    ) -> Result<HandshakeSignatureValid, tokio_rustls::rustls::Error> {
+        crypto::verify_tls12_signature(
+            message,
+            cert,
+            dss,
+            &self.0.signature_verification_algorithms,
+        )
+    }
+
+    fn verify_tls13_signature(
+        &self,
+        message: &[u8],
+        cert: &CertificateDer<'_>,
+        dss: &DigitallySignedStruct,
+    ) -> Result<HandshakeSignatureValid, tokio_rustls::rustls::Error> {
+        crypto::verify_tls13_signature(
+            message,
+            cert,
+            dss,
+            &self.0.signature_verification_algorithms,
+        )
+    }
+
+    fn supported_verify_schemes(&self) -> Vec<SignatureScheme> {
+        self.0.signature_verification_algorithms.supported_schemes()
+    }
+}
+
+impl AsyncRead for SniTcpHandle {
+    fn poll_read(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &mut ReadBuf<'_>,
+    ) -> Poll<io::Result<()>> {
+        self.get_mut().current().poll_read(cx, buf)
+    }
+}
+
+impl AsyncWrite for SniTcpHandle {
+    fn poll_write(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &[u8],
+    ) -> Poll<io::Result<usize>> {
+        self.get_mut().current().poll_write(cx, buf)
+    }
+
+    fn poll_flush(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        self.get_mut().current().poll_flush(cx)
+    }
+
+    fn poll_shutdown(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        self.get_mut().current().poll_shutdown(cx)
+    }
+}
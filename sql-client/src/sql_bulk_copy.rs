@@ -0,0 +1,639 @@
+use crate::tds_token::{ColumnMetaData, TdsToken};
+use crate::{
+    BulkCopySource, SqlBulkCopyColumn, SqlBulkCopyColumnMapping, SqlBulkCopyOptions,
+    SqlClientError, SqlCommand, SqlConnection, SqlValue,
+};
+use std::fmt::{Debug, Formatter};
+use std::time::Duration;
+
+/// Efficiently loads rows into a SQL Server table with the bulk load protocol.
+pub struct SqlBulkCopy {
+    /// The name of the destination table.
+    destination_table_name: String,
+    /// The copy options.
+    options: SqlBulkCopyOptions,
+    /// The number of rows in each batch, or 0 to send all the rows in a single batch.
+    batch_size: usize,
+    /// How long the copy may take, or zero for no limit.
+    bulk_copy_timeout: Duration,
+    /// How the source columns map to the destination columns.  If empty, they're mapped by ordinal.
+    column_mappings: Vec<SqlBulkCopyColumnMapping>,
+    /// How many rows are copied between calls to the progress callback, or 0 for no calls.
+    notify_after: u64,
+    /// Called with the total number of rows copied every [SqlBulkCopy::notify_after] rows.
+    rows_copied_callback: Option<Box<dyn FnMut(u64) + Send>>,
+}
+
+/// A destination column being loaded.
+struct BulkCopyColumn {
+    /// The ordinal of the source column.
+    source: usize,
+    /// The metadata of the destination column.
+    meta_data: ColumnMetaData,
+}
+
+impl SqlBulkCopy {
+    /// The default timeout in seconds, as in .NET.
+    const DEFAULT_TIMEOUT: u64 = 30;
+
+    /// Creates a bulk copy into the given table with the default options.
+    pub fn new(destination_table_name: &str) -> Self {
+        Self::with_options(destination_table_name, SqlBulkCopyOptions::DEFAULT)
+    }
+
+    /// Creates a bulk copy into the given table with the given options.
+    pub fn with_options(destination_table_name: &str, options: SqlBulkCopyOptions) -> Self {
+        Self {
+            destination_table_name: destination_table_name.to_string(),
+            options,
+            batch_size: 0,
+            bulk_copy_timeout: Duration::from_secs(Self::DEFAULT_TIMEOUT),
+            column_mappings: Vec::new(),
+            notify_after: 0,
+            rows_copied_callback: None,
+        }
+    }
+
+    /// The name of the destination table.
+    pub fn destination_table_name(&self) -> &str {
+        &self.destination_table_name
+    }
+    /// Sets the name of the destination table.
+    pub fn set_destination_table_name(&mut self, value: &str) {
+        self.destination_table_name = value.to_string();
+    }
+
+    /// The copy options.
+    pub fn options(&self) -> SqlBulkCopyOptions {
+        self.options
+    }
+    /// Sets the copy options.
+    pub fn set_options(&mut self, value: SqlBulkCopyOptions) {
+        self.options = value;
+    }
+
+    /// The number of rows in each batch (0 if all the rows are sent in a single batch).
+    pub fn batch_size(&self) -> usize {
+        self.batch_size
+    }
+    /// Sets the number of rows in each batch, or 0 to send all the rows in a single batch.
+    pub fn set_batch_size(&mut self, value: usize) {
+        self.batch_size = value;
+    }
+
+    /// How long the copy may take (zero if there's no limit).
+    pub fn bulk_copy_timeout(&self) -> Duration {
+        self.bulk_copy_timeout
+    }
+    /// Sets how long the copy may take, or zero for no limit.  If it's exceeded the connection is
+    /// closed, as it's left part way through sending rows.
+    pub fn set_bulk_copy_timeout(&mut self, value: Duration) {
+        self.bulk_copy_timeout = value;
+    }
+
+    /// How the source columns map to the destination columns.
+    pub fn column_mappings(&self) -> &[SqlBulkCopyColumnMapping] {
+        &self.column_mappings
+    }
+    /// How the source columns map to the destination columns, for modification.
+    pub fn column_mappings_mut(&mut self) -> &mut Vec<SqlBulkCopyColumnMapping> {
+        &mut self.column_mappings
+    }
+    /// Maps a source column to a destination column, by name or ordinal.  Once any mapping has been
+    /// added, only the mapped columns are copied.
+    pub fn add_column_mapping(
+        &mut self,
+        source: impl Into<SqlBulkCopyColumn>,
+        destination: impl Into<SqlBulkCopyColumn>,
+    ) {
+        self.column_mappings
+            .push(SqlBulkCopyColumnMapping::new(source, destination));
+    }
+
+    /// How many rows are copied between calls to the progress callback (0 if it's never called).
+    pub fn notify_after(&self) -> u64 {
+        self.notify_after
+    }
+    /// Sets how many rows are copied between calls to the progress callback, or 0 to never call it.
+    pub fn set_notify_after(&mut self, value: u64) {
+        self.notify_after = value;
+    }
+    /// Sets a callback that's given the total number of rows copied so far every
+    /// [SqlBulkCopy::notify_after] rows.
+    pub fn on_rows_copied(&mut self, callback: impl FnMut(u64) + Send + 'static) {
+        self.rows_copied_callback = Some(Box::new(callback));
+    }
+
+    /// Copies the rows of a source (e.g. [crate::DataTable::create_data_reader], a
+    /// [crate::RowSource] or a [crate::SqlDataReader]) to the destination, returning the number of
+    /// rows copied.
+    pub async fn write_to_server(
+        &mut self,
+        connection: &mut SqlConnection,
+        mut source: impl BulkCopySource,
+    ) -> Result<u64, SqlClientError> {
+        if self.bulk_copy_timeout.is_zero() {
+            return self.copy(connection, &mut source).await;
+        }
+        let timeout = self.bulk_copy_timeout;
+        match tokio::time::timeout(timeout, self.copy(connection, &mut source)).await {
+            Ok(result) => result,
+            Err(_) => {
+                // The connection may have been left part way through a message.
+                connection.detach();
+                Err(SqlClientError::Timeout(format!(
+                    "The bulk copy didn't complete within {} seconds",
+                    timeout.as_secs_f64()
+                )))
+            }
+        }
+    }
+
+    /// Copies rows to the destination in batches.
+    async fn copy(
+        &mut self,
+        connection: &mut SqlConnection,
+        source: &mut impl BulkCopySource,
+    ) -> Result<u64, SqlClientError> {
+        // Work out which columns are loaded
+        let destination =
+            Self::destination_columns(connection, &self.destination_table_name).await?;
+        let columns = self.resolve_columns(&source.column_names(), destination)?;
+        let column_list = columns
+            .iter()
+            .map(|column| {
+                format!(
+                    "[{}] {}",
+                    column.meta_data.column_name.replace(']', "]]"),
+                    column.meta_data.type_info.declaration()
+                )
+            })
+            .collect::<Vec<String>>()
+            .join(", ");
+        let mut insert_bulk = format!(
+            "INSERT BULK {} ({})",
+            self.destination_table_name, column_list
+        );
+        let hints = self.hints();
+        if !hints.is_empty() {
+            insert_bulk.push_str(&format!(" WITH ({})", hints.join(", ")));
+        }
+        // Send the rows in batches
+        let mut next_row = source.next_row().await?;
+        let mut rows_copied = 0;
+        while next_row.is_some() {
+            let internal_transaction = self
+                .options
+                .contains(SqlBulkCopyOptions::USE_INTERNAL_TRANSACTION);
+            if internal_transaction {
+                if connection.parser_mut()?.transaction_descriptor() != 0 {
+                    return Err(SqlClientError::InvalidOperation(
+                        "Unexpected existing transaction.".to_string(),
+                    ));
+                }
+                SqlCommand::new("BEGIN TRANSACTION")
+                    .execute_non_query(connection)
+                    .await?;
+            }
+            let result = self
+                .copy_batch(
+                    connection,
+                    &insert_bulk,
+                    &columns,
+                    source,
+                    &mut next_row,
+                    rows_copied,
+                )
+                .await;
+            if internal_transaction {
+                let statement = match result {
+                    Ok(_) => "COMMIT TRANSACTION",
+                    Err(_) => "ROLLBACK TRANSACTION",
+                };
+                let completed = SqlCommand::new(statement)
+                    .execute_non_query(connection)
+                    .await;
+                // A failure of the batch takes precedence.
+                rows_copied += result?;
+                completed?;
+            } else {
+                rows_copied += result?;
+            }
+        }
+        Ok(rows_copied)
+    }
+
+    /// Sends a batch of rows, starting with the row that's already been read, returning the number
+    /// of rows sent.  The row after the batch is left in `next_row`.
+    async fn copy_batch(
+        &mut self,
+        connection: &mut SqlConnection,
+        insert_bulk: &str,
+        columns: &[BulkCopyColumn],
+        source: &mut impl BulkCopySource,
+        next_row: &mut Option<Vec<SqlValue>>,
+        rows_copied: u64,
+    ) -> Result<u64, SqlClientError> {
+        SqlCommand::new(insert_bulk)
+            .execute_non_query(connection)
+            .await?;
+        let meta_data: Vec<ColumnMetaData> = columns
+            .iter()
+            .map(|column| column.meta_data.clone())
+            .collect();
+        let parser = connection.parser_mut()?;
+        parser
+            .start_bulk_load(&self.destination_table_name, &meta_data)
+            .await?;
+        let mut batch_rows = 0;
+        while self.batch_size == 0 || batch_rows < self.batch_size as u64 {
+            let row = match next_row.take() {
+                Some(row) => row,
+                None => break,
+            };
+            let values = columns
+                .iter()
+                .map(|column| {
+                    row.get(column.source).ok_or_else(|| {
+                        SqlClientError::InvalidOperation(format!(
+                            "The source row has no column {}.",
+                            column.source
+                        ))
+                    })
+                })
+                .collect::<Result<Vec<&SqlValue>, SqlClientError>>();
+            let written = match values {
+                Ok(values) => parser.write_bulk_row(&meta_data, &values).await,
+                Err(e) => Err(e),
+            };
+            // Read the next row before counting this one, so that a failure of either discards
+            // the batch.
+            let written = match written {
+                Ok(()) => source.next_row().await.map(|row| *next_row = row),
+                Err(e) => Err(e),
+            };
+            if let Err(e) = written {
+                // Tell the server to discard the rows sent so far.
+                parser.abort_message().await?;
+                return Err(e);
+            }
+            batch_rows += 1;
+            self.on_row_copied(rows_copied + batch_rows);
+        }
+        parser.end_bulk_load().await?;
+        // Read the response
+        let mut errors = Vec::new();
+        loop {
+            match parser.next_token().await? {
+                TdsToken::Error(error) => errors.push(error),
+                TdsToken::Done(done) if done.is_final() => break,
+                _ => {}
+            }
+        }
+        SqlCommand::check_errors(errors, parser.client_connection_id())?;
+        Ok(batch_rows)
+    }
+
+    /// Calls the progress callback if another [SqlBulkCopy::notify_after] rows have been copied.
+    fn on_row_copied(&mut self, rows_copied: u64) {
+        if self.notify_after == 0 || !rows_copied.is_multiple_of(self.notify_after) {
+            return;
+        }
+        if let Some(callback) = &mut self.rows_copied_callback {
+            callback(rows_copied);
+        }
+    }
+
+    /// Reads the metadata of the destination table's columns.
+    async fn destination_columns(
+        connection: &mut SqlConnection,
+        table_name: &str,
+    ) -> Result<Vec<ColumnMetaData>, SqlClientError> {
+        let mut command = SqlCommand::new(&format!("SELECT TOP 0 * FROM {}", table_name));
+        let reader = command.execute_reader(connection).await?;
+        let columns = reader.columns().to_vec();
+        drop(reader);
+        Ok(columns)
+    }
+
+    /// Matches the source columns to the destination columns, in the order of the destination.
+    fn resolve_columns(
+        &self,
+        source_names: &[String],
+        destination: Vec<ColumnMetaData>,
+    ) -> Result<Vec<BulkCopyColumn>, SqlClientError> {
+        // Without mappings, the columns are matched by ordinal.
+        let mut sources: Vec<Option<usize>> = vec![None; destination.len()];
+        if self.column_mappings.is_empty() {
+            if source_names.len() > destination.len() {
+                return Err(SqlClientError::InvalidOperation(
+                    "The given ColumnMapping does not match up with any column in the source or destination.".to_string(),
+                ));
+            }
+            for (i, source) in sources.iter_mut().take(source_names.len()).enumerate() {
+                *source = Some(i);
+            }
+        }
+        for mapping in &self.column_mappings {
+            let source = mapping
+                .source()
+                .find(source_names.iter().map(|name| name.as_str()));
+            let target = mapping
+                .destination()
+                .find(destination.iter().map(|column| column.column_name.as_str()));
+            match (source, target) {
+                (Some(source), Some(target)) => sources[target] = Some(source),
+                _ => {
+                    return Err(SqlClientError::InvalidOperation(
+                        "The given ColumnMapping does not match up with any column in the source or destination.".to_string(),
+                    ))
+                }
+            }
+        }
+        // Generated columns can't be loaded, and identity values are only kept if asked for.
+        let keep_identity = self.options.contains(SqlBulkCopyOptions::KEEP_IDENTITY);
+        Ok(destination
+            .into_iter()
+            .zip(sources)
+            .filter(|(meta_data, _)| {
+                !meta_data.is_generated() && (keep_identity || !meta_data.is_identity())
+            })
+            .filter_map(|(meta_data, source)| {
+                source.map(|source| BulkCopyColumn { source, meta_data })
+            })
+            .collect())
+    }
+
+    /// The table hints of the INSERT BULK statement.
+    fn hints(&self) -> Vec<&'static str> {
+        [
+            (SqlBulkCopyOptions::KEEP_NULLS, "KEEP_NULLS"),
+            (SqlBulkCopyOptions::TABLE_LOCK, "TABLOCK"),
+            (SqlBulkCopyOptions::CHECK_CONSTRAINTS, "CHECK_CONSTRAINTS"),
+            (SqlBulkCopyOptions::FIRE_TRIGGERS, "FIRE_TRIGGERS"),
+        ]
+        .into_iter()
+        .filter(|(option, _)| self.options.contains(*option))
+        .map(|(_, hint)| hint)
+        .collect()
+    }
+}
+
+impl Debug for SqlBulkCopy {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("SqlBulkCopy")
+            .field("destination_table_name", &self.destination_table_name)
+            .field("options", &self.options)
+            .field("batch_size", &self.batch_size)
+            .field("bulk_copy_timeout", &self.bulk_copy_timeout)
+            .field("column_mappings", &self.column_mappings)
+            .field("notify_after", &self.notify_after)
+            .finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db_connection_internal::DbConnectionInternal;
+    use crate::tds_enums::TdsEnums;
+    use crate::tds_parser::TdsParser;
+    use crate::tds_parser_state_object::decode_utf16;
+    use crate::tds_test_utils::TokenBuilder;
+    use crate::{DataTable, SqlDbType, SqlMetaData};
+    use std::sync::{Arc, Mutex};
+    use test_utils::MockStream;
+
+    /// Creates an open connection that will read the given responses.
+    fn connection(responses: &[TokenBuilder]) -> (SqlConnection, Arc<Mutex<Vec<u8>>>) {
+        let packets = responses
+            .iter()
+            .flat_map(|tokens| tokens.packets())
+            .collect();
+        let stream = MockStream::new(packets);
+        let written = stream.written();
+        let mut connection = SqlConnection::new("Server=test").unwrap();
+        connection.attach(DbConnectionInternal::new(TdsParser::new(
+            Box::new(stream),
+            TdsEnums::DEFAULT_PACKET_SIZE,
+        )));
+        (connection, written)
+    }
+
+    /// Splits the written packets into the type and payload of each message.
+    fn messages(written: &Arc<Mutex<Vec<u8>>>) -> Vec<(u8, Vec<u8>)> {
+        let written = written.lock().unwrap();
+        let mut messages = Vec::new();
+        let mut payload = Vec::new();
+        let mut position = 0;
+        while position < written.len() {
+            let length =
+                u16::from_be_bytes([written[position + 2], written[position + 3]]) as usize;
+            payload.extend_from_slice(&written[position + TdsEnums::HEADER_LEN..position + length]);
+            if written[position + 1] & TdsEnums::ST_EOM != 0 {
+                messages.push((written[position], std::mem::take(&mut payload)));
+            }
+            position += length;
+        }
+        messages
+    }
+
+    /// The text of a SQL batch message, after ALL_HEADERS.
+    fn batch_text(message: &(u8, Vec<u8>)) -> String {
+        assert_eq!(TdsEnums::MT_SQL, message.0);
+        decode_utf16(&message.1[22..]).unwrap()
+    }
+
+    /// The response to the query for the destination's columns.
+    fn destination(columns: &[(&str, SqlDbType)]) -> TokenBuilder {
+        TokenBuilder::new().col_metadata(columns).done(
+            TdsEnums::SQLDONE,
+            TdsEnums::DONE_COUNT,
+            TdsEnums::SELECT,
+            0,
+        )
+    }
+
+    /// The response to a statement.
+    fn done(row_count: u64) -> TokenBuilder {
+        TokenBuilder::new().done(TdsEnums::SQLDONE, TdsEnums::DONE_COUNT, 0, row_count)
+    }
+
+    #[tokio::test]
+    async fn test_write_to_server() {
+        let columns = [("Id", SqlDbType::Int), ("Name", SqlDbType::NVarChar)];
+        let (mut connection, written) = connection(&[destination(&columns), done(0), done(2)]);
+        let table = DataTable::from_rows(
+            vec![
+                SqlMetaData::new("Id", SqlDbType::Int),
+                SqlMetaData::new("Name", SqlDbType::NVarChar),
+            ],
+            [(1, "a"), (2, "b")],
+        )
+        .unwrap();
+        let mut bulk_copy = SqlBulkCopy::with_options(
+            "dbo.Target",
+            SqlBulkCopyOptions::TABLE_LOCK | SqlBulkCopyOptions::KEEP_NULLS,
+        );
+        assert_eq!(
+            2,
+            bulk_copy
+                .write_to_server(&mut connection, table.create_data_reader())
+                .await
+                .unwrap()
+        );
+        let messages = messages(&written);
+        assert_eq!(3, messages.len());
+        assert_eq!("SELECT TOP 0 * FROM dbo.Target", batch_text(&messages[0]));
+        assert_eq!(
+            format!(
+                "INSERT BULK dbo.Target ([Id] int, [Name] {}) WITH (KEEP_NULLS, TABLOCK)",
+                TokenBuilder::type_info(SqlDbType::NVarChar).declaration()
+            ),
+            batch_text(&messages[1])
+        );
+        let expected = TokenBuilder::new()
+            .col_metadata(&columns)
+            .row(&[SqlValue::Int(1), SqlValue::from("a")])
+            .row(&[SqlValue::Int(2), SqlValue::from("b")])
+            .done(TdsEnums::SQLDONE, 0, 0, 0);
+        assert_eq!((TdsEnums::MT_BULK, expected.bytes().to_vec()), messages[2]);
+    }
+
+    #[tokio::test]
+    async fn test_batches_in_internal_transactions() {
+        let (mut connection, written) = connection(&[
+            destination(&[("Id", SqlDbType::Int)]),
+            done(0),
+            done(0),
+            done(1),
+            done(0),
+            done(0),
+            done(0),
+            done(1),
+            done(0),
+        ]);
+        let table =
+            DataTable::from_rows(vec![SqlMetaData::new("Id", SqlDbType::Int)], [1, 2]).unwrap();
+        let mut bulk_copy =
+            SqlBulkCopy::with_options("Target", SqlBulkCopyOptions::USE_INTERNAL_TRANSACTION);
+        bulk_copy.set_batch_size(1);
+        bulk_copy.set_notify_after(1);
+        let progress = Arc::new(Mutex::new(Vec::new()));
+        let notified = progress.clone();
+        bulk_copy.on_rows_copied(move |rows| notified.lock().unwrap().push(rows));
+        assert_eq!(
+            2,
+            bulk_copy
+                .write_to_server(&mut connection, table.create_data_reader())
+                .await
+                .unwrap()
+        );
+        let statements: Vec<String> = messages(&written)
+            .iter()
+            .map(|message| match message.0 {
+                TdsEnums::MT_BULK => "(rows)".to_string(),
+                _ => batch_text(message),
+            })
+            .collect();
+        assert_eq!(
+            vec![
+                "SELECT TOP 0 * FROM Target",
+                "BEGIN TRANSACTION",
+                "INSERT BULK Target ([Id] int)",
+                "(rows)",
+                "COMMIT TRANSACTION",
+                "BEGIN TRANSACTION",
+                "INSERT BULK Target ([Id] int)",
+                "(rows)",
+                "COMMIT TRANSACTION",
+            ],
+            statements
+        );
+        assert_eq!(vec![1, 2], *progress.lock().unwrap());
+    }
+
+    #[tokio::test]
+    async fn test_copy_from_reader() {
+        let columns = [("Id", SqlDbType::Int), ("Name", SqlDbType::NVarChar)];
+        let query = TokenBuilder::new()
+            .col_metadata(&[("name", SqlDbType::NVarChar), ("id", SqlDbType::Int)])
+            .row(&[SqlValue::from("a"), SqlValue::Int(1)])
+            .done(TdsEnums::SQLDONE, TdsEnums::DONE_COUNT, TdsEnums::SELECT, 1);
+        let (mut source_connection, _) = connection(&[query]);
+        let (mut connection, written) = connection(&[destination(&columns), done(0), done(1)]);
+        let mut command = SqlCommand::new("select ...");
+        let mut reader = command
+            .execute_reader(&mut source_connection)
+            .await
+            .unwrap();
+        let mut bulk_copy = SqlBulkCopy::new("Target");
+        bulk_copy.add_column_mapping("id", "Id");
+        bulk_copy.add_column_mapping("name", "Name");
+        let copy = bulk_copy.write_to_server(&mut connection, &mut reader);
+        // The copy can be spawned on a multi-threaded runtime.
+        fn assert_send<T: Send>(value: T) -> T {
+            value
+        }
+        assert_eq!(1, assert_send(copy).await.unwrap());
+        let expected = TokenBuilder::new()
+            .col_metadata(&columns)
+            .row(&[SqlValue::Int(1), SqlValue::from("a")])
+            .done(TdsEnums::SQLDONE, 0, 0, 0);
+        assert_eq!(
+            (TdsEnums::MT_BULK, expected.bytes().to_vec()),
+            messages(&written)[2]
+        );
+    }
+
+    #[test]
+    fn test_resolve_columns() {
+        let column = |name: &str, flags: u16| ColumnMetaData {
+            user_type: 0,
+            flags,
+            type_info: TokenBuilder::type_info(SqlDbType::Int),
+            column_name: name.to_string(),
+        };
+        let destination = vec![
+            column("Id", ColumnMetaData::FLAG_IDENTITY),
+            column("Name", 0),
+            column("Total", ColumnMetaData::FLAG_COMPUTED),
+            column("Email", 0),
+        ];
+        let source_names = vec!["email".to_string(), "name".to_string(), "id".to_string()];
+        let mut bulk_copy = SqlBulkCopy::new("Target");
+        bulk_copy.add_column_mapping("id", "Id");
+        bulk_copy.add_column_mapping(1, "Name");
+        bulk_copy.add_column_mapping("EMAIL", 3);
+        bulk_copy.add_column_mapping(2, "Total");
+        let resolved = |bulk_copy: &SqlBulkCopy| {
+            bulk_copy
+                .resolve_columns(&source_names, destination.clone())
+                .unwrap()
+                .into_iter()
+                .map(|column| (column.meta_data.column_name, column.source))
+                .collect::<Vec<_>>()
+        };
+        // The identity and computed columns are skipped
+        assert_eq!(
+            vec![("Name".to_string(), 1), ("Email".to_string(), 0)],
+            resolved(&bulk_copy)
+        );
+        // ...unless identity values are kept
+        bulk_copy.set_options(SqlBulkCopyOptions::KEEP_IDENTITY);
+        assert_eq!(
+            vec![
+                ("Id".to_string(), 2),
+                ("Name".to_string(), 1),
+                ("Email".to_string(), 0)
+            ],
+            resolved(&bulk_copy)
+        );
+        // A mapping to a missing column is an error
+        bulk_copy.add_column_mapping("Phone", "Phone");
+        assert!(matches!(
+            bulk_copy.resolve_columns(&source_names, destination.clone()),
+            Err(SqlClientError::InvalidOperation(_))
+        ));
+    }
+}
@@ -0,0 +1,83 @@
+/// A column of the source or destination of a [crate::SqlBulkCopy], by name or ordinal.
+#[derive(PartialEq, Eq, Debug, Clone)]
+pub enum SqlBulkCopyColumn {
+    /// The column with this name (ignoring case).
+    Name(String),
+    /// The column at this (zero-based) position.
+    Ordinal(usize),
+}
+
+impl SqlBulkCopyColumn {
+    /// Finds the column among the given column names.
+    pub(crate) fn find<'a>(&self, mut names: impl Iterator<Item = &'a str>) -> Option<usize> {
+        match self {
+            SqlBulkCopyColumn::Name(name) => names.position(|n| n.eq_ignore_ascii_case(name)),
+            SqlBulkCopyColumn::Ordinal(ordinal) => names.nth(*ordinal).map(|_| *ordinal),
+        }
+    }
+}
+
+impl From<&str> for SqlBulkCopyColumn {
+    fn from(value: &str) -> Self {
+        SqlBulkCopyColumn::Name(value.to_string())
+    }
+}
+
+impl From<String> for SqlBulkCopyColumn {
+    fn from(value: String) -> Self {
+        SqlBulkCopyColumn::Name(value)
+    }
+}
+
+impl From<usize> for SqlBulkCopyColumn {
+    fn from(value: usize) -> Self {
+        SqlBulkCopyColumn::Ordinal(value)
+    }
+}
+
+/// Maps a column of the source of a [crate::SqlBulkCopy] to a column of the destination table.
+#[derive(PartialEq, Eq, Debug, Clone)]
+pub struct SqlBulkCopyColumnMapping {
+    /// The source column.
+    source: SqlBulkCopyColumn,
+    /// The destination column.
+    destination: SqlBulkCopyColumn,
+}
+
+impl SqlBulkCopyColumnMapping {
+    /// Creates a mapping between columns given by name or ordinal (e.g. `("Id", "CustomerId")` or
+    /// `(0, "CustomerId")`).
+    pub fn new(
+        source: impl Into<SqlBulkCopyColumn>,
+        destination: impl Into<SqlBulkCopyColumn>,
+    ) -> Self {
+        Self {
+            source: source.into(),
+            destination: destination.into(),
+        }
+    }
+
+    /// The source column.
+    pub fn source(&self) -> &SqlBulkCopyColumn {
+        &self.source
+    }
+
+    /// The destination column.
+    pub fn destination(&self) -> &SqlBulkCopyColumn {
+        &self.destination
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[rstest::rstest]
+    #[case(SqlBulkCopyColumn::from("name"), Some(1))]
+    #[case(SqlBulkCopyColumn::from("missing"), None)]
+    #[case(SqlBulkCopyColumn::from(2), Some(2))]
+    #[case(SqlBulkCopyColumn::from(3), None)]
+    fn test_find(#[case] column: SqlBulkCopyColumn, #[case] expected: Option<usize>) {
+        assert_eq!(expected, column.find(["Id", "Name", "Email"].into_iter()));
+    }
+}
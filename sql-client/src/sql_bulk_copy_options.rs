@@ -0,0 +1,26 @@
+bitflags::bitflags! {
+    /// Options for a [crate::SqlBulkCopy].
+    pub struct SqlBulkCopyOptions: u32 {
+        /// The default options.  Identity values are assigned by the destination, constraints
+        /// aren't checked, and nulls are replaced by column defaults.
+        const DEFAULT = 0x00;
+        /// Source identity values are kept rather than assigned by the destination.
+        const KEEP_IDENTITY = 0x01;
+        /// Constraints are checked as rows are inserted.
+        const CHECK_CONSTRAINTS = 0x02;
+        /// A bulk update lock is held on the table for the duration of the copy.
+        const TABLE_LOCK = 0x04;
+        /// Nulls are kept rather than replaced by column defaults.
+        const KEEP_NULLS = 0x08;
+        /// Insert triggers are fired.
+        const FIRE_TRIGGERS = 0x10;
+        /// Each batch is copied in its own transaction, which is rolled back if the batch fails.
+        const USE_INTERNAL_TRANSACTION = 0x20;
+    }
+}
+
+impl Default for SqlBulkCopyOptions {
+    fn default() -> Self {
+        SqlBulkCopyOptions::DEFAULT
+    }
+}
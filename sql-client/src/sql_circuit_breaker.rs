@@ -0,0 +1,241 @@
+use crate::retry_enumerators::SqlRetryInterval;
+use crate::{SqlCircuitBreakerOption, SqlCircuitBreakerState, SqlClientError};
+use std::fmt::{Debug, Formatter};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use tokio::time::Instant;
+
+/// Stops attempts to reach an endpoint that keeps failing.
+///
+/// The circuit opens after a number of consecutive transient failures.  While it's open, attempts
+/// fail fast.  Once a cooldown (taken from a retry interval enumerator, so that it can grow each
+/// time the circuit opens again) is over, the circuit is half-open: one trial attempt is let
+/// through, which closes the circuit if it succeeds and opens it again if it fails.
+///
+/// Clones share their state.
+#[derive(Clone)]
+pub struct SqlCircuitBreaker(Arc<Mutex<Circuit>>);
+
+/// The state of a circuit breaker.
+struct Circuit {
+    /// The endpoint the breaker protects.
+    endpoint: String,
+    /// The number of consecutive transient failures that open the circuit.
+    failure_threshold: u32,
+    /// The cooldowns to wait while the circuit is open.
+    cooldowns: Box<dyn SqlRetryInterval + Send + Sync>,
+    /// Whether the circuit is open.
+    open: bool,
+    /// When the circuit opened.
+    opened_at: Instant,
+    /// How long the circuit stays open.
+    cooldown: Duration,
+    /// Whether a trial attempt is being made while the circuit is half-open.
+    trial_in_progress: bool,
+    /// When the trial attempt started.
+    trial_started: Instant,
+    /// The number of consecutive transient failures.
+    consecutive_failures: u32,
+}
+
+impl Circuit {
+    /// The state, taking the cooldown into account.
+    fn state(&self) -> SqlCircuitBreakerState {
+        if !self.open {
+            SqlCircuitBreakerState::Closed
+        } else if self.opened_at.elapsed() < self.cooldown {
+            SqlCircuitBreakerState::Open
+        } else {
+            SqlCircuitBreakerState::HalfOpen
+        }
+    }
+}
+
+impl SqlCircuitBreaker {
+    /// Creates a closed circuit breaker for an endpoint.
+    pub fn new(endpoint: &str, option: &SqlCircuitBreakerOption) -> Result<Self, SqlClientError> {
+        if option.failure_threshold == 0 {
+            return Err(SqlClientError::ArgumentOutOfRange(
+                "failure_threshold".to_string(),
+                "failure_threshold must be at least 1".to_string(),
+            ));
+        }
+        let cooldowns = option.cooldown.build(option.cooldown_method)?;
+        Ok(Self(Arc::new(Mutex::new(Circuit {
+            endpoint: endpoint.to_string(),
+            failure_threshold: option.failure_threshold,
+            cooldowns,
+            open: false,
+            opened_at: Instant::now(),
+            cooldown: Duration::ZERO,
+            trial_in_progress: false,
+            trial_started: Instant::now(),
+            consecutive_failures: 0,
+        }))))
+    }
+
+    /// The endpoint the breaker protects.
+    pub fn endpoint(&self) -> String {
+        self.0.lock().unwrap().endpoint.clone()
+    }
+
+    /// The state of the circuit.
+    pub fn state(&self) -> SqlCircuitBreakerState {
+        self.0.lock().unwrap().state()
+    }
+
+    /// The number of consecutive transient failures.
+    pub fn consecutive_failures(&self) -> u32 {
+        self.0.lock().unwrap().consecutive_failures
+    }
+
+    /// How long until the circuit is half-open, or zero if it isn't open.
+    pub fn remaining_cooldown(&self) -> Duration {
+        let circuit = self.0.lock().unwrap();
+        match circuit.open {
+            true => circuit.cooldown.saturating_sub(circuit.opened_at.elapsed()),
+            false => Duration::ZERO,
+        }
+    }
+
+    /// Checks whether an attempt may be made, failing fast if the circuit is open (or half-open
+    /// with a trial attempt already in progress).
+    pub(crate) fn check(&self) -> Result<(), SqlClientError> {
+        let mut circuit = self.0.lock().unwrap();
+        match circuit.state() {
+            SqlCircuitBreakerState::Closed => Ok(()),
+            // Let another trial through if the last one was abandoned without a result.
+            SqlCircuitBreakerState::HalfOpen
+                if !circuit.trial_in_progress
+                    || circuit.trial_started.elapsed() >= circuit.cooldown =>
+            {
+                log::debug!("circuit for {} is half-open; trying", circuit.endpoint);
+                circuit.trial_in_progress = true;
+                circuit.trial_started = Instant::now();
+                Ok(())
+            }
+            _ => Err(SqlClientError::CircuitOpen(
+                circuit.endpoint.clone(),
+                circuit.cooldown.saturating_sub(circuit.opened_at.elapsed()),
+            )),
+        }
+    }
+
+    /// Records a successful attempt (or one that failed with an error that isn't transient, which
+    /// shows that the endpoint is reachable), closing the circuit.
+    pub(crate) fn record_success(&self) {
+        let mut circuit = self.0.lock().unwrap();
+        if circuit.open {
+            log::debug!("circuit for {} closed", circuit.endpoint);
+        }
+        circuit.open = false;
+        circuit.trial_in_progress = false;
+        circuit.consecutive_failures = 0;
+        circuit.cooldowns.reset();
+    }
+
+    /// Records an attempt that failed with a transient error, opening the circuit if there have
+    /// been enough of them or if it was the trial attempt.
+    pub(crate) fn record_failure(&self) {
+        let mut circuit = self.0.lock().unwrap();
+        circuit.consecutive_failures = circuit.consecutive_failures.saturating_add(1);
+        if circuit.trial_in_progress || circuit.consecutive_failures >= circuit.failure_threshold {
+            // Wait longer each time the circuit opens again, staying at the longest cooldown once
+            // the enumerator runs out.
+            let cooldown = match circuit.cooldowns.next() {
+                Some(cooldown) => cooldown,
+                None => circuit.cooldowns.current(),
+            };
+            log::debug!("circuit for {} opened for {:?}", circuit.endpoint, cooldown);
+            circuit.open = true;
+            circuit.opened_at = Instant::now();
+            circuit.cooldown = cooldown;
+            circuit.trial_in_progress = false;
+        }
+    }
+}
+
+impl Debug for SqlCircuitBreaker {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        let circuit = self.0.lock().unwrap();
+        f.debug_struct("SqlCircuitBreaker")
+            .field("endpoint", &circuit.endpoint)
+            .field("state", &circuit.state())
+            .field("consecutive_failures", &circuit.consecutive_failures)
+            .finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::retry_enumerators::{SqlRetryIntervalBuilder, SqlRetryMethod};
+
+    /// A breaker that opens after two failures, for 1 second and then 2 seconds.
+    fn breaker() -> SqlCircuitBreaker {
+        SqlCircuitBreaker::new(
+            "test",
+            &SqlCircuitBreakerOption {
+                failure_threshold: 2,
+                cooldown_method: SqlRetryMethod::Incremental,
+                cooldown: SqlRetryIntervalBuilder::new(Duration::from_secs(1))
+                    .max_time_interval(Duration::from_secs(2))
+                    .randomness(0.0),
+            },
+        )
+        .unwrap()
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_open_and_close() {
+        let breaker = breaker();
+        // One failure isn't enough to open the circuit.
+        breaker.check().unwrap();
+        breaker.record_failure();
+        assert_eq!(SqlCircuitBreakerState::Closed, breaker.state());
+        breaker.check().unwrap();
+        breaker.record_failure();
+        assert_eq!(SqlCircuitBreakerState::Open, breaker.state());
+        assert_eq!(Duration::from_secs(1), breaker.remaining_cooldown());
+        // Attempts fail fast while the circuit is open.
+        assert!(matches!(
+            breaker.check(),
+            Err(SqlClientError::CircuitOpen(_, _))
+        ));
+        // After the cooldown, one trial attempt is let through.
+        tokio::time::advance(Duration::from_secs(1)).await;
+        assert_eq!(SqlCircuitBreakerState::HalfOpen, breaker.state());
+        breaker.check().unwrap();
+        assert!(breaker.check().is_err());
+        // A failed trial opens the circuit again, for longer.
+        breaker.record_failure();
+        assert_eq!(SqlCircuitBreakerState::Open, breaker.state());
+        assert_eq!(Duration::from_secs(2), breaker.remaining_cooldown());
+        tokio::time::advance(Duration::from_secs(2)).await;
+        breaker.check().unwrap();
+        // A successful trial closes it.
+        breaker.record_success();
+        assert_eq!(SqlCircuitBreakerState::Closed, breaker.state());
+        assert_eq!(0, breaker.consecutive_failures());
+        breaker.check().unwrap();
+    }
+
+    #[test]
+    fn test_success_resets_failures() {
+        let breaker = breaker();
+        breaker.record_failure();
+        breaker.record_success();
+        breaker.record_failure();
+        assert_eq!(SqlCircuitBreakerState::Closed, breaker.state());
+        assert_eq!(1, breaker.consecutive_failures());
+    }
+
+    #[test]
+    fn test_invalid_threshold() {
+        let option = SqlCircuitBreakerOption {
+            failure_threshold: 0,
+            ..Default::default()
+        };
+        assert!(SqlCircuitBreaker::new("test", &option).is_err());
+    }
+}
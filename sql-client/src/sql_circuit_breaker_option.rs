@@ -0,0 +1,25 @@
+use crate::retry_enumerators::{SqlRetryIntervalBuilder, SqlRetryMethod};
+use std::time::Duration;
+
+/// The settings of the circuit breakers of a [crate::SqlRetryLogicProvider].
+#[derive(Debug, Clone)]
+pub struct SqlCircuitBreakerOption {
+    /// The number of consecutive transient failures that open the circuit.
+    pub failure_threshold: u32,
+    /// How the cooldowns grow each time the circuit opens again without having closed.
+    pub cooldown_method: SqlRetryMethod,
+    /// Builds the cooldowns to wait while the circuit is open.
+    pub cooldown: SqlRetryIntervalBuilder,
+}
+
+impl Default for SqlCircuitBreakerOption {
+    fn default() -> Self {
+        Self {
+            failure_threshold: 5,
+            cooldown_method: SqlRetryMethod::Exponential,
+            cooldown: SqlRetryIntervalBuilder::new(Duration::from_secs(5))
+                .min_time_interval(Duration::from_secs(5))
+                .max_time_interval(Duration::from_secs(60)),
+        }
+    }
+}
@@ -0,0 +1,23 @@
+use std::fmt::{Display, Formatter};
+
+/// The state of a [crate::SqlCircuitBreaker].
+#[derive(PartialEq, Debug, Clone, Copy)]
+pub enum SqlCircuitBreakerState {
+    // Attempts are made as normal.
+    Closed = 0,
+    // Attempts fail fast until the cooldown is over.
+    Open = 1,
+    // The cooldown is over and a trial attempt may be made, which closes the circuit if it
+    // succeeds and opens it again if it fails.
+    HalfOpen = 2,
+}
+
+impl Display for SqlCircuitBreakerState {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            SqlCircuitBreakerState::Closed => write!(f, "Closed"),
+            SqlCircuitBreakerState::Open => write!(f, "Open"),
+            SqlCircuitBreakerState::HalfOpen => write!(f, "HalfOpen"),
+        }
+    }
+}
@@ -1,5 +1,7 @@
+use crate::{SqlException, SqlRetryException, SqlTransientErrors};
+
 /// The SqlClient Error type.
-#[derive(Debug, thiserror::Error)]
+#[derive(Debug, Clone, thiserror::Error)]
 #[non_exhaustive]
 pub enum SqlClientError {
     /// A connection string value was not in form "Name=Value".
@@ -13,11 +15,89 @@ pub enum SqlClientError {
     UnsupportedKeyword(String),
     /// An argument was null.
     #[error("A value was not supplied for the argument {0}")]
-    ArgumentNull(String,String),
+    ArgumentNull(String, String),
     /// An argument was null.
     #[error("A value for argument {0} is out of range: {1}")]
-    ArgumentOutOfRange(String,String),
+    ArgumentOutOfRange(String, String),
     /// The length of an argument was valid (i.e. a string was too long).
     #[error("The value '{1}' supplied for argument {0} was greater than length {2}")]
     InvalidArgumentLength(String, String, usize),
+    /// The operation is not valid in the current state (e.g. executing a command on a closed connection).
+    #[error("{0}")]
+    InvalidOperation(String),
+    /// Reading from or writing to the server failed, with the kind of failure.
+    #[error("An I/O error occurred: {1}")]
+    Io(std::io::ErrorKind, String),
+    /// The server sent data that could not be understood.
+    #[error("A protocol error occurred: {0}")]
+    Protocol(String),
+    /// A typed getter was called on a column holding a null.
+    #[error("Data is Null. This method cannot be called on Null values (column {0}).")]
+    NullValue(String),
+    /// The operation or type is not supported by this client.
+    #[error("Not supported: {0}")]
+    NotSupported(String),
+    /// A value was too large or too precise for its type.
+    #[error("Arithmetic overflow: {0}")]
+    Overflow(String),
+    /// An operation didn't complete in the time allowed.
+    #[error("Timeout expired: {0}")]
+    Timeout(String),
+    /// A bulk copy source couldn't read a row.
+    #[error("The bulk copy source failed: {0}")]
+    BulkCopySource(String),
+    /// The server returned one or more errors.
+    #[error("Server error {}: {}", .0.number(), .0.message())]
+    Server(SqlException),
+    /// An operation failed after being retried.
+    #[error("{0}")]
+    Retry(SqlRetryException),
+    /// An endpoint's circuit breaker is open, so attempts to reach it fail fast.
+    #[error("The circuit breaker for '{0}' is open for another {1:?}")]
+    CircuitOpen(String, std::time::Duration),
+}
+
+impl SqlClientError {
+    /// Whether the failure is transient, so that retrying may succeed: a transient server error
+    /// (see [SqlTransientErrors]), a timeout, or a failure to reach the server.
+    pub fn is_transient(&self) -> bool {
+        self.is_transient_with(&SqlTransientErrors::contains)
+    }
+
+    /// Whether the failure is transient, given which server error numbers are (including
+    /// [SqlTransientErrors::TIMEOUT] for a timeout).  Failures to reach the server always are, but
+    /// other I/O failures (e.g. a TLS handshake rejecting the server's certificate) aren't.
+    pub(crate) fn is_transient_with(&self, is_transient_number: &dyn Fn(i32) -> bool) -> bool {
+        match self {
+            SqlClientError::Server(exception) => exception
+                .errors()
+                .iter()
+                .any(|error| is_transient_number(error.number())),
+            SqlClientError::Retry(exception) => exception
+                .last_error()
+                .is_transient_with(is_transient_number),
+            SqlClientError::Timeout(_) => is_transient_number(SqlTransientErrors::TIMEOUT),
+            SqlClientError::Io(kind, _) => matches!(
+                kind,
+                std::io::ErrorKind::ConnectionRefused
+                    | std::io::ErrorKind::ConnectionReset
+                    | std::io::ErrorKind::ConnectionAborted
+                    | std::io::ErrorKind::TimedOut
+                    | std::io::ErrorKind::BrokenPipe
+            ),
+            _ => false,
+        }
+    }
+}
+
+impl From<std::io::Error> for SqlClientError {
+    fn from(error: std::io::Error) -> Self {
+        SqlClientError::Io(error.kind(), error.to_string())
+    }
+}
+
+impl From<std::convert::Infallible> for SqlClientError {
+    fn from(error: std::convert::Infallible) -> Self {
+        match error {}
+    }
 }
@@ -0,0 +1,441 @@
+use encoding_rs::{CoderResult, Decoder, EncoderResult, Encoding};
+
+/// The characters 0x80-0xFF of code page 437 (OEM United States), which encoding_rs doesn't support.
+const OEM_437_HIGH: [char; 128] = [
+    '\u{00C7}', '\u{00FC}', '\u{00E9}', '\u{00E2}', '\u{00E4}', '\u{00E0}', '\u{00E5}', '\u{00E7}',
+    '\u{00EA}', '\u{00EB}', '\u{00E8}', '\u{00EF}', '\u{00EE}', '\u{00EC}', '\u{00C4}', '\u{00C5}',
+    '\u{00C9}', '\u{00E6}', '\u{00C6}', '\u{00F4}', '\u{00F6}', '\u{00F2}', '\u{00FB}', '\u{00F9}',
+    '\u{00FF}', '\u{00D6}', '\u{00DC}', '\u{00A2}', '\u{00A3}', '\u{00A5}', '\u{20A7}', '\u{0192}',
+    '\u{00E1}', '\u{00ED}', '\u{00F3}', '\u{00FA}', '\u{00F1}', '\u{00D1}', '\u{00AA}', '\u{00BA}',
+    '\u{00BF}', '\u{2310}', '\u{00AC}', '\u{00BD}', '\u{00BC}', '\u{00A1}', '\u{00AB}', '\u{00BB}',
+    '\u{2591}', '\u{2592}', '\u{2593}', '\u{2502}', '\u{2524}', '\u{2561}', '\u{2562}', '\u{2556}',
+    '\u{2555}', '\u{2563}', '\u{2551}', '\u{2557}', '\u{255D}', '\u{255C}', '\u{255B}', '\u{2510}',
+    '\u{2514}', '\u{2534}', '\u{252C}', '\u{251C}', '\u{2500}', '\u{253C}', '\u{255E}', '\u{255F}',
+    '\u{255A}', '\u{2554}', '\u{2569}', '\u{2566}', '\u{2560}', '\u{2550}', '\u{256C}', '\u{2567}',
+    '\u{2568}', '\u{2564}', '\u{2565}', '\u{2559}', '\u{2558}', '\u{2552}', '\u{2553}', '\u{256B}',
+    '\u{256A}', '\u{2518}', '\u{250C}', '\u{2588}', '\u{2584}', '\u{258C}', '\u{2590}', '\u{2580}',
+    '\u{03B1}', '\u{00DF}', '\u{0393}', '\u{03C0}', '\u{03A3}', '\u{03C3}', '\u{00B5}', '\u{03C4}',
+    '\u{03A6}', '\u{0398}', '\u{03A9}', '\u{03B4}', '\u{221E}', '\u{03C6}', '\u{03B5}', '\u{2229}',
+    '\u{2261}', '\u{00B1}', '\u{2265}', '\u{2264}', '\u{2320}', '\u{2321}', '\u{00F7}', '\u{2248}',
+    '\u{00B0}', '\u{2219}', '\u{00B7}', '\u{221A}', '\u{207F}', '\u{00B2}', '\u{25A0}', '\u{00A0}',
+];
+
+/// The characters 0x80-0xFF of code page 850 (OEM Multilingual Latin 1), which encoding_rs doesn't support.
+const OEM_850_HIGH: [char; 128] = [
+    '\u{00C7}', '\u{00FC}', '\u{00E9}', '\u{00E2}', '\u{00E4}', '\u{00E0}', '\u{00E5}', '\u{00E7}',
+    '\u{00EA}', '\u{00EB}', '\u{00E8}', '\u{00EF}', '\u{00EE}', '\u{00EC}', '\u{00C4}', '\u{00C5}',
+    '\u{00C9}', '\u{00E6}', '\u{00C6}', '\u{00F4}', '\u{00F6}', '\u{00F2}', '\u{00FB}', '\u{00F9}',
+    '\u{00FF}', '\u{00D6}', '\u{00DC}', '\u{00F8}', '\u{00A3}', '\u{00D8}', '\u{00D7}', '\u{0192}',
+    '\u{00E1}', '\u{00ED}', '\u{00F3}', '\u{00FA}', '\u{00F1}', '\u{00D1}', '\u{00AA}', '\u{00BA}',
+    '\u{00BF}', '\u{00AE}', '\u{00AC}', '\u{00BD}', '\u{00BC}', '\u{00A1}', '\u{00AB}', '\u{00BB}',
+    '\u{2591}', '\u{2592}', '\u{2593}', '\u{2502}', '\u{2524}', '\u{00C1}', '\u{00C2}', '\u{00C0}',
+    '\u{00A9}', '\u{2563}', '\u{2551}', '\u{2557}', '\u{255D}', '\u{00A2}', '\u{00A5}', '\u{2510}',
+    '\u{2514}', '\u{2534}', '\u{252C}', '\u{251C}', '\u{2500}', '\u{253C}', '\u{00E3}', '\u{00C3}',
+    '\u{255A}', '\u{2554}', '\u{2569}', '\u{2566}', '\u{2560}', '\u{2550}', '\u{256C}', '\u{00A4}',
+    '\u{00F0}', '\u{00D0}', '\u{00CA}', '\u{00CB}', '\u{00C8}', '\u{0131}', '\u{00CD}', '\u{00CE}',
+    '\u{00CF}', '\u{2518}', '\u{250C}', '\u{2588}', '\u{2584}', '\u{00A6}', '\u{00CC}', '\u{2580}',
+    '\u{00D3}', '\u{00DF}', '\u{00D4}', '\u{00D2}', '\u{00F5}', '\u{00D5}', '\u{00B5}', '\u{00FE}',
+    '\u{00DE}', '\u{00DA}', '\u{00DB}', '\u{00D9}', '\u{00FD}', '\u{00DD}', '\u{00AF}', '\u{00B4}',
+    '\u{00AD}', '\u{00B1}', '\u{2017}', '\u{00BE}', '\u{00B6}', '\u{00A7}', '\u{00F7}', '\u{00B8}',
+    '\u{00B0}', '\u{00A8}', '\u{00B7}', '\u{00B9}', '\u{00B3}', '\u{00B2}', '\u{25A0}', '\u{00A0}',
+];
+
+/// How non-Unicode data in a code page is encoded.
+#[derive(Debug, Clone, Copy)]
+enum TextEncoding {
+    /// A code page supported by encoding_rs.
+    Standard(&'static Encoding),
+    /// A single-byte OEM code page, given the characters of its upper half.
+    Oem(&'static [char; 128]),
+}
+
+/// A SQL Server collation, as sent on the wire.
+#[derive(PartialEq, Eq, Debug, Clone, Copy, Default)]
+pub(crate) struct SqlCollation {
+    /// The locale ID (bits 0-19), comparison flags (bits 20-27) and version (bits 28-31).
+    info: u32,
+    /// The SQL sort ID.  Non-zero for SQL collations (e.g. SQL_Latin1_General_CP1_CI_AS).
+    sort_id: u8,
+}
+
+impl SqlCollation {
+    /// The size of a collation on the wire.
+    pub const SIZE: usize = 5;
+    /// The mask of the locale ID bits.
+    const MASK_LCID: u32 = 0x000F_FFFF;
+    /// The flag set for UTF-8 collations (e.g. Latin1_General_100_CI_AS_SC_UTF8).
+    const FLAG_UTF8: u32 = 0x0400_0000;
+    /// The code page of UTF-8 data.
+    pub const CODE_PAGE_UTF8: u16 = 65001;
+    /// The code page used when a collation's code page is unknown.
+    const CODE_PAGE_DEFAULT: u16 = 1252;
+
+    /// Creates a collation from its parts.
+    pub fn new(info: u32, sort_id: u8) -> Self {
+        Self { info, sort_id }
+    }
+
+    /// Reads a collation from its wire format.
+    pub fn from_bytes(bytes: [u8; Self::SIZE]) -> Self {
+        Self {
+            info: u32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]),
+            sort_id: bytes[4],
+        }
+    }
+
+    /// Writes the collation in its wire format.
+    pub fn to_bytes(self) -> [u8; Self::SIZE] {
+        let info = self.info.to_le_bytes();
+        [info[0], info[1], info[2], info[3], self.sort_id]
+    }
+
+    /// The collation info.
+    pub fn info(&self) -> u32 {
+        self.info
+    }
+
+    /// The locale ID.
+    pub fn lcid(&self) -> u32 {
+        self.info & Self::MASK_LCID
+    }
+
+    /// The SQL sort ID.
+    pub fn sort_id(&self) -> u8 {
+        self.sort_id
+    }
+
+    /// Whether non-Unicode data in this collation is UTF-8.
+    pub fn is_utf8(&self) -> bool {
+        self.info & Self::FLAG_UTF8 != 0
+    }
+
+    /// The collation with the UTF-8 flag cleared.  Servers send the data of UTF-8 collations in
+    /// the locale's code page to clients that haven't negotiated UTF8_SUPPORT.
+    pub fn without_utf8(self) -> Self {
+        Self {
+            info: self.info & !Self::FLAG_UTF8,
+            sort_id: self.sort_id,
+        }
+    }
+
+    /// The Windows code page of non-Unicode data in this collation.
+    pub fn code_page(&self) -> u16 {
+        if self.is_utf8() {
+            return Self::CODE_PAGE_UTF8;
+        }
+        // SQL collations are identified by their sort ID, Windows collations by their locale.
+        let code_page = if self.sort_id != 0 {
+            code_page_from_sort_id(self.sort_id)
+        } else {
+            code_page_from_lcid(self.lcid())
+        };
+        code_page.unwrap_or_else(|| {
+            log::warn!(
+                "Unknown code page for collation (LCID 0x{:05X}, sort ID {}), using {}",
+                self.lcid(),
+                self.sort_id,
+                Self::CODE_PAGE_DEFAULT
+            );
+            Self::CODE_PAGE_DEFAULT
+        })
+    }
+
+    /// The encoding of non-Unicode data in this collation.
+    fn encoding(&self) -> TextEncoding {
+        match self.code_page() {
+            437 => TextEncoding::Oem(&OEM_437_HIGH),
+            850 => TextEncoding::Oem(&OEM_850_HIGH),
+            874 => TextEncoding::Standard(encoding_rs::WINDOWS_874),
+            932 => TextEncoding::Standard(encoding_rs::SHIFT_JIS),
+            936 => TextEncoding::Standard(encoding_rs::GBK),
+            949 => TextEncoding::Standard(encoding_rs::EUC_KR),
+            950 => TextEncoding::Standard(encoding_rs::BIG5),
+            1250 => TextEncoding::Standard(encoding_rs::WINDOWS_1250),
+            1251 => TextEncoding::Standard(encoding_rs::WINDOWS_1251),
+            1253 => TextEncoding::Standard(encoding_rs::WINDOWS_1253),
+            1254 => TextEncoding::Standard(encoding_rs::WINDOWS_1254),
+            1255 => TextEncoding::Standard(encoding_rs::WINDOWS_1255),
+            1256 => TextEncoding::Standard(encoding_rs::WINDOWS_1256),
+            1257 => TextEncoding::Standard(encoding_rs::WINDOWS_1257),
+            1258 => TextEncoding::Standard(encoding_rs::WINDOWS_1258),
+            Self::CODE_PAGE_UTF8 => TextEncoding::Standard(encoding_rs::UTF_8),
+            _ => TextEncoding::Standard(encoding_rs::WINDOWS_1252),
+        }
+    }
+
+    /// Decodes non-Unicode data in this collation.  Invalid bytes are replaced.
+    pub fn decode(&self, bytes: &[u8]) -> String {
+        match self.encoding() {
+            TextEncoding::Standard(encoding) => {
+                let (text, _) = encoding.decode_without_bom_handling(bytes);
+                text.into_owned()
+            }
+            TextEncoding::Oem(high) => bytes.iter().map(|&b| decode_oem(high, b)).collect(),
+        }
+    }
+
+    /// Creates a decoder for non-Unicode data in this collation that arrives in pieces.
+    pub fn decoder(&self) -> TextDecoder {
+        match self.encoding() {
+            TextEncoding::Standard(encoding) => {
+                TextDecoder::Standard(encoding.new_decoder_without_bom_handling())
+            }
+            TextEncoding::Oem(high) => TextDecoder::Oem(high),
+        }
+    }
+
+    /// Encodes text as non-Unicode data in this collation.  As in .NET, characters that the code
+    /// page can't represent are replaced with '?'.
+    pub fn encode(&self, text: &str) -> Vec<u8> {
+        let encoding = match self.encoding() {
+            TextEncoding::Standard(encoding) => encoding,
+            TextEncoding::Oem(high) => {
+                return text
+                    .chars()
+                    .map(|c| match c {
+                        c if c.is_ascii() => c as u8,
+                        c => high
+                            .iter()
+                            .position(|&h| h == c)
+                            .map_or(b'?', |i| 0x80 + i as u8),
+                    })
+                    .collect()
+            }
+        };
+        let mut encoder = encoding.new_encoder();
+        let mut bytes = Vec::with_capacity(text.len());
+        let mut remaining = text;
+        loop {
+            // Make sure there's room for whatever's left
+            let needed = encoder
+                .max_buffer_length_from_utf8_without_replacement(remaining.len())
+                .unwrap_or(remaining.len() * 4);
+            bytes.reserve(needed);
+            let (result, read) =
+                encoder.encode_from_utf8_to_vec_without_replacement(remaining, &mut bytes, true);
+            remaining = &remaining[read..];
+            match result {
+                EncoderResult::InputEmpty => break,
+                EncoderResult::OutputFull => {}
+                EncoderResult::Unmappable(_) => bytes.push(b'?'),
+            }
+        }
+        bytes
+    }
+}
+
+/// Decodes a byte of a single-byte OEM code page.
+fn decode_oem(high: &[char; 128], b: u8) -> char {
+    if b < 0x80 {
+        b as char
+    } else {
+        high[b as usize - 0x80]
+    }
+}
+
+/// Decodes text that arrives in pieces (e.g. the chunks of a large value), holding on to any
+/// character that's split between pieces until the rest of it arrives.
+pub(crate) enum TextDecoder {
+    /// Text in an encoding supported by encoding_rs.
+    Standard(Decoder),
+    /// Text in a single-byte OEM code page.
+    Oem(&'static [char; 128]),
+}
+
+impl TextDecoder {
+    /// A decoder for nchar, nvarchar and ntext data (UTF-16).
+    pub fn utf16() -> Self {
+        TextDecoder::Standard(encoding_rs::UTF_16LE.new_decoder_without_bom_handling())
+    }
+
+    /// A decoder for xml data (UTF-16, which may start with a byte order mark).
+    pub fn xml() -> Self {
+        TextDecoder::Standard(encoding_rs::UTF_16LE.new_decoder_with_bom_removal())
+    }
+
+    /// Decodes the next piece of data onto the end of `text`.  `last` is set for the final piece.
+    /// Invalid data is replaced.
+    pub fn decode(&mut self, bytes: &[u8], last: bool, text: &mut String) {
+        match self {
+            TextDecoder::Standard(decoder) => {
+                let mut remaining = bytes;
+                loop {
+                    // Make sure there's room for whatever's left
+                    let needed = decoder
+                        .max_utf8_buffer_length(remaining.len())
+                        .unwrap_or(remaining.len() * 3 + 4);
+                    text.reserve(needed);
+                    let (result, read, _) = decoder.decode_to_string(remaining, text, last);
+                    remaining = &remaining[read..];
+                    if result == CoderResult::InputEmpty {
+                        break;
+                    }
+                }
+            }
+            TextDecoder::Oem(high) => text.extend(bytes.iter().map(|&b| decode_oem(high, b))),
+        }
+    }
+}
+
+/// The code page of a SQL collation's sort order.
+fn code_page_from_sort_id(sort_id: u8) -> Option<u16> {
+    match sort_id {
+        30..=34 => Some(437),
+        40..=44 | 49 | 55..=61 => Some(850),
+        50..=54 | 71..=75 | 183..=186 | 210..=217 => Some(1252),
+        80..=98 => Some(1250),
+        104..=108 => Some(1251),
+        112..=114 | 120..=124 => Some(1253),
+        128..=130 => Some(1254),
+        136..=138 => Some(1255),
+        144..=146 => Some(1256),
+        152..=160 => Some(1257),
+        192 | 200 => Some(932),
+        193 | 201 => Some(949),
+        194 | 202 => Some(950),
+        195 | 203 => Some(936),
+        204..=206 => Some(874),
+        _ => None,
+    }
+}
+
+/// The ANSI code page of a Windows collation's locale.
+fn code_page_from_lcid(lcid: u32) -> Option<u16> {
+    // Locales whose code page depends on the script or region
+    match lcid {
+        // Chinese (Taiwan, Hong Kong, Macau) use traditional characters
+        0x0404 | 0x0C04 | 0x1404 | 0x7C04 => return Some(950),
+        // Serbian and Bosnian (Cyrillic)
+        0x0C1A | 0x1C1A | 0x201A => return Some(1251),
+        // Azeri and Uzbek (Cyrillic)
+        0x082C | 0x0843 => return Some(1251),
+        _ => {}
+    }
+    // Otherwise it's determined by the primary language
+    match lcid & 0x3FF {
+        // Arabic, Persian, Urdu
+        0x01 | 0x20 | 0x29 => Some(1256),
+        // Bulgarian, Russian, Ukrainian, Belarusian, Macedonian, Kazakh, Kyrgyz, Tatar, Mongolian
+        0x02 | 0x19 | 0x22 | 0x23 | 0x2F | 0x3F | 0x40 | 0x44 | 0x50 => Some(1251),
+        // Chinese (PRC, Singapore)
+        0x04 => Some(936),
+        // Czech, Hungarian, Polish, Romanian, Croatian, Slovak, Albanian, Slovenian, Bosnian, Serbian (Latin)
+        0x05 | 0x0E | 0x15 | 0x18 | 0x1A | 0x1B | 0x1C | 0x24 => Some(1250),
+        // Greek
+        0x08 => Some(1253),
+        // Hebrew
+        0x0D => Some(1255),
+        // Japanese
+        0x11 => Some(932),
+        // Korean
+        0x12 => Some(949),
+        // Thai
+        0x1E => Some(874),
+        // Turkish, Azeri (Latin), Uzbek (Latin)
+        0x1F | 0x2C | 0x43 => Some(1254),
+        // Estonian, Latvian, Lithuanian
+        0x25..=0x27 => Some(1257),
+        // Vietnamese
+        0x2A => Some(1258),
+        // Western European languages
+        0x03 | 0x06 | 0x07 | 0x09 | 0x0A | 0x0B | 0x0C | 0x0F | 0x10 | 0x13 | 0x14 | 0x16
+        | 0x1D | 0x21 | 0x2D | 0x36 | 0x38 | 0x3E | 0x41 | 0x56 => Some(1252),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_round_trip() {
+        // Latin1_General_CI_AS
+        let bytes = [0x09, 0x04, 0xD0, 0x00, 0x34];
+        let collation = SqlCollation::from_bytes(bytes);
+        assert_eq!(0x0409, collation.lcid());
+        assert_eq!(0x34, collation.sort_id());
+        assert_eq!(bytes, collation.to_bytes());
+    }
+
+    #[rstest::rstest]
+    // Latin1_General_CI_AS
+    #[case(SqlCollation::new(0x00D0_0409, 0), 1252)]
+    // SQL_Latin1_General_CP1_CI_AS
+    #[case(SqlCollation::new(0x00D0_0409, 52), 1252)]
+    // SQL_Latin1_General_CP437_BIN
+    #[case(SqlCollation::new(0x0000_0409, 30), 437)]
+    // SQL_Latin1_General_CP850_CI_AS
+    #[case(SqlCollation::new(0x00D0_0409, 42), 850)]
+    // Cyrillic_General_CI_AS
+    #[case(SqlCollation::new(0x00D0_0419, 0), 1251)]
+    // Serbian_Cyrillic_100_CI_AS
+    #[case(SqlCollation::new(0x20D0_0C1A, 0), 1251)]
+    // Serbian_Latin_100_CI_AS
+    #[case(SqlCollation::new(0x20D0_081A, 0), 1250)]
+    // Chinese_PRC_CI_AS
+    #[case(SqlCollation::new(0x00D0_0804, 0), 936)]
+    // Chinese_Hong_Kong_Stroke_90_CI_AS
+    #[case(SqlCollation::new(0x10D0_0C04, 0), 950)]
+    // Japanese_CI_AS
+    #[case(SqlCollation::new(0x00D0_0411, 0), 932)]
+    // Thai_CI_AS
+    #[case(SqlCollation::new(0x00D0_041E, 0), 874)]
+    // Latin1_General_100_CI_AS_SC_UTF8
+    #[case(SqlCollation::new(0x04E0_0409, 0), 65001)]
+    fn test_code_page(#[case] collation: SqlCollation, #[case] expected: u16) {
+        assert_eq!(expected, collation.code_page());
+    }
+
+    #[test]
+    fn test_without_utf8() {
+        let collation = SqlCollation::new(0x04E0_0419, 0).without_utf8();
+        assert!(!collation.is_utf8());
+        assert_eq!(1251, collation.code_page());
+    }
+
+    #[rstest::rstest]
+    #[case(SqlCollation::new(0x00D0_0409, 0x34), "café", "636166E9", "café")]
+    #[case(SqlCollation::new(0x00D0_0409, 0x34), "日本", "3F3F", "??")]
+    #[case(SqlCollation::new(0x04D0_0409, 0), "日本", "E697A5E69CAC", "日本")]
+    // Cyrillic_General_CI_AS
+    #[case(SqlCollation::new(0x00D0_0419, 0), "Привет", "CFF0E8E2E5F2", "Привет")]
+    // Japanese_CI_AS
+    #[case(SqlCollation::new(0x00D0_0411, 0), "日本", "93FA967B", "日本")]
+    // Chinese_Taiwan_Stroke_CI_AS
+    #[case(SqlCollation::new(0x00D0_0404, 0), "日本", "A4E9A5BB", "日本")]
+    // Korean_Wansung_CI_AS
+    #[case(SqlCollation::new(0x00D0_0412, 0), "한국", "C7D1B1B9", "한국")]
+    // SQL_Latin1_General_CP437_CI_AS
+    #[case(SqlCollation::new(0x00D0_0409, 30), "Ç░é", "80B082", "Ç░é")]
+    // SQL_Latin1_General_CP850_CI_AS
+    #[case(SqlCollation::new(0x00D0_0409, 42), "ø×日", "9B9E3F", "ø×?")]
+    // SQL_Polish_CP1250_CI_AS
+    #[case(SqlCollation::new(0x00D0_0415, 82), "ĄŁ", "A5A3", "ĄŁ")]
+    fn test_encode_decode(
+        #[case] collation: SqlCollation,
+        #[case] text: &str,
+        #[case] expected_bytes: &str,
+        #[case] expected_text: &str,
+    ) {
+        let bytes = collation.encode(text);
+        assert_eq!(expected_bytes, hex::encode_upper(&bytes));
+        assert_eq!(expected_text, collation.decode(&bytes));
+    }
+
+    #[rstest::rstest]
+    // A Japanese character split between pieces
+    #[case(SqlCollation::new(0x00D0_0411, 0).decoder(), "93FA967B41", "日本A")]
+    #[case(SqlCollation::new(0x00D0_0409, 30).decoder(), "80B082", "Ç░é")]
+    #[case(TextDecoder::utf16(), "FFFE41003DD800DE", "\u{FEFF}A😀")]
+    #[case(TextDecoder::xml(), "FFFE41003DD800DE", "A😀")]
+    fn test_decoder(#[case] mut decoder: TextDecoder, #[case] bytes: &str, #[case] expected: &str) {
+        let bytes = hex::decode(bytes).unwrap();
+        let mut text = String::new();
+        for piece in bytes.chunks(1) {
+            decoder.decode(piece, false, &mut text);
+        }
+        decoder.decode(&[], true, &mut text);
+        assert_eq!(expected, text);
+    }
+}
@@ -0,0 +1,956 @@
+use crate::sql_error::SqlError;
+use crate::sql_retry_logic_provider::RetryState;
+use crate::tds_enums::TdsEnums;
+use crate::tds_parser::{RpcParameter, RpcProcedure, TdsParser};
+use crate::tds_token::{ReturnValueToken, TdsToken};
+use crate::tds_type_info::TypeInfo;
+use crate::{
+    CommandBehavior, CommandType, ParameterDirection, SqlClientError, SqlConnection, SqlDataReader,
+    SqlDbType, SqlException, SqlParameterCollection, SqlRetryLogicProvider, SqlValue,
+};
+use std::future::Future;
+use std::time::Duration;
+use uuid::Uuid;
+
+/// A T-SQL statement or stored procedure to execute against a SQL Server database.
+#[derive(PartialEq, Debug, Clone, Default)]
+pub struct SqlCommand {
+    /// The T-SQL statement, or the name of the stored procedure.
+    command_text: String,
+    /// How the command text is interpreted.
+    command_type: CommandType,
+    /// The parameters.
+    parameters: SqlParameterCollection,
+    /// Retries executing the command, if set.
+    retry_logic_provider: Option<SqlRetryLogicProvider>,
+    /// How many seconds the command (including its retries) may take, or None to use the
+    /// connection's Command Timeout.
+    command_timeout: Option<u16>,
+}
+
+impl SqlCommand {
+    /// Creates a new command with the given text.
+    pub fn new(command_text: &str) -> Self {
+        Self {
+            command_text: command_text.to_string(),
+            ..Default::default()
+        }
+    }
+
+    /// Creates a new command that calls a stored procedure.
+    pub fn new_stored_procedure(procedure_name: &str) -> Self {
+        Self {
+            command_text: procedure_name.to_string(),
+            command_type: CommandType::StoredProcedure,
+            ..Default::default()
+        }
+    }
+
+    /// The T-SQL statement, or the name of the stored procedure.
+    pub fn command_text(&self) -> &str {
+        &self.command_text
+    }
+    /// Sets the T-SQL statement, or the name of the stored procedure.
+    pub fn set_command_text(&mut self, value: &str) {
+        self.command_text = value.to_string();
+    }
+
+    /// How the command text is interpreted.
+    pub fn command_type(&self) -> CommandType {
+        self.command_type
+    }
+    /// Sets how the command text is interpreted.
+    pub fn set_command_type(&mut self, value: CommandType) {
+        self.command_type = value;
+    }
+
+    /// The parameters.
+    pub fn parameters(&self) -> &SqlParameterCollection {
+        &self.parameters
+    }
+    /// The parameters, for modification.
+    pub fn parameters_mut(&mut self) -> &mut SqlParameterCollection {
+        &mut self.parameters
+    }
+
+    /// The provider that retries executing the command, if any.  If not set, the connection's
+    /// provider is used.
+    pub fn retry_logic_provider(&self) -> Option<&SqlRetryLogicProvider> {
+        self.retry_logic_provider.as_ref()
+    }
+    /// Sets the provider that retries executing the command, or None to use the connection's.
+    pub fn set_retry_logic_provider(&mut self, value: Option<SqlRetryLogicProvider>) {
+        self.retry_logic_provider = value;
+    }
+
+    /// How many seconds the command (including its retries) may take (zero for no limit), or None
+    /// to use the connection's Command Timeout.
+    pub fn command_timeout(&self) -> Option<u16> {
+        self.command_timeout
+    }
+    /// Sets how many seconds the command (including its retries) may take (zero for no limit), or
+    /// None to use the connection's Command Timeout.
+    pub fn set_command_timeout(&mut self, value: Option<u16>) {
+        self.command_timeout = value;
+    }
+
+    /// Starts retrying the command with its retry logic provider (or else the connection's),
+    /// within its command timeout.  Commands with stream parameters aren't retried.
+    fn retry_state(&self, connection: &SqlConnection) -> RetryState {
+        let command_timeout = self.timeout_seconds(connection);
+        // A stream parameter is used up by the first attempt, so the command can't be retried.
+        let retry_logic_provider = match self.parameters.iter().any(|p| p.stream().is_some()) {
+            true => None,
+            false => self
+                .retry_logic_provider
+                .as_ref()
+                .or(connection.retry_logic_provider()),
+        };
+        RetryState::new(retry_logic_provider, Some(&self.command_text))
+            .with_budget(Duration::from_secs(command_timeout as u64))
+    }
+
+    /// How many seconds the command may take (zero for no limit).
+    fn timeout_seconds(&self, connection: &SqlConnection) -> u16 {
+        self.command_timeout
+            .unwrap_or_else(|| connection.connection_options().command_timeout())
+    }
+
+    /// Runs an attempt at the command within what's left of its timeout, or returns None if it
+    /// overran (leaving the connection broken).
+    async fn within_timeout<F: Future>(retry: &RetryState, attempt: F) -> Option<F::Output> {
+        match retry.remaining() {
+            Some(remaining) => tokio::time::timeout(remaining, attempt).await.ok(),
+            None => Some(attempt.await),
+        }
+    }
+
+    /// The error for a command that overran its timeout.
+    fn timeout_error(command_timeout: u16) -> SqlClientError {
+        SqlClientError::Timeout(format!(
+            "The command didn't complete within {} seconds",
+            command_timeout
+        ))
+    }
+
+    /// Executes the command and returns the number of rows affected.
+    ///
+    /// Output parameters and the return value are populated once the command completes.
+    pub async fn execute_non_query(
+        &mut self,
+        connection: &mut SqlConnection,
+    ) -> Result<u64, SqlClientError> {
+        let command_timeout = self.timeout_seconds(connection);
+        let mut retry = self.retry_state(connection);
+        loop {
+            let attempt = self.execute_non_query_once(connection);
+            let result = Self::within_timeout(&retry, attempt)
+                .await
+                .unwrap_or_else(|| Err(Self::timeout_error(command_timeout)));
+            match result {
+                // A connection whose stream has failed can't make another attempt.
+                Err(error) if connection.parser_mut()?.is_broken() => return Err(retry.stop(error)),
+                Err(error) => retry.retry(error).await?,
+                result => return result,
+            }
+        }
+    }
+
+    /// Makes one attempt to execute the command and return the number of rows affected.
+    async fn execute_non_query_once(
+        &mut self,
+        connection: &mut SqlConnection,
+    ) -> Result<u64, SqlClientError> {
+        let parser = connection.parser_mut()?;
+        self.send(parser).await?;
+        // Read the response, skipping any rows.
+        let mut records_affected = 0;
+        let mut errors = Vec::new();
+        loop {
+            match parser.next_token().await? {
+                TdsToken::Row => parser.skip_row(false).await?,
+                TdsToken::NbcRow => parser.skip_row(true).await?,
+                TdsToken::Error(error) => errors.push(error),
+                token => {
+                    if let TdsToken::Done(done) = &token {
+                        records_affected += done.records_affected().unwrap_or(0);
+                    }
+                    if self.on_token(&token) {
+                        break;
+                    }
+                }
+            }
+        }
+        Self::check_errors(errors, parser.client_connection_id())?;
+        Ok(records_affected)
+    }
+
+    /// Executes the command and returns a reader over its results.
+    pub async fn execute_reader<'a>(
+        &'a mut self,
+        connection: &'a mut SqlConnection,
+    ) -> Result<SqlDataReader<'a>, SqlClientError> {
+        self.execute_reader_with_behavior(connection, CommandBehavior::DEFAULT)
+            .await
+    }
+
+    /// Executes the command and returns a reader over its results that reads them as specified
+    /// (e.g. with [CommandBehavior::SEQUENTIAL_ACCESS] to stream large values).
+    pub async fn execute_reader_with_behavior<'a>(
+        &'a mut self,
+        connection: &'a mut SqlConnection,
+        behavior: CommandBehavior,
+    ) -> Result<SqlDataReader<'a>, SqlClientError> {
+        let command_timeout = self.timeout_seconds(connection);
+        let mut retry = self.retry_state(connection);
+        let mut parser = connection.parser_mut()?;
+        let mut command = self;
+        loop {
+            let sent = Self::within_timeout(&retry, command.send(parser))
+                .await
+                .unwrap_or_else(|| Err(Self::timeout_error(command_timeout)));
+            if let Err(error) = sent {
+                if parser.is_broken() {
+                    return Err(retry.stop(error));
+                }
+                retry.retry(error).await?;
+                continue;
+            }
+            let attempt = SqlDataReader::new(parser, command, behavior);
+            match Self::within_timeout(&retry, attempt).await {
+                // The connection can't be used again after the overrun.
+                None => return Err(retry.stop(Self::timeout_error(command_timeout))),
+                Some(Ok(reader)) => return Ok(reader),
+                Some(Err((error, returned_parser, _))) if returned_parser.is_broken() => {
+                    return Err(retry.stop(error))
+                }
+                Some(Err((error, returned_parser, returned_command))) => {
+                    retry.retry(error).await?;
+                    parser = returned_parser;
+                    command = returned_command;
+                }
+            }
+        }
+    }
+
+    /// Sends the command to the server.
+    pub(crate) async fn send(&self, parser: &mut TdsParser) -> Result<(), SqlClientError> {
+        // If the previous command's results weren't all read, discard them.
+        if parser.has_pending_data() {
+            parser.drain().await?;
+        }
+        let collation = parser.default_collation();
+        match self.command_type {
+            CommandType::Text if self.parameters.is_empty() => {
+                parser.tds_execute_sql_batch(&self.command_text).await
+            }
+            CommandType::Text => {
+                // Parameterized text is sent as a call to sp_executesql with the statement, the parameter
+                // declarations, and then the parameters themselves.
+                let declarations = self
+                    .parameters
+                    .iter()
+                    .filter(|parameter| parameter.direction() != ParameterDirection::ReturnValue)
+                    .map(|parameter| parameter.declaration())
+                    .collect::<Result<Vec<String>, SqlClientError>>()?
+                    .join(",");
+                let statement = SqlValue::String(self.command_text.clone());
+                let declarations = SqlValue::String(declarations);
+                let ntext = TypeInfo::for_parameter(SqlDbType::NVarChar, -1, 0, 0, 0, collation)?;
+                let mut rpc_parameters = vec![
+                    RpcParameter {
+                        name: "",
+                        is_output: false,
+                        type_info: ntext.clone(),
+                        value: &statement,
+                        stream: None,
+                        table: None,
+                    },
+                    RpcParameter {
+                        name: "",
+                        is_output: false,
+                        type_info: ntext,
+                        value: &declarations,
+                        stream: None,
+                        table: None,
+                    },
+                ];
+                let names = self.rpc_names();
+                rpc_parameters.extend(self.rpc_parameters(&names, collation)?);
+                parser
+                    .tds_execute_rpc(RpcProcedure::Id(TdsEnums::SP_EXECUTESQL), &rpc_parameters)
+                    .await
+            }
+            CommandType::StoredProcedure => {
+                let procedure = match Self::well_known_procedure_id(&self.command_text) {
+                    Some(id) => RpcProcedure::Id(id),
+                    None => RpcProcedure::Name(&self.command_text),
+                };
+                let names = self.rpc_names();
+                let rpc_parameters = self.rpc_parameters(&names, collation)?;
+                parser.tds_execute_rpc(procedure, &rpc_parameters).await
+            }
+            CommandType::TableDirect => Err(SqlClientError::NotSupported(
+                "The CommandType 'TableDirect'".to_string(),
+            )),
+        }
+    }
+
+    /// The names the parameters are sent with.
+    fn rpc_names(&self) -> Vec<String> {
+        self.parameters
+            .iter()
+            .map(|parameter| parameter.rpc_name())
+            .collect()
+    }
+
+    /// The parameters to send.  The return value parameter isn't sent.
+    fn rpc_parameters<'a>(
+        &'a self,
+        names: &'a [String],
+        collation: Option<crate::sql_collation::SqlCollation>,
+    ) -> Result<Vec<RpcParameter<'a>>, SqlClientError> {
+        self.parameters
+            .iter()
+            .zip(names)
+            .filter(|(parameter, _)| parameter.direction() != ParameterDirection::ReturnValue)
+            .map(|(parameter, name)| {
+                Ok(RpcParameter {
+                    name,
+                    is_output: parameter.direction().is_output(),
+                    type_info: parameter.type_info(collation)?,
+                    // A pure output parameter has no value to send.
+                    value: if parameter.direction().is_input() {
+                        parameter.value()
+                    } else {
+                        &SqlValue::Null
+                    },
+                    stream: if parameter.direction().is_input() {
+                        parameter.stream()
+                    } else {
+                        None
+                    },
+                    table: parameter.table_type(collation)?,
+                })
+            })
+            .collect()
+    }
+
+    /// The ID of a system procedure that can be called by ID instead of by name.
+    fn well_known_procedure_id(procedure_name: &str) -> Option<u16> {
+        // The name may be qualified (e.g. "sys.sp_executesql")
+        let name = procedure_name
+            .rsplit('.')
+            .next()
+            .unwrap_or(procedure_name)
+            .trim_matches(|c| c == '[' || c == ']')
+            .to_ascii_lowercase();
+        let id = match name.as_str() {
+            "sp_cursor" => TdsEnums::SP_CURSOR,
+            "sp_cursoropen" => TdsEnums::SP_CURSOROPEN,
+            "sp_cursorprepare" => TdsEnums::SP_CURSORPREPARE,
+            "sp_cursorexecute" => TdsEnums::SP_CURSOREXECUTE,
+            "sp_cursorprepexec" => TdsEnums::SP_CURSORPREPEXEC,
+            "sp_cursorunprepare" => TdsEnums::SP_CURSORUNPREPARE,
+            "sp_cursorfetch" => TdsEnums::SP_CURSORFETCH,
+            "sp_cursoroption" => TdsEnums::SP_CURSOROPTION,
+            "sp_cursorclose" => TdsEnums::SP_CURSORCLOSE,
+            "sp_executesql" => TdsEnums::SP_EXECUTESQL,
+            "sp_prepare" => TdsEnums::SP_PREPARE,
+            "sp_execute" => TdsEnums::SP_EXECUTE,
+            "sp_prepexec" => TdsEnums::SP_PREPEXEC,
+            "sp_prepexecrpc" => TdsEnums::SP_PREPEXECRPC,
+            "sp_unprepare" => TdsEnums::SP_UNPREPARE,
+            _ => return None,
+        };
+        Some(id)
+    }
+
+    /// Handles a token that affects the command rather than the results.  Returns true if it was
+    /// the final token of the response.
+    pub(crate) fn on_token(&mut self, token: &TdsToken) -> bool {
+        match token {
+            TdsToken::ReturnStatus(status) => self.on_return_status(*status),
+            TdsToken::ReturnValue(return_value) => self.on_return_value(return_value),
+            TdsToken::Done(done) => return done.is_final(),
+            _ => {}
+        }
+        false
+    }
+
+    /// Sets the return value parameter from a RETURNSTATUS token.
+    fn on_return_status(&mut self, status: i32) {
+        if let Some(parameter) = self
+            .parameters
+            .iter_mut()
+            .find(|parameter| parameter.direction() == ParameterDirection::ReturnValue)
+        {
+            parameter.set_value(status);
+        }
+    }
+
+    /// Sets an output parameter from a RETURNVALUE token.
+    fn on_return_value(&mut self, return_value: &ReturnValueToken) {
+        log::debug!(
+            "on_return_value - {} = {:?}",
+            return_value.parameter_name,
+            return_value.value
+        );
+        // The return value of a user-defined function goes to the return value parameter.
+        let parameter = if return_value.status & TdsEnums::RETURNVALUE_UDF != 0 {
+            self.parameters
+                .iter_mut()
+                .find(|parameter| parameter.direction() == ParameterDirection::ReturnValue)
+        } else {
+            self.parameters.iter_mut().find(|parameter| {
+                parameter.direction().is_output()
+                    && parameter.has_name(&return_value.parameter_name)
+            })
+        };
+        match parameter {
+            Some(parameter) => parameter.set_value(return_value.value.clone()),
+            None => log::warn!(
+                "on_return_value - no output parameter named {}",
+                return_value.parameter_name
+            ),
+        }
+    }
+
+    /// Turns any errors the server returned on a connection into a failure.
+    pub(crate) fn check_errors(
+        errors: Vec<SqlError>,
+        client_connection_id: Uuid,
+    ) -> Result<(), SqlClientError> {
+        if errors.is_empty() {
+            return Ok(());
+        }
+        Err(SqlClientError::Server(SqlException::new(
+            errors,
+            client_connection_id,
+        )))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db_connection_internal::DbConnectionInternal;
+    use crate::tds_parser_state_object::{decode_utf16, encode_utf16, write_b_varchar};
+    use crate::tds_test_utils::{read_message, TokenBuilder};
+    use crate::{DataTable, SqlMetaData, SqlParameter};
+    use std::sync::{Arc, Mutex};
+    use test_utils::MockStream;
+    use tokio::io::AsyncWriteExt;
+
+    /// Creates an open connection that will read the given response.
+    fn connection(tokens: &TokenBuilder) -> (SqlConnection, Arc<Mutex<Vec<u8>>>) {
+        let stream = MockStream::new(tokens.packets());
+        let written = stream.written();
+        let mut connection = SqlConnection::new("Server=test").unwrap();
+        connection.attach(DbConnectionInternal::new(TdsParser::new(
+            Box::new(stream),
+            TdsEnums::DEFAULT_PACKET_SIZE,
+        )));
+        (connection, written)
+    }
+
+    /// The payload of the request, after the packet header and ALL_HEADERS.
+    fn request_body(written: &Arc<Mutex<Vec<u8>>>) -> Vec<u8> {
+        written.lock().unwrap()[TdsEnums::HEADER_LEN + 22..].to_vec()
+    }
+
+    #[tokio::test]
+    async fn test_stored_procedure_with_output_parameters() {
+        let tokens = TokenBuilder::new()
+            .done(
+                TdsEnums::SQLDONEINPROC,
+                TdsEnums::DONE_COUNT | TdsEnums::DONE_MORE,
+                0xC3,
+                1,
+            )
+            .return_status(7)
+            .return_value(
+                1,
+                "@total",
+                TdsEnums::RETURNVALUE_OUTPUT_PARAMETER,
+                &TokenBuilder::type_info(SqlDbType::Int),
+                &SqlValue::Int(42),
+            )
+            .return_value(
+                2,
+                "@name",
+                TdsEnums::RETURNVALUE_OUTPUT_PARAMETER,
+                &TokenBuilder::type_info(SqlDbType::NVarChar),
+                &SqlValue::from("updated"),
+            )
+            .done(TdsEnums::SQLDONEPROC, 0, 0xE0, 0);
+        let (mut connection, written) = connection(&tokens);
+        let mut command = SqlCommand::new_stored_procedure("dbo.UpdateOrder");
+        command.parameters_mut().add_with_value("@id", 5);
+        command
+            .parameters_mut()
+            .add(SqlParameter::new("@total", SqlDbType::Int))
+            .set_direction(ParameterDirection::Output);
+        command
+            .parameters_mut()
+            .add_with_value("@name", "original")
+            .set_direction(ParameterDirection::InputOutput);
+        command
+            .parameters_mut()
+            .add(SqlParameter::new("@RETURN_VALUE", SqlDbType::Int))
+            .set_direction(ParameterDirection::ReturnValue);
+        let records_affected = command.execute_non_query(&mut connection).await.unwrap();
+        assert_eq!(1, records_affected);
+        assert_eq!(&SqlValue::Int(42), command.parameters()["@total"].value());
+        assert_eq!(
+            &SqlValue::from("updated"),
+            command.parameters()["@name"].value()
+        );
+        assert_eq!(
+            &SqlValue::Int(7),
+            command.parameters()["@RETURN_VALUE"].value()
+        );
+        // Check the request: an RPC by name, with the output parameters flagged and no return value parameter
+        let written_bytes = written.lock().unwrap().clone();
+        assert_eq!(TdsEnums::MT_RPC, written_bytes[0]);
+        let body = request_body(&written);
+        let name = encode_utf16("dbo.UpdateOrder");
+        assert_eq!(&(name.len() as u16 / 2).to_le_bytes(), &body[0..2]);
+        assert_eq!(&name[..], &body[2..2 + name.len()]);
+        let mut expected = Vec::new();
+        expected.extend_from_slice(&[0, 0]);
+        crate::tds_parser_state_object::write_b_varchar(&mut expected, "@id");
+        expected.extend_from_slice(&[0, TdsEnums::SQLINTN, 4, 4, 5, 0, 0, 0]);
+        crate::tds_parser_state_object::write_b_varchar(&mut expected, "@total");
+        expected.extend_from_slice(&[TdsEnums::RPC_PARAM_BYREF, TdsEnums::SQLINTN, 4, 0]);
+        assert_eq!(
+            &expected[..],
+            &body[2 + name.len()..2 + name.len() + expected.len()]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_well_known_procedure_by_id() {
+        let tokens = TokenBuilder::new().done(TdsEnums::SQLDONEPROC, 0, 0, 0);
+        let (mut connection, written) = connection(&tokens);
+        let mut command = SqlCommand::new_stored_procedure("sys.sp_executesql");
+        command.parameters_mut().add_with_value("@stmt", "select 1");
+        command.execute_non_query(&mut connection).await.unwrap();
+        let body = request_body(&written);
+        assert_eq!(&[0xFF, 0xFF, 10, 0], &body[0..4]);
+    }
+
+    #[tokio::test]
+    async fn test_text_with_parameters_uses_sp_executesql() {
+        let tokens = TokenBuilder::new()
+            .done(TdsEnums::SQLDONE, TdsEnums::DONE_COUNT, 0xC3, 3)
+            .done(TdsEnums::SQLDONEPROC, 0, 0, 0);
+        let (mut connection, written) = connection(&tokens);
+        let mut command = SqlCommand::new("update t set x = @x");
+        command.parameters_mut().add_with_value("@x", 1);
+        assert_eq!(3, command.execute_non_query(&mut connection).await.unwrap());
+        let body = request_body(&written);
+        assert_eq!(&[0xFF, 0xFF, 10, 0], &body[0..4]);
+        // The declarations are the second parameter
+        let declarations = encode_utf16("@x int");
+        assert!(body
+            .windows(declarations.len())
+            .any(|window| window == &declarations[..]));
+    }
+
+    #[tokio::test]
+    async fn test_text_without_parameters_uses_batch() {
+        let tokens = TokenBuilder::new().done(TdsEnums::SQLDONE, TdsEnums::DONE_COUNT, 0xC3, 2);
+        let (mut connection, written) = connection(&tokens);
+        let mut command = SqlCommand::new("delete from t");
+        assert_eq!(2, command.execute_non_query(&mut connection).await.unwrap());
+        let written = written.lock().unwrap().clone();
+        assert_eq!(TdsEnums::MT_SQL, written[0]);
+        assert_eq!(
+            "delete from t",
+            decode_utf16(&written[TdsEnums::HEADER_LEN + 22..]).unwrap()
+        );
+    }
+
+    #[tokio::test]
+    async fn test_server_error() {
+        let tokens = TokenBuilder::new()
+            .error(
+                TdsEnums::SQLERROR,
+                2812,
+                16,
+                "Could not find stored procedure 'nope'.",
+            )
+            .done(TdsEnums::SQLDONEPROC, TdsEnums::DONE_ERROR, 0, 0);
+        let (mut connection, _) = connection(&tokens);
+        let mut command = SqlCommand::new_stored_procedure("nope");
+        match command.execute_non_query(&mut connection).await {
+            Err(SqlClientError::Server(exception)) => {
+                assert_eq!(2812, exception.number());
+                assert_eq!(
+                    "Could not find stored procedure 'nope'.",
+                    exception.message()
+                );
+                assert_eq!(1, exception.errors().len());
+                assert_eq!(
+                    connection.client_connection_id(),
+                    exception.client_connection_id()
+                );
+            }
+            result => panic!("Unexpected result {:?}", result),
+        }
+    }
+
+    #[rstest::rstest]
+    #[case(false, 1, true)]
+    #[case(true, 2, false)]
+    #[tokio::test]
+    async fn test_info_messages(
+        #[case] fire_on_user_errors: bool,
+        #[case] expected_messages: usize,
+        #[case] expected_failure: bool,
+    ) {
+        let tokens = TokenBuilder::new()
+            .error(TdsEnums::SQLINFO, 0, 0, "50% done")
+            .error(TdsEnums::SQLERROR, 50000, 16, "Something went wrong")
+            .done(TdsEnums::SQLDONEPROC, TdsEnums::DONE_ERROR, 0, 0);
+        let (mut connection, _) = connection(&tokens);
+        let messages = Arc::new(Mutex::new(Vec::new()));
+        let received = messages.clone();
+        connection.on_info_message(move |args| received.lock().unwrap().push(args.message()));
+        connection.set_fire_info_message_event_on_user_errors(fire_on_user_errors);
+        let mut command = SqlCommand::new_stored_procedure("maintenance");
+        let result = command.execute_non_query(&mut connection).await;
+        assert_eq!(expected_failure, result.is_err());
+        let messages = messages.lock().unwrap();
+        assert_eq!(expected_messages, messages.len());
+        assert_eq!("50% done", messages[0]);
+    }
+
+    /// A provider that retries deadlocks immediately.
+    fn retry_logic_provider(number_of_tries: u32) -> SqlRetryLogicProvider {
+        SqlRetryLogicProvider::none(&crate::SqlRetryLogicOption {
+            number_of_tries,
+            ..Default::default()
+        })
+        .unwrap()
+    }
+
+    /// A response to a command that was chosen as a deadlock victim.
+    fn deadlock() -> TokenBuilder {
+        TokenBuilder::new()
+            .error(TdsEnums::SQLERROR, 1205, 13, "Transaction was deadlocked")
+            .done(TdsEnums::SQLDONE, TdsEnums::DONE_ERROR, 0, 0)
+    }
+
+    #[rstest::rstest]
+    #[case::retried(2, Some(3))]
+    #[case::out_of_tries(1, None)]
+    #[tokio::test]
+    async fn test_retry_non_query(#[case] number_of_tries: u32, #[case] expected: Option<u64>) {
+        let packets = [
+            deadlock(),
+            TokenBuilder::new().done(TdsEnums::SQLDONE, TdsEnums::DONE_COUNT, 0xC3, 3),
+        ]
+        .iter()
+        .flat_map(|tokens| tokens.packets())
+        .collect();
+        let mut connection = SqlConnection::new("Server=test").unwrap();
+        connection.attach(DbConnectionInternal::new(TdsParser::new(
+            Box::new(MockStream::new(packets)),
+            TdsEnums::DEFAULT_PACKET_SIZE,
+        )));
+        let mut command = SqlCommand::new("update t set a = 1");
+        command.set_retry_logic_provider(Some(retry_logic_provider(number_of_tries)));
+        let result = command.execute_non_query(&mut connection).await;
+        assert_eq!(expected, result.as_ref().ok().copied());
+        if expected.is_none() {
+            assert!(result.unwrap_err().is_transient());
+        }
+    }
+
+    #[rstest::rstest]
+    #[case::non_query(false)]
+    #[case::reader(true)]
+    #[tokio::test]
+    async fn test_no_retry_on_broken_connection(#[case] reader: bool) {
+        // A fatal error closes the connection, so the transient error can't be retried on it.
+        let packets = [
+            TokenBuilder::new()
+                .error(TdsEnums::SQLERROR, 1205, 20, "Transaction was deadlocked")
+                .done(TdsEnums::SQLDONE, TdsEnums::DONE_ERROR, 0, 0),
+            TokenBuilder::new().done(TdsEnums::SQLDONE, TdsEnums::DONE_COUNT, 0xC3, 3),
+        ]
+        .iter()
+        .flat_map(|tokens| tokens.packets())
+        .collect();
+        let mut connection = SqlConnection::new("Server=test").unwrap();
+        connection.attach(DbConnectionInternal::new(TdsParser::new(
+            Box::new(MockStream::new(packets)),
+            TdsEnums::DEFAULT_PACKET_SIZE,
+        )));
+        let mut command = SqlCommand::new("update t set a = 1");
+        command.set_retry_logic_provider(Some(retry_logic_provider(2)));
+        let error = match reader {
+            true => command.execute_reader(&mut connection).await.err().unwrap(),
+            false => command
+                .execute_non_query(&mut connection)
+                .await
+                .unwrap_err(),
+        };
+        assert!(matches!(error, SqlClientError::Server(_)));
+    }
+
+    #[rstest::rstest]
+    #[case::connection_timeout(None, false)]
+    #[case::command_timeout(Some(1), false)]
+    #[case::no_timeout(Some(0), true)]
+    #[tokio::test(start_paused = true)]
+    async fn test_retry_within_command_timeout(
+        #[case] command_timeout: Option<u16>,
+        #[case] retried: bool,
+    ) {
+        let packets = [
+            deadlock(),
+            TokenBuilder::new().done(TdsEnums::SQLDONE, TdsEnums::DONE_COUNT, 0xC3, 3),
+        ]
+        .iter()
+        .flat_map(|tokens| tokens.packets())
+        .collect();
+        let mut connection = SqlConnection::new("Server=test;Command Timeout=1").unwrap();
+        connection.attach(DbConnectionInternal::new(TdsParser::new(
+            Box::new(MockStream::new(packets)),
+            TdsEnums::DEFAULT_PACKET_SIZE,
+        )));
+        let mut command = SqlCommand::new("update t set a = 1");
+        command.set_command_timeout(command_timeout);
+        // Waits of about 2 seconds between attempts don't fit in a 1 second timeout.
+        command.set_retry_logic_provider(Some(
+            SqlRetryLogicProvider::fixed(&crate::SqlRetryLogicOption {
+                delta_time: Duration::from_secs(2),
+                ..Default::default()
+            })
+            .unwrap(),
+        ));
+        let result = command.execute_non_query(&mut connection).await;
+        assert_eq!(retried, result.is_ok());
+    }
+
+    #[rstest::rstest]
+    #[case::non_query(false)]
+    #[case::reader(true)]
+    #[tokio::test(start_paused = true)]
+    async fn test_attempt_within_command_timeout(#[case] reader: bool) {
+        // The server answers the first attempt with a deadlock, and never answers the second.
+        let (client, mut server) = tokio::io::duplex(64 * 1024);
+        let server = tokio::spawn(async move {
+            read_message(&mut server).await;
+            server.write_all(&deadlock().packets()).await.unwrap();
+            read_message(&mut server).await;
+            std::future::pending::<()>().await;
+        });
+        let mut connection = SqlConnection::new("Server=test;Command Timeout=1").unwrap();
+        connection.attach(DbConnectionInternal::new(TdsParser::new(
+            Box::new(client),
+            TdsEnums::DEFAULT_PACKET_SIZE,
+        )));
+        let mut command = SqlCommand::new("update t set a = 1");
+        command.set_retry_logic_provider(Some(retry_logic_provider(3)));
+        let started = tokio::time::Instant::now();
+        let error = match reader {
+            true => command.execute_reader(&mut connection).await.err().unwrap(),
+            false => command
+                .execute_non_query(&mut connection)
+                .await
+                .unwrap_err(),
+        };
+        assert!(started.elapsed() <= Duration::from_secs(1));
+        match error {
+            SqlClientError::Retry(exception) => {
+                assert!(matches!(exception.last_error(), SqlClientError::Timeout(_)))
+            }
+            _ => panic!("Expected the attempts to fail"),
+        }
+        server.abort();
+    }
+
+    #[tokio::test]
+    async fn test_retry_with_connection_provider() {
+        let packets = [
+            deadlock(),
+            TokenBuilder::new().done(TdsEnums::SQLDONE, TdsEnums::DONE_COUNT, 0xC3, 3),
+        ]
+        .iter()
+        .flat_map(|tokens| tokens.packets())
+        .collect();
+        let mut connection =
+            SqlConnection::new("Server=test;Retry Method=None;Retry Number Of Tries=2").unwrap();
+        connection.attach(DbConnectionInternal::new(TdsParser::new(
+            Box::new(MockStream::new(packets)),
+            TdsEnums::DEFAULT_PACKET_SIZE,
+        )));
+        let mut command = SqlCommand::new("update t set a = 1");
+        assert_eq!(3, command.execute_non_query(&mut connection).await.unwrap());
+    }
+
+    #[tokio::test]
+    async fn test_retry_reader() {
+        let packets = [
+            deadlock(),
+            TokenBuilder::new()
+                .col_metadata(&[("Id", SqlDbType::Int)])
+                .row(&[SqlValue::Int(7)])
+                .done(TdsEnums::SQLDONE, TdsEnums::DONE_COUNT, 0xC1, 1),
+        ]
+        .iter()
+        .flat_map(|tokens| tokens.packets())
+        .collect();
+        let stream = MockStream::new(packets);
+        let written = stream.written();
+        let mut connection = SqlConnection::new("Server=test").unwrap();
+        connection.attach(DbConnectionInternal::new(TdsParser::new(
+            Box::new(stream),
+            TdsEnums::DEFAULT_PACKET_SIZE,
+        )));
+        let mut command = SqlCommand::new("select Id from t");
+        command.set_retry_logic_provider(Some(retry_logic_provider(3)));
+        let mut reader = command.execute_reader(&mut connection).await.unwrap();
+        assert!(reader.read().await.unwrap());
+        assert_eq!(7, reader.get_i32(0).unwrap());
+        // The batch was sent twice, in a packet each.
+        let written = written.lock().unwrap();
+        let length = u16::from_be_bytes([written[2], written[3]]) as usize;
+        assert_eq!(2 * length, written.len());
+        assert_eq!(written[..length], written[length..]);
+    }
+
+    #[tokio::test]
+    async fn test_closed_connection() {
+        let mut connection = SqlConnection::new("Server=test").unwrap();
+        let mut command = SqlCommand::new("select 1");
+        assert!(matches!(
+            command.execute_non_query(&mut connection).await,
+            Err(SqlClientError::InvalidOperation(_))
+        ));
+    }
+
+    /// A stream that fails after returning a couple of buffers of data.
+    struct FailingStream {
+        /// The number of reads so far.
+        reads: usize,
+    }
+
+    impl tokio::io::AsyncRead for FailingStream {
+        fn poll_read(
+            mut self: std::pin::Pin<&mut Self>,
+            _cx: &mut std::task::Context<'_>,
+            buf: &mut tokio::io::ReadBuf<'_>,
+        ) -> std::task::Poll<std::io::Result<()>> {
+            if self.reads == 2 {
+                return std::task::Poll::Ready(Err(std::io::Error::other("disk error")));
+            }
+            self.reads += 1;
+            buf.put_slice(&vec![1; buf.remaining()]);
+            std::task::Poll::Ready(Ok(()))
+        }
+    }
+
+    #[tokio::test]
+    async fn test_streamed_parameter() {
+        let tokens = TokenBuilder::new().done(TdsEnums::SQLDONEPROC, 0, 0, 0);
+        let (mut connection, written) = connection(&tokens);
+        let mut command = SqlCommand::new_stored_procedure("save_note");
+        command.parameters_mut().add(SqlParameter::with_stream(
+            "@note",
+            SqlDbType::NVarChar,
+            "héllo".as_bytes(),
+        ));
+        command.execute_non_query(&mut connection).await.unwrap();
+        // The value is sent as UTF-16 in chunks of unknown total length
+        let mut expected = TdsEnums::SQL_PLP_UNKNOWNLEN.to_le_bytes().to_vec();
+        expected.extend_from_slice(&10u32.to_le_bytes());
+        expected.extend(encode_utf16("héllo"));
+        expected.extend_from_slice(&TdsEnums::SQL_PLP_CHUNK_TERMINATOR.to_le_bytes());
+        assert!(request_body(&written).ends_with(&expected));
+    }
+
+    #[tokio::test]
+    async fn test_streamed_parameter_is_not_retried() {
+        let (mut connection, written) = connection(&deadlock());
+        let mut command = SqlCommand::new_stored_procedure("save_note");
+        command.set_retry_logic_provider(Some(retry_logic_provider(3)));
+        command.parameters_mut().add(SqlParameter::with_stream(
+            "@note",
+            SqlDbType::VarBinary,
+            &b"hello"[..],
+        ));
+        // The original error is returned, rather than retrying with the used up stream.
+        match command.execute_non_query(&mut connection).await {
+            Err(SqlClientError::Server(exception)) => {
+                assert_eq!(1205, exception.errors()[0].number())
+            }
+            result => panic!("unexpected result: {:?}", result),
+        }
+        // The request was sent once, in a single packet.
+        let written = written.lock().unwrap();
+        let length = u16::from_be_bytes([written[2], written[3]]) as usize;
+        assert_eq!(length, written.len());
+    }
+
+    #[tokio::test]
+    async fn test_table_valued_parameter() {
+        let tokens = TokenBuilder::new().done(TdsEnums::SQLDONEPROC, 0, 0, 0);
+        let (mut connection, written) = connection(&tokens);
+        let mut command = SqlCommand::new_stored_procedure("get_customers");
+        let ids = DataTable::from_rows(vec![SqlMetaData::new("Id", SqlDbType::BigInt)], 1i64..=3)
+            .unwrap();
+        command
+            .parameters_mut()
+            .add(SqlParameter::with_table("@ids", "dbo.IdList", ids));
+        command.execute_non_query(&mut connection).await.unwrap();
+        // The parameter is sent as the table type, followed by its rows
+        let mut expected = vec![TdsEnums::SQLTABLE, 0];
+        write_b_varchar(&mut expected, "dbo");
+        write_b_varchar(&mut expected, "IdList");
+        let body = request_body(&written);
+        let start = body
+            .windows(expected.len())
+            .position(|window| window == expected)
+            .unwrap();
+        let mut rows = Vec::new();
+        for id in 1i64..=3 {
+            rows.extend_from_slice(&[TdsEnums::TVP_ROW_TOKEN, 8]);
+            rows.extend_from_slice(&id.to_le_bytes());
+        }
+        rows.push(TdsEnums::TVP_END_TOKEN);
+        assert!(body[start..].ends_with(&rows));
+    }
+
+    #[tokio::test]
+    async fn test_failed_stream_is_ignored() {
+        let (mut connection, written) = connection(&TokenBuilder::new());
+        let mut command = SqlCommand::new_stored_procedure("save_data");
+        command.parameters_mut().add(SqlParameter::with_stream(
+            "@data",
+            SqlDbType::VarBinary,
+            FailingStream { reads: 0 },
+        ));
+        assert!(matches!(
+            command.execute_non_query(&mut connection).await,
+            Err(SqlClientError::Io(_, _))
+        ));
+        // Packets had been sent, so the last one tells the server to ignore the message
+        let written = written.lock().unwrap().clone();
+        let mut statuses = Vec::new();
+        let mut position = 0;
+        while position < written.len() {
+            statuses.push(written[position + 1]);
+            position += u16::from_be_bytes([written[position + 2], written[position + 3]]) as usize;
+        }
+        assert!(statuses.len() > 1);
+        assert_eq!(
+            Some(&(TdsEnums::ST_EOM | TdsEnums::ST_IGNORE)),
+            statuses.last()
+        );
+    }
+}
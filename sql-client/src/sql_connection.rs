@@ -1,6 +1,21 @@
+use crate::db_connection_internal::DbConnectionInternal;
+use crate::db_connection_pool::DbConnectionPool;
+use crate::db_connection_pool_key::DbConnectionPoolKey;
 use crate::sql_connection_string::SqlConnectionString;
 use crate::sql_credential::SqlCredential;
-use crate::{sql_credential, SqlClientError};
+use crate::sql_info_message_event_args::SqlInfoMessageEventHandler;
+use crate::sql_login::SqlLogin;
+use crate::sql_retry_logic_provider::RetryState;
+use crate::tds_parser::TdsParser;
+use crate::{
+    sql_credential, SqlClientError, SqlConnectionPoolStatistics, SqlInfoMessageEventArgs,
+    SqlRetryLogicProvider,
+};
+use std::future::Future;
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::task::JoinHandle;
+use uuid::Uuid;
 
 /// A connection to a SQL server.
 pub struct SqlConnection {
@@ -10,6 +25,16 @@ pub struct SqlConnection {
     connection_options: SqlConnectionString,
     /// The login credentials
     sql_credential: Option<SqlCredential>,
+    /// The internal connection, when open.
+    inner_connection: Option<DbConnectionInternal>,
+    /// Handles informational messages.
+    info_message_handler: Option<SqlInfoMessageEventHandler>,
+    /// Whether errors that the user can correct are handled as informational messages.
+    fire_info_message_event_on_user_errors: bool,
+    /// Retries opening the connection, if set.
+    retry_logic_provider: Option<SqlRetryLogicProvider>,
+    /// Whether a pooled connection is checked with a trivial request before it's reused.
+    ping_on_checkout: bool,
 }
 impl SqlConnection {
     /// Tries to create a new connection given a connection string.
@@ -18,11 +43,18 @@ impl SqlConnection {
         let connection_options: SqlConnectionString = connection_string.try_into()?;
         // Get the credentials from the string
         let sql_credential = connection_options.sql_credential()?;
+        // Get the retry logic from the string
+        let retry_logic_provider = connection_options.retry_logic_provider()?;
         // Return the connection
         Ok(Self {
             connection_string: connection_string.to_string(),
             connection_options,
             sql_credential,
+            inner_connection: None,
+            info_message_handler: None,
+            fire_info_message_event_on_user_errors: false,
+            retry_logic_provider,
+            ping_on_checkout: false,
         })
     }
     /// Tries to create a new connection given a connection string and login credentials.
@@ -33,14 +65,362 @@ impl SqlConnection {
         // Create the connection string object
         let connection_options: SqlConnectionString = connection_string.try_into()?;
         // TODO - The .NET implementation will throw errors if a credential is used in connection with certain other settings.
+        // Get the retry logic from the string
+        let retry_logic_provider = connection_options.retry_logic_provider()?;
         // Return the connection string
         Ok(Self {
             connection_string: connection_string.to_string(),
             connection_options,
             sql_credential: Some(sql_credential),
+            inner_connection: None,
+            info_message_handler: None,
+            fire_info_message_event_on_user_errors: false,
+            retry_logic_provider,
+            ping_on_checkout: false,
         })
     }
+    /// Sets a callback that receives the server's informational messages (e.g. PRINT output, or a
+    /// RAISERROR with a severity of 10 or less) as commands run.  It replaces any previous
+    /// callback.
+    pub fn on_info_message(
+        &mut self,
+        callback: impl Fn(&SqlInfoMessageEventArgs) + Send + Sync + 'static,
+    ) {
+        self.info_message_handler = Some(Arc::new(callback));
+        self.update_parser();
+    }
+    /// Whether errors that the user can correct (with a severity of 16 or less) are passed to the
+    /// [SqlConnection::on_info_message] callback instead of failing the command.
+    pub fn fire_info_message_event_on_user_errors(&self) -> bool {
+        self.fire_info_message_event_on_user_errors
+    }
+    /// Sets whether errors that the user can correct (with a severity of 16 or less) are passed to
+    /// the [SqlConnection::on_info_message] callback instead of failing the command.
+    pub fn set_fire_info_message_event_on_user_errors(&mut self, value: bool) {
+        self.fire_info_message_event_on_user_errors = value;
+        self.update_parser();
+    }
+    /// The provider that retries opening the connection, and executing the commands that don't
+    /// have their own provider, if any.  It's initially set from the connection string's Retry
+    /// Method and related keywords.
+    pub fn retry_logic_provider(&self) -> Option<&SqlRetryLogicProvider> {
+        self.retry_logic_provider.as_ref()
+    }
+    /// Sets the provider that retries opening the connection, and executing the commands that don't
+    /// have their own provider, or None to not retry.
+    pub fn set_retry_logic_provider(&mut self, value: Option<SqlRetryLogicProvider>) {
+        self.retry_logic_provider = value;
+    }
+    /// Whether a pooled connection is checked with a trivial request before it's reused, so that
+    /// a connection the server has dropped is discarded rather than failing the first command.
+    pub fn ping_on_checkout(&self) -> bool {
+        self.ping_on_checkout
+    }
+    /// Sets whether a pooled connection is checked with a trivial request before it's reused.
+    pub fn set_ping_on_checkout(&mut self, value: bool) {
+        self.ping_on_checkout = value;
+    }
+    /// Opens the connection: borrows an idle connection from the pool (unless pooling is off), or
+    /// connects to the server and logs in, retrying transient failures with the connection's retry
+    /// logic provider.  Only SQL Server authentication over TCP is supported.
+    pub async fn open(&mut self) -> Result<(), SqlClientError> {
+        let login = SqlLogin::new(&self.connection_options, self.sql_credential.as_ref())?;
+        let connect = move || {
+            let login = login.clone();
+            async move { DbConnectionInternal::connect(&login).await }
+        };
+        self.open_with(connect.clone()).await?;
+        // Keep the pool's minimum number of connections (on first use, and after some have been
+        // discarded) without making the caller wait for them.
+        if let Some(connection_pool) = self.connection_pool() {
+            let connect_timeout = self.connection_options.connect_timeout();
+            Self::fill_pool(connection_pool, connect_timeout, connect);
+        }
+        Ok(())
+    }
+    /// Opens the connection with a function that connects and logs in, retrying transient failures
+    /// with the connection's retry logic provider.
+    pub(crate) async fn open_with<F, Fut>(&mut self, mut connect: F) -> Result<(), SqlClientError>
+    where
+        F: FnMut() -> Fut,
+        Fut: Future<Output = Result<DbConnectionInternal, SqlClientError>>,
+    {
+        if self.inner_connection.is_some() {
+            return Err(SqlClientError::InvalidOperation(
+                "The connection is already open.".to_string(),
+            ));
+        }
+        // All the attempts (including waiting for a pooled connection) must finish within the
+        // connect timeout.
+        let connect_timeout = self.connection_options.connect_timeout();
+        let endpoint = self.connection_options.data_source().unwrap_or_default();
+        let mut retry = RetryState::new(self.retry_logic_provider.as_ref(), None)
+            .with_budget(Duration::from_secs(connect_timeout as u64))
+            .with_endpoint(&endpoint);
+        // Unless pooling is off, wait for our turn to borrow one of the pool's connections.
+        let pooled = match self.connection_options.pooling() {
+            true => {
+                let key = DbConnectionPoolKey::new(
+                    &self.connection_string,
+                    self.sql_credential.as_ref(),
+                )?;
+                let connection_pool = DbConnectionPool::get(&key, &self.connection_options)?;
+                let permit = connection_pool.acquire(retry.remaining()).await?;
+                Some((connection_pool, permit))
+            }
+            false => None,
+        };
+        // Use an idle connection if there is one, otherwise connect.
+        let idle = match &pooled {
+            Some((connection_pool, _)) => self.take_idle(connection_pool, retry.remaining()).await,
+            None => None,
+        };
+        let mut inner_connection = match idle {
+            Some(inner_connection) => inner_connection,
+            None => match &pooled {
+                Some((connection_pool, _)) => {
+                    // Fail fast while the pool is blocked after a failure.
+                    connection_pool.check_error()?;
+                    match Self::connect(&mut retry, connect_timeout, &mut connect).await {
+                        Ok(mut inner_connection) => {
+                            connection_pool.record_success();
+                            connection_pool.add_connection(&mut inner_connection);
+                            inner_connection
+                        }
+                        Err(error) => {
+                            connection_pool.record_error(&error);
+                            return Err(error);
+                        }
+                    }
+                }
+                None => Self::connect(&mut retry, connect_timeout, &mut connect).await?,
+            },
+        };
+        if let Some((connection_pool, permit)) = pooled {
+            inner_connection.lend(connection_pool, permit);
+        }
+        self.attach(inner_connection);
+        Ok(())
+    }
+    /// Takes an idle connection from the pool, asking the server to reset it with the next request
+    /// (and first checking it still works within the given time, if we ping on checkout).
+    async fn take_idle(
+        &self,
+        connection_pool: &DbConnectionPool,
+        timeout: Option<Duration>,
+    ) -> Option<DbConnectionInternal> {
+        while let Some(mut inner_connection) = connection_pool.take_idle() {
+            inner_connection.reset();
+            if !self.ping_on_checkout {
+                return Some(inner_connection);
+            }
+            let ping = inner_connection.ping();
+            let result = match timeout {
+                Some(timeout) => tokio::time::timeout(timeout, ping)
+                    .await
+                    .unwrap_or_else(|_| {
+                        Err(SqlClientError::Timeout(
+                            "The ping didn't complete in time".to_string(),
+                        ))
+                    }),
+                None => ping.await,
+            };
+            match result {
+                Ok(()) => return Some(inner_connection),
+                Err(error) => {
+                    log::debug!(
+                        "discarding a pooled connection that failed a ping: {}",
+                        error
+                    );
+                    connection_pool.remove_connection();
+                }
+            }
+        }
+        None
+    }
+    /// Creates connections in a pool in the background until it has Min Pool Size of them, each
+    /// within the connect timeout.  A failure blocks the pool as a failed open would, and is left
+    /// for a later open to report.  Returns the task, if there was anything to create.
+    pub(crate) fn fill_pool<F, Fut>(
+        connection_pool: Arc<DbConnectionPool>,
+        connect_timeout: u16,
+        mut connect: F,
+    ) -> Option<JoinHandle<()>>
+    where
+        F: FnMut() -> Fut + Send + 'static,
+        Fut: Future<Output = Result<DbConnectionInternal, SqlClientError>> + Send,
+    {
+        if !connection_pool.reserve_connection() {
+            return None;
+        }
+        let timeout = (connect_timeout != 0).then(|| Duration::from_secs(connect_timeout as u64));
+        Some(tokio::spawn(async move {
+            loop {
+                let result = match timeout {
+                    Some(timeout) => tokio::time::timeout(timeout, connect())
+                        .await
+                        .unwrap_or_else(|_| {
+                            Err(SqlClientError::Timeout(format!(
+                                "Connecting didn't complete within {} seconds",
+                                connect_timeout
+                            )))
+                        }),
+                    None => connect().await,
+                };
+                match result {
+                    Ok(inner_connection) => connection_pool.add_reserved(inner_connection),
+                    Err(error) => {
+                        log::debug!("couldn't add a connection to the pool: {}", error);
+                        connection_pool.cancel_reservation();
+                        connection_pool.record_error(&error);
+                        return;
+                    }
+                }
+                if !connection_pool.reserve_connection() {
+                    return;
+                }
+            }
+        }))
+    }
+    /// Connects and logs in, retrying transient failures.
+    async fn connect<F, Fut>(
+        retry: &mut RetryState,
+        connect_timeout: u16,
+        connect: &mut F,
+    ) -> Result<DbConnectionInternal, SqlClientError>
+    where
+        F: FnMut() -> Fut,
+        Fut: Future<Output = Result<DbConnectionInternal, SqlClientError>>,
+    {
+        loop {
+            // Fail fast if the endpoint's circuit breaker is open.
+            if let Err(error) = retry.check() {
+                // The error isn't retried, but is reported with any earlier attempts.
+                retry.retry(error).await?;
+                continue;
+            }
+            let result = match retry.remaining() {
+                Some(remaining) => tokio::time::timeout(remaining, connect())
+                    .await
+                    .unwrap_or_else(|_| {
+                        Err(SqlClientError::Timeout(format!(
+                            "Connecting didn't complete within {} seconds",
+                            connect_timeout
+                        )))
+                    }),
+                None => connect().await,
+            };
+            match result {
+                Ok(inner_connection) => {
+                    retry.succeeded();
+                    return Ok(inner_connection);
+                }
+                Err(error) => retry.retry(error).await?,
+            }
+        }
+    }
+    /// Closes the connection.  A pooled connection goes back into the pool to be used again.
+    pub fn close(&mut self) {
+        if let Some(inner_connection) = self.inner_connection.take() {
+            inner_connection.close();
+        }
+    }
+    /// The counts of the pool that the connection borrows from, or None if the connection isn't
+    /// pooled or the pool hasn't been created yet.
+    pub fn pool_statistics(&self) -> Option<SqlConnectionPoolStatistics> {
+        self.connection_pool()
+            .map(|connection_pool| connection_pool.statistics())
+    }
+    /// Empties the pool that a connection borrows from: its idle connections are closed, and the
+    /// connections borrowed from it are closed rather than returned.
+    pub fn clear_pool(connection: &SqlConnection) {
+        if let Some(connection_pool) = connection.connection_pool() {
+            connection_pool.clear();
+        }
+    }
+    /// Empties all the connection pools.
+    pub fn clear_all_pools() {
+        for connection_pool in DbConnectionPool::all() {
+            connection_pool.clear();
+        }
+    }
+    /// Shuts the connection pools down gracefully (e.g. before the process exits): opening a
+    /// pooled connection fails from now on, the idle connections are closed, and the borrowed
+    /// connections are closed as they're returned.  Waits until they've all been closed, failing
+    /// if that takes longer than the timeout (unless it's zero, for no limit).
+    pub async fn drain(timeout: Duration) -> Result<(), SqlClientError> {
+        let deadline = match timeout.is_zero() {
+            true => None,
+            false => Some(tokio::time::Instant::now() + timeout),
+        };
+        // Stop every pool lending (and new pools being created) before waiting for any of them.
+        let connection_pools = DbConnectionPool::stop_lending_all();
+        for connection_pool in connection_pools {
+            connection_pool.drain(deadline).await?;
+        }
+        Ok(())
+    }
+    /// The pool that the connection borrows from, if it's been created.
+    fn connection_pool(&self) -> Option<Arc<DbConnectionPool>> {
+        if !self.connection_options.pooling() {
+            return None;
+        }
+        let key =
+            DbConnectionPoolKey::new(&self.connection_string, self.sql_credential.as_ref()).ok()?;
+        DbConnectionPool::find(&key)
+    }
+    /// The options parsed from the connection string.
+    pub(crate) fn connection_options(&self) -> &SqlConnectionString {
+        &self.connection_options
+    }
+    /// Attaches an open internal connection.
+    pub(crate) fn attach(&mut self, mut inner_connection: DbConnectionInternal) {
+        inner_connection
+            .parser_mut()
+            .set_type_system(self.connection_options.type_system());
+        self.inner_connection = Some(inner_connection);
+        self.update_parser();
+    }
+    /// Passes the message handling settings to the open connection's parser.
+    fn update_parser(&mut self) {
+        if let Some(inner_connection) = &mut self.inner_connection {
+            let parser = inner_connection.parser_mut();
+            parser.set_info_message_handler(self.info_message_handler.clone());
+            parser.set_fire_info_message_event_on_user_errors(
+                self.fire_info_message_event_on_user_errors,
+            );
+        }
+    }
+    /// The ID of the open connection, reported with its errors (nil if the connection is not
+    /// open).
+    pub fn client_connection_id(&self) -> Uuid {
+        match &self.inner_connection {
+            Some(inner_connection) => inner_connection.parser().client_connection_id(),
+            None => Uuid::nil(),
+        }
+    }
+    /// Detaches the internal connection, e.g. when it's been left part way through a message and
+    /// can't be used again.
+    pub(crate) fn detach(&mut self) {
+        self.inner_connection = None;
+    }
+    /// Gets the parser of the open connection, or an error if the connection is not open.
+    pub(crate) fn parser_mut(&mut self) -> Result<&mut TdsParser, SqlClientError> {
+        match &mut self.inner_connection {
+            Some(inner_connection) => Ok(inner_connection.parser_mut()),
+            None => Err(SqlClientError::InvalidOperation(
+                "The connection is not open.".to_string(),
+            )),
+        }
+    }
 }
+impl Drop for SqlConnection {
+    /// Closes the connection, returning it to the pool if it's pooled.
+    fn drop(&mut self) {
+        self.close();
+    }
+}
+
 /// Allows the SQL connection to be cloned.
 ///
 /// The properties are cloned but not the state (e.g. the resulting connection will be closed even if the original connection was open).
@@ -48,7 +428,7 @@ impl Clone for SqlConnection {
     /// Clones the connection.
     fn clone(&self) -> Self {
         // If credentials were supplied originally...
-        if let Some(sql_credential) = &self.sql_credential {
+        let mut connection = if let Some(sql_credential) = &self.sql_credential {
             // Create another connection with the same credentials
             SqlConnection::new_auth(
                 self.connection_string.clone().as_str(),
@@ -61,6 +441,260 @@ impl Clone for SqlConnection {
         else {
             // Create another connection from the connection string alone.
             SqlConnection::new(self.connection_string.clone().as_str()).unwrap()
+        };
+        connection.info_message_handler = self.info_message_handler.clone();
+        connection.fire_info_message_event_on_user_errors =
+            self.fire_info_message_event_on_user_errors;
+        connection.retry_logic_provider = self.retry_logic_provider.clone();
+        connection.ping_on_checkout = self.ping_on_checkout;
+        connection
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tds_enums::TdsEnums;
+    use crate::tds_test_utils::{serve_login, TokenBuilder};
+    use crate::{
+        SqlCircuitBreakerOption, SqlCircuitBreakerState, SqlRetryIntervalBuilder,
+        SqlRetryLogicOption, SqlRetryMethod,
+    };
+    use std::io;
+    use test_utils::MockStream;
+
+    #[tokio::test]
+    async fn test_open() {
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let port = listener.local_addr().unwrap().port();
+        // The first login fails with a transient error, so the open is retried.
+        let server = tokio::spawn(async move {
+            let responses = [
+                TokenBuilder::new()
+                    .error(TdsEnums::SQLERROR, 4060, 11, "Cannot open database")
+                    .done(TdsEnums::SQLDONE, TdsEnums::DONE_ERROR, 0, 0),
+                TokenBuilder::new()
+                    .login_ack()
+                    .done(TdsEnums::SQLDONE, 0, 0, 0),
+            ];
+            for response in responses {
+                let (stream, _) = listener.accept().await.unwrap();
+                serve_login(stream, TdsEnums::ENCRYPT_NOT_SUP, None, &response).await;
+            }
+        });
+        let mut connection = SqlConnection::new(&format!(
+            "Server=tcp:127.0.0.1,{};User ID=me;Password=secret;Encrypt=False",
+            port
+        ))
+        .unwrap();
+        connection.set_retry_logic_provider(Some(
+            SqlRetryLogicProvider::none(&SqlRetryLogicOption::default()).unwrap(),
+        ));
+        connection.open().await.unwrap();
+        server.await.unwrap();
+        assert!(!connection.client_connection_id().is_nil());
+        connection.close();
+    }
+
+    #[tokio::test]
+    async fn test_open_pooled() {
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let port = listener.local_addr().unwrap().port();
+        // The server only takes one connection, so the second open has to reuse it from the pool.
+        let server = tokio::spawn(async move {
+            let (stream, _) = listener.accept().await.unwrap();
+            let response = TokenBuilder::new()
+                .login_ack()
+                .done(TdsEnums::SQLDONE, 0, 0, 0);
+            serve_login(stream, TdsEnums::ENCRYPT_NOT_SUP, None, &response).await;
+        });
+        let mut connection = SqlConnection::new(&format!(
+            "Server=tcp:127.0.0.1,{};User ID=me;Password=secret;Encrypt=False",
+            port
+        ))
+        .unwrap();
+        connection.open().await.unwrap();
+        server.await.unwrap();
+        let client_connection_id = connection.client_connection_id();
+        connection.close();
+        connection.open().await.unwrap();
+        assert_eq!(client_connection_id, connection.client_connection_id());
+        connection.close();
+    }
+
+    #[tokio::test]
+    async fn test_open_fills_pool_in_background() {
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let port = listener.local_addr().unwrap().port();
+        // The server logs in the first connection, but never answers the one filling the pool.
+        let server = tokio::spawn(async move {
+            let (stream, _) = listener.accept().await.unwrap();
+            let response = TokenBuilder::new()
+                .login_ack()
+                .done(TdsEnums::SQLDONE, 0, 0, 0);
+            serve_login(stream, TdsEnums::ENCRYPT_NOT_SUP, None, &response).await;
+            let (_stream, _) = listener.accept().await.unwrap();
+            std::future::pending::<()>().await;
+        });
+        let mut connection = SqlConnection::new(&format!(
+            "Server=tcp:127.0.0.1,{};User ID=me;Password=secret;Encrypt=False;Min Pool Size=2",
+            port
+        ))
+        .unwrap();
+        let started = std::time::Instant::now();
+        connection.open().await.unwrap();
+        assert!(started.elapsed() < Duration::from_secs(5));
+        connection.close();
+        server.abort();
+    }
+
+    #[tokio::test]
+    async fn test_open_needs_sql_server_authentication() {
+        let mut connection =
+            SqlConnection::new("Server=test;Integrated Security=true;Pooling=false").unwrap();
+        assert!(matches!(
+            connection.open().await,
+            Err(SqlClientError::NotSupported(_))
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_open_with_retry() {
+        let mut connection = SqlConnection::new("Server=test;Pooling=false").unwrap();
+        connection.set_retry_logic_provider(Some(
+            SqlRetryLogicProvider::none(&SqlRetryLogicOption::default()).unwrap(),
+        ));
+        let mut attempts = 0;
+        connection
+            .open_with(|| {
+                attempts += 1;
+                let result = match attempts {
+                    1 => Err(SqlClientError::Io(
+                        io::ErrorKind::ConnectionRefused,
+                        "connection refused".to_string(),
+                    )),
+                    _ => Ok(DbConnectionInternal::new(TdsParser::new(
+                        Box::new(MockStream::new(Vec::new())),
+                        TdsEnums::DEFAULT_PACKET_SIZE,
+                    ))),
+                };
+                async move { result }
+            })
+            .await
+            .unwrap();
+        assert_eq!(2, attempts);
+        assert!(!connection.client_connection_id().is_nil());
+        // A connection can't be opened twice.
+        assert!(matches!(
+            connection.open_with(|| async { unreachable!() }).await,
+            Err(SqlClientError::InvalidOperation(_))
+        ));
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_open_with_connect_timeout() {
+        let mut connection =
+            SqlConnection::new("Server=test;Pooling=false;Connect Timeout=1").unwrap();
+        connection.set_retry_logic_provider(Some(
+            SqlRetryLogicProvider::none(&SqlRetryLogicOption::default()).unwrap(),
+        ));
+        let mut attempts = 0;
+        let result = connection
+            .open_with(|| {
+                attempts += 1;
+                std::future::pending()
+            })
+            .await;
+        assert!(matches!(result, Err(SqlClientError::Timeout(_))));
+        assert_eq!(1, attempts);
+    }
+
+    /// Opens a connection with a connect that succeeds or is refused, counting the attempts.
+    async fn open(
+        connection: &mut SqlConnection,
+        attempts: &mut u32,
+        succeed: bool,
+    ) -> Result<(), SqlClientError> {
+        connection
+            .open_with(|| {
+                *attempts += 1;
+                let result = match succeed {
+                    true => Ok(DbConnectionInternal::new(TdsParser::new(
+                        Box::new(MockStream::new(Vec::new())),
+                        TdsEnums::DEFAULT_PACKET_SIZE,
+                    ))),
+                    false => Err(SqlClientError::Io(
+                        io::ErrorKind::ConnectionRefused,
+                        "connection refused".to_string(),
+                    )),
+                };
+                async move { result }
+            })
+            .await
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_open_with_circuit_breaker() {
+        let mut provider = SqlRetryLogicProvider::none(&SqlRetryLogicOption::default()).unwrap();
+        provider
+            .set_circuit_breaker(Some(SqlCircuitBreakerOption {
+                failure_threshold: 2,
+                cooldown_method: SqlRetryMethod::Fixed,
+                cooldown: SqlRetryIntervalBuilder::new(Duration::from_secs(5)).randomness(0.0),
+            }))
+            .unwrap();
+        let mut connection = SqlConnection::new("Server=test;Pooling=false").unwrap();
+        connection.set_retry_logic_provider(Some(provider.clone()));
+        let mut attempts = 0;
+        // The retries stop once the circuit opens.
+        match open(&mut connection, &mut attempts, false).await {
+            Err(SqlClientError::Retry(exception)) => assert_eq!(2, exception.attempts().len()),
+            result => panic!("unexpected result {:?}", result.err()),
         }
+        let circuit_breakers = provider.circuit_breakers();
+        assert_eq!(1, circuit_breakers.len());
+        assert_eq!("test", circuit_breakers[0].endpoint());
+        assert_eq!(SqlCircuitBreakerState::Open, circuit_breakers[0].state());
+        // While it's open, opening fails fast.
+        assert!(matches!(
+            open(&mut connection, &mut attempts, true).await,
+            Err(SqlClientError::CircuitOpen(_, _))
+        ));
+        // After the cooldown, a successful trial closes it.
+        tokio::time::advance(Duration::from_secs(5)).await;
+        open(&mut connection, &mut attempts, true).await.unwrap();
+        assert_eq!(SqlCircuitBreakerState::Closed, circuit_breakers[0].state());
+        assert_eq!(3, attempts);
+    }
+
+    #[test]
+    fn test_clone_keeps_info_message_settings() {
+        let mut connection = SqlConnection::new("Server=test").unwrap();
+        connection.on_info_message(|_| {});
+        connection.set_fire_info_message_event_on_user_errors(true);
+        let clone = connection.clone();
+        assert!(clone.info_message_handler.is_some());
+        assert!(clone.fire_info_message_event_on_user_errors());
+    }
+
+    #[test]
+    fn test_clone_keeps_ping_on_checkout() {
+        let mut connection = SqlConnection::new("Server=test").unwrap();
+        connection.set_ping_on_checkout(true);
+        assert!(connection.clone().ping_on_checkout());
+    }
+
+    #[test]
+    fn test_retry_logic_provider_from_connection_string() {
+        let connection =
+            SqlConnection::new("Server=test;Retry Method=Incremental;Retry Number Of Tries=4")
+                .unwrap();
+        let provider = connection.retry_logic_provider().unwrap();
+        assert_eq!(SqlRetryMethod::Incremental, provider.method());
+        assert_eq!(4, provider.number_of_tries());
+        assert!(SqlConnection::new("Server=test")
+            .unwrap()
+            .retry_logic_provider()
+            .is_none());
     }
 }
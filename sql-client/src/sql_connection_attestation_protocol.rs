@@ -2,6 +2,7 @@ use crate::sql_client_error::SqlClientError;
 use std::fmt::{Display, Formatter};
 
 #[derive(PartialEq, Debug, Clone, Copy)]
+#[allow(clippy::upper_case_acronyms)]
 pub(crate) enum SqlConnectionAttestationProtocol {
     NotSpecified = 0,
     AAS = 1,
@@ -0,0 +1,135 @@
+use std::time::Duration;
+
+/// The upper bounds of the wait time histogram's buckets (the last bucket holds longer waits).
+const WAIT_TIME_BUCKETS: [Duration; 5] = [
+    Duration::from_millis(1),
+    Duration::from_millis(10),
+    Duration::from_millis(100),
+    Duration::from_secs(1),
+    Duration::from_secs(10),
+];
+
+/// A snapshot of a connection pool's counts, from [crate::SqlConnection::pool_statistics].
+#[derive(PartialEq, Debug, Clone)]
+pub struct SqlConnectionPoolStatistics {
+    /// The number of connections in the pool waiting to be borrowed.
+    idle_count: usize,
+    /// The number of connections that are borrowed.
+    active_count: usize,
+    /// The number of opens waiting for a connection.
+    pending_count: usize,
+    /// The number of connections the pool has created.
+    created_count: u64,
+    /// The number of connections the pool has discarded.
+    destroyed_count: u64,
+    /// The number of opens that waited for a connection for up to each bucket's upper bound.
+    wait_time_counts: [u64; WAIT_TIME_BUCKETS.len() + 1],
+}
+
+impl SqlConnectionPoolStatistics {
+    /// Creates statistics for a new pool.
+    pub(crate) fn new() -> Self {
+        Self {
+            idle_count: 0,
+            active_count: 0,
+            pending_count: 0,
+            created_count: 0,
+            destroyed_count: 0,
+            wait_time_counts: [0; WAIT_TIME_BUCKETS.len() + 1],
+        }
+    }
+
+    /// Takes a snapshot, with the pool's current counts.
+    pub(crate) fn snapshot(
+        &self,
+        idle_count: usize,
+        active_count: usize,
+        pending_count: usize,
+    ) -> Self {
+        Self {
+            idle_count,
+            active_count,
+            pending_count,
+            ..self.clone()
+        }
+    }
+
+    /// Counts a connection the pool has created.
+    pub(crate) fn record_created(&mut self) {
+        self.created_count += 1;
+    }
+
+    /// Counts connections the pool has discarded.
+    pub(crate) fn record_destroyed(&mut self, count: usize) {
+        self.destroyed_count += count as u64;
+    }
+
+    /// Counts an open that waited for a connection.
+    pub(crate) fn record_wait(&mut self, wait_time: Duration) {
+        let bucket = WAIT_TIME_BUCKETS
+            .iter()
+            .position(|upper_bound| wait_time <= *upper_bound)
+            .unwrap_or(WAIT_TIME_BUCKETS.len());
+        self.wait_time_counts[bucket] += 1;
+    }
+
+    /// The number of connections in the pool waiting to be borrowed.
+    pub fn idle_count(&self) -> usize {
+        self.idle_count
+    }
+
+    /// The number of connections that are borrowed.
+    pub fn active_count(&self) -> usize {
+        self.active_count
+    }
+
+    /// The number of opens waiting for a connection.
+    pub fn pending_count(&self) -> usize {
+        self.pending_count
+    }
+
+    /// The number of connections the pool has created.
+    pub fn created_count(&self) -> u64 {
+        self.created_count
+    }
+
+    /// The number of connections the pool has discarded.
+    pub fn destroyed_count(&self) -> u64 {
+        self.destroyed_count
+    }
+
+    /// How long opens waited for a connection, as the upper bound of each bucket (the last being
+    /// [Duration::MAX]) and the number of opens that waited up to it.
+    pub fn wait_time_histogram(&self) -> Vec<(Duration, u64)> {
+        WAIT_TIME_BUCKETS
+            .iter()
+            .copied()
+            .chain([Duration::MAX])
+            .zip(self.wait_time_counts)
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_wait_time_histogram() {
+        let mut statistics = SqlConnectionPoolStatistics::new();
+        for millis in [0, 1, 5, 250, 60_000] {
+            statistics.record_wait(Duration::from_millis(millis));
+        }
+        assert_eq!(
+            vec![
+                (Duration::from_millis(1), 2),
+                (Duration::from_millis(10), 1),
+                (Duration::from_millis(100), 0),
+                (Duration::from_secs(1), 1),
+                (Duration::from_secs(10), 0),
+                (Duration::MAX, 1),
+            ],
+            statistics.wait_time_histogram()
+        );
+    }
+}
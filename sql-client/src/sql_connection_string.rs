@@ -1,15 +1,18 @@
 use crate::db_connection_string_defaults::DbConnectionStringDefaults;
 use crate::db_connection_string_keywords::DbConnectionStringKeywordsLower;
 use crate::db_connection_string_utils::{
-    convert_to_boolean, convert_to_integrated_security, get_local_db_instance_name_from_server_name,
+    convert_to_boolean, convert_to_duration, convert_to_error_numbers,
+    convert_to_integrated_security, get_local_db_instance_name_from_server_name,
 };
 use crate::sql_credential::SqlCredential;
 use crate::{
     ApplicationIntent, PoolBlockingPeriod, SqlAuthenticationMethod, SqlClientError,
     SqlConnectionAttestationProtocol, SqlConnectionColumnEncryptionSetting,
-    SqlConnectionIpAddressPreference, TransactionBinding, TypeSystem,
+    SqlConnectionIpAddressPreference, SqlRetryLogicOption, SqlRetryLogicProvider, SqlRetryMethod,
+    TransactionBinding, TypeSystem,
 };
 use secstr::SecStr;
+use std::time::Duration;
 
 /// A parsed connection string.  Very similar to [ConnectionStringBuilder].
 pub(crate) struct SqlConnectionString {
@@ -75,6 +78,18 @@ pub(crate) struct SqlConnectionString {
     pool_blocking_period: PoolBlockingPeriod,
     /// Whether replication is supported using the connection.
     replication: bool,
+    /// The gap that the intervals between retries are based on.
+    retry_delta_time: Duration,
+    /// The longest interval between retries.
+    retry_max_time_interval: Duration,
+    /// How the intervals between retries grow, or None to not retry.
+    retry_method: Option<SqlRetryMethod>,
+    /// The shortest interval between retries.
+    retry_min_time_interval: Duration,
+    /// The number of attempts, including the first.
+    retry_number_of_tries: u32,
+    /// The numbers of the errors to retry, or None to retry the errors classified as transient.
+    retry_transient_errors: Option<Vec<i32>>,
     /// Indicates how the connection maintains its association with an enlisted System.Transactions transaction.
     transaction_binding: TransactionBinding,
     /// Whether the channel will be encrypted while bypassing walking the certificate chain to validate trust.
@@ -96,7 +111,7 @@ pub(crate) struct SqlConnectionString {
 impl SqlConnectionString {
     /// Declares the application workload type when connecting to a database in an SQL Server Availability Group.
     pub fn application_intent(&self) -> ApplicationIntent {
-        self.application_intent.clone()
+        self.application_intent
     }
 
     /// The name of the application associated with the connection string.
@@ -111,32 +126,32 @@ impl SqlConnectionString {
 
     /// ???
     pub fn auth_type(&self) -> SqlAuthenticationMethod {
-        self.auth_type.clone()
+        self.auth_type
     }
 
     /// ???
     pub fn column_encryption_setting(&self) -> SqlConnectionColumnEncryptionSetting {
-        self.column_encryption_setting.clone()
+        self.column_encryption_setting
     }
 
     /// The number of reconnections attempted after identifying that there was an idle connection failure. This must be an integer between 0 and 255. Default is 1. Set to 0 to disable reconnecting on idle connection failures.
     pub fn connect_retry_count(&self) -> u8 {
-        self.connect_retry_count.clone()
+        self.connect_retry_count
     }
 
     /// Amount of time (in seconds) between each reconnection attempt after identifying that there was an idle connection failure. This must be an integer between 1 and 60. The default is 10 seconds.
     pub fn connect_retry_interval(&self) -> u8 {
-        self.connect_retry_interval.clone()
+        self.connect_retry_interval
     }
 
     /// The length of time (in seconds) to wait for a connection to the server before terminating the attempt and generating an error.
     pub fn connect_timeout(&self) -> u16 {
-        self.connect_timeout.clone()
+        self.connect_timeout
     }
 
     /// The length of time (in seconds) to wait for a command to the server before terminating the attempt and generating an error.
     pub fn command_timeout(&self) -> u16 {
-        self.command_timeout.clone()
+        self.command_timeout
     }
 
     /// The SQL Server Language record name.
@@ -156,12 +171,12 @@ impl SqlConnectionString {
 
     /// Whether SQL Server uses SSL encryption for all data sent between the client and server if the server has a certificate installed.
     pub fn encrypt(&self) -> bool {
-        self.encrypt.clone()
+        self.encrypt
     }
 
     /// Whether the SQL Server connection pooler automatically enlists the connection in the creation thread's current transaction context.
     pub fn enlist(&self) -> bool {
-        self.enlist.clone()
+        self.enlist
     }
 
     /// Gets or sets a string that contains the name of the primary data file. This includes the full path name of an attachable database.
@@ -181,41 +196,41 @@ impl SqlConnectionString {
 
     /// Whether User ID and Password are specified in the connection (when false) or whether the current Windows account credentials are used for authentication (when true).
     pub fn integrated_security(&self) -> bool {
-        self.integrated_security.clone()
+        self.integrated_security
     }
 
     /// ???
     pub fn ip_address_preference(&self) -> SqlConnectionIpAddressPreference {
-        self.ip_address_preference.clone()
+        self.ip_address_preference
     }
     /// ???
     pub fn load_balance_timeout(&self) -> u16 {
-        self.load_balance_timeout.clone()
+        self.load_balance_timeout
     }
 
     /// The maximum number of connections allowed in the connection pool for this specific connection string.
     pub fn max_pool_size(&self) -> u8 {
-        self.max_pool_size.clone()
+        self.max_pool_size
     }
 
     /// The minimum number of connections allowed in the connection pool for this specific connection string.
     pub fn min_pool_size(&self) -> u8 {
-        self.min_pool_size.clone()
+        self.min_pool_size
     }
 
     /// When true{ self.x.clone() } an application can maintain multiple active result sets (MARS). When false{ self.x.clone() } an application must process or cancel all result sets from one batch before it can execute any other batch on that connection.
     pub fn multiple_active_result_sets(&self) -> bool {
-        self.multiple_active_result_sets.clone()
+        self.multiple_active_result_sets
     }
 
     /// If your application is connecting to an Always On availability group (AG) or Always On Failover Cluster Instance (FCI) on different subnets{ self.x.clone() } setting MultiSubnetFailover=true provides faster detection of and connection to the (currently) active server.
     pub fn multi_subnet_failover(&self) -> bool {
-        self.multi_subnet_failover.clone()
+        self.multi_subnet_failover
     }
 
     /// The size in bytes of the network packets used to communicate with an instance of SQL Server.
     pub fn packet_size(&self) -> u16 {
-        self.packet_size.clone()
+        self.packet_size
     }
 
     /// The password for the SQL Server account.
@@ -225,32 +240,58 @@ impl SqlConnectionString {
 
     /// Indicates if security-sensitive information{ self.x.clone() } such as the password or access token{ self.x.clone() } should be returned as part of the connection string on a connection created with this SqlConnectionStringBuilder after that connection has ever been in an open state.
     pub fn persist_security_info(&self) -> bool {
-        self.persist_security_info.clone()
+        self.persist_security_info
     }
 
     /// Whether the connection will be pooled or explicitly opened every time that the connection is requested.
     pub fn pooling(&self) -> bool {
-        self.pooling.clone()
+        self.pooling
     }
 
     /// The blocking period behavior for a connection pool.
     pub fn pool_blocking_period(&self) -> PoolBlockingPeriod {
-        self.pool_blocking_period.clone()
+        self.pool_blocking_period
     }
 
     /// Whether replication is supported using the connection.
     pub fn replication(&self) -> bool {
-        self.replication.clone()
+        self.replication
+    }
+
+    /// How the intervals between retries grow, or None if opening connections and executing
+    /// commands isn't retried.
+    pub fn retry_method(&self) -> Option<SqlRetryMethod> {
+        self.retry_method
+    }
+
+    /// The settings of the retries.
+    pub fn retry_logic_option(&self) -> SqlRetryLogicOption {
+        SqlRetryLogicOption {
+            number_of_tries: self.retry_number_of_tries,
+            delta_time: self.retry_delta_time,
+            min_time_interval: self.retry_min_time_interval,
+            max_time_interval: self.retry_max_time_interval,
+            transient_errors: self.retry_transient_errors.clone(),
+            authorized_sql_condition: None,
+        }
+    }
+
+    /// The provider that retries opening connections and executing commands, if the connection
+    /// string sets a retry method.
+    pub fn retry_logic_provider(&self) -> Result<Option<SqlRetryLogicProvider>, SqlClientError> {
+        self.retry_method
+            .map(|method| SqlRetryLogicProvider::new(method, &self.retry_logic_option()))
+            .transpose()
     }
 
     /// Indicates how the connection maintains its association with an enlisted System.Transactions transaction.
     pub fn transaction_binding(&self) -> TransactionBinding {
-        self.transaction_binding.clone()
+        self.transaction_binding
     }
 
     /// Whether the channel will be encrypted while bypassing walking the certificate chain to validate trust.
     pub fn trust_server_certificate(&self) -> bool {
-        self.trust_server_certificate.clone()
+        self.trust_server_certificate
     }
 
     /// Indicates the type system the application expects.
@@ -258,6 +299,11 @@ impl SqlConnectionString {
         self.type_system_version.clone()
     }
 
+    /// The type system that values are surfaced as.
+    pub fn type_system(&self) -> TypeSystem {
+        self.type_system_assembly_version
+    }
+
     /// The user ID to be used when connecting to SQL Server.
     pub fn user_id(&self) -> Option<String> {
         self.user_id.clone()
@@ -265,7 +311,7 @@ impl SqlConnectionString {
 
     /// Gets or sets a value that indicates whether to redirect the connection from the default SQL Server Express instance to a runtime-initiated instance running under the account of the caller.
     pub fn user_instance(&self) -> bool {
-        self.user_instance.clone()
+        self.user_instance
     }
 
     /// The name of the workstation connecting to SQL Server.
@@ -335,6 +381,15 @@ impl TryFrom<&str> for SqlConnectionString {
         let mut pool_blocking_period: PoolBlockingPeriod =
             DbConnectionStringDefaults::POOL_BLOCKING_PERIOD;
         let mut replication: bool = DbConnectionStringDefaults::REPLICATION;
+        let mut retry_delta_time: Duration = DbConnectionStringDefaults::RETRY_DELTA_TIME;
+        let mut retry_max_time_interval: Duration =
+            DbConnectionStringDefaults::RETRY_MAX_TIME_INTERVAL;
+        let mut retry_method: Option<SqlRetryMethod> = DbConnectionStringDefaults::RETRY_METHOD;
+        let mut retry_min_time_interval: Duration =
+            DbConnectionStringDefaults::RETRY_MIN_TIME_INTERVAL;
+        let mut retry_number_of_tries: u32 = DbConnectionStringDefaults::RETRY_NUMBER_OF_TRIES;
+        let mut retry_transient_errors: Option<Vec<i32>> =
+            DbConnectionStringDefaults::RETRY_TRANSIENT_ERRORS;
         let mut transaction_binding: TransactionBinding =
             DbConnectionStringDefaults::TRANSACTION_BINDING;
         let mut trust_server_certificate: bool =
@@ -493,6 +548,32 @@ impl TryFrom<&str> for SqlConnectionString {
                     DbConnectionStringKeywordsLower::REPLICATION => {
                         replication = convert_to_boolean(value)?;
                     }
+                    DbConnectionStringKeywordsLower::RETRY_DELTA_TIME
+                    | DbConnectionStringKeywordsLower::RETRY_DELTA_TIME_ALT => {
+                        retry_delta_time = convert_to_duration(value)?;
+                    }
+                    DbConnectionStringKeywordsLower::RETRY_MAX_TIME_INTERVAL
+                    | DbConnectionStringKeywordsLower::RETRY_MAX_TIME_INTERVAL_ALT => {
+                        retry_max_time_interval = convert_to_duration(value)?;
+                    }
+                    DbConnectionStringKeywordsLower::RETRY_METHOD
+                    | DbConnectionStringKeywordsLower::RETRY_METHOD_ALT => {
+                        retry_method = Some(value.try_into()?);
+                    }
+                    DbConnectionStringKeywordsLower::RETRY_MIN_TIME_INTERVAL
+                    | DbConnectionStringKeywordsLower::RETRY_MIN_TIME_INTERVAL_ALT => {
+                        retry_min_time_interval = convert_to_duration(value)?;
+                    }
+                    DbConnectionStringKeywordsLower::RETRY_NUMBER_OF_TRIES
+                    | DbConnectionStringKeywordsLower::RETRY_NUMBER_OF_TRIES_ALT => {
+                        retry_number_of_tries = value.parse().map_err(|_| {
+                            SqlClientError::UnsupportedValue("u32".to_string(), value.to_string())
+                        })?;
+                    }
+                    DbConnectionStringKeywordsLower::RETRY_TRANSIENT_ERRORS
+                    | DbConnectionStringKeywordsLower::RETRY_TRANSIENT_ERRORS_ALT => {
+                        retry_transient_errors = Some(convert_to_error_numbers(value)?);
+                    }
                     DbConnectionStringKeywordsLower::TRANSACTION_BINDING => {
                         transaction_binding = value.try_into()?;
                     }
@@ -563,6 +644,12 @@ impl TryFrom<&str> for SqlConnectionString {
             pooling,
             pool_blocking_period,
             replication,
+            retry_delta_time,
+            retry_max_time_interval,
+            retry_method,
+            retry_min_time_interval,
+            retry_number_of_tries,
+            retry_transient_errors,
             transaction_binding,
             trust_server_certificate,
             type_system_version,
@@ -572,6 +659,8 @@ impl TryFrom<&str> for SqlConnectionString {
             workstation_id,
             expanded_attach_db_filename,
         };
+        // Make sure the retry settings are valid.
+        sql_connection_string.retry_logic_provider()?;
         // Return it
         Ok(sql_connection_string)
     }
@@ -697,13 +786,13 @@ mod tests {
     #[test]
     pub fn test_parse_encrypt() {
         let connection_string: SqlConnectionString = "Encrypt=Yes".try_into().unwrap();
-        assert_eq!(true, connection_string.encrypt)
+        assert!(connection_string.encrypt)
     }
 
     #[test]
     pub fn test_parse_enlist() {
         let connection_string: SqlConnectionString = "Enlist=Yes".try_into().unwrap();
-        assert_eq!(true, connection_string.enlist)
+        assert!(connection_string.enlist)
     }
 
     #[test]
@@ -724,7 +813,7 @@ mod tests {
     #[test]
     pub fn test_parse_integrated_security() {
         let connection_string: SqlConnectionString = "Integrated Security=Yes".try_into().unwrap();
-        assert_eq!(true, connection_string.integrated_security)
+        assert!(connection_string.integrated_security)
     }
 
     #[test]
@@ -759,14 +848,14 @@ mod tests {
     pub fn test_parse_multiple_active_result_sets() {
         let connection_string: SqlConnectionString =
             "Multiple Active Result Sets=Yes".try_into().unwrap();
-        assert_eq!(true, connection_string.multiple_active_result_sets)
+        assert!(connection_string.multiple_active_result_sets)
     }
 
     #[test]
     pub fn test_parse_multi_subnet_failover() {
         let connection_string: SqlConnectionString =
             "Multi Subnet Failover=Yes".try_into().unwrap();
-        assert_eq!(true, connection_string.multi_subnet_failover)
+        assert!(connection_string.multi_subnet_failover)
     }
 
     #[test]
@@ -785,7 +874,7 @@ mod tests {
     pub fn test_parse_persist_security_info() {
         let connection_string: SqlConnectionString =
             "Persist Security Info=Yes".try_into().unwrap();
-        assert_eq!(true, connection_string.persist_security_info)
+        assert!(connection_string.persist_security_info)
     }
 
     #[test]
@@ -801,13 +890,51 @@ mod tests {
     #[test]
     pub fn test_parse_pool_blocking_period() {
         let connection_string: SqlConnectionString = "Pooling=Yes".try_into().unwrap();
-        assert_eq!(true, connection_string.pooling)
+        assert!(connection_string.pooling)
     }
 
     #[test]
     pub fn test_parse_replication() {
         let connection_string: SqlConnectionString = "Replication=Yes".try_into().unwrap();
-        assert_eq!(true, connection_string.replication)
+        assert!(connection_string.replication)
+    }
+
+    #[test]
+    pub fn test_parse_retry_logic() {
+        let connection_string: SqlConnectionString = "Retry Method=Fixed;Retry Number Of Tries=5;\
+            Retry Delta Time=0.5;Retry Min Time Interval=0.25;Retry Max Time Interval=10;\
+            Retry Transient Errors=1205,4060"
+            .try_into()
+            .unwrap();
+        assert_eq!(
+            Some(SqlRetryMethod::Fixed),
+            connection_string.retry_method()
+        );
+        let option = connection_string.retry_logic_option();
+        assert_eq!(5, option.number_of_tries);
+        assert_eq!(Duration::from_millis(500), option.delta_time);
+        assert_eq!(Duration::from_millis(250), option.min_time_interval);
+        assert_eq!(Duration::from_secs(10), option.max_time_interval);
+        assert_eq!(Some(vec![1205, 4060]), option.transient_errors);
+        let provider = connection_string.retry_logic_provider().unwrap().unwrap();
+        assert_eq!(SqlRetryMethod::Fixed, provider.method());
+        assert_eq!(5, provider.number_of_tries());
+    }
+
+    #[test]
+    pub fn test_parse_no_retry_logic() {
+        let connection_string: SqlConnectionString = "Retry Number Of Tries=5".try_into().unwrap();
+        assert!(connection_string.retry_logic_provider().unwrap().is_none());
+    }
+
+    #[rstest::rstest]
+    #[case("Retry Method=Linear")]
+    #[case("Retry Method=Fixed;Retry Number Of Tries=0")]
+    #[case("Retry Method=Fixed;Retry Delta Time=-1")]
+    #[case("Retry Method=Fixed;Retry Min Time Interval=5;Retry Max Time Interval=1")]
+    #[case("Retry Method=Fixed;Retry Transient Errors=deadlock")]
+    pub fn test_parse_invalid_retry_logic(#[case] value: &str) {
+        assert!(SqlConnectionString::try_from(value).is_err());
     }
 
     #[test]
@@ -824,7 +951,7 @@ mod tests {
     pub fn test_parse_trust_server_certificate() {
         let connection_string: SqlConnectionString =
             "Trust Server Certificate=Yes".try_into().unwrap();
-        assert_eq!(true, connection_string.trust_server_certificate)
+        assert!(connection_string.trust_server_certificate)
     }
 
     #[test]
@@ -841,6 +968,13 @@ mod tests {
         );
     }
 
+    #[test]
+    pub fn test_parse_type_system_version_latest() {
+        let connection_string: SqlConnectionString =
+            "Type System Version=Latest".try_into().unwrap();
+        assert_eq!(TypeSystem::SqlServer2012, connection_string.type_system());
+    }
+
     #[test]
     pub fn test_parse_user_id() {
         let connection_string: SqlConnectionString = "User ID=Some User".try_into().unwrap();
@@ -850,7 +984,7 @@ mod tests {
     #[test]
     pub fn test_parse_user_instance() {
         let connection_string: SqlConnectionString = "User Instance=True".try_into().unwrap();
-        assert_eq!(true, connection_string.user_instance)
+        assert!(connection_string.user_instance)
     }
 
     #[test]
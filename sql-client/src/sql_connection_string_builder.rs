@@ -5,11 +5,12 @@ use super::db_connection_string_keywords::{
 use super::db_connection_string_utils::*;
 use crate::{
     ApplicationIntent, PoolBlockingPeriod, SqlAuthenticationMethod, SqlClientError,
-    SqlConnectionColumnEncryptionSetting, SqlConnectionIpAddressPreference,
+    SqlConnectionColumnEncryptionSetting, SqlConnectionIpAddressPreference, SqlRetryMethod,
 };
 use secstr::SecStr;
 use std::collections::{HashMap, HashSet};
 use std::fmt::Display;
+use std::time::Duration;
 
 // The available keywords
 enum Keyword {
@@ -42,6 +43,12 @@ enum Keyword {
     Pooling,
     PoolBlockingPeriod,
     Replication,
+    RetryDeltaTime,
+    RetryMaxTimeInterval,
+    RetryMethod,
+    RetryMinTimeInterval,
+    RetryNumberOfTries,
+    RetryTransientErrors,
     TransactionBinding,
     TypeSystemVersion,
     UserId,
@@ -55,7 +62,7 @@ enum Keyword {
 /// Appends a keyword/value pair to a connection string.
 fn append(connection_string: &mut String, keyword: &str, value: &str) {
     // If we have existing values...
-    if connection_string.len() > 0 {
+    if !connection_string.is_empty() {
         // Add the value delimiter
         connection_string.push(';');
     }
@@ -158,6 +165,18 @@ pub struct SqlConnectionStringBuilder {
     pool_blocking_period: PoolBlockingPeriod,
     /// Whether replication is supported using the connection.
     replication: bool,
+    /// The gap that the intervals between retries are based on.
+    retry_delta_time: Duration,
+    /// The longest interval between retries.
+    retry_max_time_interval: Duration,
+    /// How the intervals between retries grow, or None to not retry.
+    retry_method: Option<SqlRetryMethod>,
+    /// The shortest interval between retries.
+    retry_min_time_interval: Duration,
+    /// The number of attempts, including the first.
+    retry_number_of_tries: u32,
+    /// The numbers of the errors to retry, or None to retry the errors classified as transient.
+    retry_transient_errors: Option<Vec<i32>>,
     /// Indicates how the connection maintains its association with an enlisted System.Transactions transaction.
     transaction_binding: String,
     /// Whether the channel will be encrypted while bypassing walking the certificate chain to validate trust.
@@ -177,7 +196,7 @@ pub struct SqlConnectionStringBuilder {
 impl SqlConnectionStringBuilder {
     /// Declares the application workload type when connecting to a database in an SQL Server Availability Group.
     pub fn application_intent(&self) -> ApplicationIntent {
-        self.application_intent.clone()
+        self.application_intent
     }
 
     /// The name of the application associated with the connection string.
@@ -192,32 +211,32 @@ impl SqlConnectionStringBuilder {
 
     /// ?
     pub fn authentication(&self) -> SqlAuthenticationMethod {
-        self.authentication.clone()
+        self.authentication
     }
 
     /// ?
     pub fn column_encryption_setting(&self) -> SqlConnectionColumnEncryptionSetting {
-        self.column_encryption_setting.clone()
+        self.column_encryption_setting
     }
 
     /// The number of reconnections attempted after identifying that there was an idle connection failure. This must be an integer between 0 and 255. Default is 1. Set to 0 to disable reconnecting on idle connection failures.
     pub fn connect_retry_count(&self) -> u8 {
-        self.connect_retry_count.clone()
+        self.connect_retry_count
     }
 
     /// Amount of time (in seconds) between each reconnection attempt after identifying that there was an idle connection failure. This must be an integer between 1 and 60. The default is 10 seconds.
     pub fn connect_retry_interval(&self) -> u8 {
-        self.connect_retry_interval.clone()
+        self.connect_retry_interval
     }
 
     /// The length of time (in seconds) to wait for a connection to the server before terminating the attempt and generating an error.
     pub fn connect_timeout(&self) -> u16 {
-        self.connect_timeout.clone()
+        self.connect_timeout
     }
 
     /// The length of time (in seconds) to wait for a command to the server before terminating the attempt and generating an error.
     pub fn command_timeout(&self) -> u16 {
-        self.command_timeout.clone()
+        self.command_timeout
     }
 
     /// The SQL Server Language record name.
@@ -237,12 +256,12 @@ impl SqlConnectionStringBuilder {
 
     /// Whether SQL Server uses SSL encryption for all data sent between the client and server if the server has a certificate installed.
     pub fn encrypt(&self) -> bool {
-        self.encrypt.clone()
+        self.encrypt
     }
 
     /// Whether the SQL Server connection pooler automatically enlists the connection in the creation thread's current transaction context.
     pub fn enlist(&self) -> bool {
-        self.enlist.clone()
+        self.enlist
     }
 
     /// The name or address of the partner server to connect to if the primary server is down.
@@ -257,41 +276,41 @@ impl SqlConnectionStringBuilder {
 
     /// Whether User ID and Password are specified in the connection (when false) or whether the current Windows account credentials are used for authentication (when true).
     pub fn integrated_security(&self) -> bool {
-        self.integrated_security.clone()
+        self.integrated_security
     }
 
     /// ?
     pub fn ip_address_preference(&self) -> SqlConnectionIpAddressPreference {
-        self.ip_address_preference.clone()
+        self.ip_address_preference
     }
     /// ??
     pub fn load_balance_timeout(&self) -> u16 {
-        self.load_balance_timeout.clone()
+        self.load_balance_timeout
     }
 
     /// The maximum number of connections allowed in the connection pool for this specific connection string.
     pub fn max_pool_size(&self) -> u8 {
-        self.max_pool_size.clone()
+        self.max_pool_size
     }
 
     /// The minimum number of connections allowed in the connection pool for this specific connection string.
     pub fn min_pool_size(&self) -> u8 {
-        self.min_pool_size.clone()
+        self.min_pool_size
     }
 
     /// When true{ self.x.clone() } an application can maintain multiple active result sets (MARS). When false{ self.x.clone() } an application must process or cancel all result sets from one batch before it can execute any other batch on that connection.
     pub fn multiple_active_result_sets(&self) -> bool {
-        self.multiple_active_result_sets.clone()
+        self.multiple_active_result_sets
     }
 
     /// If your application is connecting to an Always On availability group (AG) or Always On Failover Cluster Instance (FCI) on different subnets{ self.x.clone() } setting MultiSubnetFailover=true provides faster detection of and connection to the (currently) active server.
     pub fn multi_subnet_failover(&self) -> bool {
-        self.multi_subnet_failover.clone()
+        self.multi_subnet_failover
     }
 
     /// The size in bytes of the network packets used to communicate with an instance of SQL Server.
     pub fn packet_size(&self) -> u16 {
-        self.packet_size.clone()
+        self.packet_size
     }
 
     /// The password for the SQL Server account.
@@ -301,22 +320,53 @@ impl SqlConnectionStringBuilder {
 
     /// Indicates if security-sensitive information{ self.x.clone() } such as the password or access token{ self.x.clone() } should be returned as part of the connection string on a connection created with this SqlConnectionStringBuilder after that connection has ever been in an open state.
     pub fn persist_security_info(&self) -> bool {
-        self.persist_security_info.clone()
+        self.persist_security_info
     }
 
     /// Whether the connection will be pooled or explicitly opened every time that the connection is requested.
     pub fn pooling(&self) -> bool {
-        self.pooling.clone()
+        self.pooling
     }
 
     /// The blocking period behavior for a connection pool.
     pub fn pool_blocking_period(&self) -> PoolBlockingPeriod {
-        self.pool_blocking_period.clone()
+        self.pool_blocking_period
     }
 
     /// Whether replication is supported using the connection.
     pub fn replication(&self) -> bool {
-        self.replication.clone()
+        self.replication
+    }
+
+    /// The gap that the intervals between retries are based on.
+    pub fn retry_delta_time(&self) -> Duration {
+        self.retry_delta_time
+    }
+
+    /// The longest interval between retries.
+    pub fn retry_max_time_interval(&self) -> Duration {
+        self.retry_max_time_interval
+    }
+
+    /// How the intervals between retries of opening the connection and executing commands grow,
+    /// or None to not retry.
+    pub fn retry_method(&self) -> Option<SqlRetryMethod> {
+        self.retry_method
+    }
+
+    /// The shortest interval between retries.
+    pub fn retry_min_time_interval(&self) -> Duration {
+        self.retry_min_time_interval
+    }
+
+    /// The number of attempts, including the first.
+    pub fn retry_number_of_tries(&self) -> u32 {
+        self.retry_number_of_tries
+    }
+
+    /// The numbers of the errors to retry, or None to retry the errors classified as transient.
+    pub fn retry_transient_errors(&self) -> Option<Vec<i32>> {
+        self.retry_transient_errors.clone()
     }
 
     /// Indicates how the connection maintains its association with an enlisted System.Transactions transaction.
@@ -326,7 +376,7 @@ impl SqlConnectionStringBuilder {
 
     /// Whether the channel will be encrypted while bypassing walking the certificate chain to validate trust.
     pub fn trust_server_certificate(&self) -> bool {
-        self.trust_server_certificate.clone()
+        self.trust_server_certificate
     }
 
     /// Indicates the type system the application expects.
@@ -341,7 +391,7 @@ impl SqlConnectionStringBuilder {
 
     /// Gets or sets a value that indicates whether to redirect the connection from the default SQL Server Express instance to a runtime-initiated instance running under the account of the caller.
     pub fn user_instance(&self) -> bool {
-        self.user_instance.clone()
+        self.user_instance
     }
 
     /// The name of the workstation connecting to SQL Server.
@@ -547,6 +597,50 @@ impl SqlConnectionStringBuilder {
                         &self.replication,
                     );
                 }
+                Keyword::RetryDeltaTime => {
+                    append_str(
+                        &mut value,
+                        DbConnectionStringKeywords::RETRY_DELTA_TIME,
+                        format_duration(&self.retry_delta_time),
+                    );
+                }
+                Keyword::RetryMaxTimeInterval => {
+                    append_str(
+                        &mut value,
+                        DbConnectionStringKeywords::RETRY_MAX_TIME_INTERVAL,
+                        format_duration(&self.retry_max_time_interval),
+                    );
+                }
+                Keyword::RetryMethod => {
+                    append_opt(
+                        &mut value,
+                        DbConnectionStringKeywords::RETRY_METHOD,
+                        self.retry_method.map(|method| method.to_string()),
+                    );
+                }
+                Keyword::RetryMinTimeInterval => {
+                    append_str(
+                        &mut value,
+                        DbConnectionStringKeywords::RETRY_MIN_TIME_INTERVAL,
+                        format_duration(&self.retry_min_time_interval),
+                    );
+                }
+                Keyword::RetryNumberOfTries => {
+                    append_str(
+                        &mut value,
+                        DbConnectionStringKeywords::RETRY_NUMBER_OF_TRIES,
+                        self.retry_number_of_tries.to_string(),
+                    );
+                }
+                Keyword::RetryTransientErrors => {
+                    append_opt(
+                        &mut value,
+                        DbConnectionStringKeywords::RETRY_TRANSIENT_ERRORS,
+                        self.retry_transient_errors
+                            .as_deref()
+                            .map(format_error_numbers),
+                    );
+                }
                 Keyword::TransactionBinding => {
                     append_str(
                         &mut value,
@@ -769,6 +863,43 @@ impl SqlConnectionStringBuilder {
         self.keywords_in_use.push(Keyword::Replication);
     }
 
+    /// The gap that the intervals between retries are based on.
+    pub fn set_retry_delta_time(&mut self, value: Duration) {
+        self.retry_delta_time = value;
+        self.keywords_in_use.push(Keyword::RetryDeltaTime);
+    }
+
+    /// The longest interval between retries.
+    pub fn set_retry_max_time_interval(&mut self, value: Duration) {
+        self.retry_max_time_interval = value;
+        self.keywords_in_use.push(Keyword::RetryMaxTimeInterval);
+    }
+
+    /// How the intervals between retries of opening the connection and executing commands grow,
+    /// or None to not retry.
+    pub fn set_retry_method(&mut self, value: Option<SqlRetryMethod>) {
+        self.retry_method = value;
+        self.keywords_in_use.push(Keyword::RetryMethod);
+    }
+
+    /// The shortest interval between retries.
+    pub fn set_retry_min_time_interval(&mut self, value: Duration) {
+        self.retry_min_time_interval = value;
+        self.keywords_in_use.push(Keyword::RetryMinTimeInterval);
+    }
+
+    /// The number of attempts, including the first.
+    pub fn set_retry_number_of_tries(&mut self, value: u32) {
+        self.retry_number_of_tries = value;
+        self.keywords_in_use.push(Keyword::RetryNumberOfTries);
+    }
+
+    /// The numbers of the errors to retry, or None to retry the errors classified as transient.
+    pub fn set_retry_transient_errors(&mut self, value: Option<Vec<i32>>) {
+        self.retry_transient_errors = value;
+        self.keywords_in_use.push(Keyword::RetryTransientErrors);
+    }
+
     /// Indicates how the connection maintains its association with an enlisted System.Transactions transaction.
     pub fn set_transaction_binding(&mut self, value: String) {
         self.transaction_binding = value;
@@ -838,6 +969,12 @@ impl Default for SqlConnectionStringBuilder {
             pooling: DbConnectionStringDefaults::POOLING,
             pool_blocking_period: DbConnectionStringDefaults::POOL_BLOCKING_PERIOD,
             replication: DbConnectionStringDefaults::REPLICATION,
+            retry_delta_time: DbConnectionStringDefaults::RETRY_DELTA_TIME,
+            retry_max_time_interval: DbConnectionStringDefaults::RETRY_MAX_TIME_INTERVAL,
+            retry_method: DbConnectionStringDefaults::RETRY_METHOD,
+            retry_min_time_interval: DbConnectionStringDefaults::RETRY_MIN_TIME_INTERVAL,
+            retry_number_of_tries: DbConnectionStringDefaults::RETRY_NUMBER_OF_TRIES,
+            retry_transient_errors: DbConnectionStringDefaults::RETRY_TRANSIENT_ERRORS,
             transaction_binding: DbConnectionStringDefaults::TRANSACTION_BINDING_STR.to_string(),
             type_system_version: DbConnectionStringDefaults::TYPE_SYSTEM_VERSION.to_string(),
             user_id: DbConnectionStringDefaults::USER_ID,
@@ -1020,6 +1157,37 @@ impl TryFrom<&str> for SqlConnectionStringBuilder {
                         let replication = convert_to_boolean(value)?;
                         connection_string_builder.set_replication(replication);
                     }
+                    DbConnectionStringKeywordsLower::RETRY_DELTA_TIME
+                    | DbConnectionStringKeywordsLower::RETRY_DELTA_TIME_ALT => {
+                        connection_string_builder.set_retry_delta_time(convert_to_duration(value)?);
+                    }
+                    DbConnectionStringKeywordsLower::RETRY_MAX_TIME_INTERVAL
+                    | DbConnectionStringKeywordsLower::RETRY_MAX_TIME_INTERVAL_ALT => {
+                        connection_string_builder
+                            .set_retry_max_time_interval(convert_to_duration(value)?);
+                    }
+                    DbConnectionStringKeywordsLower::RETRY_METHOD
+                    | DbConnectionStringKeywordsLower::RETRY_METHOD_ALT => {
+                        let retry_method: SqlRetryMethod = value.try_into()?;
+                        connection_string_builder.set_retry_method(Some(retry_method));
+                    }
+                    DbConnectionStringKeywordsLower::RETRY_MIN_TIME_INTERVAL
+                    | DbConnectionStringKeywordsLower::RETRY_MIN_TIME_INTERVAL_ALT => {
+                        connection_string_builder
+                            .set_retry_min_time_interval(convert_to_duration(value)?);
+                    }
+                    DbConnectionStringKeywordsLower::RETRY_NUMBER_OF_TRIES
+                    | DbConnectionStringKeywordsLower::RETRY_NUMBER_OF_TRIES_ALT => {
+                        let retry_number_of_tries: u32 = value.parse().map_err(|_| {
+                            SqlClientError::UnsupportedValue("u32".to_string(), value.to_string())
+                        })?;
+                        connection_string_builder.set_retry_number_of_tries(retry_number_of_tries);
+                    }
+                    DbConnectionStringKeywordsLower::RETRY_TRANSIENT_ERRORS
+                    | DbConnectionStringKeywordsLower::RETRY_TRANSIENT_ERRORS_ALT => {
+                        connection_string_builder
+                            .set_retry_transient_errors(Some(convert_to_error_numbers(value)?));
+                    }
                     DbConnectionStringKeywordsLower::TRANSACTION_BINDING => {
                         connection_string_builder.set_transaction_binding(value.to_string());
                     }
@@ -1132,6 +1300,21 @@ mod tests {
     #[case("Pool Blocking Period=AlwaysBlock", "Pool Blocking Period=AlwaysBlock")]
     #[case("PoolBlockingPeriod=AlwaysBlock", "Pool Blocking Period=AlwaysBlock")]
     #[case("Replication=yes", "Replication=True")]
+    #[case("Retry Delta Time=0.5", "Retry Delta Time=0.5")]
+    #[case("RetryDeltaTime=2", "Retry Delta Time=2")]
+    #[case("Retry Max Time Interval=10", "Retry Max Time Interval=10")]
+    #[case("RetryMaxTimeInterval=10", "Retry Max Time Interval=10")]
+    #[case("Retry Method=incremental", "Retry Method=Incremental")]
+    #[case("RetryMethod=None", "Retry Method=None")]
+    #[case("Retry Min Time Interval=0.25", "Retry Min Time Interval=0.25")]
+    #[case("RetryMinTimeInterval=1", "Retry Min Time Interval=1")]
+    #[case("Retry Number Of Tries=5", "Retry Number Of Tries=5")]
+    #[case("RetryNumberOfTries=5", "Retry Number Of Tries=5")]
+    #[case(
+        "Retry Transient Errors=1205, 4060",
+        "Retry Transient Errors=1205,4060"
+    )]
+    #[case("RetryTransientErrors=1205", "Retry Transient Errors=1205")]
     #[case("Transaction Binding=ABC", "Transaction Binding=ABC")]
     #[case("Trust Server Certificate=Yes", "Trust Server Certificate=True")]
     #[case("TrustServerCertificate=No", "Trust Server Certificate=False")]
@@ -0,0 +1,996 @@
+use crate::sql_collation::TextDecoder;
+use crate::sql_error::SqlError;
+use crate::tds_enums::TdsEnums;
+use crate::tds_parser::TdsParser;
+use crate::tds_token::{ColumnMetaData, TdsToken};
+use crate::tds_type_info::TypeInfo;
+use crate::{
+    CommandBehavior, FromSql, FromSqlRow, SqlClientError, SqlCommand, SqlDbType, SqlDecimal,
+    SqlMoney, SqlStream, SqlTextReader, SqlValue,
+};
+use chrono::{DateTime, FixedOffset, NaiveDate, NaiveDateTime, NaiveTime};
+use std::sync::Arc;
+use uuid::Uuid;
+
+/// Where the reader is within the response.
+#[derive(PartialEq, Debug, Clone, Copy)]
+enum ReaderState {
+    /// Positioned in a result set (before its first row, on a row, or after its last row has been read).
+    InResult,
+    /// The current result set has been read and the next one hasn't been started.
+    BetweenResults,
+    /// The whole response has been read.
+    Finished,
+}
+
+/// Reads a forward-only stream of rows from the results of a [SqlCommand].
+///
+/// Rows are read from the connection one at a time as [SqlDataReader::read] is called, so only
+/// the current row is held in memory.  Output parameters and the return value of the command are
+/// populated once all the results have been read (e.g. by [SqlDataReader::close]).
+///
+/// With [CommandBehavior::SEQUENTIAL_ACCESS], even the current row isn't held in memory: moving to
+/// a row reads its columns up to the first large (max, text, ntext, image or xml) column, and the
+/// rest are read in order as they're asked for with [SqlDataReader::load_value],
+/// [SqlDataReader::get_stream] or [SqlDataReader::get_text_reader].  Once a column has been passed,
+/// its value is only available if it was kept in memory.
+pub struct SqlDataReader<'a> {
+    /// The connection's parser.
+    parser: &'a mut TdsParser,
+    /// The command being executed.
+    command: &'a mut SqlCommand,
+    /// The metadata of the current result set.
+    columns: Arc<Vec<ColumnMetaData>>,
+    /// How the results are read.
+    behavior: CommandBehavior,
+    /// The values of the current row.  With sequential access, columns that haven't been read (or
+    /// that were streamed or skipped) are None.
+    row: Vec<Option<SqlValue>>,
+    /// Which columns of the current row are known to be null.
+    nulls: Vec<bool>,
+    /// The next column of the current row to be read from the connection.
+    next_column: usize,
+    /// Whether the reader is positioned on a row.
+    has_row: bool,
+    /// Where the reader is within the response.
+    state: ReaderState,
+    /// Whether a new result set's metadata has been read but not yet moved to.
+    pending_metadata: bool,
+    /// The number of rows changed, inserted or deleted so far.
+    records_affected: u64,
+    /// Errors returned by the server that haven't yet been reported.
+    errors: Vec<SqlError>,
+}
+
+impl<'a> SqlDataReader<'a> {
+    /// Opens a reader over a command's results.  If the results fail before the first result set,
+    /// the parser and command are returned with the error, so that the command can be retried.
+    pub(crate) async fn new(
+        parser: &'a mut TdsParser,
+        command: &'a mut SqlCommand,
+        behavior: CommandBehavior,
+    ) -> Result<SqlDataReader<'a>, (SqlClientError, &'a mut TdsParser, &'a mut SqlCommand)> {
+        let mut reader = Self {
+            parser,
+            command,
+            columns: Arc::new(Vec::new()),
+            behavior,
+            row: Vec::new(),
+            nulls: Vec::new(),
+            next_column: 0,
+            has_row: false,
+            state: ReaderState::BetweenResults,
+            pending_metadata: false,
+            records_affected: 0,
+            errors: Vec::new(),
+        };
+        match reader.advance_to_result().await {
+            Ok(_) => Ok(reader),
+            Err(error) => Err((error, reader.parser, reader.command)),
+        }
+    }
+
+    /// Handles a token that doesn't belong to a result set.
+    fn on_token(&mut self, token: TdsToken) -> Result<(), SqlClientError> {
+        match &token {
+            TdsToken::Error(error) => self.errors.push(error.clone()),
+            TdsToken::Done(done) => {
+                self.records_affected += done.records_affected().unwrap_or(0);
+                if done.is_final() {
+                    self.state = ReaderState::Finished;
+                }
+            }
+            _ => {}
+        }
+        self.command.on_token(&token);
+        // Errors are reported at the end of the statement that raised them.
+        if matches!(token, TdsToken::Done(_)) && !self.errors.is_empty() {
+            return SqlCommand::check_errors(
+                std::mem::take(&mut self.errors),
+                self.parser.client_connection_id(),
+            );
+        }
+        Ok(())
+    }
+
+    /// Reads up to the start of the next result set.  Returns false if there are no more.
+    async fn advance_to_result(&mut self) -> Result<bool, SqlClientError> {
+        loop {
+            // If we've already read the metadata, we're there.
+            if self.pending_metadata {
+                self.pending_metadata = false;
+                self.columns = self.parser.columns().clone();
+                self.state = ReaderState::InResult;
+                return Ok(true);
+            }
+            if self.state == ReaderState::Finished {
+                return Ok(false);
+            }
+            match self.parser.next_token().await? {
+                // Statements that return no columns (e.g. an empty metadata token) don't start a result set.
+                TdsToken::ColMetaData => self.pending_metadata = !self.parser.columns().is_empty(),
+                TdsToken::Row => self.parser.skip_row(false).await?,
+                TdsToken::NbcRow => self.parser.skip_row(true).await?,
+                token => self.on_token(token)?,
+            }
+        }
+    }
+
+    /// Whether columns are read in order as they're asked for.
+    fn is_sequential(&self) -> bool {
+        self.behavior.contains(CommandBehavior::SEQUENTIAL_ACCESS)
+    }
+
+    /// Whether a column's values are large enough that they aren't read unless asked for.
+    fn is_large(type_info: &TypeInfo) -> bool {
+        type_info.is_plp()
+            || matches!(
+                type_info.tds_type,
+                TdsEnums::SQLTEXT | TdsEnums::SQLNTEXT | TdsEnums::SQLIMAGE
+            )
+    }
+
+    /// Starts reading a row, which is read up to its first large column with sequential access or
+    /// completely otherwise.
+    async fn start_row(&mut self, nbc: bool) -> Result<bool, SqlClientError> {
+        if self.is_sequential() {
+            self.nulls = self.parser.read_row_nulls(nbc).await?;
+            self.row = vec![None; self.nulls.len()];
+            self.next_column = 0;
+            let first_large = self
+                .columns
+                .iter()
+                .position(|column| Self::is_large(&column.type_info))
+                .unwrap_or(self.columns.len());
+            self.skip_to(first_large).await?;
+        } else {
+            let row = self.parser.read_row(nbc).await?;
+            self.nulls = row.iter().map(SqlValue::is_null).collect();
+            self.row = row.into_iter().map(Some).collect();
+            self.next_column = self.row.len();
+        }
+        self.has_row = true;
+        Ok(true)
+    }
+
+    /// Reads up to column `i` of the current row, finishing any stream first.  Small values are kept
+    /// in memory on the way and large ones are skipped.
+    async fn skip_to(&mut self, i: usize) -> Result<(), SqlClientError> {
+        self.parser.skip_plp().await?;
+        while self.next_column < i {
+            let column = self.next_column;
+            if self.nulls[column] {
+                self.row[column] = Some(SqlValue::Null);
+            } else if Self::is_large(&self.columns[column].type_info) {
+                self.parser.skip_column(column).await?;
+            } else {
+                self.row[column] = Some(self.parser.read_column(column).await?);
+            }
+            self.next_column += 1;
+        }
+        Ok(())
+    }
+
+    /// Reads whatever is left of the current row.
+    async fn finish_row(&mut self) -> Result<(), SqlClientError> {
+        self.skip_to(self.row.len()).await?;
+        self.has_row = false;
+        Ok(())
+    }
+
+    /// Advances to the next row of the current result set.  Returns false if there are no more rows.
+    pub async fn read(&mut self) -> Result<bool, SqlClientError> {
+        self.finish_row().await?;
+        while self.state == ReaderState::InResult {
+            match self.parser.next_token().await? {
+                TdsToken::Row => return self.start_row(false).await,
+                TdsToken::NbcRow => return self.start_row(true).await,
+                // A new result set starts without the current one having finished.
+                TdsToken::ColMetaData => {
+                    self.pending_metadata = !self.parser.columns().is_empty();
+                    self.state = ReaderState::BetweenResults;
+                }
+                token @ TdsToken::Done(_) => {
+                    // The end of the result set
+                    self.state = ReaderState::BetweenResults;
+                    self.on_token(token)?;
+                }
+                token => self.on_token(token)?,
+            }
+        }
+        Ok(false)
+    }
+
+    /// Advances to the next result set.  Returns false if there are no more result sets.
+    pub async fn next_result(&mut self) -> Result<bool, SqlClientError> {
+        self.finish_row().await?;
+        // Skip the rest of the current result set
+        while self.state == ReaderState::InResult {
+            match self.parser.next_token().await? {
+                TdsToken::Row => self.parser.skip_row(false).await?,
+                TdsToken::NbcRow => self.parser.skip_row(true).await?,
+                TdsToken::ColMetaData => {
+                    self.pending_metadata = !self.parser.columns().is_empty();
+                    self.state = ReaderState::BetweenResults;
+                }
+                token @ TdsToken::Done(_) => {
+                    self.state = ReaderState::BetweenResults;
+                    self.on_token(token)?;
+                }
+                token => self.on_token(token)?,
+            }
+        }
+        // Move to the next one
+        self.advance_to_result().await
+    }
+
+    /// Reads the rest of the response, so that the command's output parameters are populated.
+    pub async fn close(mut self) -> Result<(), SqlClientError> {
+        while self.next_result().await? {}
+        Ok(())
+    }
+
+    /// Whether the whole response has been read.
+    pub fn is_closed(&self) -> bool {
+        self.state == ReaderState::Finished
+    }
+
+    /// The number of rows changed, inserted, or deleted by the statements executed so far.
+    pub fn records_affected(&self) -> u64 {
+        self.records_affected
+    }
+
+    /// The number of columns in the current result set.
+    pub fn field_count(&self) -> usize {
+        self.columns.len()
+    }
+
+    /// The metadata of the current result set.
+    pub(crate) fn columns(&self) -> &[ColumnMetaData] {
+        &self.columns
+    }
+
+    /// The name of a column.
+    pub fn get_name(&self, i: usize) -> Result<&str, SqlClientError> {
+        self.columns
+            .get(i)
+            .map(|column| column.column_name.as_str())
+            .ok_or_else(|| SqlClientError::ArgumentOutOfRange("i".to_string(), i.to_string()))
+    }
+
+    /// The name of a column's data type, as surfaced in the connection's type system.
+    pub fn get_data_type_name(&self, i: usize) -> Result<String, SqlClientError> {
+        self.columns
+            .get(i)
+            .map(|column| {
+                column
+                    .type_info
+                    .for_type_system(self.parser.type_system())
+                    .sql_db_type()
+                    .to_string()
+            })
+            .ok_or_else(|| SqlClientError::ArgumentOutOfRange("i".to_string(), i.to_string()))
+    }
+
+    /// The ordinal of a column given its name.  An exact match is preferred, otherwise case is ignored.
+    pub fn get_ordinal(&self, name: &str) -> Result<usize, SqlClientError> {
+        self.columns
+            .iter()
+            .position(|column| column.column_name == name)
+            .or_else(|| {
+                self.columns
+                    .iter()
+                    .position(|column| column.column_name.eq_ignore_ascii_case(name))
+            })
+            .ok_or_else(|| SqlClientError::ArgumentOutOfRange("name".to_string(), name.to_string()))
+    }
+
+    /// Checks that the reader is on a row that has a column `i`.
+    fn check_column(&self, i: usize) -> Result<(), SqlClientError> {
+        if !self.has_row {
+            return Err(SqlClientError::InvalidOperation(
+                "Invalid attempt to read when no data is present.".to_string(),
+            ));
+        }
+        if i >= self.row.len() {
+            return Err(SqlClientError::ArgumentOutOfRange(
+                "i".to_string(),
+                i.to_string(),
+            ));
+        }
+        Ok(())
+    }
+
+    /// The error for a column that has already been passed with sequential access.
+    fn column_passed(&self, i: usize) -> SqlClientError {
+        SqlClientError::InvalidOperation(format!(
+            "Invalid attempt to read from column ordinal '{}'.  With CommandBehavior.SequentialAccess, \
+             you may only read from column ordinal '{}' or greater.",
+            i, self.next_column
+        ))
+    }
+
+    /// The value of a column in the current row.
+    ///
+    /// With sequential access, large columns and the columns after them must be read with
+    /// [SqlDataReader::load_value] first.
+    pub fn get_value(&self, i: usize) -> Result<&SqlValue, SqlClientError> {
+        self.check_column(i)?;
+        match &self.row[i] {
+            Some(value) => Ok(value),
+            None if i < self.next_column => Err(self.column_passed(i)),
+            None => Err(SqlClientError::InvalidOperation(format!(
+                "Column ordinal '{}' hasn't been read.  With CommandBehavior.SequentialAccess, call \
+                 load_value to read it.",
+                i
+            ))),
+        }
+    }
+
+    /// Reads the value of a column of the current row into memory, if it isn't already, and returns
+    /// it.  This is only needed with sequential access, where the columns before it that haven't been
+    /// read are skipped.
+    pub async fn load_value(&mut self, i: usize) -> Result<&SqlValue, SqlClientError> {
+        self.check_column(i)?;
+        if self.row[i].is_none() && i >= self.next_column {
+            self.skip_to(i).await?;
+            let value = if self.nulls[i] {
+                SqlValue::Null
+            } else {
+                self.parser.read_column(i).await?
+            };
+            self.row[i] = Some(value);
+            self.next_column = i + 1;
+        }
+        self.get_value(i)
+    }
+
+    /// Gets ready to stream a column of the current row.  Returns true if a large value is to be
+    /// read from the connection, or false if the value is in memory.
+    async fn start_stream(&mut self, i: usize) -> Result<bool, SqlClientError> {
+        self.check_column(i)?;
+        if self.row[i].is_some() {
+            return Ok(false);
+        }
+        if i < self.next_column {
+            return Err(self.column_passed(i));
+        }
+        self.skip_to(i).await?;
+        self.next_column = i + 1;
+        let type_info = &self.columns[i].type_info;
+        if !self.nulls[i] && type_info.is_plp() {
+            if self.parser.start_plp_column(i).await? {
+                return Ok(true);
+            }
+            self.row[i] = Some(SqlValue::Null);
+        } else if self.nulls[i] {
+            self.row[i] = Some(SqlValue::Null);
+        } else {
+            self.row[i] = Some(self.parser.read_column(i).await?);
+        }
+        Ok(false)
+    }
+
+    /// Gets a stream over the bytes of a binary, varbinary, image or UDT column.  A null value is an
+    /// empty stream.
+    ///
+    /// With sequential access, a large value is read from the connection as the stream is read.  The
+    /// stream must be dropped before the reader is used again; whatever hasn't been read is
+    /// skipped.
+    pub async fn get_stream(&mut self, i: usize) -> Result<SqlStream<'_>, SqlClientError> {
+        self.check_column(i)?;
+        let sql_db_type = self.columns[i].type_info.sql_db_type();
+        if !matches!(
+            sql_db_type,
+            SqlDbType::Binary | SqlDbType::VarBinary | SqlDbType::Image | SqlDbType::Udt
+        ) {
+            return Err(SqlClientError::UnsupportedValue(
+                "stream".to_string(),
+                sql_db_type.to_string(),
+            ));
+        }
+        if self.start_stream(i).await? {
+            let (state, plp) = self.parser.plp_stream().unwrap();
+            return Ok(SqlStream::from_plp(state, plp));
+        }
+        match self.get_value(i)? {
+            SqlValue::Binary(bytes) => Ok(SqlStream::from_bytes(bytes)),
+            _ => Ok(SqlStream::from_bytes(&[])),
+        }
+    }
+
+    /// Gets a reader over the text of a character or xml column, as UTF-8.  A null value is empty.
+    ///
+    /// With sequential access, a large value is read from the connection and decoded as the reader
+    /// is read.  The text reader must be dropped before this reader is used again; whatever hasn't
+    /// been read is skipped.
+    pub async fn get_text_reader(&mut self, i: usize) -> Result<SqlTextReader<'_>, SqlClientError> {
+        self.check_column(i)?;
+        let type_info = &self.columns[i].type_info;
+        let decoder = match type_info.tds_type {
+            TdsEnums::SQLXMLTYPE => TextDecoder::xml(),
+            TdsEnums::SQLNVARCHAR | TdsEnums::SQLNCHAR | TdsEnums::SQLNTEXT => TextDecoder::utf16(),
+            tds_type if TypeInfo::has_collation(tds_type) => {
+                type_info.collation.unwrap_or_default().decoder()
+            }
+            _ => {
+                return Err(SqlClientError::UnsupportedValue(
+                    "text reader".to_string(),
+                    type_info.sql_db_type().to_string(),
+                ))
+            }
+        };
+        if self.start_stream(i).await? {
+            let (state, plp) = self.parser.plp_stream().unwrap();
+            let stream = SqlStream::from_plp(state, plp);
+            return Ok(SqlTextReader::new(stream, Some(decoder)));
+        }
+        match self.get_value(i)? {
+            SqlValue::String(text) => Ok(SqlTextReader::from_text(text)),
+            _ => Ok(SqlTextReader::from_text("")),
+        }
+    }
+
+    /// Whether a column in the current row is null.
+    pub fn is_db_null(&self, i: usize) -> Result<bool, SqlClientError> {
+        Ok(self.get_value(i)?.is_null())
+    }
+
+    /// Gets the value of a column as any type that can be read from it (e.g. `i64`, a newtype, or an
+    /// `Option` for a column that can be null).
+    pub fn get<T: FromSql>(&self, i: usize) -> Result<T, SqlClientError> {
+        let value = self.get_value(i)?;
+        if value.is_null() {
+            return T::from_sql_null()
+                .ok_or_else(|| SqlClientError::NullValue(self.columns[i].column_name.clone()));
+        }
+        T::from_sql(value)
+    }
+
+    /// Builds a value (usually a struct that derives [FromSqlRow]) from the current row.
+    pub fn get_row<T: FromSqlRow>(&self) -> Result<T, SqlClientError> {
+        T::from_row(self)
+    }
+
+    /// Gets a non-null value and converts it with the given function.
+    fn get_typed<'b, T>(
+        &'b self,
+        i: usize,
+        type_name: &str,
+        convert: impl FnOnce(&'b SqlValue) -> Option<T>,
+    ) -> Result<T, SqlClientError> {
+        let value = self.get_value(i)?;
+        if value.is_null() {
+            return Err(SqlClientError::NullValue(self.get_name(i)?.to_string()));
+        }
+        convert(value).ok_or_else(|| {
+            SqlClientError::UnsupportedValue(type_name.to_string(), format!("{:?}", value))
+        })
+    }
+
+    /// Gets the value of a bit column.
+    pub fn get_bool(&self, i: usize) -> Result<bool, SqlClientError> {
+        self.get(i)
+    }
+
+    /// Gets the value of a tinyint column.
+    pub fn get_u8(&self, i: usize) -> Result<u8, SqlClientError> {
+        self.get(i)
+    }
+
+    /// Gets the value of a smallint column.
+    pub fn get_i16(&self, i: usize) -> Result<i16, SqlClientError> {
+        self.get(i)
+    }
+
+    /// Gets the value of an int column.
+    pub fn get_i32(&self, i: usize) -> Result<i32, SqlClientError> {
+        self.get(i)
+    }
+
+    /// Gets the value of a bigint column.
+    pub fn get_i64(&self, i: usize) -> Result<i64, SqlClientError> {
+        self.get(i)
+    }
+
+    /// Gets the value of a real column.
+    pub fn get_f32(&self, i: usize) -> Result<f32, SqlClientError> {
+        self.get(i)
+    }
+
+    /// Gets the value of a float column.
+    pub fn get_f64(&self, i: usize) -> Result<f64, SqlClientError> {
+        self.get(i)
+    }
+
+    /// Gets the value of a decimal, numeric or money column as a [SqlDecimal] or any type that
+    /// can be converted from one (e.g. `f64`, or `rust_decimal::Decimal` and
+    /// `bigdecimal::BigDecimal` with their features enabled).
+    pub fn get_decimal<T>(&self, i: usize) -> Result<T, SqlClientError>
+    where
+        T: TryFrom<SqlDecimal>,
+        SqlClientError: From<T::Error>,
+    {
+        let decimal: SqlDecimal = self.get(i)?;
+        Ok(T::try_from(decimal)?)
+    }
+
+    /// Gets the value of a money or smallmoney column.
+    pub fn get_money(&self, i: usize) -> Result<SqlMoney, SqlClientError> {
+        self.get(i)
+    }
+
+    /// Gets the value of a character column.
+    pub fn get_string(&self, i: usize) -> Result<&str, SqlClientError> {
+        self.get_typed(i, "string", |value| match value {
+            SqlValue::String(value) => Some(value.as_str()),
+            _ => None,
+        })
+    }
+
+    /// Gets the value of a binary column.
+    pub fn get_bytes(&self, i: usize) -> Result<&[u8], SqlClientError> {
+        self.get_typed(i, "bytes", |value| match value {
+            SqlValue::Binary(value) => Some(value.as_slice()),
+            _ => None,
+        })
+    }
+
+    /// Gets the value of a datetime, smalldatetime or datetime2 column.
+    pub fn get_datetime(&self, i: usize) -> Result<NaiveDateTime, SqlClientError> {
+        self.get(i)
+    }
+
+    /// Gets the value of a date column.
+    pub fn get_date(&self, i: usize) -> Result<NaiveDate, SqlClientError> {
+        self.get(i)
+    }
+
+    /// Gets the value of a time column.
+    pub fn get_time(&self, i: usize) -> Result<NaiveTime, SqlClientError> {
+        self.get(i)
+    }
+
+    /// Gets the value of a datetimeoffset column.
+    pub fn get_datetime_offset(&self, i: usize) -> Result<DateTime<FixedOffset>, SqlClientError> {
+        self.get(i)
+    }
+
+    /// Gets the value of a uniqueidentifier column.
+    pub fn get_guid(&self, i: usize) -> Result<Uuid, SqlClientError> {
+        self.get(i)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db_connection_internal::DbConnectionInternal;
+    use crate::tds_enums::TdsEnums;
+    use crate::tds_test_utils::TokenBuilder;
+    use crate::tds_type_info::TypeInfo;
+    use crate::{ParameterDirection, SqlConnection, SqlDbType, SqlParameter};
+    use test_utils::MockStream;
+    use tokio::io::AsyncReadExt;
+
+    /// Creates an open connection that will read the given bytes.
+    fn connection(bytes: Vec<u8>) -> SqlConnection {
+        connection_with(bytes, "Server=test")
+    }
+
+    /// Creates an open connection with the given connection string.
+    fn connection_with(bytes: Vec<u8>, connection_string: &str) -> SqlConnection {
+        let mut connection = SqlConnection::new(connection_string).unwrap();
+        connection.attach(DbConnectionInternal::new(TdsParser::new(
+            Box::new(MockStream::new(bytes)),
+            TdsEnums::DEFAULT_PACKET_SIZE,
+        )));
+        connection
+    }
+
+    #[tokio::test]
+    async fn test_multiple_result_sets() {
+        let tokens = TokenBuilder::new()
+            .col_metadata(&[("Id", SqlDbType::Int), ("Name", SqlDbType::NVarChar)])
+            .row(&[SqlValue::Int(1), SqlValue::from("one")])
+            .nbc_row(&[SqlValue::Int(2), SqlValue::Null])
+            .done(
+                TdsEnums::SQLDONE,
+                TdsEnums::DONE_COUNT | TdsEnums::DONE_MORE,
+                TdsEnums::SELECT,
+                2,
+            )
+            .done(
+                TdsEnums::SQLDONE,
+                TdsEnums::DONE_COUNT | TdsEnums::DONE_MORE,
+                0xC3,
+                4,
+            )
+            .col_metadata(&[
+                ("Key", SqlDbType::UniqueIdentifier),
+                ("At", SqlDbType::DateTime),
+            ])
+            .row(&[SqlValue::Guid(Uuid::nil()), SqlValue::Null])
+            .done(TdsEnums::SQLDONE, TdsEnums::DONE_COUNT, TdsEnums::SELECT, 1);
+        let mut connection = connection(tokens.packets());
+        let mut command = SqlCommand::new("select ...; update ...; select ...");
+        let mut reader = command.execute_reader(&mut connection).await.unwrap();
+        // First result set
+        assert_eq!(2, reader.field_count());
+        assert_eq!(1, reader.get_ordinal("name").unwrap());
+        assert!(reader.read().await.unwrap());
+        assert_eq!(1, reader.get_i32(0).unwrap());
+        assert_eq!("one", reader.get_string(1).unwrap());
+        assert!(reader.read().await.unwrap());
+        assert_eq!(2, reader.get_i32(0).unwrap());
+        assert!(reader.is_db_null(1).unwrap());
+        assert!(matches!(
+            reader.get_string(1),
+            Err(SqlClientError::NullValue(_))
+        ));
+        assert!(!reader.read().await.unwrap());
+        // The update between them doesn't produce a result set
+        assert!(reader.next_result().await.unwrap());
+        assert_eq!("Key", reader.get_name(0).unwrap());
+        assert!(reader.read().await.unwrap());
+        assert_eq!(Uuid::nil(), reader.get_guid(0).unwrap());
+        assert!(!reader.read().await.unwrap());
+        assert!(!reader.next_result().await.unwrap());
+        assert!(reader.is_closed());
+        assert_eq!(4, reader.records_affected());
+    }
+
+    #[tokio::test]
+    async fn test_next_result_skips_rows() {
+        let tokens = TokenBuilder::new()
+            .col_metadata(&[("Id", SqlDbType::Int)])
+            .row(&[SqlValue::Int(1)])
+            .row(&[SqlValue::Int(2)])
+            .done(
+                TdsEnums::SQLDONE,
+                TdsEnums::DONE_COUNT | TdsEnums::DONE_MORE,
+                TdsEnums::SELECT,
+                2,
+            )
+            .col_metadata(&[("Name", SqlDbType::NVarChar)])
+            .row(&[SqlValue::from("x")])
+            .done(TdsEnums::SQLDONE, TdsEnums::DONE_COUNT, TdsEnums::SELECT, 1);
+        let mut connection = connection(tokens.packets());
+        let mut command = SqlCommand::new("select ...");
+        let mut reader = command.execute_reader(&mut connection).await.unwrap();
+        assert!(reader.read().await.unwrap());
+        assert!(reader.next_result().await.unwrap());
+        assert!(reader.read().await.unwrap());
+        assert_eq!("x", reader.get_string(0).unwrap());
+    }
+
+    #[tokio::test]
+    async fn test_type_mismatch() {
+        let tokens = TokenBuilder::new()
+            .col_metadata(&[("Id", SqlDbType::BigInt)])
+            .row(&[SqlValue::BigInt(1)])
+            .done(TdsEnums::SQLDONE, TdsEnums::DONE_COUNT, TdsEnums::SELECT, 1);
+        let mut connection = connection(tokens.packets());
+        let mut command = SqlCommand::new("select ...");
+        let mut reader = command.execute_reader(&mut connection).await.unwrap();
+        // No current row yet
+        assert!(matches!(
+            reader.get_i64(0),
+            Err(SqlClientError::InvalidOperation(_))
+        ));
+        assert!(reader.read().await.unwrap());
+        assert_eq!(1, reader.get_i64(0).unwrap());
+        assert!(matches!(
+            reader.get_i32(0),
+            Err(SqlClientError::UnsupportedValue(_, _))
+        ));
+        assert!(matches!(
+            reader.get_i64(1),
+            Err(SqlClientError::ArgumentOutOfRange(_, _))
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_rows_are_streamed() {
+        // The response is cut off after the first row.  If the reader buffered the whole result
+        // set it would fail before returning the first row.
+        let tokens = TokenBuilder::new()
+            .col_metadata(&[("Id", SqlDbType::Int)])
+            .row(&[SqlValue::Int(1)]);
+        let mut bytes = tokens.bytes().to_vec();
+        bytes.push(TdsEnums::SQLROW);
+        let mut connection = connection(crate::tds_test_utils::split_into_packets(&bytes, 4088));
+        let mut command = SqlCommand::new("select ...");
+        let mut reader = command.execute_reader(&mut connection).await.unwrap();
+        assert!(reader.read().await.unwrap());
+        assert_eq!(1, reader.get_i32(0).unwrap());
+        assert!(matches!(reader.read().await, Err(SqlClientError::Io(_, _))));
+    }
+
+    #[tokio::test]
+    async fn test_error_in_result() {
+        let tokens = TokenBuilder::new()
+            .col_metadata(&[("Id", SqlDbType::Int)])
+            .row(&[SqlValue::Int(1)])
+            .error(
+                TdsEnums::SQLERROR,
+                8134,
+                16,
+                "Divide by zero error encountered.",
+            )
+            .done(TdsEnums::SQLDONE, TdsEnums::DONE_ERROR, TdsEnums::SELECT, 0);
+        let mut connection = connection(tokens.packets());
+        let mut command = SqlCommand::new("select ...");
+        let mut reader = command.execute_reader(&mut connection).await.unwrap();
+        assert!(reader.read().await.unwrap());
+        assert!(matches!(
+            reader.read().await,
+            Err(SqlClientError::Server(exception)) if exception.number() == 8134
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_output_parameters_after_close() {
+        let tokens = TokenBuilder::new()
+            .col_metadata(&[("Id", SqlDbType::Int)])
+            .row(&[SqlValue::Int(1)])
+            .done(
+                TdsEnums::SQLDONEINPROC,
+                TdsEnums::DONE_COUNT | TdsEnums::DONE_MORE,
+                TdsEnums::SELECT,
+                1,
+            )
+            .return_status(0)
+            .return_value(
+                1,
+                "@count",
+                TdsEnums::RETURNVALUE_OUTPUT_PARAMETER,
+                &TokenBuilder::type_info(SqlDbType::Int),
+                &SqlValue::Int(1),
+            )
+            .done(TdsEnums::SQLDONEPROC, 0, 0xE0, 0);
+        let mut connection = connection(tokens.packets());
+        let mut command = SqlCommand::new_stored_procedure("GetThings");
+        command
+            .parameters_mut()
+            .add(SqlParameter::new("@count", SqlDbType::Int))
+            .set_direction(ParameterDirection::Output);
+        let mut reader = command.execute_reader(&mut connection).await.unwrap();
+        assert!(reader.read().await.unwrap());
+        reader.close().await.unwrap();
+        assert_eq!(&SqlValue::Int(1), command.parameters()["@count"].value());
+    }
+
+    #[tokio::test]
+    async fn test_date_and_time_getters() {
+        let date = NaiveDate::from_ymd_opt(2020, 2, 3).unwrap();
+        let time = NaiveTime::from_hms_milli_opt(4, 5, 6, 500).unwrap();
+        let offset: DateTime<FixedOffset> = "2020-02-03T04:05:06.5+10:00".parse().unwrap();
+        let tokens = TokenBuilder::new()
+            .col_metadata(&[
+                ("Date", SqlDbType::Date),
+                ("Time", SqlDbType::Time),
+                ("Offset", SqlDbType::DateTimeOffset),
+            ])
+            .row(&[
+                SqlValue::Date(date),
+                SqlValue::Time(time),
+                SqlValue::DateTimeOffset(offset),
+            ])
+            .done(TdsEnums::SQLDONE, TdsEnums::DONE_COUNT, TdsEnums::SELECT, 1);
+        let mut connection = connection(tokens.packets());
+        let mut command = SqlCommand::new("select ...");
+        let mut reader = command.execute_reader(&mut connection).await.unwrap();
+        assert!(reader.read().await.unwrap());
+        assert_eq!(date, reader.get_date(0).unwrap());
+        assert_eq!(time, reader.get_time(1).unwrap());
+        assert_eq!(offset, reader.get_datetime_offset(2).unwrap());
+        assert!(reader.get_time(0).is_err());
+    }
+
+    #[rstest::rstest]
+    #[case("Type System Version=SQL Server 2000", ["text", "ntext", "image", "ntext", "nvarchar"], SqlValue::from("2020-02-03"))]
+    #[case("Type System Version=SQL Server 2005", ["varchar", "nvarchar", "varbinary", "xml", "nvarchar"], SqlValue::from("2020-02-03"))]
+    #[case("Type System Version=Latest", ["varchar", "nvarchar", "varbinary", "xml", "date"], SqlValue::Date(NaiveDate::from_ymd_opt(2020, 2, 3).unwrap()))]
+    #[tokio::test]
+    async fn test_type_system(
+        #[case] connection_string: &str,
+        #[case] expected_names: [&str; 5],
+        #[case] expected_date: SqlValue,
+    ) {
+        let max = |sql_db_type| TypeInfo::for_parameter(sql_db_type, -1, 0, 0, 0, None).unwrap();
+        let tokens = TokenBuilder::new()
+            .col_metadata_with_types(&[
+                ("A", max(SqlDbType::VarChar)),
+                ("B", max(SqlDbType::NVarChar)),
+                ("C", max(SqlDbType::VarBinary)),
+                ("D", max(SqlDbType::Xml)),
+                ("E", max(SqlDbType::Date)),
+            ])
+            .row(&[
+                SqlValue::from("a"),
+                SqlValue::from("b"),
+                SqlValue::Binary(vec![1]),
+                SqlValue::from("<d/>"),
+                SqlValue::Date(NaiveDate::from_ymd_opt(2020, 2, 3).unwrap()),
+            ])
+            .done(TdsEnums::SQLDONE, TdsEnums::DONE_COUNT, TdsEnums::SELECT, 1);
+        let mut connection = connection_with(tokens.packets(), connection_string);
+        let mut command = SqlCommand::new("select ...");
+        let mut reader = command.execute_reader(&mut connection).await.unwrap();
+        let names: Vec<String> = (0..5)
+            .map(|i| reader.get_data_type_name(i).unwrap())
+            .collect();
+        assert_eq!(expected_names.to_vec(), names);
+        assert!(reader.read().await.unwrap());
+        assert_eq!("a", reader.get_string(0).unwrap());
+        assert_eq!(&expected_date, reader.get_value(4).unwrap());
+    }
+
+    /// A response with a row whose varbinary(max) value is split into chunks, and a row with a
+    /// value longer than a packet.
+    fn large_value_tokens() -> TokenBuilder {
+        let max = |sql_db_type| TypeInfo::for_parameter(sql_db_type, -1, 0, 0, 0, None).unwrap();
+        let int = TokenBuilder::type_info(SqlDbType::Int);
+        let mut row = vec![TdsEnums::SQLROW];
+        crate::tds_value::write_value(&mut row, &int, &SqlValue::Int(1)).unwrap();
+        row.extend_from_slice(&6u64.to_le_bytes());
+        for chunk in [b"abc", b"def"] {
+            row.extend_from_slice(&3u32.to_le_bytes());
+            row.extend_from_slice(chunk);
+        }
+        row.extend_from_slice(&TdsEnums::SQL_PLP_CHUNK_TERMINATOR.to_le_bytes());
+        crate::tds_value::write_value(&mut row, &max(SqlDbType::NVarChar), &"héllo".into())
+            .unwrap();
+        crate::tds_value::write_value(&mut row, &int, &SqlValue::Int(7)).unwrap();
+        TokenBuilder::new()
+            .col_metadata_with_types(&[
+                ("Id", int.clone()),
+                ("Data", max(SqlDbType::VarBinary)),
+                ("Name", max(SqlDbType::NVarChar)),
+                ("Count", int),
+            ])
+            .raw(&row)
+            .nbc_row(&[
+                SqlValue::Int(2),
+                SqlValue::Binary(vec![0xAB; 10_000]),
+                SqlValue::Null,
+                SqlValue::Int(8),
+            ])
+            .done(TdsEnums::SQLDONE, TdsEnums::DONE_COUNT, TdsEnums::SELECT, 2)
+    }
+
+    #[tokio::test]
+    async fn test_sequential_access() {
+        let mut connection = connection(large_value_tokens().packets());
+        let mut command = SqlCommand::new("select ...");
+        let mut reader = command
+            .execute_reader_with_behavior(&mut connection, CommandBehavior::SEQUENTIAL_ACCESS)
+            .await
+            .unwrap();
+        assert!(reader.read().await.unwrap());
+        // Columns before the first large one are read with the row, the rest on demand.
+        assert_eq!(1, reader.get_i32(0).unwrap());
+        assert!(matches!(
+            reader.get_i32(3),
+            Err(SqlClientError::InvalidOperation(_))
+        ));
+        let mut data = Vec::new();
+        let mut stream = reader.get_stream(1).await.unwrap();
+        stream.read_to_end(&mut data).await.unwrap();
+        drop(stream);
+        assert_eq!(b"abcdef".to_vec(), data);
+        let mut name = String::new();
+        let mut text_reader = reader.get_text_reader(2).await.unwrap();
+        text_reader.read_to_string(&mut name).await.unwrap();
+        drop(text_reader);
+        assert_eq!("héllo", name);
+        // The streamed columns have been passed
+        assert!(matches!(
+            reader.get_stream(1).await,
+            Err(SqlClientError::InvalidOperation(_))
+        ));
+        assert_eq!(&SqlValue::Int(7), reader.load_value(3).await.unwrap());
+        assert_eq!(7, reader.get_i32(3).unwrap());
+        // Whatever isn't read of a stream is skipped
+        assert!(reader.read().await.unwrap());
+        let mut data = [0u8; 5];
+        let mut stream = reader.get_stream(1).await.unwrap();
+        stream.read_exact(&mut data).await.unwrap();
+        drop(stream);
+        assert_eq!([0xAB; 5], data);
+        assert_eq!(&SqlValue::Int(8), reader.load_value(3).await.unwrap());
+        // The skipped null is still known
+        assert!(reader.is_db_null(2).unwrap());
+        assert!(!reader.read().await.unwrap());
+        assert!(!reader.next_result().await.unwrap());
+    }
+
+    #[rstest::rstest]
+    #[case::mid_row(1, 0)]
+    #[case::mid_chunk(1, 4)]
+    #[case::mid_packet(2, 5)]
+    #[tokio::test]
+    async fn test_drop_sequential_reader(#[case] rows: usize, #[case] streamed: usize) {
+        let mut bytes = large_value_tokens().packets();
+        bytes.extend(
+            TokenBuilder::new()
+                .col_metadata(&[("Id", SqlDbType::Int)])
+                .row(&[SqlValue::Int(42)])
+                .done(TdsEnums::SQLDONE, TdsEnums::DONE_COUNT, TdsEnums::SELECT, 1)
+                .packets(),
+        );
+        let mut connection = connection(bytes);
+        let mut command = SqlCommand::new("select ...");
+        let mut reader = command
+            .execute_reader_with_behavior(&mut connection, CommandBehavior::SEQUENTIAL_ACCESS)
+            .await
+            .unwrap();
+        for _ in 0..rows {
+            assert!(reader.read().await.unwrap());
+        }
+        // Stop part way through the row (and part way through a value).
+        if streamed > 0 {
+            let mut data = vec![0u8; streamed];
+            let mut stream = reader.get_stream(1).await.unwrap();
+            stream.read_exact(&mut data).await.unwrap();
+        }
+        drop(reader);
+        // The rest of the first command's results are skipped before the second command runs.
+        let mut command = SqlCommand::new("select 42");
+        let mut reader = command.execute_reader(&mut connection).await.unwrap();
+        assert!(reader.read().await.unwrap());
+        assert_eq!(42, reader.get_i32(0).unwrap());
+        assert!(!reader.read().await.unwrap());
+    }
+
+    #[tokio::test]
+    async fn test_streams_in_default_mode() {
+        let mut connection = connection(large_value_tokens().packets());
+        let mut command = SqlCommand::new("select ...");
+        let mut reader = command.execute_reader(&mut connection).await.unwrap();
+        assert!(reader.read().await.unwrap());
+        // Values are in memory, so they can be read in any order, as often as needed.
+        assert_eq!(7, reader.get_i32(3).unwrap());
+        for _ in 0..2 {
+            let mut data = Vec::new();
+            let mut stream = reader.get_stream(1).await.unwrap();
+            stream.read_to_end(&mut data).await.unwrap();
+            assert_eq!(b"abcdef".to_vec(), data);
+        }
+        assert!(matches!(
+            reader.get_stream(0).await,
+            Err(SqlClientError::UnsupportedValue(_, _))
+        ));
+        assert!(matches!(
+            reader.get_text_reader(1).await,
+            Err(SqlClientError::UnsupportedValue(_, _))
+        ));
+        // A null is empty
+        assert!(reader.read().await.unwrap());
+        let mut name = String::new();
+        let mut text_reader = reader.get_text_reader(2).await.unwrap();
+        text_reader.read_to_string(&mut name).await.unwrap();
+        assert_eq!("", name);
+    }
+}
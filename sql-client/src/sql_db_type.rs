@@ -0,0 +1,161 @@
+use std::fmt::{Display, Formatter};
+
+/// Specifies the SQL Server data type of a field or parameter.
+#[derive(PartialEq, Debug, Clone, Copy)]
+pub enum SqlDbType {
+    /// A 64-bit signed integer.
+    BigInt,
+    /// A fixed-length stream of binary data.
+    Binary,
+    /// An unsigned numeric value that can be 0, 1, or null.
+    Bit,
+    /// A fixed-length stream of non-Unicode characters.
+    Char,
+    /// Date data ranging in value from January 1, 1 AD through December 31, 9999 AD.
+    Date,
+    /// Date and time data ranging in value from January 1, 1753 to December 31, 9999 to an accuracy of 3.33 milliseconds.
+    DateTime,
+    /// Date and time data with a greater range and precision than [SqlDbType::DateTime].
+    DateTime2,
+    /// Date and time data with time zone awareness.
+    DateTimeOffset,
+    /// A fixed precision and scale numeric value.
+    Decimal,
+    /// A floating point number within the range of -1.79E +308 through 1.79E +308.
+    Float,
+    /// A variable-length stream of binary data (deprecated in favor of varbinary(max)).
+    Image,
+    /// A 32-bit signed integer.
+    Int,
+    /// A currency value with an accuracy to a ten-thousandth of a currency unit.
+    Money,
+    /// A fixed-length stream of Unicode characters.
+    NChar,
+    /// A variable-length stream of Unicode data (deprecated in favor of nvarchar(max)).
+    NText,
+    /// A variable-length stream of Unicode characters.
+    NVarChar,
+    /// A floating point number within the range of -3.40E +38 through 3.40E +38.
+    Real,
+    /// Date and time data ranging in value from January 1, 1900 to June 6, 2079 to an accuracy of one minute.
+    SmallDateTime,
+    /// A 16-bit signed integer.
+    SmallInt,
+    /// A currency value ranging from -214,748.3648 to +214,748.3647.
+    SmallMoney,
+    /// A table-valued parameter.
+    Structured,
+    /// A variable-length stream of non-Unicode data (deprecated in favor of varchar(max)).
+    Text,
+    /// Time data based on a 24-hour clock.
+    Time,
+    /// Automatically generated binary numbers, which are guaranteed to be unique within a database.
+    Timestamp,
+    /// An 8-bit unsigned integer.
+    TinyInt,
+    /// A SQL Server user-defined type.
+    Udt,
+    /// A globally unique identifier (GUID).
+    UniqueIdentifier,
+    /// A variable-length stream of binary data.
+    VarBinary,
+    /// A variable-length stream of non-Unicode characters.
+    VarChar,
+    /// A value that can contain any of the other base types (sql_variant).
+    Variant,
+    /// An XML value.
+    Xml,
+}
+
+impl SqlDbType {
+    /// Whether a size is included in the type's declaration (e.g. "nvarchar(50)").
+    pub(crate) fn has_size(&self) -> bool {
+        matches!(
+            self,
+            SqlDbType::Binary
+                | SqlDbType::Char
+                | SqlDbType::NChar
+                | SqlDbType::NVarChar
+                | SqlDbType::VarBinary
+                | SqlDbType::VarChar
+        )
+    }
+
+    /// Whether the type's size is measured in two-byte characters.
+    pub(crate) fn is_unicode(&self) -> bool {
+        matches!(
+            self,
+            SqlDbType::NChar | SqlDbType::NText | SqlDbType::NVarChar | SqlDbType::Xml
+        )
+    }
+
+    /// Whether the type has a fractional seconds scale (e.g. "datetime2(7)").
+    pub(crate) fn has_time_scale(&self) -> bool {
+        matches!(
+            self,
+            SqlDbType::DateTime2 | SqlDbType::DateTimeOffset | SqlDbType::Time
+        )
+    }
+}
+
+impl Display for SqlDbType {
+    /// Writes the T-SQL name of the type.
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            SqlDbType::BigInt => write!(f, "bigint"),
+            SqlDbType::Binary => write!(f, "binary"),
+            SqlDbType::Bit => write!(f, "bit"),
+            SqlDbType::Char => write!(f, "char"),
+            SqlDbType::Date => write!(f, "date"),
+            SqlDbType::DateTime => write!(f, "datetime"),
+            SqlDbType::DateTime2 => write!(f, "datetime2"),
+            SqlDbType::DateTimeOffset => write!(f, "datetimeoffset"),
+            SqlDbType::Decimal => write!(f, "decimal"),
+            SqlDbType::Float => write!(f, "float"),
+            SqlDbType::Image => write!(f, "image"),
+            SqlDbType::Int => write!(f, "int"),
+            SqlDbType::Money => write!(f, "money"),
+            SqlDbType::NChar => write!(f, "nchar"),
+            SqlDbType::NText => write!(f, "ntext"),
+            SqlDbType::NVarChar => write!(f, "nvarchar"),
+            SqlDbType::Real => write!(f, "real"),
+            SqlDbType::SmallDateTime => write!(f, "smalldatetime"),
+            SqlDbType::SmallInt => write!(f, "smallint"),
+            SqlDbType::SmallMoney => write!(f, "smallmoney"),
+            SqlDbType::Structured => write!(f, "table"),
+            SqlDbType::Text => write!(f, "text"),
+            SqlDbType::Time => write!(f, "time"),
+            SqlDbType::Timestamp => write!(f, "timestamp"),
+            SqlDbType::TinyInt => write!(f, "tinyint"),
+            SqlDbType::Udt => write!(f, "udt"),
+            SqlDbType::UniqueIdentifier => write!(f, "uniqueidentifier"),
+            SqlDbType::VarBinary => write!(f, "varbinary"),
+            SqlDbType::VarChar => write!(f, "varchar"),
+            SqlDbType::Variant => write!(f, "sql_variant"),
+            SqlDbType::Xml => write!(f, "xml"),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[rstest::rstest]
+    #[case(SqlDbType::BigInt, "bigint")]
+    #[case(SqlDbType::NVarChar, "nvarchar")]
+    #[case(SqlDbType::UniqueIdentifier, "uniqueidentifier")]
+    #[case(SqlDbType::Variant, "sql_variant")]
+    fn test_to_string(#[case] value: SqlDbType, #[case] expected: &str) {
+        assert_eq!(expected, value.to_string());
+    }
+
+    #[rstest::rstest]
+    #[case(SqlDbType::NVarChar, true)]
+    #[case(SqlDbType::VarBinary, true)]
+    #[case(SqlDbType::Int, false)]
+    #[case(SqlDbType::NText, false)]
+    fn test_has_size(#[case] value: SqlDbType, #[case] expected: bool) {
+        assert_eq!(expected, value.has_size());
+    }
+}
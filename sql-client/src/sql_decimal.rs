@@ -0,0 +1,797 @@
+use crate::{SqlClientError, SqlMoney};
+use std::cmp::Ordering;
+use std::fmt::{Display, Formatter};
+use std::hash::{Hash, Hasher};
+use std::ops::{Add, Div, Mul, Neg, Sub};
+use std::str::FromStr;
+
+/// An unsigned 256-bit integer, used for the intermediate results of multiplication and division.
+#[derive(PartialEq, Eq, PartialOrd, Ord, Debug, Clone, Copy)]
+struct U256 {
+    /// The high 128 bits.
+    hi: u128,
+    /// The low 128 bits.
+    lo: u128,
+}
+
+impl U256 {
+    /// Zero.
+    const ZERO: U256 = U256 { hi: 0, lo: 0 };
+
+    /// Creates a value from a u128.
+    fn from_u128(value: u128) -> Self {
+        Self { hi: 0, lo: value }
+    }
+
+    /// Multiplies two u128s.
+    fn mul(a: u128, b: u128) -> Self {
+        const MASK: u128 = u64::MAX as u128;
+        let (a1, a0) = (a >> 64, a & MASK);
+        let (b1, b0) = (b >> 64, b & MASK);
+        let p00 = a0 * b0;
+        let p01 = a0 * b1;
+        let p10 = a1 * b0;
+        let p11 = a1 * b1;
+        let mid = (p00 >> 64) + (p01 & MASK) + (p10 & MASK);
+        Self {
+            hi: p11 + (p01 >> 64) + (p10 >> 64) + (mid >> 64),
+            lo: (p00 & MASK) | (mid << 64),
+        }
+    }
+
+    /// Multiplies by a small number, or None on overflow.
+    fn checked_mul_small(self, m: u64) -> Option<Self> {
+        let low = Self::mul(self.lo, m as u128);
+        let hi = self.hi.checked_mul(m as u128)?.checked_add(low.hi)?;
+        Some(Self { hi, lo: low.lo })
+    }
+
+    /// Adds a small number, or None on overflow.
+    fn checked_add_small(self, a: u64) -> Option<Self> {
+        let (lo, carry) = self.lo.overflowing_add(a as u128);
+        let hi = self.hi.checked_add(carry as u128)?;
+        Some(Self { hi, lo })
+    }
+
+    /// Divides by a small number, returning the quotient and remainder.
+    fn div_rem_small(self, d: u64) -> (Self, u64) {
+        let limbs = [
+            (self.hi >> 64) as u64,
+            self.hi as u64,
+            (self.lo >> 64) as u64,
+            self.lo as u64,
+        ];
+        let mut quotient = [0u64; 4];
+        let mut remainder = 0u128;
+        for (i, limb) in limbs.iter().enumerate() {
+            let current = (remainder << 64) | *limb as u128;
+            quotient[i] = (current / d as u128) as u64;
+            remainder = current % d as u128;
+        }
+        let value = Self {
+            hi: ((quotient[0] as u128) << 64) | quotient[1] as u128,
+            lo: ((quotient[2] as u128) << 64) | quotient[3] as u128,
+        };
+        (value, remainder as u64)
+    }
+
+    /// Divides by a u128 (using binary long division).
+    fn div(self, d: u128) -> Self {
+        let mut quotient = Self::ZERO;
+        let mut remainder = 0u128;
+        for i in (0..256).rev() {
+            let bit = if i >= 128 {
+                (self.hi >> (i - 128)) & 1
+            } else {
+                (self.lo >> i) & 1
+            };
+            let carry = remainder >> 127;
+            remainder = (remainder << 1) | bit;
+            if carry == 1 || remainder >= d {
+                remainder = remainder.wrapping_sub(d);
+                if i >= 128 {
+                    quotient.hi |= 1 << (i - 128);
+                } else {
+                    quotient.lo |= 1 << i;
+                }
+            }
+        }
+        quotient
+    }
+
+    /// The value as a u128, if it fits.
+    fn to_u128(self) -> Option<u128> {
+        (self.hi == 0).then_some(self.lo)
+    }
+}
+
+/// 10 to the given power (up to 38).
+fn pow10(n: u8) -> u128 {
+    10u128.pow(n as u32)
+}
+
+/// The number of decimal digits in a value.
+fn digits(value: u128) -> u8 {
+    let mut count = 1;
+    let mut value = value / 10;
+    while value > 0 {
+        count += 1;
+        value /= 10;
+    }
+    count
+}
+
+/// A decimal or numeric value with up to 38 digits of precision, as stored by SQL Server.
+///
+/// Values compare equal if they're numerically equal, whatever their precision and scale.
+#[derive(Debug, Clone, Copy)]
+pub struct SqlDecimal {
+    /// The unscaled value (i.e. the value is `value / 10^scale`).
+    value: i128,
+    /// The maximum number of digits.
+    precision: u8,
+    /// The number of digits after the decimal point.
+    scale: u8,
+}
+
+impl SqlDecimal {
+    /// The maximum precision (and scale) of a decimal.
+    pub const MAX_PRECISION: u8 = 38;
+    /// The largest unscaled value (38 nines).
+    const MAX_UNSCALED: u128 = 99_999_999_999_999_999_999_999_999_999_999_999_999;
+    /// The minimum scale of the result of a division.
+    const MIN_DIVISION_SCALE: u8 = 6;
+
+    /// Creates a decimal from its unscaled value (e.g. 12345 with a scale of 2 is 123.45), precision and scale.
+    pub fn new(value: i128, precision: u8, scale: u8) -> Result<Self, SqlClientError> {
+        if precision == 0 || precision > Self::MAX_PRECISION {
+            return Err(SqlClientError::ArgumentOutOfRange(
+                "precision".to_string(),
+                precision.to_string(),
+            ));
+        }
+        if scale > precision {
+            return Err(SqlClientError::ArgumentOutOfRange(
+                "scale".to_string(),
+                scale.to_string(),
+            ));
+        }
+        if value.unsigned_abs() >= pow10(precision) {
+            return Err(SqlClientError::Overflow(format!(
+                "{} doesn't fit in decimal({},{})",
+                value, precision, scale
+            )));
+        }
+        Ok(Self {
+            value,
+            precision,
+            scale,
+        })
+    }
+
+    /// Creates a decimal from its unscaled value and scale, with the smallest precision that fits it.
+    pub fn from_unscaled(value: i128, scale: u8) -> Result<Self, SqlClientError> {
+        Self::from_wide(
+            value < 0,
+            U256::from_u128(value.unsigned_abs()),
+            scale as u32,
+        )
+        .ok_or_else(|| SqlClientError::Overflow(format!("{}e-{}", value, scale)))
+    }
+
+    /// Creates a decimal from a sign, magnitude and scale, rounding away digits after the
+    /// decimal point if it has too many.  Returns None if it doesn't fit in 38 digits.
+    fn from_wide(negative: bool, magnitude: U256, scale: u32) -> Option<Self> {
+        let max = U256::from_u128(Self::MAX_UNSCALED);
+        // Work out how many digits need to be dropped from the end
+        let mut dropped = scale.saturating_sub(Self::MAX_PRECISION as u32);
+        let magnitude = loop {
+            let mut truncated = magnitude;
+            for _ in 1..dropped {
+                truncated = truncated.div_rem_small(10).0;
+            }
+            // Round half away from zero using the last dropped digit
+            let rounded = if dropped == 0 {
+                truncated
+            } else {
+                let (quotient, digit) = truncated.div_rem_small(10);
+                if digit >= 5 {
+                    quotient.checked_add_small(1)?
+                } else {
+                    quotient
+                }
+            };
+            if rounded <= max {
+                break rounded.to_u128()?;
+            }
+            if dropped >= scale {
+                return None;
+            }
+            dropped += 1;
+        };
+        let scale = (scale - dropped) as u8;
+        let value = if negative {
+            -(magnitude as i128)
+        } else {
+            magnitude as i128
+        };
+        Some(Self {
+            value,
+            precision: digits(magnitude).max(scale).max(1),
+            scale,
+        })
+    }
+
+    /// The unscaled value (e.g. 12345 for 123.45).
+    pub fn unscaled_value(&self) -> i128 {
+        self.value
+    }
+
+    /// The maximum number of digits.
+    pub fn precision(&self) -> u8 {
+        self.precision
+    }
+
+    /// The number of digits after the decimal point.
+    pub fn scale(&self) -> u8 {
+        self.scale
+    }
+
+    /// Whether the value is greater than or equal to zero.
+    pub fn is_positive(&self) -> bool {
+        self.value >= 0
+    }
+
+    /// Changes the scale, either rounding (half away from zero) or truncating any digits removed.
+    pub fn adjust_scale(&self, scale: u8, round: bool) -> Result<Self, SqlClientError> {
+        let overflow =
+            || SqlClientError::Overflow(format!("{} can't have a scale of {}", self, scale));
+        if scale > Self::MAX_PRECISION {
+            return Err(overflow());
+        }
+        let magnitude = self.value.unsigned_abs();
+        let magnitude = if scale >= self.scale {
+            U256::mul(magnitude, pow10(scale - self.scale))
+        } else {
+            let divisor = pow10(self.scale - scale);
+            let quotient = magnitude / divisor;
+            if round && (magnitude % divisor) * 2 >= divisor {
+                U256::from_u128(quotient + 1)
+            } else {
+                U256::from_u128(quotient)
+            }
+        };
+        let magnitude = magnitude
+            .to_u128()
+            .filter(|m| *m <= Self::MAX_UNSCALED)
+            .ok_or_else(overflow)?;
+        let value = if self.value < 0 {
+            -(magnitude as i128)
+        } else {
+            magnitude as i128
+        };
+        // Keep the number of integer digits
+        let precision = (self.precision - self.scale.min(self.precision))
+            .saturating_add(scale)
+            .clamp(digits(magnitude).max(scale).max(1), Self::MAX_PRECISION);
+        Ok(Self {
+            value,
+            precision,
+            scale,
+        })
+    }
+
+    /// The two values with the same scale, or None if that doesn't fit.
+    fn aligned(self, other: Self) -> Option<(i128, i128, u8)> {
+        let scale = self.scale.max(other.scale);
+        let a = self.value.checked_mul(pow10(scale - self.scale) as i128)?;
+        let b = other
+            .value
+            .checked_mul(pow10(scale - other.scale) as i128)?;
+        Some((a, b, scale))
+    }
+
+    /// Adds two decimals, or returns None on overflow.
+    pub fn checked_add(self, other: Self) -> Option<Self> {
+        let (a, b, scale) = self.aligned(other)?;
+        let sum = a.checked_add(b)?;
+        Self::from_wide(sum < 0, U256::from_u128(sum.unsigned_abs()), scale as u32)
+    }
+
+    /// Subtracts a decimal, or returns None on overflow.
+    pub fn checked_sub(self, other: Self) -> Option<Self> {
+        self.checked_add(-other)
+    }
+
+    /// Multiplies two decimals, or returns None on overflow.
+    pub fn checked_mul(self, other: Self) -> Option<Self> {
+        let product = U256::mul(self.value.unsigned_abs(), other.value.unsigned_abs());
+        let negative = (self.value < 0) != (other.value < 0);
+        Self::from_wide(negative, product, self.scale as u32 + other.scale as u32)
+    }
+
+    /// Divides by a decimal, or returns None on overflow or division by zero.
+    ///
+    /// As in SQL Server, the result has a scale of at least six.
+    pub fn checked_div(self, other: Self) -> Option<Self> {
+        if other.value == 0 {
+            return None;
+        }
+        let scale =
+            (self.scale + other.precision + 1).clamp(Self::MIN_DIVISION_SCALE, Self::MAX_PRECISION);
+        // Scale the dividend so the quotient has the result scale plus one digit for rounding
+        let mut dividend = U256::from_u128(self.value.unsigned_abs());
+        for _ in 0..(scale + other.scale + 1 - self.scale) {
+            dividend = dividend.checked_mul_small(10)?;
+        }
+        let (quotient, digit) = dividend.div(other.value.unsigned_abs()).div_rem_small(10);
+        let quotient = if digit >= 5 {
+            quotient.checked_add_small(1)?
+        } else {
+            quotient
+        };
+        let negative = (self.value < 0) != (other.value < 0);
+        Self::from_wide(negative, quotient, scale as u32)
+    }
+
+    /// Converts the value to a float, which may lose precision.
+    pub fn to_f64(&self) -> f64 {
+        self.value as f64 / 10f64.powi(self.scale as i32)
+    }
+
+    /// Reads a decimal from the TDS wire format (a sign byte followed by the little-endian magnitude).
+    pub(crate) fn from_tds(bytes: &[u8], precision: u8, scale: u8) -> Result<Self, SqlClientError> {
+        let invalid =
+            || SqlClientError::Protocol(format!("Invalid decimal of length {}", bytes.len()));
+        let (sign, magnitude) = bytes.split_first().ok_or_else(invalid)?;
+        if magnitude.len() > 16 {
+            return Err(invalid());
+        }
+        let mut buffer = [0u8; 16];
+        buffer[..magnitude.len()].copy_from_slice(magnitude);
+        let magnitude = u128::from_le_bytes(buffer);
+        if magnitude > Self::MAX_UNSCALED {
+            return Err(invalid());
+        }
+        let value = if *sign == 0 {
+            -(magnitude as i128)
+        } else {
+            magnitude as i128
+        };
+        Ok(Self {
+            value,
+            precision: precision.clamp(digits(magnitude).max(scale).max(1), Self::MAX_PRECISION),
+            scale,
+        })
+    }
+
+    /// Writes the decimal in the TDS wire format (a sign byte followed by a 16-byte magnitude).
+    pub(crate) fn to_tds(self) -> Vec<u8> {
+        let mut bytes = vec![self.is_positive() as u8];
+        bytes.extend_from_slice(&self.value.unsigned_abs().to_le_bytes());
+        bytes
+    }
+
+    /// The value with trailing zeros after the decimal point removed.
+    fn normalized(&self) -> (i128, u8) {
+        let (mut value, mut scale) = (self.value, self.scale);
+        while scale > 0 && value % 10 == 0 {
+            value /= 10;
+            scale -= 1;
+        }
+        (value, scale)
+    }
+}
+
+impl Default for SqlDecimal {
+    fn default() -> Self {
+        Self {
+            value: 0,
+            precision: 1,
+            scale: 0,
+        }
+    }
+}
+
+impl PartialEq for SqlDecimal {
+    fn eq(&self, other: &Self) -> bool {
+        self.cmp(other) == Ordering::Equal
+    }
+}
+
+impl Eq for SqlDecimal {}
+
+impl PartialOrd for SqlDecimal {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for SqlDecimal {
+    fn cmp(&self, other: &Self) -> Ordering {
+        let sign = |value: i128| value.signum();
+        match sign(self.value).cmp(&sign(other.value)) {
+            Ordering::Equal => {}
+            ordering => return ordering,
+        }
+        // Compare the magnitudes with the same scale
+        let scale = self.scale.max(other.scale);
+        let a = U256::mul(self.value.unsigned_abs(), pow10(scale - self.scale));
+        let b = U256::mul(other.value.unsigned_abs(), pow10(scale - other.scale));
+        if self.value < 0 {
+            b.cmp(&a)
+        } else {
+            a.cmp(&b)
+        }
+    }
+}
+
+impl Hash for SqlDecimal {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.normalized().hash(state);
+    }
+}
+
+impl Display for SqlDecimal {
+    /// Writes the value with all the digits of its scale (e.g. "-123.4500").
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        let digits = format!(
+            "{:0width$}",
+            self.value.unsigned_abs(),
+            width = self.scale as usize + 1
+        );
+        let (integer, fraction) = digits.split_at(digits.len() - self.scale as usize);
+        if self.value < 0 {
+            write!(f, "-")?;
+        }
+        if fraction.is_empty() {
+            write!(f, "{}", integer)
+        } else {
+            write!(f, "{}.{}", integer, fraction)
+        }
+    }
+}
+
+impl FromStr for SqlDecimal {
+    type Err = SqlClientError;
+
+    /// Parses a decimal such as "-123.45".  Digits after the decimal point beyond the 38 that can
+    /// be stored are rounded away.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let invalid = || SqlClientError::UnsupportedValue("SqlDecimal".to_string(), s.to_string());
+        let text = s.trim();
+        let (negative, text) = match text.strip_prefix('-') {
+            Some(text) => (true, text),
+            None => (false, text.strip_prefix('+').unwrap_or(text)),
+        };
+        let (integer, fraction) = text.split_once('.').unwrap_or((text, ""));
+        if integer.is_empty() && fraction.is_empty() {
+            return Err(invalid());
+        }
+        let mut magnitude = U256::ZERO;
+        for c in integer.chars().chain(fraction.chars()) {
+            let digit = c.to_digit(10).ok_or_else(invalid)?;
+            magnitude = magnitude
+                .checked_mul_small(10)
+                .and_then(|m| m.checked_add_small(digit as u64))
+                .ok_or_else(invalid)?;
+        }
+        Self::from_wide(negative, magnitude, fraction.len() as u32).ok_or_else(invalid)
+    }
+}
+
+impl Neg for SqlDecimal {
+    type Output = SqlDecimal;
+
+    fn neg(self) -> Self::Output {
+        Self {
+            value: -self.value,
+            ..self
+        }
+    }
+}
+
+impl Add for SqlDecimal {
+    type Output = SqlDecimal;
+
+    fn add(self, rhs: Self) -> Self::Output {
+        self.checked_add(rhs).expect("attempt to add with overflow")
+    }
+}
+
+impl Sub for SqlDecimal {
+    type Output = SqlDecimal;
+
+    fn sub(self, rhs: Self) -> Self::Output {
+        self.checked_sub(rhs)
+            .expect("attempt to subtract with overflow")
+    }
+}
+
+impl Mul for SqlDecimal {
+    type Output = SqlDecimal;
+
+    fn mul(self, rhs: Self) -> Self::Output {
+        self.checked_mul(rhs)
+            .expect("attempt to multiply with overflow")
+    }
+}
+
+impl Div for SqlDecimal {
+    type Output = SqlDecimal;
+
+    fn div(self, rhs: Self) -> Self::Output {
+        self.checked_div(rhs)
+            .expect("attempt to divide by zero or with overflow")
+    }
+}
+
+impl From<i32> for SqlDecimal {
+    fn from(value: i32) -> Self {
+        Self::from(value as i64)
+    }
+}
+
+impl From<i64> for SqlDecimal {
+    fn from(value: i64) -> Self {
+        Self {
+            value: value as i128,
+            precision: digits(value.unsigned_abs() as u128),
+            scale: 0,
+        }
+    }
+}
+
+impl From<SqlMoney> for SqlDecimal {
+    fn from(value: SqlMoney) -> Self {
+        Self {
+            value: value.to_ten_thousandths() as i128,
+            precision: 19,
+            scale: 4,
+        }
+    }
+}
+
+impl TryFrom<f64> for SqlDecimal {
+    type Error = SqlClientError;
+
+    /// Converts a float using its shortest exact decimal representation.
+    fn try_from(value: f64) -> Result<Self, Self::Error> {
+        if !value.is_finite() {
+            return Err(SqlClientError::UnsupportedValue(
+                "SqlDecimal".to_string(),
+                value.to_string(),
+            ));
+        }
+        value.to_string().parse()
+    }
+}
+
+impl From<SqlDecimal> for f64 {
+    fn from(value: SqlDecimal) -> Self {
+        value.to_f64()
+    }
+}
+
+#[cfg(feature = "rust_decimal")]
+impl From<rust_decimal::Decimal> for SqlDecimal {
+    fn from(value: rust_decimal::Decimal) -> Self {
+        // A rust_decimal has a 96-bit mantissa and a scale of at most 28, so it always fits.
+        Self::from_unscaled(value.mantissa(), value.scale() as u8).unwrap()
+    }
+}
+
+#[cfg(feature = "rust_decimal")]
+impl TryFrom<SqlDecimal> for rust_decimal::Decimal {
+    type Error = SqlClientError;
+
+    fn try_from(value: SqlDecimal) -> Result<Self, Self::Error> {
+        rust_decimal::Decimal::try_from_i128_with_scale(value.value, value.scale as u32)
+            .map_err(|e| SqlClientError::Overflow(format!("{} ({})", value, e)))
+    }
+}
+
+#[cfg(feature = "bigdecimal")]
+impl TryFrom<bigdecimal::BigDecimal> for SqlDecimal {
+    type Error = SqlClientError;
+
+    fn try_from(value: bigdecimal::BigDecimal) -> Result<Self, Self::Error> {
+        let (digits, exponent) = value.as_bigint_and_exponent();
+        let negative = digits.sign() == bigdecimal::num_bigint::Sign::Minus;
+        let mut digits = digits.magnitude().to_string();
+        // A negative exponent means trailing zeros
+        let scale = if exponent < 0 {
+            digits.push_str(&"0".repeat(exponent.unsigned_abs() as usize));
+            0
+        } else {
+            exponent as usize
+        };
+        if digits.len() <= scale {
+            digits.insert_str(0, &"0".repeat(scale + 1 - digits.len()));
+        }
+        digits.insert(digits.len() - scale, '.');
+        if negative {
+            digits.insert(0, '-');
+        }
+        digits
+            .parse()
+            .map_err(|_| SqlClientError::Overflow(value.to_string()))
+    }
+}
+
+#[cfg(feature = "bigdecimal")]
+impl From<SqlDecimal> for bigdecimal::BigDecimal {
+    fn from(value: SqlDecimal) -> Self {
+        bigdecimal::BigDecimal::new(value.value.into(), value.scale as i64)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Parses a decimal.
+    fn d(s: &str) -> SqlDecimal {
+        s.parse().unwrap()
+    }
+
+    #[rstest::rstest]
+    #[case("123.45", 12345, 5, 2, "123.45")]
+    #[case("-0.001", -1, 3, 3, "-0.001")]
+    #[case("+7", 7, 1, 0, "7")]
+    #[case(".5", 5, 1, 1, "0.5")]
+    #[case("1.", 1, 1, 0, "1")]
+    #[case(
+        "99999999999999999999999999999999999999",
+        99_999_999_999_999_999_999_999_999_999_999_999_999,
+        38,
+        0,
+        "99999999999999999999999999999999999999"
+    )]
+    // Extra digits after the decimal point are rounded away
+    #[case(
+        "1.23456789012345678901234567890123456789",
+        12_345_678_901_234_567_890_123_456_789_012_345_679,
+        38,
+        37,
+        "1.2345678901234567890123456789012345679"
+    )]
+    fn test_parse(
+        #[case] text: &str,
+        #[case] value: i128,
+        #[case] precision: u8,
+        #[case] scale: u8,
+        #[case] display: &str,
+    ) {
+        let decimal = d(text);
+        assert_eq!(value, decimal.unscaled_value());
+        assert_eq!(precision, decimal.precision());
+        assert_eq!(scale, decimal.scale());
+        assert_eq!(display, decimal.to_string());
+    }
+
+    #[rstest::rstest]
+    #[case("")]
+    #[case("-")]
+    #[case("1.2.3")]
+    #[case("1e5")]
+    #[case("100000000000000000000000000000000000000")]
+    fn test_parse_invalid(#[case] text: &str) {
+        assert!(text.parse::<SqlDecimal>().is_err());
+    }
+
+    #[rstest::rstest]
+    #[case("1.10", "1.1", Ordering::Equal)]
+    #[case("-1.5", "1", Ordering::Less)]
+    #[case("-1.5", "-1.49", Ordering::Less)]
+    #[case("99999999999999999999999999999999999999", "0.1", Ordering::Greater)]
+    fn test_compare(#[case] a: &str, #[case] b: &str, #[case] expected: Ordering) {
+        assert_eq!(expected, d(a).cmp(&d(b)));
+    }
+
+    #[rstest::rstest]
+    #[case("1.5", "2.25", "3.75", "-0.75", "3.375", "0.666667")]
+    #[case("-10", "4", "-6", "-14", "-40", "-2.500000")]
+    #[case("1", "3", "4", "-2", "3", "0.333333")]
+    #[case("2", "3", "5", "-1", "6", "0.666667")]
+    #[case(
+        "12345678901234567890.123456789",
+        "98765.4321",
+        "12345678901234666655.555556789",
+        "12345678901234469124.691356789",
+        "1219326311248285321124828.5321112635269",
+        "124999998873437.4999015820312398552"
+    )]
+    fn test_arithmetic(
+        #[case] a: &str,
+        #[case] b: &str,
+        #[case] sum: &str,
+        #[case] difference: &str,
+        #[case] product: &str,
+        #[case] quotient: &str,
+    ) {
+        assert_eq!(sum, (d(a) + d(b)).to_string());
+        assert_eq!(difference, (d(a) - d(b)).to_string());
+        assert_eq!(product, (d(a) * d(b)).to_string());
+        assert_eq!(quotient, (d(a) / d(b)).to_string());
+    }
+
+    #[test]
+    fn test_overflow() {
+        let max = d("99999999999999999999999999999999999999");
+        assert_eq!(None, max.checked_add(d("1")));
+        assert_eq!(None, max.checked_mul(d("10")));
+        assert_eq!(None, d("1").checked_div(d("0")));
+        assert!(SqlDecimal::new(1000, 3, 0).is_err());
+    }
+
+    #[rstest::rstest]
+    #[case("123.455", 2, true, "123.46")]
+    #[case("-123.455", 2, true, "-123.46")]
+    #[case("123.455", 2, false, "123.45")]
+    #[case("1.5", 4, true, "1.5000")]
+    fn test_adjust_scale(
+        #[case] value: &str,
+        #[case] scale: u8,
+        #[case] round: bool,
+        #[case] expected: &str,
+    ) {
+        let adjusted = d(value).adjust_scale(scale, round).unwrap();
+        assert_eq!(expected, adjusted.to_string());
+        assert_eq!(scale, adjusted.scale());
+    }
+
+    #[rstest::rstest]
+    #[case("0039300000000000000000000000000000", 5, 2, "-123.45")]
+    #[case("0139300000", 5, 2, "123.45")]
+    #[case(
+        "01FFFFFFFF3F228A097AC4865AA84C3B4B",
+        38,
+        0,
+        "99999999999999999999999999999999999999"
+    )]
+    fn test_tds(
+        #[case] bytes: &str,
+        #[case] precision: u8,
+        #[case] scale: u8,
+        #[case] expected: &str,
+    ) {
+        let bytes = hex::decode(bytes).unwrap();
+        let decimal = SqlDecimal::from_tds(&bytes, precision, scale).unwrap();
+        assert_eq!(expected, decimal.to_string());
+        let round_tripped = SqlDecimal::from_tds(&decimal.to_tds(), precision, scale).unwrap();
+        assert_eq!(decimal, round_tripped);
+    }
+
+    #[rstest::rstest]
+    #[case(0.1, "0.1")]
+    #[case(-2.5e-5, "-0.000025")]
+    #[case(1e20, "100000000000000000000")]
+    fn test_from_f64(#[case] value: f64, #[case] expected: &str) {
+        assert_eq!(expected, SqlDecimal::try_from(value).unwrap().to_string());
+    }
+
+    #[cfg(feature = "rust_decimal")]
+    #[test]
+    fn test_rust_decimal() {
+        let value: rust_decimal::Decimal = "-123.4500".parse().unwrap();
+        let decimal = SqlDecimal::from(value);
+        assert_eq!("-123.4500", decimal.to_string());
+        assert_eq!(value, rust_decimal::Decimal::try_from(decimal).unwrap());
+        assert!(
+            rust_decimal::Decimal::try_from(d("99999999999999999999999999999999999999")).is_err()
+        );
+    }
+
+    #[cfg(feature = "bigdecimal")]
+    #[rstest::rstest]
+    #[case("-123.4500", "-123.4500")]
+    #[case("0.001", "0.001")]
+    #[case("1E+3", "1000")]
+    fn test_bigdecimal(#[case] value: &str, #[case] expected: &str) {
+        let value: bigdecimal::BigDecimal = value.parse().unwrap();
+        let decimal = SqlDecimal::try_from(value.clone()).unwrap();
+        assert_eq!(expected, decimal.to_string());
+        assert_eq!(value, bigdecimal::BigDecimal::from(decimal));
+    }
+}
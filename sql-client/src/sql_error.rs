@@ -0,0 +1,82 @@
+use crate::SqlTransientErrors;
+use std::fmt::{Display, Formatter};
+
+/// An error or warning returned by SQL Server (an ERROR or INFO token).
+#[derive(PartialEq, Debug, Clone, Default)]
+pub struct SqlError {
+    /// The error number.
+    number: i32,
+    /// The error state, used to tell apart different places the same error can be raised.
+    state: u8,
+    /// The severity of the error.  Values of 10 or less are informational messages.
+    class: u8,
+    /// The error message.
+    message: String,
+    /// The name of the server that raised the error.
+    server: String,
+    /// The name of the stored procedure or RPC that raised the error, if any.
+    procedure: String,
+    /// The line number within the batch or procedure at which the error was raised.
+    line_number: i32,
+}
+
+impl SqlError {
+    /// Creates a new error.
+    pub(crate) fn new(
+        number: i32,
+        state: u8,
+        class: u8,
+        message: &str,
+        server: &str,
+        procedure: &str,
+        line_number: i32,
+    ) -> Self {
+        Self {
+            number,
+            state,
+            class,
+            message: message.to_string(),
+            server: server.to_string(),
+            procedure: procedure.to_string(),
+            line_number,
+        }
+    }
+    /// The error number.
+    pub fn number(&self) -> i32 {
+        self.number
+    }
+    /// The error state.
+    pub fn state(&self) -> u8 {
+        self.state
+    }
+    /// The severity of the error.
+    pub fn class(&self) -> u8 {
+        self.class
+    }
+    /// The error message.
+    pub fn message(&self) -> &str {
+        &self.message
+    }
+    /// The name of the server that raised the error.
+    pub fn server(&self) -> &str {
+        &self.server
+    }
+    /// The name of the stored procedure or RPC that raised the error.
+    pub fn procedure(&self) -> &str {
+        &self.procedure
+    }
+    /// The line number at which the error was raised.
+    pub fn line_number(&self) -> i32 {
+        self.line_number
+    }
+    /// Whether the error is transient, so that retrying may succeed (see [SqlTransientErrors]).
+    pub fn is_transient(&self) -> bool {
+        SqlTransientErrors::contains(self.number)
+    }
+}
+
+impl Display for SqlError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.message)
+    }
+}
@@ -0,0 +1,153 @@
+use crate::SqlError;
+use std::fmt::{Display, Formatter};
+use uuid::Uuid;
+
+/// The errors SQL Server returned for a request (its ERROR tokens), as raised by .NET's
+/// SqlException.
+///
+/// The number, state, class, server, procedure and line number are those of the first error, and
+/// the message is the messages of all the errors, one per line.
+#[derive(PartialEq, Debug, Clone)]
+pub struct SqlException {
+    /// The errors, in the order the server returned them.  There's at least one.
+    errors: Vec<SqlError>,
+    /// The ID of the connection the errors were raised on.
+    client_connection_id: Uuid,
+}
+
+impl SqlException {
+    /// Creates an exception from a non-empty list of errors.
+    pub(crate) fn new(errors: Vec<SqlError>, client_connection_id: Uuid) -> Self {
+        debug_assert!(!errors.is_empty());
+        Self {
+            errors,
+            client_connection_id,
+        }
+    }
+
+    /// The errors, in the order the server returned them.
+    pub fn errors(&self) -> &[SqlError] {
+        &self.errors
+    }
+
+    /// The ID of the connection the errors were raised on.
+    pub fn client_connection_id(&self) -> Uuid {
+        self.client_connection_id
+    }
+
+    /// The number of the first error.
+    pub fn number(&self) -> i32 {
+        self.first().number()
+    }
+
+    /// The state of the first error.
+    pub fn state(&self) -> u8 {
+        self.first().state()
+    }
+
+    /// The severity of the first error.
+    pub fn class(&self) -> u8 {
+        self.first().class()
+    }
+
+    /// The name of the server that raised the first error.
+    pub fn server(&self) -> &str {
+        self.first().server()
+    }
+
+    /// The name of the stored procedure or RPC that raised the first error.
+    pub fn procedure(&self) -> &str {
+        self.first().procedure()
+    }
+
+    /// The line number at which the first error was raised.
+    pub fn line_number(&self) -> i32 {
+        self.first().line_number()
+    }
+
+    /// Whether any of the errors is transient, so that retrying may succeed.
+    pub fn is_transient(&self) -> bool {
+        self.errors.iter().any(|error| error.is_transient())
+    }
+
+    /// The messages of all the errors, one per line.
+    pub fn message(&self) -> String {
+        self.errors
+            .iter()
+            .map(|error| error.message())
+            .collect::<Vec<&str>>()
+            .join("\n")
+    }
+
+    /// The first error.
+    fn first(&self) -> &SqlError {
+        &self.errors[0]
+    }
+}
+
+impl Display for SqlException {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.message())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::SqlClientError;
+
+    #[test]
+    fn test_first_error() {
+        let exception = SqlException::new(
+            vec![
+                SqlError::new(
+                    547,
+                    0,
+                    16,
+                    "The INSERT statement conflicted...",
+                    "db1",
+                    "",
+                    3,
+                ),
+                SqlError::new(
+                    3621,
+                    0,
+                    0,
+                    "The statement has been terminated.",
+                    "db1",
+                    "",
+                    3,
+                ),
+            ],
+            Uuid::nil(),
+        );
+        assert_eq!(547, exception.number());
+        assert_eq!(16, exception.class());
+        assert_eq!("db1", exception.server());
+        assert_eq!(3, exception.line_number());
+        assert!(!exception.is_transient());
+        assert_eq!(
+            "The INSERT statement conflicted...\nThe statement has been terminated.",
+            exception.message()
+        );
+    }
+
+    #[test]
+    fn test_is_transient() {
+        let exception = SqlException::new(
+            vec![SqlError::new(
+                1205,
+                51,
+                13,
+                "Transaction was deadlocked",
+                "db1",
+                "",
+                1,
+            )],
+            Uuid::nil(),
+        );
+        assert!(exception.is_transient());
+        assert!(SqlClientError::Server(exception).is_transient());
+        assert!(!SqlClientError::InvalidOperation("closed".to_string()).is_transient());
+    }
+}
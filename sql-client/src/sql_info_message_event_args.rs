@@ -0,0 +1,36 @@
+use crate::SqlError;
+use std::sync::Arc;
+
+/// Handles the informational messages of a connection.
+pub(crate) type SqlInfoMessageEventHandler = Arc<dyn Fn(&SqlInfoMessageEventArgs) + Send + Sync>;
+
+/// An informational message from SQL Server (e.g. PRINT output or a RAISERROR with a severity of
+/// 10 or less), passed to [crate::SqlConnection::on_info_message].
+#[derive(PartialEq, Debug, Clone)]
+pub struct SqlInfoMessageEventArgs {
+    /// The messages.
+    errors: Vec<SqlError>,
+}
+
+impl SqlInfoMessageEventArgs {
+    /// Creates the arguments for a message.
+    pub(crate) fn new(error: SqlError) -> Self {
+        Self {
+            errors: vec![error],
+        }
+    }
+
+    /// The messages, with their numbers, severities, etc.
+    pub fn errors(&self) -> &[SqlError] {
+        &self.errors
+    }
+
+    /// The text of the messages, one per line.
+    pub fn message(&self) -> String {
+        self.errors
+            .iter()
+            .map(|error| error.message())
+            .collect::<Vec<&str>>()
+            .join("\n")
+    }
+}
@@ -0,0 +1,213 @@
+use crate::sql_connection_string::SqlConnectionString;
+use crate::sql_credential::SqlCredential;
+use crate::tds_enums::LoginValidationRules;
+use crate::{ApplicationIntent, SqlClientError};
+use secstr::SecStr;
+
+/// What a connection logs in with, taken from its connection string and credentials.
+#[derive(Clone)]
+pub(crate) struct SqlLogin {
+    /// The server to connect to (e.g. "tcp:myserver,1433").
+    data_source: String,
+    /// Whether the whole connection is encrypted (rather than just the login).
+    encrypt: bool,
+    /// Whether the server's certificate is trusted without being checked.
+    trust_server_certificate: bool,
+    /// The packet size to ask for.
+    packet_size: u16,
+    /// The name of the client machine.
+    host_name: String,
+    /// The SQL Server login.
+    user_name: String,
+    /// The login's password.
+    password: SecStr,
+    /// The name of the application.
+    application_name: String,
+    /// The initial language, if not the login's default.
+    language: String,
+    /// The initial database, if not the login's default.
+    database: String,
+    /// A database file to attach.
+    attach_db_filename: String,
+    /// Whether the application only reads from the server.
+    read_only_intent: bool,
+}
+
+impl SqlLogin {
+    /// Takes the login details from a connection string and credentials.  Only SQL Server
+    /// authentication is supported.
+    pub fn new(
+        options: &SqlConnectionString,
+        sql_credential: Option<&SqlCredential>,
+    ) -> Result<Self, SqlClientError> {
+        if options.integrated_security() {
+            return Err(SqlClientError::NotSupported(
+                "Integrated Security (only SQL Server authentication is supported)".to_string(),
+            ));
+        }
+        let sql_credential = sql_credential.ok_or_else(|| {
+            SqlClientError::ArgumentNull("User ID and Password".to_string(), String::new())
+        })?;
+        let data_source = options.data_source().ok_or_else(|| {
+            SqlClientError::ArgumentNull("Data Source".to_string(), String::new())
+        })?;
+        let host_name = options.workstation_id().unwrap_or_else(|| {
+            std::env::var("COMPUTERNAME")
+                .or_else(|_| std::env::var("HOSTNAME"))
+                .unwrap_or_default()
+        });
+        let login = Self {
+            data_source,
+            encrypt: options.encrypt(),
+            trust_server_certificate: options.trust_server_certificate(),
+            packet_size: options.packet_size(),
+            host_name,
+            user_name: sql_credential.user_id(),
+            password: sql_credential.password(),
+            application_name: options.application_name(),
+            language: options.current_language().unwrap_or_default(),
+            database: options.initial_catalog().unwrap_or_default(),
+            attach_db_filename: options.attach_db_filename().unwrap_or_default(),
+            read_only_intent: options.application_intent() == ApplicationIntent::ReadOnly,
+        };
+        // Check the lengths the login message allows
+        let lengths = [
+            (
+                "Workstation ID",
+                &login.host_name,
+                LoginValidationRules::MAXLEN_HOSTNAME,
+            ),
+            (
+                "Application Name",
+                &login.application_name,
+                LoginValidationRules::MAXLEN_APPNAME,
+            ),
+            (
+                "Data Source",
+                &login.data_source,
+                LoginValidationRules::MAXLEN_SERVERNAME,
+            ),
+            (
+                "Current Language",
+                &login.language,
+                LoginValidationRules::MAXLEN_LANGUAGE,
+            ),
+            (
+                "Initial Catalog",
+                &login.database,
+                LoginValidationRules::MAXLEN_DATABASE,
+            ),
+            (
+                "AttachDbFilename",
+                &login.attach_db_filename,
+                LoginValidationRules::MAXLEN_ATTACHDBFILE,
+            ),
+        ];
+        for (name, value, max_length) in lengths {
+            if value.encode_utf16().count() > max_length {
+                return Err(SqlClientError::InvalidArgumentLength(
+                    name.to_string(),
+                    value.clone(),
+                    max_length,
+                ));
+            }
+        }
+        Ok(login)
+    }
+
+    /// The server to connect to.
+    pub fn data_source(&self) -> &str {
+        &self.data_source
+    }
+
+    /// Whether the whole connection is encrypted.
+    pub fn encrypt(&self) -> bool {
+        self.encrypt
+    }
+
+    /// Whether the server's certificate is trusted without being checked.
+    pub fn trust_server_certificate(&self) -> bool {
+        self.trust_server_certificate
+    }
+
+    /// The packet size to ask for.
+    pub fn packet_size(&self) -> u16 {
+        self.packet_size
+    }
+
+    /// The name of the client machine.
+    pub fn host_name(&self) -> &str {
+        &self.host_name
+    }
+
+    /// The SQL Server login.
+    pub fn user_name(&self) -> &str {
+        &self.user_name
+    }
+
+    /// The login's password.
+    pub fn password(&self) -> &SecStr {
+        &self.password
+    }
+
+    /// The name of the application.
+    pub fn application_name(&self) -> &str {
+        &self.application_name
+    }
+
+    /// The initial language, or empty for the login's default.
+    pub fn language(&self) -> &str {
+        &self.language
+    }
+
+    /// The initial database, or empty for the login's default.
+    pub fn database(&self) -> &str {
+        &self.database
+    }
+
+    /// A database file to attach, or empty for none.
+    pub fn attach_db_filename(&self) -> &str {
+        &self.attach_db_filename
+    }
+
+    /// Whether the application only reads from the server.
+    pub fn read_only_intent(&self) -> bool {
+        self.read_only_intent
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[rstest::rstest]
+    #[case::sql_login("Server=myserver;User ID=me;Password=secret", true)]
+    #[case::no_credentials("Server=myserver", false)]
+    #[case::integrated_security("Server=myserver;Integrated Security=SSPI", false)]
+    #[case::no_data_source("User ID=me;Password=secret", false)]
+    fn test_new(#[case] connection_string: &str, #[case] expected: bool) {
+        let options = SqlConnectionString::try_from(connection_string).unwrap();
+        let sql_credential = options.sql_credential().unwrap();
+        let login = SqlLogin::new(&options, sql_credential.as_ref());
+        assert_eq!(expected, login.is_ok());
+    }
+
+    #[test]
+    fn test_options() {
+        let options = SqlConnectionString::try_from(
+            "Server=myserver;User ID=me;Password=secret;Database=db;Application Name=app;\
+             Workstation ID=ws;ApplicationIntent=ReadOnly;Encrypt=False",
+        )
+        .unwrap();
+        let sql_credential = options.sql_credential().unwrap();
+        let login = SqlLogin::new(&options, sql_credential.as_ref()).unwrap();
+        assert_eq!("myserver", login.data_source());
+        assert_eq!("me", login.user_name());
+        assert_eq!(b"secret", login.password().unsecure());
+        assert_eq!("db", login.database());
+        assert_eq!("app", login.application_name());
+        assert_eq!("ws", login.host_name());
+        assert!(login.read_only_intent());
+        assert!(!login.encrypt());
+    }
+}
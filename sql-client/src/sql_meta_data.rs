@@ -0,0 +1,170 @@
+use crate::sql_collation::SqlCollation;
+use crate::tds_token::ColumnMetaData;
+use crate::tds_type_info::{TdsLengthKind, TypeInfo};
+use crate::{SqlClientError, SqlDbType, SqlDecimal, SqlValue};
+
+/// Describes a column of a [crate::DataTable].
+#[derive(PartialEq, Debug, Clone)]
+pub struct SqlMetaData {
+    /// The column name.
+    name: String,
+    /// The column type.
+    sql_db_type: SqlDbType,
+    /// The maximum size of the column's values in characters or bytes, 0 to infer it from the
+    /// values, or -1 for "max".
+    size: i32,
+    /// The precision of a decimal, if set.
+    precision: Option<u8>,
+    /// The scale of a decimal or fractional seconds, if set.
+    scale: Option<u8>,
+}
+
+impl SqlMetaData {
+    /// The default precision of a decimal column with no decimal values.
+    const DEFAULT_PRECISION: u8 = 18;
+    /// The default fractional seconds scale of a time column.
+    const DEFAULT_TIME_SCALE: u8 = 7;
+
+    /// Creates a column of a given type.
+    pub fn new(name: &str, sql_db_type: SqlDbType) -> Self {
+        Self {
+            name: name.to_string(),
+            sql_db_type,
+            size: 0,
+            precision: None,
+            scale: None,
+        }
+    }
+
+    /// Creates a column of a type with a size (e.g. nvarchar(50)), or -1 for "max".
+    pub fn with_size(name: &str, sql_db_type: SqlDbType, size: i32) -> Self {
+        let mut meta_data = Self::new(name, sql_db_type);
+        meta_data.size = size;
+        meta_data
+    }
+
+    /// Creates a decimal column with a precision and scale.
+    pub fn with_precision(name: &str, sql_db_type: SqlDbType, precision: u8, scale: u8) -> Self {
+        let mut meta_data = Self::new(name, sql_db_type);
+        meta_data.precision = Some(precision);
+        meta_data.scale = Some(scale);
+        meta_data
+    }
+
+    /// Creates a column like a column of a result set.
+    pub(crate) fn from_column(column: &ColumnMetaData) -> Self {
+        let type_info = &column.type_info;
+        let sql_db_type = type_info.sql_db_type();
+        let size = match type_info.length_kind() {
+            TdsLengthKind::Plp | TdsLengthKind::LongLen => -1,
+            _ if sql_db_type.has_size() => {
+                let units = if sql_db_type.is_unicode() { 2 } else { 1 };
+                (type_info.max_length / units) as i32
+            }
+            _ => 0,
+        };
+        let mut meta_data = Self::with_size(&column.column_name, sql_db_type, size);
+        if sql_db_type == SqlDbType::Decimal {
+            meta_data.precision = Some(type_info.precision);
+        }
+        if sql_db_type == SqlDbType::Decimal || sql_db_type.has_time_scale() {
+            meta_data.scale = Some(type_info.scale);
+        }
+        meta_data
+    }
+
+    /// The column name.
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// The column type.
+    pub fn sql_db_type(&self) -> SqlDbType {
+        self.sql_db_type
+    }
+
+    /// The maximum size of the column's values in characters or bytes (0 if inferred, -1 for "max").
+    pub fn size(&self) -> i32 {
+        self.size
+    }
+
+    /// The precision of a decimal (0 if inferred).
+    pub fn precision(&self) -> u8 {
+        self.precision.unwrap_or(0)
+    }
+
+    /// The scale of a decimal or fractional seconds (0 if inferred).
+    pub fn scale(&self) -> u8 {
+        self.scale.unwrap_or(0)
+    }
+
+    /// The TYPE_INFO the column is sent with, given the values it holds.
+    pub(crate) fn type_info<'a>(
+        &self,
+        values: impl Iterator<Item = &'a SqlValue>,
+        collation: Option<SqlCollation>,
+    ) -> Result<TypeInfo, SqlClientError> {
+        // Every row is sent with the same type, so it's sized to fit the largest value.
+        let mut value_length = 0;
+        let mut integer_digits = None;
+        let mut decimal_scale = 0;
+        for value in values {
+            value_length = value_length.max(value.length(self.sql_db_type.is_unicode()));
+            if let SqlValue::Decimal(decimal) = value {
+                let digits = decimal.precision().max(decimal.scale()) - decimal.scale();
+                integer_digits = Some(integer_digits.unwrap_or(0).max(digits));
+                decimal_scale = decimal_scale.max(decimal.scale());
+            }
+        }
+        let scale = match self.scale {
+            Some(scale) => scale,
+            None if self.sql_db_type.has_time_scale() => Self::DEFAULT_TIME_SCALE,
+            None => decimal_scale,
+        };
+        let precision = match (self.precision, integer_digits) {
+            (Some(precision), _) => precision,
+            (None, Some(digits)) => (digits + scale).min(SqlDecimal::MAX_PRECISION),
+            (None, None) => Self::DEFAULT_PRECISION,
+        };
+        TypeInfo::for_parameter(
+            self.sql_db_type,
+            self.size,
+            value_length,
+            precision,
+            scale,
+            collation,
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tds_enums::TdsEnums;
+
+    #[test]
+    fn test_type_info() {
+        let column = SqlMetaData::new("Name", SqlDbType::NVarChar);
+        let short = SqlValue::from("abc");
+        let long = SqlValue::from("x".repeat(5000));
+        let type_info = column.type_info([&short].into_iter(), None).unwrap();
+        assert_eq!(TdsEnums::MAXSIZE, type_info.max_length);
+        let type_info = column.type_info([&short, &long].into_iter(), None).unwrap();
+        assert!(type_info.is_plp());
+    }
+
+    #[test]
+    fn test_decimal_type_info() {
+        let column = SqlMetaData::new("Amount", SqlDbType::Decimal);
+        let values = [
+            SqlValue::Decimal("12345.6".parse().unwrap()),
+            SqlValue::Decimal("1.234".parse().unwrap()),
+            SqlValue::Null,
+        ];
+        let type_info = column.type_info(values.iter(), None).unwrap();
+        assert_eq!((8, 3), (type_info.precision, type_info.scale));
+        let column = SqlMetaData::with_precision("Amount", SqlDbType::Decimal, 10, 2);
+        let type_info = column.type_info(values.iter(), None).unwrap();
+        assert_eq!((10, 2), (type_info.precision, type_info.scale));
+    }
+}
@@ -0,0 +1,306 @@
+use crate::{SqlClientError, SqlDecimal};
+use std::fmt::{Display, Formatter};
+use std::ops::{Add, Div, Mul, Neg, Sub};
+use std::str::FromStr;
+
+/// A money or smallmoney value, stored exactly in ten-thousandths of a currency unit.
+#[derive(PartialEq, Eq, PartialOrd, Ord, Hash, Debug, Clone, Copy, Default)]
+pub struct SqlMoney {
+    /// The value in ten-thousandths.
+    value: i64,
+}
+
+impl SqlMoney {
+    /// The smallest money value (-922,337,203,685,477.5808).
+    pub const MIN: SqlMoney = SqlMoney { value: i64::MIN };
+    /// The largest money value (922,337,203,685,477.5807).
+    pub const MAX: SqlMoney = SqlMoney { value: i64::MAX };
+    /// The number of digits after the decimal point.
+    pub const SCALE: u8 = 4;
+    /// The number of ten-thousandths in a currency unit.
+    const UNIT: i64 = 10_000;
+
+    /// Creates a value from a number of ten-thousandths of a currency unit (e.g. 12345 is 1.2345).
+    pub fn from_ten_thousandths(value: i64) -> Self {
+        Self { value }
+    }
+
+    /// The value in ten-thousandths of a currency unit.
+    pub fn to_ten_thousandths(&self) -> i64 {
+        self.value
+    }
+
+    /// Whether the value fits in a smallmoney (-214,748.3648 to 214,748.3647).
+    pub fn is_small(&self) -> bool {
+        i32::try_from(self.value).is_ok()
+    }
+
+    /// Adds two values, or returns None on overflow.
+    pub fn checked_add(self, other: Self) -> Option<Self> {
+        self.value
+            .checked_add(other.value)
+            .map(Self::from_ten_thousandths)
+    }
+
+    /// Subtracts a value, or returns None on overflow.
+    pub fn checked_sub(self, other: Self) -> Option<Self> {
+        self.value
+            .checked_sub(other.value)
+            .map(Self::from_ten_thousandths)
+    }
+
+    /// Multiplies two values, rounding to the nearest ten-thousandth, or returns None on overflow.
+    pub fn checked_mul(self, other: Self) -> Option<Self> {
+        let product = self.value as i128 * other.value as i128;
+        Self::from_wide(Self::round_div(product, Self::UNIT as i128))
+    }
+
+    /// Divides by a value, rounding to the nearest ten-thousandth, or returns None on overflow or division by zero.
+    pub fn checked_div(self, other: Self) -> Option<Self> {
+        if other.value == 0 {
+            return None;
+        }
+        let dividend = self.value as i128 * Self::UNIT as i128;
+        Self::from_wide(Self::round_div(dividend, other.value as i128))
+    }
+
+    /// Divides, rounding half away from zero.
+    fn round_div(dividend: i128, divisor: i128) -> i128 {
+        let quotient = dividend / divisor;
+        let remainder = dividend % divisor;
+        if remainder.unsigned_abs() * 2 >= divisor.unsigned_abs() {
+            quotient + dividend.signum() * divisor.signum()
+        } else {
+            quotient
+        }
+    }
+
+    /// Creates a value from a wider number of ten-thousandths, if it fits.
+    fn from_wide(value: i128) -> Option<Self> {
+        i64::try_from(value).ok().map(Self::from_ten_thousandths)
+    }
+
+    /// Converts the value to a float, which may lose precision.
+    pub fn to_f64(&self) -> f64 {
+        self.value as f64 / Self::UNIT as f64
+    }
+
+    /// Reads a money (high four bytes first) or smallmoney value from the TDS wire format.
+    pub(crate) fn from_tds(bytes: &[u8]) -> Result<Self, SqlClientError> {
+        match bytes.len() {
+            4 => Ok(Self::from_ten_thousandths(
+                i32::from_le_bytes(bytes.try_into().unwrap()) as i64,
+            )),
+            8 => {
+                let high = i32::from_le_bytes(bytes[0..4].try_into().unwrap()) as i64;
+                let low = u32::from_le_bytes(bytes[4..8].try_into().unwrap()) as i64;
+                Ok(Self::from_ten_thousandths((high << 32) | low))
+            }
+            length => Err(SqlClientError::Protocol(format!(
+                "Invalid money of length {}",
+                length
+            ))),
+        }
+    }
+
+    /// Writes the value as a money (8 bytes) or smallmoney (4 bytes), or None if it doesn't fit.
+    pub(crate) fn to_tds(self, length: u32) -> Option<Vec<u8>> {
+        if length == 4 {
+            return i32::try_from(self.value)
+                .ok()
+                .map(|value| value.to_le_bytes().to_vec());
+        }
+        let mut bytes = ((self.value >> 32) as i32).to_le_bytes().to_vec();
+        bytes.extend_from_slice(&(self.value as u32).to_le_bytes());
+        Some(bytes)
+    }
+}
+
+impl Display for SqlMoney {
+    /// Writes the value with between two and four digits after the decimal point (e.g. "12.50", "0.1234").
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        let magnitude = self.value.unsigned_abs();
+        let unit = Self::UNIT as u64;
+        let mut fraction = format!("{:04}", magnitude % unit);
+        while fraction.len() > 2 && fraction.ends_with('0') {
+            fraction.pop();
+        }
+        let sign = if self.value < 0 { "-" } else { "" };
+        write!(f, "{}{}.{}", sign, magnitude / unit, fraction)
+    }
+}
+
+impl FromStr for SqlMoney {
+    type Err = SqlClientError;
+
+    /// Parses a value such as "-12.50", rounding to the nearest ten-thousandth.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let decimal: SqlDecimal = s
+            .parse()
+            .map_err(|_| SqlClientError::UnsupportedValue("SqlMoney".to_string(), s.to_string()))?;
+        Self::try_from(decimal)
+    }
+}
+
+impl Neg for SqlMoney {
+    type Output = SqlMoney;
+
+    fn neg(self) -> Self::Output {
+        Self::from_ten_thousandths(-self.value)
+    }
+}
+
+impl Add for SqlMoney {
+    type Output = SqlMoney;
+
+    fn add(self, rhs: Self) -> Self::Output {
+        self.checked_add(rhs).expect("attempt to add with overflow")
+    }
+}
+
+impl Sub for SqlMoney {
+    type Output = SqlMoney;
+
+    fn sub(self, rhs: Self) -> Self::Output {
+        self.checked_sub(rhs)
+            .expect("attempt to subtract with overflow")
+    }
+}
+
+impl Mul for SqlMoney {
+    type Output = SqlMoney;
+
+    fn mul(self, rhs: Self) -> Self::Output {
+        self.checked_mul(rhs)
+            .expect("attempt to multiply with overflow")
+    }
+}
+
+impl Div for SqlMoney {
+    type Output = SqlMoney;
+
+    fn div(self, rhs: Self) -> Self::Output {
+        self.checked_div(rhs)
+            .expect("attempt to divide by zero or with overflow")
+    }
+}
+
+impl From<i32> for SqlMoney {
+    fn from(value: i32) -> Self {
+        Self::from_ten_thousandths(value as i64 * Self::UNIT)
+    }
+}
+
+impl TryFrom<SqlDecimal> for SqlMoney {
+    type Error = SqlClientError;
+
+    /// Converts a decimal, rounding to the nearest ten-thousandth.
+    fn try_from(value: SqlDecimal) -> Result<Self, Self::Error> {
+        let overflow = || SqlClientError::Overflow(format!("{} doesn't fit in money", value));
+        let adjusted = value
+            .adjust_scale(Self::SCALE, true)
+            .map_err(|_| overflow())?;
+        i64::try_from(adjusted.unscaled_value())
+            .map(Self::from_ten_thousandths)
+            .map_err(|_| overflow())
+    }
+}
+
+impl TryFrom<f64> for SqlMoney {
+    type Error = SqlClientError;
+
+    fn try_from(value: f64) -> Result<Self, Self::Error> {
+        Self::try_from(SqlDecimal::try_from(value)?)
+    }
+}
+
+impl From<SqlMoney> for f64 {
+    fn from(value: SqlMoney) -> Self {
+        value.to_f64()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Parses a money value.
+    fn m(s: &str) -> SqlMoney {
+        s.parse().unwrap()
+    }
+
+    #[rstest::rstest]
+    #[case("12.5", 125_000, "12.50")]
+    #[case("-0.1234", -1_234, "-0.1234")]
+    #[case("1.23456", 12_346, "1.2346")]
+    #[case("7", 70_000, "7.00")]
+    #[case("922337203685477.5807", i64::MAX, "922337203685477.5807")]
+    #[case("-922337203685477.5808", i64::MIN, "-922337203685477.5808")]
+    fn test_parse(#[case] text: &str, #[case] value: i64, #[case] display: &str) {
+        let money = m(text);
+        assert_eq!(value, money.to_ten_thousandths());
+        assert_eq!(display, money.to_string());
+    }
+
+    #[rstest::rstest]
+    #[case("922337203685477.5808")]
+    #[case("abc")]
+    fn test_parse_invalid(#[case] text: &str) {
+        assert!(text.parse::<SqlMoney>().is_err());
+    }
+
+    #[rstest::rstest]
+    #[case("1.50", "2.25", "3.75", "-0.75", "3.375", "0.6667")]
+    #[case("-10", "4", "-6.00", "-14.00", "-40.00", "-2.50")]
+    #[case("0.0001", "3", "3.0001", "-2.9999", "0.0003", "0.00")]
+    fn test_arithmetic(
+        #[case] a: &str,
+        #[case] b: &str,
+        #[case] sum: &str,
+        #[case] difference: &str,
+        #[case] product: &str,
+        #[case] quotient: &str,
+    ) {
+        assert_eq!(sum, (m(a) + m(b)).to_string());
+        assert_eq!(difference, (m(a) - m(b)).to_string());
+        assert_eq!(product, (m(a) * m(b)).to_string());
+        assert_eq!(quotient, (m(a) / m(b)).to_string());
+    }
+
+    #[test]
+    fn test_overflow() {
+        assert_eq!(None, SqlMoney::MAX.checked_add(m("0.0001")));
+        assert_eq!(None, SqlMoney::MAX.checked_mul(m("2")));
+        assert_eq!(None, m("1").checked_div(m("0")));
+    }
+
+    #[rstest::rstest]
+    #[case("0000000050C30000", "5.00")]
+    #[case("FFFFFFFFB03CFFFF", "-5.00")]
+    #[case("50C30000", "5.00")]
+    #[case("B03CFFFF", "-5.00")]
+    fn test_tds(#[case] bytes: &str, #[case] expected: &str) {
+        let bytes = hex::decode(bytes).unwrap();
+        let money = SqlMoney::from_tds(&bytes).unwrap();
+        assert_eq!(expected, money.to_string());
+        assert_eq!(Some(bytes.clone()), money.to_tds(bytes.len() as u32));
+    }
+
+    #[test]
+    fn test_small() {
+        assert!(m("214748.3647").is_small());
+        assert!(!m("214748.3648").is_small());
+        assert_eq!(None, m("214748.3648").to_tds(4));
+    }
+
+    #[test]
+    fn test_decimal_conversion() {
+        let decimal: SqlDecimal = "-12.34565".parse().unwrap();
+        let money = SqlMoney::try_from(decimal).unwrap();
+        assert_eq!("-12.3457", money.to_string());
+        assert_eq!(
+            "-12.3457".parse::<SqlDecimal>().unwrap(),
+            SqlDecimal::from(money)
+        );
+    }
+}
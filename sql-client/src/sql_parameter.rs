@@ -0,0 +1,488 @@
+use crate::sql_collation::SqlCollation;
+use crate::tds_enums::TdsEnums;
+use crate::tds_type_info::TypeInfo;
+use crate::tds_value::TableType;
+use crate::{DataTable, ParameterDirection, SqlClientError, SqlDbType, SqlValue, ToSql};
+use std::fmt::{Debug, Formatter};
+use std::sync::Arc;
+use tokio::io::AsyncRead;
+use tokio::sync::{Mutex, MutexGuard};
+
+/// A stream that a parameter's value is read from when its command is executed.
+#[derive(Clone)]
+pub(crate) struct ParameterStream(Arc<Mutex<Box<dyn AsyncRead + Send + Unpin>>>);
+
+impl ParameterStream {
+    /// Locks the stream for reading.
+    pub async fn lock(&self) -> MutexGuard<'_, Box<dyn AsyncRead + Send + Unpin>> {
+        self.0.lock().await
+    }
+}
+
+impl PartialEq for ParameterStream {
+    /// Streams are only equal to themselves.
+    fn eq(&self, other: &Self) -> bool {
+        Arc::ptr_eq(&self.0, &other.0)
+    }
+}
+
+impl Debug for ParameterStream {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.write_str("ParameterStream")
+    }
+}
+
+/// A parameter to a [crate::SqlCommand].
+#[derive(PartialEq, Debug, Clone, Default)]
+pub struct SqlParameter {
+    /// The parameter name.
+    parameter_name: String,
+    /// The parameter type, if set explicitly.  If not set, the type is inferred from the value.
+    sql_db_type: Option<SqlDbType>,
+    /// Whether the parameter is input, output, or both, or the procedure's return value.
+    direction: ParameterDirection,
+    /// The maximum size of the value in characters or bytes, 0 to infer it from the value, or -1 for "max".
+    size: i32,
+    /// The precision of a decimal, if set.
+    precision: Option<u8>,
+    /// The scale of a decimal or fractional seconds, if set.
+    scale: Option<u8>,
+    /// The value.
+    value: SqlValue,
+    /// A stream that the value is read from instead, when the command is executed.
+    stream: Option<ParameterStream>,
+    /// The rows of a table-valued parameter.
+    table: Option<DataTable>,
+    /// The name of the user-defined table type of a table-valued parameter (e.g. "dbo.IdList").
+    type_name: String,
+}
+
+impl SqlParameter {
+    /// The default precision of a decimal parameter.
+    const DEFAULT_PRECISION: u8 = 18;
+    /// The default fractional seconds scale of a time parameter.
+    const DEFAULT_TIME_SCALE: u8 = 7;
+
+    /// Creates a parameter of a given type.
+    pub fn new(parameter_name: &str, sql_db_type: SqlDbType) -> Self {
+        Self {
+            parameter_name: parameter_name.to_string(),
+            sql_db_type: Some(sql_db_type),
+            ..Default::default()
+        }
+    }
+
+    /// Creates a parameter with a value (anything that implements [ToSql]), inferring the type from
+    /// the value.
+    pub fn with_value(parameter_name: &str, value: impl ToSql) -> Self {
+        Self {
+            parameter_name: parameter_name.to_string(),
+            value: value.to_sql(),
+            ..Default::default()
+        }
+    }
+
+    /// Creates a parameter whose value is read from a stream as the command is sent, so that a large
+    /// value doesn't need to be held in memory.
+    ///
+    /// The parameter is sent as the "max" version of its type (e.g. varbinary(max)).  For binary
+    /// types, the stream's bytes are sent as they are; for character and xml types, the stream must
+    /// be UTF-8 text.  The stream is consumed by the first command that sends it, so the command
+    /// isn't retried.
+    pub fn with_stream(
+        parameter_name: &str,
+        sql_db_type: SqlDbType,
+        stream: impl AsyncRead + Send + Unpin + 'static,
+    ) -> Self {
+        let mut parameter = Self::new(parameter_name, sql_db_type);
+        parameter.set_stream(stream);
+        parameter
+    }
+
+    /// Creates a table-valued parameter, whose rows are sent as the given user-defined table type
+    /// (e.g. "dbo.IdList").
+    pub fn with_table(parameter_name: &str, type_name: &str, table: DataTable) -> Self {
+        let mut parameter = Self::new(parameter_name, SqlDbType::Structured);
+        parameter.set_type_name(type_name);
+        parameter.set_table(table);
+        parameter
+    }
+
+    /// The parameter name.
+    pub fn parameter_name(&self) -> &str {
+        &self.parameter_name
+    }
+    /// Sets the parameter name.
+    pub fn set_parameter_name(&mut self, value: &str) {
+        self.parameter_name = value.to_string();
+    }
+
+    /// The parameter type.
+    pub fn sql_db_type(&self) -> SqlDbType {
+        self.sql_db_type.unwrap_or_else(|| self.value.sql_db_type())
+    }
+    /// Sets the parameter type.
+    pub fn set_sql_db_type(&mut self, value: SqlDbType) {
+        self.sql_db_type = Some(value);
+    }
+
+    /// The parameter direction.
+    pub fn direction(&self) -> ParameterDirection {
+        self.direction
+    }
+    /// Sets the parameter direction.
+    pub fn set_direction(&mut self, value: ParameterDirection) {
+        self.direction = value;
+    }
+
+    /// The maximum size of the value in characters or bytes (0 if inferred, -1 for "max").
+    pub fn size(&self) -> i32 {
+        self.size
+    }
+    /// Sets the maximum size of the value.
+    pub fn set_size(&mut self, value: i32) {
+        self.size = value;
+    }
+
+    /// The precision of a decimal.
+    pub fn precision(&self) -> u8 {
+        self.precision.unwrap_or(0)
+    }
+    /// Sets the precision of a decimal.
+    pub fn set_precision(&mut self, value: u8) {
+        self.precision = Some(value);
+    }
+
+    /// The scale of a decimal or fractional seconds.
+    pub fn scale(&self) -> u8 {
+        self.scale.unwrap_or(0)
+    }
+    /// Sets the scale of a decimal or fractional seconds.
+    pub fn set_scale(&mut self, value: u8) {
+        self.scale = Some(value);
+    }
+
+    /// The value.
+    pub fn value(&self) -> &SqlValue {
+        &self.value
+    }
+    /// Sets the value.
+    pub fn set_value(&mut self, value: impl ToSql) {
+        self.value = value.to_sql();
+        self.stream = None;
+        self.table = None;
+    }
+
+    /// Sets a stream to read the value from when the command is executed, in place of the value.
+    /// See [SqlParameter::with_stream].
+    pub fn set_stream(&mut self, stream: impl AsyncRead + Send + Unpin + 'static) {
+        self.value = SqlValue::Null;
+        self.table = None;
+        self.stream = Some(ParameterStream(Arc::new(Mutex::new(Box::new(stream)))));
+    }
+
+    /// The rows of a table-valued parameter.
+    pub fn table(&self) -> Option<&DataTable> {
+        self.table.as_ref()
+    }
+    /// Sets the rows of a table-valued parameter, in place of the value.  See
+    /// [SqlParameter::with_table].
+    pub fn set_table(&mut self, table: DataTable) {
+        self.value = SqlValue::Null;
+        self.stream = None;
+        self.table = Some(table);
+    }
+
+    /// The name of the user-defined table type of a table-valued parameter.
+    pub fn type_name(&self) -> &str {
+        &self.type_name
+    }
+    /// Sets the name of the user-defined table type of a table-valued parameter.
+    pub fn set_type_name(&mut self, value: &str) {
+        self.type_name = value.to_string();
+    }
+
+    /// The stream the value is read from, if it has one.
+    pub(crate) fn stream(&self) -> Option<&ParameterStream> {
+        self.stream.as_ref()
+    }
+
+    /// The name sent to the server, which must start with "@".
+    pub(crate) fn rpc_name(&self) -> String {
+        if self.parameter_name.starts_with('@') {
+            self.parameter_name.clone()
+        } else {
+            format!("@{}", self.parameter_name)
+        }
+    }
+
+    /// Whether the parameter has the given name, ignoring case and the leading "@".
+    pub(crate) fn has_name(&self, name: &str) -> bool {
+        self.parameter_name
+            .trim_start_matches('@')
+            .eq_ignore_ascii_case(name.trim_start_matches('@'))
+    }
+
+    /// The TYPE_INFO the parameter is sent with.
+    pub(crate) fn type_info(
+        &self,
+        collation: Option<SqlCollation>,
+    ) -> Result<TypeInfo, SqlClientError> {
+        let sql_db_type = self.sql_db_type();
+        // Tables are sent with their own type info (see table_type).
+        if sql_db_type == SqlDbType::Structured {
+            self.check_table()?;
+            return Ok(TypeInfo {
+                tds_type: TdsEnums::SQLTABLE,
+                ..Default::default()
+            });
+        }
+        // Streamed values are sent in chunks, which only the max types allow.
+        let size = match (&self.stream, sql_db_type) {
+            (None, _) => self.size,
+            (
+                Some(_),
+                SqlDbType::VarBinary
+                | SqlDbType::Image
+                | SqlDbType::VarChar
+                | SqlDbType::Text
+                | SqlDbType::NVarChar
+                | SqlDbType::NText
+                | SqlDbType::Xml,
+            ) => -1,
+            (Some(_), _) => {
+                return Err(SqlClientError::NotSupported(format!(
+                    "Streaming parameters of type {}",
+                    sql_db_type
+                )))
+            }
+        };
+        // A decimal value's own precision and scale are used unless they've been set explicitly.
+        let decimal = match &self.value {
+            SqlValue::Decimal(value) => Some(value),
+            _ => None,
+        };
+        let scale = match (self.scale, decimal) {
+            (Some(scale), _) => scale,
+            (None, _) if sql_db_type.has_time_scale() => Self::DEFAULT_TIME_SCALE,
+            (None, Some(decimal)) => decimal.scale(),
+            (None, None) => 0,
+        };
+        let precision = match (self.precision, decimal) {
+            (Some(precision), _) => precision,
+            (None, Some(decimal)) => decimal.precision().max(scale),
+            (None, None) => Self::DEFAULT_PRECISION,
+        };
+        TypeInfo::for_parameter(
+            sql_db_type,
+            size,
+            self.value.length(sql_db_type.is_unicode()),
+            precision,
+            scale,
+            collation,
+        )
+    }
+
+    /// Checks that a table-valued parameter can be sent.
+    fn check_table(&self) -> Result<(), SqlClientError> {
+        if self.table.is_none() {
+            return Err(SqlClientError::ArgumentNull(
+                "table".to_string(),
+                self.parameter_name.clone(),
+            ));
+        }
+        if self.direction != ParameterDirection::Input {
+            return Err(SqlClientError::NotSupported(format!(
+                "The direction {:?} for table-valued parameters",
+                self.direction
+            )));
+        }
+        Ok(())
+    }
+
+    /// The schema and name of the user-defined table type, which may be quoted with brackets (e.g.
+    /// "[dbo].[IdList]").
+    fn type_name_parts(&self) -> Result<(String, String), SqlClientError> {
+        let mut parts = vec![String::new()];
+        let mut quoted = false;
+        let mut chars = self.type_name.chars().peekable();
+        while let Some(c) = chars.next() {
+            match c {
+                // A "]]" in a quoted part is an escaped "]"
+                ']' if quoted && chars.peek() == Some(&']') => {
+                    chars.next();
+                    parts.last_mut().unwrap().push(']');
+                }
+                ']' if quoted => quoted = false,
+                '[' if !quoted => quoted = true,
+                '.' if !quoted => parts.push(String::new()),
+                c => parts.last_mut().unwrap().push(c),
+            }
+        }
+        let invalid =
+            || SqlClientError::ArgumentOutOfRange("type_name".to_string(), self.type_name.clone());
+        let name = parts
+            .pop()
+            .filter(|name| !name.is_empty())
+            .ok_or_else(invalid)?;
+        // The type must be in the database being called.
+        let schema = parts.pop().unwrap_or_default();
+        if quoted || !parts.is_empty() {
+            return Err(invalid());
+        }
+        Ok((schema, name))
+    }
+
+    /// The type and rows of a table-valued parameter, or None if it isn't one.
+    pub(crate) fn table_type(
+        &self,
+        collation: Option<SqlCollation>,
+    ) -> Result<Option<TableType<'_>>, SqlClientError> {
+        if self.sql_db_type() != SqlDbType::Structured {
+            return Ok(None);
+        }
+        self.check_table()?;
+        let table = self.table.as_ref().unwrap();
+        let (schema, name) = self.type_name_parts()?;
+        Ok(Some(TableType {
+            schema,
+            name,
+            columns: table.type_infos(collation)?,
+            rows: table.rows(),
+        }))
+    }
+
+    /// The T-SQL declaration of the parameter used by sp_executesql (e.g. "@name nvarchar(50) output").
+    pub(crate) fn declaration(&self) -> Result<String, SqlClientError> {
+        let sql_db_type = self.sql_db_type();
+        // Table-valued parameters are declared with their table type, and can't be changed.
+        if sql_db_type == SqlDbType::Structured {
+            self.check_table()?;
+            return Ok(format!("{} {} readonly", self.rpc_name(), self.type_name));
+        }
+        let type_info = self.type_info(None)?;
+        let mut declaration = format!("{} {}", self.rpc_name(), sql_db_type);
+        if sql_db_type.has_size() {
+            if type_info.max_length == TdsEnums::SQL_USHORTVARMAXLEN as u32 {
+                declaration.push_str("(max)");
+            } else {
+                let units = if sql_db_type.is_unicode() { 2 } else { 1 };
+                declaration.push_str(&format!("({})", type_info.max_length / units));
+            }
+        } else if sql_db_type == SqlDbType::Decimal {
+            declaration.push_str(&format!("({},{})", type_info.precision, type_info.scale));
+        } else if sql_db_type.has_time_scale() {
+            declaration.push_str(&format!("({})", type_info.scale));
+        }
+        if self.direction.is_output() {
+            declaration.push_str(" output");
+        }
+        Ok(declaration)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[rstest::rstest]
+    #[case(SqlParameter::with_value("id", 1), "@id int")]
+    #[case(SqlParameter::with_value("@name", "abc"), "@name nvarchar(4000)")]
+    #[case(SqlParameter::with_value("@name", "x".repeat(4001)), "@name nvarchar(max)")]
+    #[case(SqlParameter::with_value("@data", vec![1u8]), "@data varbinary(8000)")]
+    #[case(
+        SqlParameter::new("@amount", SqlDbType::Decimal),
+        "@amount decimal(18,0)"
+    )]
+    #[case(SqlParameter::new("@when", SqlDbType::DateTime2), "@when datetime2(7)")]
+    #[case(
+        SqlParameter::with_value("@amount", "-123.4500".parse::<crate::SqlDecimal>().unwrap()),
+        "@amount decimal(7,4)"
+    )]
+    #[case(
+        SqlParameter::with_value("@amount", crate::SqlMoney::from(5)),
+        "@amount money"
+    )]
+    fn test_declaration(#[case] parameter: SqlParameter, #[case] expected: &str) {
+        assert_eq!(expected, parameter.declaration().unwrap());
+    }
+
+    #[rstest::rstest]
+    #[case(SqlDbType::VarBinary, "@data varbinary(max)")]
+    #[case(SqlDbType::NVarChar, "@data nvarchar(max)")]
+    #[case(SqlDbType::Xml, "@data xml")]
+    fn test_stream_declaration(#[case] sql_db_type: SqlDbType, #[case] expected: &str) {
+        let mut parameter = SqlParameter::with_stream("data", sql_db_type, &b"abc"[..]);
+        parameter.set_size(10);
+        assert_eq!(expected, parameter.declaration().unwrap());
+    }
+
+    #[test]
+    fn test_stream_unsupported_type() {
+        let parameter = SqlParameter::with_stream("data", SqlDbType::Int, &b"abc"[..]);
+        assert!(matches!(
+            parameter.declaration(),
+            Err(SqlClientError::NotSupported(_))
+        ));
+    }
+
+    #[rstest::rstest]
+    #[case("IdList", "", "IdList")]
+    #[case("dbo.IdList", "dbo", "IdList")]
+    #[case("[my schema].[Id.List]", "my schema", "Id.List")]
+    #[case("[a]]b].c", "a]b", "c")]
+    fn test_type_name_parts(#[case] type_name: &str, #[case] schema: &str, #[case] name: &str) {
+        let parameter = SqlParameter::with_table("ids", type_name, DataTable::default());
+        assert_eq!(
+            (schema.to_string(), name.to_string()),
+            parameter.type_name_parts().unwrap()
+        );
+        assert_eq!(
+            format!("@ids {} readonly", type_name),
+            parameter.declaration().unwrap()
+        );
+    }
+
+    #[rstest::rstest]
+    #[case("")]
+    #[case("db.dbo.IdList")]
+    #[case("dbo.")]
+    #[case("[dbo.IdList")]
+    fn test_invalid_type_name(#[case] type_name: &str) {
+        let parameter = SqlParameter::with_table("ids", type_name, DataTable::default());
+        assert!(matches!(
+            parameter.type_name_parts(),
+            Err(SqlClientError::ArgumentOutOfRange(_, _))
+        ));
+    }
+
+    #[test]
+    fn test_table_required() {
+        let parameter = SqlParameter::new("ids", SqlDbType::Structured);
+        assert!(matches!(
+            parameter.declaration(),
+            Err(SqlClientError::ArgumentNull(_, _))
+        ));
+        let mut parameter = SqlParameter::with_table("ids", "IdList", DataTable::default());
+        parameter.set_direction(ParameterDirection::Output);
+        assert!(matches!(
+            parameter.type_info(None),
+            Err(SqlClientError::NotSupported(_))
+        ));
+    }
+
+    #[test]
+    fn test_output_declaration() {
+        let mut parameter = SqlParameter::new("@count", SqlDbType::Int);
+        parameter.set_direction(ParameterDirection::Output);
+        assert_eq!("@count int output", parameter.declaration().unwrap());
+    }
+
+    #[rstest::rstest]
+    #[case("@id", true)]
+    #[case("ID", true)]
+    #[case("@idx", false)]
+    fn test_has_name(#[case] name: &str, #[case] expected: bool) {
+        assert_eq!(expected, SqlParameter::with_value("id", 1).has_name(name));
+    }
+}
@@ -0,0 +1,127 @@
+use crate::{SqlParameter, ToSql};
+use std::ops::{Index, IndexMut};
+
+/// The parameters of a [crate::SqlCommand].
+#[derive(PartialEq, Debug, Clone, Default)]
+pub struct SqlParameterCollection {
+    /// The parameters, in the order they were added.
+    parameters: Vec<SqlParameter>,
+}
+
+impl SqlParameterCollection {
+    /// Creates an empty collection.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Adds a parameter, returning a reference to it so that it can be configured further.
+    pub fn add(&mut self, parameter: SqlParameter) -> &mut SqlParameter {
+        self.parameters.push(parameter);
+        self.parameters.last_mut().unwrap()
+    }
+
+    /// Adds a parameter with a value, inferring its type from the value.
+    pub fn add_with_value(&mut self, parameter_name: &str, value: impl ToSql) -> &mut SqlParameter {
+        self.add(SqlParameter::with_value(parameter_name, value))
+    }
+
+    /// The number of parameters.
+    pub fn len(&self) -> usize {
+        self.parameters.len()
+    }
+
+    /// Whether there are no parameters.
+    pub fn is_empty(&self) -> bool {
+        self.parameters.is_empty()
+    }
+
+    /// Removes all the parameters.
+    pub fn clear(&mut self) {
+        self.parameters.clear();
+    }
+
+    /// The index of the parameter with the given name (ignoring case and the leading "@").
+    pub fn index_of(&self, parameter_name: &str) -> Option<usize> {
+        self.parameters
+            .iter()
+            .position(|parameter| parameter.has_name(parameter_name))
+    }
+
+    /// Gets the parameter with the given name.
+    pub fn get(&self, parameter_name: &str) -> Option<&SqlParameter> {
+        self.index_of(parameter_name).map(|i| &self.parameters[i])
+    }
+
+    /// Gets the parameter with the given name for modification.
+    pub fn get_mut(&mut self, parameter_name: &str) -> Option<&mut SqlParameter> {
+        self.index_of(parameter_name)
+            .map(move |i| &mut self.parameters[i])
+    }
+
+    /// Iterates over the parameters.
+    pub fn iter(&self) -> std::slice::Iter<'_, SqlParameter> {
+        self.parameters.iter()
+    }
+
+    /// Iterates over the parameters for modification.
+    pub fn iter_mut(&mut self) -> std::slice::IterMut<'_, SqlParameter> {
+        self.parameters.iter_mut()
+    }
+}
+
+impl Index<usize> for SqlParameterCollection {
+    type Output = SqlParameter;
+    fn index(&self, index: usize) -> &Self::Output {
+        &self.parameters[index]
+    }
+}
+
+impl IndexMut<usize> for SqlParameterCollection {
+    fn index_mut(&mut self, index: usize) -> &mut Self::Output {
+        &mut self.parameters[index]
+    }
+}
+
+/// Gets a parameter by name.  Panics if there's no parameter with the name, as .NET throws.
+impl Index<&str> for SqlParameterCollection {
+    type Output = SqlParameter;
+    fn index(&self, parameter_name: &str) -> &Self::Output {
+        self.get(parameter_name)
+            .unwrap_or_else(|| panic!("The parameter '{}' does not exist", parameter_name))
+    }
+}
+
+impl IndexMut<&str> for SqlParameterCollection {
+    fn index_mut(&mut self, parameter_name: &str) -> &mut Self::Output {
+        self.get_mut(parameter_name)
+            .unwrap_or_else(|| panic!("The parameter '{}' does not exist", parameter_name))
+    }
+}
+
+impl<'a> IntoIterator for &'a SqlParameterCollection {
+    type Item = &'a SqlParameter;
+    type IntoIter = std::slice::Iter<'a, SqlParameter>;
+    fn into_iter(self) -> Self::IntoIter {
+        self.parameters.iter()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{ParameterDirection, SqlDbType, SqlValue};
+
+    #[test]
+    fn test_add_and_get() {
+        let mut parameters = SqlParameterCollection::new();
+        parameters.add_with_value("@id", 5);
+        parameters
+            .add(SqlParameter::new("@count", SqlDbType::Int))
+            .set_direction(ParameterDirection::Output);
+        assert_eq!(2, parameters.len());
+        assert_eq!(Some(1), parameters.index_of("COUNT"));
+        assert_eq!(&SqlValue::Int(5), parameters["id"].value());
+        assert_eq!(ParameterDirection::Output, parameters[1].direction());
+        assert!(parameters.get("@missing").is_none());
+    }
+}
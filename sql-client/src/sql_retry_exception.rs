@@ -0,0 +1,105 @@
+use crate::SqlClientError;
+use std::fmt::{Display, Formatter};
+use std::time::Duration;
+
+/// One failed attempt of a retried operation.
+#[derive(Debug, Clone)]
+pub struct SqlRetryAttempt {
+    /// Why the attempt failed.
+    error: SqlClientError,
+    /// When the attempt started, relative to the start of the first attempt.
+    started: Duration,
+    /// How long the attempt took.
+    duration: Duration,
+}
+
+impl SqlRetryAttempt {
+    /// Records a failed attempt.
+    pub(crate) fn new(error: SqlClientError, started: Duration, duration: Duration) -> Self {
+        Self {
+            error,
+            started,
+            duration,
+        }
+    }
+
+    /// Why the attempt failed.
+    pub fn error(&self) -> &SqlClientError {
+        &self.error
+    }
+
+    /// Takes the error of the attempt.
+    pub(crate) fn into_error(self) -> SqlClientError {
+        self.error
+    }
+
+    /// When the attempt started, relative to the start of the first attempt.
+    pub fn started(&self) -> Duration {
+        self.started
+    }
+
+    /// How long the attempt took.
+    pub fn duration(&self) -> Duration {
+        self.duration
+    }
+}
+
+/// The failure of an operation that was retried, with every attempt's error and timing, as
+/// raised in .NET as an AggregateException.
+#[derive(Debug, Clone)]
+pub struct SqlRetryException {
+    /// The attempts, oldest first.  There are at least two.
+    attempts: Vec<SqlRetryAttempt>,
+    /// How long the operation took, from the start of the first attempt.
+    elapsed: Duration,
+}
+
+impl SqlRetryException {
+    /// Creates an exception from the failed attempts.
+    pub(crate) fn new(attempts: Vec<SqlRetryAttempt>, elapsed: Duration) -> Self {
+        debug_assert!(!attempts.is_empty());
+        Self { attempts, elapsed }
+    }
+
+    /// The attempts, oldest first.
+    pub fn attempts(&self) -> &[SqlRetryAttempt] {
+        &self.attempts
+    }
+
+    /// How long the operation took, from the start of the first attempt.
+    pub fn elapsed(&self) -> Duration {
+        self.elapsed
+    }
+
+    /// The error of the last attempt.
+    pub fn last_error(&self) -> &SqlClientError {
+        &self.attempts[self.attempts.len() - 1].error
+    }
+
+    /// Takes the error of the last attempt.
+    pub fn into_last_error(mut self) -> SqlClientError {
+        self.attempts.pop().unwrap().into_error()
+    }
+}
+
+impl Display for SqlRetryException {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{} attempts failed in {:.3} seconds",
+            self.attempts.len(),
+            self.elapsed.as_secs_f64()
+        )?;
+        for (i, attempt) in self.attempts.iter().enumerate() {
+            write!(
+                f,
+                "\n  {} (at {:.3}s, took {:.3}s): {}",
+                i + 1,
+                attempt.started.as_secs_f64(),
+                attempt.duration.as_secs_f64(),
+                attempt.error
+            )?;
+        }
+        Ok(())
+    }
+}
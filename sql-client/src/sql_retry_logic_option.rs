@@ -0,0 +1,68 @@
+use std::fmt::{Debug, Formatter};
+use std::sync::Arc;
+use std::time::Duration;
+
+/// Decides from a command's text whether the command may be retried.
+pub(crate) type SqlRetryCondition = Arc<dyn Fn(&str) -> bool + Send + Sync>;
+
+/// The settings of a [crate::SqlRetryLogicProvider].
+#[derive(Clone)]
+pub struct SqlRetryLogicOption {
+    /// The number of attempts, including the first (between 1 and 60).
+    pub number_of_tries: u32,
+    /// The gap that the intervals between attempts are based on.
+    pub delta_time: Duration,
+    /// The shortest interval between attempts.
+    pub min_time_interval: Duration,
+    /// The longest interval between attempts.
+    pub max_time_interval: Duration,
+    /// The numbers of the errors to retry.  If not set, the errors classified by
+    /// [crate::SqlTransientErrors] are retried.
+    pub transient_errors: Option<Vec<i32>>,
+    /// Decides from a command's text whether the command may be retried (e.g. to only retry
+    /// SELECT statements).  If not set, any command may be retried.
+    pub authorized_sql_condition: Option<SqlRetryCondition>,
+}
+
+impl SqlRetryLogicOption {
+    /// The most attempts that can be made, as in .NET.
+    pub const MAX_NUMBER_OF_TRIES: u32 = 60;
+
+    /// Sets the condition that decides from a command's text whether the command may be retried.
+    pub fn with_authorized_sql_condition(
+        mut self,
+        condition: impl Fn(&str) -> bool + Send + Sync + 'static,
+    ) -> Self {
+        self.authorized_sql_condition = Some(Arc::new(condition));
+        self
+    }
+}
+
+impl Default for SqlRetryLogicOption {
+    fn default() -> Self {
+        Self {
+            number_of_tries: 3,
+            delta_time: Duration::from_secs(1),
+            min_time_interval: Duration::ZERO,
+            max_time_interval: Duration::from_secs(30),
+            transient_errors: None,
+            authorized_sql_condition: None,
+        }
+    }
+}
+
+impl Debug for SqlRetryLogicOption {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("SqlRetryLogicOption")
+            .field("number_of_tries", &self.number_of_tries)
+            .field("delta_time", &self.delta_time)
+            .field("min_time_interval", &self.min_time_interval)
+            .field("max_time_interval", &self.max_time_interval)
+            .field("transient_errors", &self.transient_errors)
+            .field(
+                "authorized_sql_condition",
+                &self.authorized_sql_condition.is_some(),
+            )
+            .finish()
+    }
+}
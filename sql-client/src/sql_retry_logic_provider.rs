@@ -0,0 +1,585 @@
+use crate::retry_enumerators::{SqlRetryInterval, SqlRetryIntervalBuilder, SqlRetryMethod};
+use crate::sql_retry_exception::SqlRetryAttempt;
+use crate::sql_retry_logic_option::SqlRetryCondition;
+use crate::sql_retrying_event_args::SqlRetryingEventHandler;
+use crate::{
+    SqlCircuitBreaker, SqlCircuitBreakerOption, SqlCircuitBreakerState, SqlClientError,
+    SqlRetryException, SqlRetryLogicOption, SqlRetryingEventArgs, SqlTransientErrors,
+};
+use std::collections::{BTreeSet, HashMap};
+use std::fmt::{Debug, Formatter};
+use std::future::Future;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use tokio::time::Instant;
+
+/// Retries operations that fail with transient errors, waiting between attempts.
+///
+/// A provider can be set on a [crate::SqlConnection] to retry opening it, and on a
+/// [crate::SqlCommand] to retry executing it.  Providers are created with the interval strategy
+/// they wait with, as in .NET's SqlConfigurableRetryFactory.
+#[derive(Clone)]
+pub struct SqlRetryLogicProvider(Arc<RetryLogic>);
+
+/// The settings of a provider.
+#[derive(Clone)]
+struct RetryLogic {
+    /// The number of attempts, including the first.
+    number_of_tries: u32,
+    /// How the intervals between attempts grow.
+    method: SqlRetryMethod,
+    /// Builds the intervals between attempts.
+    intervals: SqlRetryIntervalBuilder,
+    /// The numbers of the errors to retry, or None to retry the errors that are classified as
+    /// transient.
+    transient_errors: Option<BTreeSet<i32>>,
+    /// Decides from a command's text whether the command may be retried.
+    authorized_sql_condition: Option<SqlRetryCondition>,
+    /// Called before each retry.
+    retrying_handler: Option<SqlRetryingEventHandler>,
+    /// The settings of the endpoints' circuit breakers, or None to not use circuit breakers.
+    circuit_breaker_option: Option<SqlCircuitBreakerOption>,
+    /// The endpoints' circuit breakers, shared by the provider's clones.
+    circuit_breakers: Arc<Mutex<HashMap<String, SqlCircuitBreaker>>>,
+}
+
+impl SqlRetryLogicProvider {
+    /// Creates a provider that waits exponentially longer between attempts.
+    pub fn exponential(option: &SqlRetryLogicOption) -> Result<Self, SqlClientError> {
+        Self::new(SqlRetryMethod::Exponential, option)
+    }
+
+    /// Creates a provider that waits longer by the delta time between attempts.
+    pub fn incremental(option: &SqlRetryLogicOption) -> Result<Self, SqlClientError> {
+        Self::new(SqlRetryMethod::Incremental, option)
+    }
+
+    /// Creates a provider that waits for about the delta time between attempts.
+    pub fn fixed(option: &SqlRetryLogicOption) -> Result<Self, SqlClientError> {
+        Self::new(SqlRetryMethod::Fixed, option)
+    }
+
+    /// Creates a provider that retries immediately.
+    pub fn none(option: &SqlRetryLogicOption) -> Result<Self, SqlClientError> {
+        Self::new(SqlRetryMethod::None, option)
+    }
+
+    /// Creates a provider that waits for a random time up to a ceiling that doubles after each
+    /// attempt ("full jitter"), so that clients that failed together don't retry together.
+    pub fn full_jitter(option: &SqlRetryLogicOption) -> Result<Self, SqlClientError> {
+        Self::new(SqlRetryMethod::FullJitter, option)
+    }
+
+    /// Creates a provider that waits for a random time from the delta time up to three times the
+    /// previous wait ("decorrelated jitter").
+    pub fn decorrelated_jitter(option: &SqlRetryLogicOption) -> Result<Self, SqlClientError> {
+        Self::new(SqlRetryMethod::DecorrelatedJitter, option)
+    }
+
+    /// Creates a provider that waits longer by the Fibonacci sequence times the delta time
+    /// between attempts.
+    pub fn fibonacci(option: &SqlRetryLogicOption) -> Result<Self, SqlClientError> {
+        Self::new(SqlRetryMethod::Fibonacci, option)
+    }
+
+    /// Creates a provider whose intervals grow with the given method.
+    pub fn new(
+        method: SqlRetryMethod,
+        option: &SqlRetryLogicOption,
+    ) -> Result<Self, SqlClientError> {
+        let intervals = SqlRetryIntervalBuilder::new(option.delta_time)
+            .min_time_interval(option.min_time_interval)
+            .max_time_interval(option.max_time_interval);
+        intervals.validate()?;
+        if !(1..=SqlRetryLogicOption::MAX_NUMBER_OF_TRIES).contains(&option.number_of_tries) {
+            return Err(SqlClientError::ArgumentOutOfRange(
+                "number_of_tries".to_string(),
+                format!(
+                    "number_of_tries must be between 1 and {}",
+                    SqlRetryLogicOption::MAX_NUMBER_OF_TRIES
+                ),
+            ));
+        }
+        Ok(Self(Arc::new(RetryLogic {
+            number_of_tries: option.number_of_tries,
+            method,
+            intervals,
+            transient_errors: option
+                .transient_errors
+                .as_ref()
+                .map(|numbers| numbers.iter().copied().collect()),
+            authorized_sql_condition: option.authorized_sql_condition.clone(),
+            retrying_handler: None,
+            circuit_breaker_option: None,
+            circuit_breakers: Arc::new(Mutex::new(HashMap::new())),
+        })))
+    }
+
+    /// The number of attempts, including the first.
+    pub fn number_of_tries(&self) -> u32 {
+        self.0.number_of_tries
+    }
+
+    /// How the intervals between attempts grow.
+    pub fn method(&self) -> SqlRetryMethod {
+        self.0.method
+    }
+
+    /// Sets a callback that's called before each retry, which can cancel it.  It replaces any
+    /// previous callback.
+    pub fn on_retrying(
+        &mut self,
+        callback: impl Fn(&mut SqlRetryingEventArgs) + Send + Sync + 'static,
+    ) {
+        Arc::make_mut(&mut self.0).retrying_handler = Some(Arc::new(callback));
+    }
+
+    /// Sets up a circuit breaker for each endpoint (i.e. data source) that connections are opened
+    /// to, or None to not use circuit breakers.  It replaces any previous circuit breakers.
+    pub fn set_circuit_breaker(
+        &mut self,
+        option: Option<SqlCircuitBreakerOption>,
+    ) -> Result<(), SqlClientError> {
+        // Make sure the settings are valid.
+        if let Some(option) = &option {
+            SqlCircuitBreaker::new("", option)?;
+        }
+        let retry_logic = Arc::make_mut(&mut self.0);
+        retry_logic.circuit_breaker_option = option;
+        retry_logic.circuit_breakers = Arc::new(Mutex::new(HashMap::new()));
+        Ok(())
+    }
+
+    /// The circuit breaker of an endpoint, or None if the provider doesn't use circuit breakers.
+    pub fn circuit_breaker(&self, endpoint: &str) -> Option<SqlCircuitBreaker> {
+        let option = self.0.circuit_breaker_option.as_ref()?;
+        let mut circuit_breakers = self.0.circuit_breakers.lock().unwrap();
+        if let Some(circuit_breaker) = circuit_breakers.get(endpoint) {
+            return Some(circuit_breaker.clone());
+        }
+        // The settings were validated when they were set.
+        let circuit_breaker = SqlCircuitBreaker::new(endpoint, option).ok()?;
+        circuit_breakers.insert(endpoint.to_string(), circuit_breaker.clone());
+        Some(circuit_breaker)
+    }
+
+    /// The circuit breakers of the endpoints that have been reached, e.g. for health checks.
+    pub fn circuit_breakers(&self) -> Vec<SqlCircuitBreaker> {
+        self.0
+            .circuit_breakers
+            .lock()
+            .unwrap()
+            .values()
+            .cloned()
+            .collect()
+    }
+
+    /// Whether a failure should be retried.
+    pub fn is_transient(&self, error: &SqlClientError) -> bool {
+        match &self.0.transient_errors {
+            Some(numbers) => error.is_transient_with(&|number| numbers.contains(&number)),
+            None => error.is_transient(),
+        }
+    }
+
+    /// Runs an operation, retrying it while it fails with transient errors.
+    pub async fn execute<T, F, Fut>(&self, mut operation: F) -> Result<T, SqlClientError>
+    where
+        F: FnMut() -> Fut,
+        Fut: Future<Output = Result<T, SqlClientError>>,
+    {
+        let mut retry = RetryState::new(Some(self), None);
+        loop {
+            match operation().await {
+                Err(error) => retry.retry(error).await?,
+                result => return result,
+            }
+        }
+    }
+}
+
+impl PartialEq for SqlRetryLogicProvider {
+    /// Providers are only equal to themselves (or their clones).
+    fn eq(&self, other: &Self) -> bool {
+        Arc::ptr_eq(&self.0, &other.0)
+    }
+}
+
+impl Debug for SqlRetryLogicProvider {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("SqlRetryLogicProvider")
+            .field("number_of_tries", &self.0.number_of_tries)
+            .field("method", &self.0.method)
+            .field("transient_errors", &self.0.transient_errors)
+            .field("circuit_breaker_option", &self.0.circuit_breaker_option)
+            .finish()
+    }
+}
+
+/// The progress of an operation through its attempts.
+pub(crate) struct RetryState {
+    /// The provider, or None if the operation isn't retried.
+    provider: Option<SqlRetryLogicProvider>,
+    /// The intervals still to wait.
+    intervals: Option<Box<dyn SqlRetryInterval + Send + Sync>>,
+    /// How long all the attempts may take in total, or None for no limit.
+    budget: Option<Duration>,
+    /// When the first attempt started.
+    started: Instant,
+    /// When the current attempt started.
+    attempt_started: Instant,
+    /// The failed attempts so far.
+    attempts: Vec<SqlRetryAttempt>,
+    /// The circuit breaker of the endpoint the operation reaches, if any.
+    circuit_breaker: Option<SqlCircuitBreaker>,
+}
+
+impl RetryState {
+    /// Starts an operation, such as executing the command with the given text, that's retried by
+    /// the given provider (if any).
+    pub fn new(provider: Option<&SqlRetryLogicProvider>, command_text: Option<&str>) -> Self {
+        // Only retry the commands that the provider's condition allows.
+        let provider = provider.filter(|provider| {
+            match (&provider.0.authorized_sql_condition, command_text) {
+                (Some(condition), Some(command_text)) => condition(command_text),
+                _ => true,
+            }
+        });
+        let now = Instant::now();
+        Self {
+            // The intervals were validated when the provider was created.
+            intervals: provider
+                .and_then(|provider| provider.0.intervals.build(provider.0.method).ok()),
+            provider: provider.cloned(),
+            budget: None,
+            started: now,
+            attempt_started: now,
+            attempts: Vec::new(),
+            circuit_breaker: None,
+        }
+    }
+
+    /// Guards the attempts with the provider's circuit breaker for the given endpoint, if it
+    /// uses circuit breakers.
+    pub fn with_endpoint(mut self, endpoint: &str) -> Self {
+        self.circuit_breaker = self
+            .provider
+            .as_ref()
+            .and_then(|provider| provider.circuit_breaker(endpoint));
+        self
+    }
+
+    /// Checks that an attempt may be made, failing fast if the endpoint's circuit is open.
+    pub fn check(&self) -> Result<(), SqlClientError> {
+        match &self.circuit_breaker {
+            Some(circuit_breaker) => circuit_breaker.check(),
+            None => Ok(()),
+        }
+    }
+
+    /// Records that an attempt succeeded.
+    pub fn succeeded(&self) {
+        if let Some(circuit_breaker) = &self.circuit_breaker {
+            circuit_breaker.record_success();
+        }
+    }
+
+    /// Limits how long all the attempts may take in total (zero for no limit).
+    pub fn with_budget(mut self, budget: Duration) -> Self {
+        self.budget = (!budget.is_zero()).then_some(budget);
+        self
+    }
+
+    /// How much of the budget is left, or None if there's no limit.
+    pub fn remaining(&self) -> Option<Duration> {
+        self.budget
+            .map(|budget| budget.saturating_sub(self.started.elapsed()))
+    }
+
+    /// Handles a failed attempt.  If it should be retried, waits until the next attempt is due.
+    /// Otherwise returns the attempt's error, or a [SqlRetryException] with the errors of every
+    /// attempt if there were retries.
+    pub async fn retry(&mut self, error: SqlClientError) -> Result<(), SqlClientError> {
+        let now = Instant::now();
+        let transient = self
+            .provider
+            .as_ref()
+            .is_some_and(|provider| provider.is_transient(&error));
+        // Let the circuit breaker know how the attempt went, unless it didn't let it through.
+        if let Some(circuit_breaker) = &self.circuit_breaker {
+            match (&error, transient) {
+                (SqlClientError::CircuitOpen(_, _), _) => {}
+                (_, true) => circuit_breaker.record_failure(),
+                (_, false) => circuit_breaker.record_success(),
+            }
+        }
+        self.record(error, now);
+        let (Some(provider), Some(intervals)) = (&self.provider, &mut self.intervals) else {
+            return Err(self.failure());
+        };
+        let retry_count = self.attempts.len() as u32;
+        if retry_count >= provider.0.number_of_tries || !transient {
+            return Err(self.failure());
+        }
+        let Some(delay) = intervals.next() else {
+            return Err(self.failure());
+        };
+        // Stop hammering the endpoint once its circuit opens.
+        if self
+            .circuit_breaker
+            .as_ref()
+            .is_some_and(|circuit_breaker| {
+                circuit_breaker.state() != SqlCircuitBreakerState::Closed
+            })
+        {
+            log::debug!("retry {} stopped by the circuit breaker", retry_count);
+            return Err(self.failure());
+        }
+        // Don't start an attempt that would begin after the budget runs out.
+        if self.remaining().is_some_and(|remaining| delay >= remaining) {
+            log::debug!("retry {} would exceed the budget", retry_count);
+            return Err(self.failure());
+        }
+        log::debug!("retry {} in {:?}", retry_count, delay);
+        if let Some(handler) = &provider.0.retrying_handler {
+            let mut args = SqlRetryingEventArgs::new(retry_count, delay, &self.attempts);
+            handler(&mut args);
+            if args.cancel() {
+                return Err(self.failure());
+            }
+        }
+        tokio::time::sleep(delay).await;
+        self.attempt_started = Instant::now();
+        Ok(())
+    }
+
+    /// Handles a failed attempt that can't be retried whatever the error (e.g. because the
+    /// connection it used is broken), returning the error the operation failed with.
+    pub fn stop(&mut self, error: SqlClientError) -> SqlClientError {
+        self.record(error, Instant::now());
+        self.failure()
+    }
+
+    /// Records a failed attempt that ended at the given time.
+    fn record(&mut self, error: SqlClientError, now: Instant) {
+        self.attempts.push(SqlRetryAttempt::new(
+            error,
+            self.attempt_started - self.started,
+            now - self.attempt_started,
+        ));
+    }
+
+    /// The error that the operation failed with: the only attempt's error, or all the attempts'
+    /// errors if there were retries.
+    fn failure(&mut self) -> SqlClientError {
+        let attempts = std::mem::take(&mut self.attempts);
+        if attempts.len() == 1 {
+            return attempts.into_iter().next().unwrap().into_error();
+        }
+        SqlClientError::Retry(SqlRetryException::new(attempts, self.started.elapsed()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{SqlError, SqlException};
+    use std::io;
+    use std::sync::Mutex;
+    use uuid::Uuid;
+
+    /// A server error with the given number.
+    fn server_error(number: i32) -> SqlClientError {
+        SqlClientError::Server(SqlException::new(
+            vec![SqlError::new(number, 1, 16, "Failed", "test", "", 1)],
+            Uuid::nil(),
+        ))
+    }
+
+    /// Options that retry within a millisecond.
+    fn option(number_of_tries: u32) -> SqlRetryLogicOption {
+        SqlRetryLogicOption {
+            number_of_tries,
+            delta_time: Duration::ZERO,
+            max_time_interval: Duration::from_millis(1),
+            ..Default::default()
+        }
+    }
+
+    #[rstest::rstest]
+    #[case::succeeds_on_retry(3, vec![1205, 0], Ok(2))]
+    #[case::runs_out_of_tries(2, vec![1205, 1205, 0], Err(1205))]
+    #[case::not_transient(3, vec![208, 0], Err(208))]
+    #[tokio::test]
+    async fn test_execute(
+        #[case] number_of_tries: u32,
+        #[case] results: Vec<i32>,
+        #[case] expected: Result<usize, i32>,
+    ) {
+        let provider = SqlRetryLogicProvider::exponential(&option(number_of_tries)).unwrap();
+        let mut attempts = 0;
+        let result = provider
+            .execute(|| {
+                attempts += 1;
+                let result = match results[attempts - 1] {
+                    0 => Ok(attempts),
+                    number => Err(server_error(number)),
+                };
+                async move { result }
+            })
+            .await;
+        let actual = result.map_err(|error| match error {
+            SqlClientError::Server(exception) => exception.number(),
+            // Every attempt is reported once there were retries.
+            SqlClientError::Retry(exception) => {
+                assert_eq!(number_of_tries as usize, exception.attempts().len());
+                match exception.into_last_error() {
+                    SqlClientError::Server(exception) => exception.number(),
+                    error => panic!("unexpected error {:?}", error),
+                }
+            }
+            error => panic!("unexpected error {:?}", error),
+        });
+        assert_eq!(expected, actual);
+    }
+
+    #[tokio::test]
+    async fn test_on_retrying() {
+        let mut provider = SqlRetryLogicProvider::fixed(&option(5)).unwrap();
+        let retries = Arc::new(Mutex::new(Vec::new()));
+        let log = retries.clone();
+        provider.on_retrying(move |args| {
+            log.lock()
+                .unwrap()
+                .push((args.retry_count(), args.attempts().len()));
+            // Give up after the second retry.
+            args.set_cancel(args.retry_count() == 2);
+        });
+        let result: Result<(), SqlClientError> = provider
+            .execute(|| async {
+                Err(SqlClientError::Io(
+                    io::ErrorKind::ConnectionReset,
+                    "reset".to_string(),
+                ))
+            })
+            .await;
+        match result {
+            Err(SqlClientError::Retry(exception)) => {
+                assert_eq!(2, exception.attempts().len());
+                assert!(matches!(exception.last_error(), SqlClientError::Io(_, _)));
+            }
+            result => panic!("unexpected result {:?}", result),
+        }
+        assert_eq!(vec![(1, 1), (2, 2)], *retries.lock().unwrap());
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_budget() {
+        let provider = SqlRetryLogicProvider::fixed(&SqlRetryLogicOption {
+            number_of_tries: 10,
+            delta_time: Duration::from_millis(400),
+            ..Default::default()
+        })
+        .unwrap();
+        let mut retry = RetryState::new(Some(&provider), None).with_budget(Duration::from_secs(1));
+        let error = loop {
+            if let Err(error) = retry
+                .retry(SqlClientError::Io(
+                    io::ErrorKind::ConnectionReset,
+                    "reset".to_string(),
+                ))
+                .await
+            {
+                break error;
+            }
+        };
+        // Waits of 320 to 480ms leave room for two or three attempts.
+        let SqlClientError::Retry(exception) = error else {
+            panic!("unexpected error {:?}", error);
+        };
+        assert!((2..=3).contains(&exception.attempts().len()));
+        assert!(exception.elapsed() < Duration::from_secs(1));
+        for (previous, attempt) in exception.attempts().iter().zip(&exception.attempts()[1..]) {
+            assert!(attempt.started() > previous.started());
+        }
+        assert!(exception
+            .to_string()
+            .starts_with(&format!("{} attempts failed", exception.attempts().len())));
+    }
+
+    #[rstest::rstest]
+    #[case(Some(vec![50000]), 50000, true)]
+    #[case(Some(vec![50000]), 1205, false)]
+    #[case(None, 1205, true)]
+    #[case(None, 50000, false)]
+    fn test_is_transient(
+        #[case] transient_errors: Option<Vec<i32>>,
+        #[case] number: i32,
+        #[case] expected: bool,
+    ) {
+        let option = SqlRetryLogicOption {
+            transient_errors,
+            ..option(2)
+        };
+        let provider = SqlRetryLogicProvider::incremental(&option).unwrap();
+        assert_eq!(expected, provider.is_transient(&server_error(number)));
+    }
+
+    #[rstest::rstest]
+    #[case(Some(vec![50000]), false)]
+    #[case(Some(vec![SqlTransientErrors::TIMEOUT]), true)]
+    #[case(None, true)]
+    fn test_timeouts_and_network_failures(
+        #[case] transient_errors: Option<Vec<i32>>,
+        #[case] timeout_expected: bool,
+    ) {
+        let option = SqlRetryLogicOption {
+            transient_errors,
+            ..option(2)
+        };
+        let provider = SqlRetryLogicProvider::incremental(&option).unwrap();
+        let timeout = SqlClientError::Timeout("timed out".to_string());
+        assert_eq!(timeout_expected, provider.is_transient(&timeout));
+        // Failures to reach the server are always transient.
+        assert!(provider.is_transient(&SqlClientError::Io(
+            io::ErrorKind::ConnectionReset,
+            "reset".to_string()
+        )));
+    }
+
+    #[rstest::rstest]
+    #[case::refused(io::ErrorKind::ConnectionRefused, true)]
+    #[case::reset(io::ErrorKind::ConnectionReset, true)]
+    #[case::aborted(io::ErrorKind::ConnectionAborted, true)]
+    #[case::timed_out(io::ErrorKind::TimedOut, true)]
+    #[case::broken_pipe(io::ErrorKind::BrokenPipe, true)]
+    #[case::tls(io::ErrorKind::InvalidData, false)]
+    #[case::end_of_stream(io::ErrorKind::UnexpectedEof, false)]
+    fn test_io_errors(#[case] kind: io::ErrorKind, #[case] expected: bool) {
+        let provider = SqlRetryLogicProvider::incremental(&option(2)).unwrap();
+        let error = SqlClientError::Io(kind, "failed".to_string());
+        assert_eq!(expected, provider.is_transient(&error));
+    }
+
+    #[tokio::test]
+    async fn test_authorized_sql_condition() {
+        let option = option(3).with_authorized_sql_condition(|text| text.starts_with("select"));
+        let provider = SqlRetryLogicProvider::none(&option).unwrap();
+        let mut retry = RetryState::new(Some(&provider), Some("update t set a = 1"));
+        assert!(retry.retry(server_error(1205)).await.is_err());
+        let mut retry = RetryState::new(Some(&provider), Some("select 1"));
+        assert!(retry.retry(server_error(1205)).await.is_ok());
+    }
+
+    #[test]
+    fn test_invalid_options() {
+        assert!(matches!(
+            SqlRetryLogicProvider::none(&option(0)),
+            Err(SqlClientError::ArgumentOutOfRange(_, _))
+        ));
+        assert!(matches!(
+            SqlRetryLogicProvider::none(&option(61)),
+            Err(SqlClientError::ArgumentOutOfRange(_, _))
+        ));
+    }
+}
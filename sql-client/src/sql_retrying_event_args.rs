@@ -0,0 +1,55 @@
+use crate::sql_retry_exception::SqlRetryAttempt;
+use std::sync::Arc;
+use std::time::Duration;
+
+/// Handles the retries of a retry logic provider.
+pub(crate) type SqlRetryingEventHandler = Arc<dyn Fn(&mut SqlRetryingEventArgs) + Send + Sync>;
+
+/// A retry that's about to be made, passed to [crate::SqlRetryLogicProvider::on_retrying].
+#[derive(Debug)]
+pub struct SqlRetryingEventArgs<'a> {
+    /// The number of the retry (1 for the first retry).
+    retry_count: u32,
+    /// How long until the retry is made.
+    delay: Duration,
+    /// The failed attempts so far, oldest first.
+    attempts: &'a [SqlRetryAttempt],
+    /// Whether the retry should be cancelled.
+    cancel: bool,
+}
+
+impl<'a> SqlRetryingEventArgs<'a> {
+    /// Creates the arguments for a retry.
+    pub(crate) fn new(retry_count: u32, delay: Duration, attempts: &'a [SqlRetryAttempt]) -> Self {
+        Self {
+            retry_count,
+            delay,
+            attempts,
+            cancel: false,
+        }
+    }
+
+    /// The number of the retry (1 for the first retry).
+    pub fn retry_count(&self) -> u32 {
+        self.retry_count
+    }
+
+    /// How long until the retry is made.
+    pub fn delay(&self) -> Duration {
+        self.delay
+    }
+
+    /// The failed attempts so far, oldest first, with their errors and timing.
+    pub fn attempts(&self) -> &[SqlRetryAttempt] {
+        self.attempts
+    }
+
+    /// Whether the retry will be cancelled, failing with the attempts' errors.
+    pub fn cancel(&self) -> bool {
+        self.cancel
+    }
+    /// Sets whether the retry will be cancelled, failing with the attempts' errors.
+    pub fn set_cancel(&mut self, value: bool) {
+        self.cancel = value;
+    }
+}
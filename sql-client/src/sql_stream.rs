@@ -0,0 +1,232 @@
+use crate::tds_enums::TdsEnums;
+use crate::tds_parser_state_object::TdsParserStateObject;
+use crate::tds_value::skip_plp_chunks;
+use crate::SqlClientError;
+use std::borrow::Cow;
+use std::future::Future;
+use std::io;
+use std::pin::Pin;
+use std::task::{Context, Poll};
+use tokio::io::{AsyncRead, ReadBuf};
+
+/// How far through the chunks of a PLP value a stream has read.
+#[derive(Debug, Default)]
+pub(crate) struct PlpState {
+    /// The number of bytes of the current chunk that haven't been read.
+    chunk_remaining: u32,
+    /// Whether the chunk terminator has been read.
+    finished: bool,
+}
+
+impl PlpState {
+    /// Reads up to `max_length` bytes of the value.  Returns an empty buffer at the end.
+    async fn read(
+        &mut self,
+        state: &mut TdsParserStateObject,
+        max_length: usize,
+    ) -> Result<Vec<u8>, SqlClientError> {
+        if self.finished {
+            return Ok(Vec::new());
+        }
+        // Start the next chunk if needed
+        if self.chunk_remaining == 0 {
+            self.chunk_remaining = state.read_u32().await?;
+            if self.chunk_remaining == TdsEnums::SQL_PLP_CHUNK_TERMINATOR {
+                self.finished = true;
+                return Ok(Vec::new());
+            }
+        }
+        let length = (self.chunk_remaining as usize).min(max_length);
+        let bytes = state.read_bytes(length).await?;
+        self.chunk_remaining -= length as u32;
+        Ok(bytes)
+    }
+
+    /// Skips whatever is left of the value.
+    pub async fn skip(&mut self, state: &mut TdsParserStateObject) -> Result<(), SqlClientError> {
+        if !self.finished {
+            state.skip(self.chunk_remaining as usize).await?;
+            skip_plp_chunks(state).await?;
+            self.chunk_remaining = 0;
+            self.finished = true;
+        }
+        Ok(())
+    }
+}
+
+/// The result of reading the next piece of a PLP value, which hands back what it borrowed.
+type PlpRead<'a> = (
+    &'a mut TdsParserStateObject,
+    &'a mut PlpState,
+    Result<Vec<u8>, SqlClientError>,
+);
+
+/// Where a stream's bytes come from.
+enum StreamSource<'a> {
+    /// A value that's already in memory.
+    Memory,
+    /// A PLP value being read from the connection.  The connection is held by the pending read, if
+    /// there is one.
+    Plp {
+        /// The connection and progress through the value, when no read is pending.
+        idle: Option<(&'a mut TdsParserStateObject, &'a mut PlpState)>,
+        /// The pending read of the next piece.
+        pending: Option<Pin<Box<dyn Future<Output = PlpRead<'a>> + Send + 'a>>>,
+    },
+}
+
+/// A stream over the bytes of a binary column, returned by [crate::SqlDataReader::get_stream].
+///
+/// With [crate::CommandBehavior::SEQUENTIAL_ACCESS], large values are read from the connection a
+/// chunk at a time as the stream is read, so they're never held in memory.  A null value is an
+/// empty stream.
+pub struct SqlStream<'a> {
+    /// Where the bytes come from.
+    source: StreamSource<'a>,
+    /// Bytes that have been read but not yet returned.
+    buffer: Cow<'a, [u8]>,
+    /// The position of the next byte to return in the buffer.
+    position: usize,
+}
+
+impl<'a> SqlStream<'a> {
+    /// Creates a stream over a value in memory.
+    pub(crate) fn from_bytes(bytes: &'a [u8]) -> Self {
+        Self {
+            source: StreamSource::Memory,
+            buffer: Cow::Borrowed(bytes),
+            position: 0,
+        }
+    }
+
+    /// Creates a stream over a PLP value whose length has been read.
+    pub(crate) fn from_plp(state: &'a mut TdsParserStateObject, plp: &'a mut PlpState) -> Self {
+        Self {
+            source: StreamSource::Plp {
+                idle: Some((state, plp)),
+                pending: None,
+            },
+            buffer: Cow::Owned(Vec::new()),
+            position: 0,
+        }
+    }
+}
+
+impl<'a> AsyncRead for SqlStream<'a> {
+    fn poll_read(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &mut ReadBuf<'_>,
+    ) -> Poll<io::Result<()>> {
+        let this = &mut *self;
+        loop {
+            // Return what we have
+            if this.position < this.buffer.len() {
+                let count = buf.remaining().min(this.buffer.len() - this.position);
+                buf.put_slice(&this.buffer[this.position..this.position + count]);
+                this.position += count;
+                return Poll::Ready(Ok(()));
+            }
+            let (idle, pending) = match &mut this.source {
+                // That was everything
+                StreamSource::Memory => return Poll::Ready(Ok(())),
+                StreamSource::Plp { idle, pending } => (idle, pending),
+            };
+            // Start reading the next piece
+            if pending.is_none() {
+                let (state, plp) = idle.take().expect("stream has no connection");
+                if plp.finished {
+                    *idle = Some((state, plp));
+                    return Poll::Ready(Ok(()));
+                }
+                let max_length = buf.remaining().max(state.packet_size());
+                *pending = Some(Box::pin(async move {
+                    let result = plp.read(state, max_length).await;
+                    (state, plp, result)
+                }));
+            }
+            // Wait for it
+            let (state, plp, result) = match pending.as_mut().unwrap().as_mut().poll(cx) {
+                Poll::Pending => return Poll::Pending,
+                Poll::Ready(read) => read,
+            };
+            *pending = None;
+            *idle = Some((state, plp));
+            match result {
+                Ok(bytes) => {
+                    this.buffer = Cow::Owned(bytes);
+                    this.position = 0;
+                }
+                Err(e) => return Poll::Ready(Err(io::Error::other(e))),
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tds_test_utils::split_into_packets;
+    use test_utils::MockStream;
+    use tokio::io::AsyncReadExt;
+
+    /// The chunks of a PLP value (after its total length) followed by a trailing byte.
+    fn chunks(chunks: &[&[u8]]) -> Vec<u8> {
+        let mut bytes = Vec::new();
+        for chunk in chunks {
+            bytes.extend_from_slice(&(chunk.len() as u32).to_le_bytes());
+            bytes.extend_from_slice(chunk);
+        }
+        bytes.extend_from_slice(&TdsEnums::SQL_PLP_CHUNK_TERMINATOR.to_le_bytes());
+        bytes.push(0xEE);
+        bytes
+    }
+
+    #[tokio::test]
+    async fn test_read_plp() {
+        // Chunks that span packets
+        let data = chunks(&[b"hello ", b"large ", b"world"]);
+        let stream = MockStream::new(split_into_packets(&data, 7));
+        let mut state = TdsParserStateObject::new(Box::new(stream), 4096);
+        let mut plp = PlpState::default();
+        let mut stream = SqlStream::from_plp(&mut state, &mut plp);
+        // Read a few bytes at a time
+        let mut bytes = Vec::new();
+        let mut buffer = [0u8; 4];
+        loop {
+            let count = stream.read(&mut buffer).await.unwrap();
+            if count == 0 {
+                break;
+            }
+            bytes.extend_from_slice(&buffer[..count]);
+        }
+        assert_eq!(b"hello large world".to_vec(), bytes);
+        // There's nothing left to skip
+        drop(stream);
+        plp.skip(&mut state).await.unwrap();
+        assert_eq!(0xEE, state.read_u8().await.unwrap());
+    }
+
+    #[tokio::test]
+    async fn test_skip_partly_read() {
+        let data = chunks(&[b"hello ", b"world"]);
+        let stream = MockStream::new(split_into_packets(&data, 5));
+        let mut state = TdsParserStateObject::new(Box::new(stream), 4096);
+        let mut plp = PlpState::default();
+        let mut stream = SqlStream::from_plp(&mut state, &mut plp);
+        let mut buffer = [0u8; 3];
+        stream.read_exact(&mut buffer).await.unwrap();
+        assert_eq!(b"hel", &buffer);
+        drop(stream);
+        plp.skip(&mut state).await.unwrap();
+        assert_eq!(0xEE, state.read_u8().await.unwrap());
+    }
+
+    #[tokio::test]
+    async fn test_from_bytes() {
+        let mut stream = SqlStream::from_bytes(&[1, 2, 3]);
+        let mut bytes = Vec::new();
+        stream.read_to_end(&mut bytes).await.unwrap();
+        assert_eq!(vec![1, 2, 3], bytes);
+    }
+}
@@ -0,0 +1,111 @@
+use crate::sql_collation::TextDecoder;
+use crate::SqlStream;
+use std::io;
+use std::pin::Pin;
+use std::task::{Context, Poll};
+use tokio::io::{AsyncRead, ReadBuf};
+
+/// A reader over the text of a character or xml column, returned by
+/// [crate::SqlDataReader::get_text_reader].
+///
+/// The text is read as UTF-8, whatever its encoding on the server, so it can be used with
+/// [tokio::io::AsyncReadExt::read_to_string] or [tokio::io::AsyncBufReadExt::lines].  With
+/// [crate::CommandBehavior::SEQUENTIAL_ACCESS], large values are decoded a chunk at a time as they're
+/// read, so they're never held in memory.  A null value is empty.
+pub struct SqlTextReader<'a> {
+    /// The bytes of the value.
+    stream: SqlStream<'a>,
+    /// The decoder of the value's encoding, or None if the stream is already UTF-8.
+    decoder: Option<TextDecoder>,
+    /// Text that has been decoded but not yet returned.
+    text: String,
+    /// The position of the next byte to return in the text.
+    position: usize,
+    /// Whether the end of the value has been reached.
+    finished: bool,
+}
+
+impl<'a> SqlTextReader<'a> {
+    /// The number of bytes read from the value at a time.
+    const READ_SIZE: usize = 8192;
+
+    /// Creates a reader over text that's already in memory.
+    pub(crate) fn from_text(text: &'a str) -> Self {
+        Self::new(SqlStream::from_bytes(text.as_bytes()), None)
+    }
+
+    /// Creates a reader that decodes a stream of bytes.
+    pub(crate) fn new(stream: SqlStream<'a>, decoder: Option<TextDecoder>) -> Self {
+        Self {
+            stream,
+            decoder,
+            text: String::new(),
+            position: 0,
+            finished: false,
+        }
+    }
+}
+
+impl<'a> AsyncRead for SqlTextReader<'a> {
+    fn poll_read(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &mut ReadBuf<'_>,
+    ) -> Poll<io::Result<()>> {
+        let this = &mut *self;
+        // Without a decoder, the stream is the text.
+        let decoder = match &mut this.decoder {
+            Some(decoder) => decoder,
+            None => return Pin::new(&mut this.stream).poll_read(cx, buf),
+        };
+        loop {
+            // Return what we have
+            if this.position < this.text.len() {
+                let count = buf.remaining().min(this.text.len() - this.position);
+                buf.put_slice(&this.text.as_bytes()[this.position..this.position + count]);
+                this.position += count;
+                return Poll::Ready(Ok(()));
+            }
+            if this.finished {
+                return Poll::Ready(Ok(()));
+            }
+            // Read and decode the next piece
+            let mut bytes = [0u8; Self::READ_SIZE];
+            let mut bytes = ReadBuf::new(&mut bytes);
+            match Pin::new(&mut this.stream).poll_read(cx, &mut bytes) {
+                Poll::Pending => return Poll::Pending,
+                Poll::Ready(Err(e)) => return Poll::Ready(Err(e)),
+                Poll::Ready(Ok(())) => {}
+            }
+            this.text.clear();
+            this.position = 0;
+            this.finished = bytes.filled().is_empty();
+            decoder.decode(bytes.filled(), this.finished, &mut this.text);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tds_parser_state_object::encode_utf16;
+    use tokio::io::AsyncReadExt;
+
+    #[tokio::test]
+    async fn test_decode() {
+        let bytes = encode_utf16("Grüße 😀");
+        let stream = SqlStream::from_bytes(&bytes);
+        let mut reader = SqlTextReader::new(stream, Some(TextDecoder::utf16()));
+        let mut text = String::new();
+        reader.read_to_string(&mut text).await.unwrap();
+        assert_eq!("Grüße 😀", text);
+    }
+
+    #[tokio::test]
+    async fn test_from_text() {
+        let mut reader = SqlTextReader::from_text("abc");
+        let mut text = String::new();
+        reader.read_to_string(&mut text).await.unwrap();
+        assert_eq!("abc", text);
+    }
+}
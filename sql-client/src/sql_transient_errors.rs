@@ -0,0 +1,102 @@
+use std::collections::BTreeSet;
+use std::sync::RwLock;
+
+/// The numbers that applications have added to the transient errors.
+static ADDED_TRANSIENT_ERRORS: RwLock<BTreeSet<i32>> = RwLock::new(BTreeSet::new());
+
+/// Classifies server errors as transient (likely to succeed if retried), as used by retry logic
+/// and connection pooling.
+///
+/// The classification is shared by the whole process.  It starts with the errors that Azure SQL
+/// and .NET's Microsoft.Data.SqlClient treat as transient, and applications can add their own.
+pub struct SqlTransientErrors;
+
+impl SqlTransientErrors {
+    /// The number reported for a client-side timeout, as in .NET.
+    pub const TIMEOUT: i32 = -2;
+
+    /// The errors that are always transient.
+    pub const BASE: &'static [i32] = &[
+        // The login failed because the database is unavailable
+        4060,
+        // The database is being moved or is unavailable (Azure SQL)
+        40197,
+        // The service is busy (Azure SQL)
+        40501,
+        // The database is unavailable (Azure SQL)
+        40613,
+        // The database is in an unexpected state or is being reconfigured (Azure SQL)
+        49918,
+        49919,
+        49920,
+        // Resource limits have been reached (Azure SQL)
+        10928,
+        10929,
+        // The transaction was chosen as a deadlock victim
+        1205,
+        // A network error occurred on an existing connection
+        233,
+        64,
+        // The connection was aborted, reset or timed out by the network
+        10053,
+        10054,
+        10060,
+        // A timeout
+        Self::TIMEOUT,
+    ];
+
+    /// Whether errors with the given number are transient.
+    pub fn contains(number: i32) -> bool {
+        Self::BASE.contains(&number)
+            || ADDED_TRANSIENT_ERRORS
+                .read()
+                .unwrap_or_else(|e| e.into_inner())
+                .contains(&number)
+    }
+
+    /// Adds error numbers that the application treats as transient.
+    pub fn add(numbers: impl IntoIterator<Item = i32>) {
+        ADDED_TRANSIENT_ERRORS
+            .write()
+            .unwrap_or_else(|e| e.into_inner())
+            .extend(numbers);
+    }
+
+    /// Removes error numbers that the application added.  The base errors can't be removed.
+    pub fn remove(numbers: impl IntoIterator<Item = i32>) {
+        let mut added = ADDED_TRANSIENT_ERRORS
+            .write()
+            .unwrap_or_else(|e| e.into_inner());
+        for number in numbers {
+            added.remove(&number);
+        }
+    }
+
+    /// All the transient error numbers, including those the application added.
+    pub fn all() -> Vec<i32> {
+        let added = ADDED_TRANSIENT_ERRORS
+            .read()
+            .unwrap_or_else(|e| e.into_inner());
+        let mut all: BTreeSet<i32> = Self::BASE.iter().copied().collect();
+        all.extend(added.iter());
+        all.into_iter().collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_add_and_remove() {
+        assert!(SqlTransientErrors::contains(1205));
+        // A number that no other test uses, as the list is shared
+        assert!(!SqlTransientErrors::contains(987_654));
+        SqlTransientErrors::add([987_654]);
+        assert!(SqlTransientErrors::contains(987_654));
+        assert!(SqlTransientErrors::all().contains(&987_654));
+        SqlTransientErrors::remove([987_654, 1205]);
+        assert!(!SqlTransientErrors::contains(987_654));
+        assert!(SqlTransientErrors::contains(1205));
+    }
+}
@@ -0,0 +1,250 @@
+use crate::{SqlClientError, SqlDbType, SqlDecimal, SqlMoney};
+use chrono::{DateTime, FixedOffset, NaiveDate, NaiveDateTime, NaiveTime};
+use uuid::Uuid;
+
+/// A value sent to or received from SQL Server.
+#[derive(PartialEq, Debug, Clone, Default)]
+pub enum SqlValue {
+    /// A database null.
+    #[default]
+    Null,
+    /// A bit.
+    Bit(bool),
+    /// A tinyint.
+    TinyInt(u8),
+    /// A smallint.
+    SmallInt(i16),
+    /// An int.
+    Int(i32),
+    /// A bigint.
+    BigInt(i64),
+    /// A real.
+    Real(f32),
+    /// A float.
+    Float(f64),
+    /// A decimal or numeric.
+    Decimal(SqlDecimal),
+    /// A money or smallmoney.
+    Money(SqlMoney),
+    /// A character string.
+    String(String),
+    /// Binary data.
+    Binary(Vec<u8>),
+    /// A date.
+    Date(NaiveDate),
+    /// A time of day.
+    Time(NaiveTime),
+    /// A date and time without a time zone (datetime, smalldatetime or datetime2).
+    DateTime(NaiveDateTime),
+    /// A date and time with a time zone offset.
+    DateTimeOffset(DateTime<FixedOffset>),
+    /// A uniqueidentifier.
+    Guid(Uuid),
+}
+
+impl SqlValue {
+    /// Whether the value is a database null.
+    pub fn is_null(&self) -> bool {
+        matches!(self, SqlValue::Null)
+    }
+
+    /// The length of a string or binary value in characters (two-byte characters if `unicode`) or
+    /// bytes, or 0 for other values.
+    pub(crate) fn length(&self, unicode: bool) -> usize {
+        match self {
+            SqlValue::String(value) if unicode => value.encode_utf16().count(),
+            SqlValue::String(value) => value.len(),
+            SqlValue::Binary(value) => value.len(),
+            _ => 0,
+        }
+    }
+
+    /// The SQL type used to send the value when a parameter's type has not been set explicitly.
+    pub(crate) fn sql_db_type(&self) -> SqlDbType {
+        match self {
+            // A null has no type of its own.  nvarchar is what .NET uses.
+            SqlValue::Null => SqlDbType::NVarChar,
+            SqlValue::Bit(_) => SqlDbType::Bit,
+            SqlValue::TinyInt(_) => SqlDbType::TinyInt,
+            SqlValue::SmallInt(_) => SqlDbType::SmallInt,
+            SqlValue::Int(_) => SqlDbType::Int,
+            SqlValue::BigInt(_) => SqlDbType::BigInt,
+            SqlValue::Real(_) => SqlDbType::Real,
+            SqlValue::Float(_) => SqlDbType::Float,
+            SqlValue::String(_) => SqlDbType::NVarChar,
+            SqlValue::Binary(_) => SqlDbType::VarBinary,
+            SqlValue::Decimal(_) => SqlDbType::Decimal,
+            SqlValue::Money(_) => SqlDbType::Money,
+            SqlValue::Date(_) => SqlDbType::Date,
+            SqlValue::Time(_) => SqlDbType::Time,
+            // datetime2 is used rather than datetime so that no precision is lost.
+            SqlValue::DateTime(_) => SqlDbType::DateTime2,
+            SqlValue::DateTimeOffset(_) => SqlDbType::DateTimeOffset,
+            SqlValue::Guid(_) => SqlDbType::UniqueIdentifier,
+        }
+    }
+}
+
+impl From<bool> for SqlValue {
+    fn from(value: bool) -> Self {
+        SqlValue::Bit(value)
+    }
+}
+
+impl From<u8> for SqlValue {
+    fn from(value: u8) -> Self {
+        SqlValue::TinyInt(value)
+    }
+}
+
+impl From<i16> for SqlValue {
+    fn from(value: i16) -> Self {
+        SqlValue::SmallInt(value)
+    }
+}
+
+impl From<i32> for SqlValue {
+    fn from(value: i32) -> Self {
+        SqlValue::Int(value)
+    }
+}
+
+impl From<i64> for SqlValue {
+    fn from(value: i64) -> Self {
+        SqlValue::BigInt(value)
+    }
+}
+
+impl From<f32> for SqlValue {
+    fn from(value: f32) -> Self {
+        SqlValue::Real(value)
+    }
+}
+
+impl From<f64> for SqlValue {
+    fn from(value: f64) -> Self {
+        SqlValue::Float(value)
+    }
+}
+
+impl From<SqlDecimal> for SqlValue {
+    fn from(value: SqlDecimal) -> Self {
+        SqlValue::Decimal(value)
+    }
+}
+
+impl From<SqlMoney> for SqlValue {
+    fn from(value: SqlMoney) -> Self {
+        SqlValue::Money(value)
+    }
+}
+
+#[cfg(feature = "rust_decimal")]
+impl From<rust_decimal::Decimal> for SqlValue {
+    fn from(value: rust_decimal::Decimal) -> Self {
+        SqlValue::Decimal(value.into())
+    }
+}
+
+#[cfg(feature = "bigdecimal")]
+impl TryFrom<bigdecimal::BigDecimal> for SqlValue {
+    type Error = SqlClientError;
+
+    fn try_from(value: bigdecimal::BigDecimal) -> Result<Self, Self::Error> {
+        Ok(SqlValue::Decimal(value.try_into()?))
+    }
+}
+
+impl From<&str> for SqlValue {
+    fn from(value: &str) -> Self {
+        SqlValue::String(value.to_string())
+    }
+}
+
+impl From<String> for SqlValue {
+    fn from(value: String) -> Self {
+        SqlValue::String(value)
+    }
+}
+
+impl From<&[u8]> for SqlValue {
+    fn from(value: &[u8]) -> Self {
+        SqlValue::Binary(value.to_vec())
+    }
+}
+
+impl From<Vec<u8>> for SqlValue {
+    fn from(value: Vec<u8>) -> Self {
+        SqlValue::Binary(value)
+    }
+}
+
+impl From<NaiveDate> for SqlValue {
+    fn from(value: NaiveDate) -> Self {
+        SqlValue::Date(value)
+    }
+}
+
+impl From<NaiveTime> for SqlValue {
+    fn from(value: NaiveTime) -> Self {
+        SqlValue::Time(value)
+    }
+}
+
+impl From<DateTime<FixedOffset>> for SqlValue {
+    fn from(value: DateTime<FixedOffset>) -> Self {
+        SqlValue::DateTimeOffset(value)
+    }
+}
+
+impl From<NaiveDateTime> for SqlValue {
+    fn from(value: NaiveDateTime) -> Self {
+        SqlValue::DateTime(value)
+    }
+}
+
+impl From<Uuid> for SqlValue {
+    fn from(value: Uuid) -> Self {
+        SqlValue::Guid(value)
+    }
+}
+
+impl<T: Into<SqlValue>> From<Option<T>> for SqlValue {
+    fn from(value: Option<T>) -> Self {
+        match value {
+            Some(value) => value.into(),
+            None => SqlValue::Null,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[rstest::rstest]
+    #[case(SqlValue::from(true), SqlDbType::Bit)]
+    #[case(SqlValue::from(1u8), SqlDbType::TinyInt)]
+    #[case(SqlValue::from(1i16), SqlDbType::SmallInt)]
+    #[case(SqlValue::from(1i32), SqlDbType::Int)]
+    #[case(SqlValue::from(1i64), SqlDbType::BigInt)]
+    #[case(SqlValue::from(1.0f32), SqlDbType::Real)]
+    #[case(SqlValue::from(1.0f64), SqlDbType::Float)]
+    #[case(SqlValue::from("abc"), SqlDbType::NVarChar)]
+    #[case(SqlValue::from(vec![1u8, 2u8]), SqlDbType::VarBinary)]
+    #[case(SqlValue::from(Uuid::nil()), SqlDbType::UniqueIdentifier)]
+    #[case(SqlValue::from(NaiveDateTime::default()), SqlDbType::DateTime2)]
+    #[case(SqlValue::from(NaiveDate::default()), SqlDbType::Date)]
+    #[case(SqlValue::from(NaiveTime::default()), SqlDbType::Time)]
+    #[case(SqlValue::from(SqlDecimal::from(1)), SqlDbType::Decimal)]
+    #[case(SqlValue::from(SqlMoney::from(1)), SqlDbType::Money)]
+    fn test_sql_db_type(#[case] value: SqlValue, #[case] expected: SqlDbType) {
+        assert_eq!(expected, value.sql_db_type());
+    }
+
+    #[test]
+    fn test_from_option() {
+        assert_eq!(SqlValue::Null, SqlValue::from(None::<i32>));
+        assert_eq!(SqlValue::Int(5), SqlValue::from(Some(5)));
+    }
+}
@@ -0,0 +1,199 @@
+use crate::tds_enums::TdsEnums;
+use std::io;
+use std::pin::Pin;
+use std::task::{ready, Context, Poll};
+use tokio::io::{AsyncRead, AsyncWrite, ReadBuf};
+
+/// Carries a TLS handshake inside PRELOGIN packets, as TDS 7 requires, and then the encrypted
+/// traffic straight over the underlying stream once the handshake is done.
+pub(crate) struct SslOverTdsStream<S> {
+    /// The underlying stream.
+    inner: S,
+    /// Whether the handshake is still going, so that traffic is wrapped in packets.
+    handshaking: bool,
+    /// Handshake data that has been written but not yet sent.
+    write_buffer: Vec<u8>,
+    /// The packets being sent.
+    packets: Vec<u8>,
+    /// How much of the packets has been sent.
+    packets_position: usize,
+    /// Bytes that have been read from the underlying stream but not yet returned (including packet
+    /// headers during the handshake).
+    read_buffer: Vec<u8>,
+    /// How much of the current incoming packet's payload is still to be returned.
+    payload_remaining: usize,
+}
+
+impl<S: AsyncRead + AsyncWrite + Unpin> SslOverTdsStream<S> {
+    /// Wraps a stream for the handshake.
+    pub fn new(inner: S) -> Self {
+        Self {
+            inner,
+            handshaking: true,
+            write_buffer: Vec::new(),
+            packets: Vec::new(),
+            packets_position: 0,
+            read_buffer: Vec::new(),
+            payload_remaining: 0,
+        }
+    }
+
+    /// The underlying stream.
+    pub fn get_mut(&mut self) -> &mut S {
+        &mut self.inner
+    }
+
+    /// Stops wrapping traffic in packets, once the handshake is done.
+    pub fn finish_handshake(&mut self) {
+        self.handshaking = false;
+    }
+
+    /// Wraps the buffered handshake data in PRELOGIN packets, ready to be sent.
+    fn build_packets(&mut self) {
+        let payload_size = TdsEnums::DEFAULT_PACKET_SIZE - TdsEnums::HEADER_LEN;
+        let chunks: Vec<&[u8]> = self.write_buffer.chunks(payload_size).collect();
+        let count = chunks.len();
+        self.packets.clear();
+        self.packets_position = 0;
+        for (i, chunk) in chunks.into_iter().enumerate() {
+            let status = if i == count - 1 { TdsEnums::ST_EOM } else { 0 };
+            self.packets.push(TdsEnums::MT_PRELOGIN);
+            self.packets.push(status);
+            self.packets
+                .extend_from_slice(&((chunk.len() + TdsEnums::HEADER_LEN) as u16).to_be_bytes());
+            self.packets.extend_from_slice(&[0, 0, (i + 1) as u8, 0]);
+            self.packets.extend_from_slice(chunk);
+        }
+        self.write_buffer.clear();
+    }
+}
+
+impl<S: AsyncRead + AsyncWrite + Unpin> AsyncRead for SslOverTdsStream<S> {
+    fn poll_read(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &mut ReadBuf<'_>,
+    ) -> Poll<io::Result<()>> {
+        let this = self.get_mut();
+        loop {
+            // Return anything already read (once the handshake is done there are no more packet
+            // headers, so it's all data).
+            let available = if this.handshaking {
+                this.payload_remaining.min(this.read_buffer.len())
+            } else {
+                this.read_buffer.len()
+            };
+            if available > 0 {
+                let count = available.min(buf.remaining());
+                buf.put_slice(&this.read_buffer[..count]);
+                this.read_buffer.drain(..count);
+                this.payload_remaining = this.payload_remaining.saturating_sub(count);
+                return Poll::Ready(Ok(()));
+            }
+            if !this.handshaking {
+                return Pin::new(&mut this.inner).poll_read(cx, buf);
+            }
+            // Start the next packet once we have its header
+            if this.payload_remaining == 0 && this.read_buffer.len() >= TdsEnums::HEADER_LEN {
+                let length =
+                    u16::from_be_bytes([this.read_buffer[2], this.read_buffer[3]]) as usize;
+                if length < TdsEnums::HEADER_LEN {
+                    return Poll::Ready(Err(io::Error::new(
+                        io::ErrorKind::InvalidData,
+                        format!("Invalid packet length {}", length),
+                    )));
+                }
+                this.payload_remaining = length - TdsEnums::HEADER_LEN;
+                this.read_buffer.drain(..TdsEnums::HEADER_LEN);
+                continue;
+            }
+            // Otherwise read some more
+            let mut chunk = [0u8; 4096];
+            let mut chunk_buf = ReadBuf::new(&mut chunk);
+            ready!(Pin::new(&mut this.inner).poll_read(cx, &mut chunk_buf))?;
+            if chunk_buf.filled().is_empty() {
+                // The end of the stream
+                return Poll::Ready(Ok(()));
+            }
+            this.read_buffer.extend_from_slice(chunk_buf.filled());
+        }
+    }
+}
+
+impl<S: AsyncRead + AsyncWrite + Unpin> AsyncWrite for SslOverTdsStream<S> {
+    fn poll_write(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &[u8],
+    ) -> Poll<io::Result<usize>> {
+        let this = self.get_mut();
+        if this.handshaking {
+            // Held until the flush, so that each flight of handshake messages goes as one message
+            this.write_buffer.extend_from_slice(buf);
+            Poll::Ready(Ok(buf.len()))
+        } else {
+            Pin::new(&mut this.inner).poll_write(cx, buf)
+        }
+    }
+
+    fn poll_flush(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        let this = self.get_mut();
+        if this.packets_position == this.packets.len() && !this.write_buffer.is_empty() {
+            this.build_packets();
+        }
+        // Send the packets
+        while this.packets_position < this.packets.len() {
+            let count =
+                ready!(Pin::new(&mut this.inner)
+                    .poll_write(cx, &this.packets[this.packets_position..]))?;
+            if count == 0 {
+                return Poll::Ready(Err(io::ErrorKind::WriteZero.into()));
+            }
+            this.packets_position += count;
+        }
+        Pin::new(&mut this.inner).poll_flush(cx)
+    }
+
+    fn poll_shutdown(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        Pin::new(&mut self.get_mut().inner).poll_shutdown(cx)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tds_test_utils::split_into_packets;
+    use test_utils::MockStream;
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+    #[tokio::test]
+    async fn test_handshake_in_packets() {
+        // Two packets of handshake data, then data after the handshake
+        let mut data = split_into_packets(&[1, 2, 3, 4, 5], 3);
+        data.extend_from_slice(&[6, 7]);
+        let stream = MockStream::new(data);
+        let written = stream.written();
+        let mut subject = SslOverTdsStream::new(stream);
+        // Packet headers are stripped
+        let mut buffer = [0u8; 5];
+        subject.read_exact(&mut buffer).await.unwrap();
+        assert_eq!([1, 2, 3, 4, 5], buffer);
+        // Writes are sent as one message when flushed
+        subject.write_all(&[1, 2]).await.unwrap();
+        subject.write_all(&[3]).await.unwrap();
+        assert!(written.lock().unwrap().is_empty());
+        subject.flush().await.unwrap();
+        assert_eq!(
+            vec![0x12, 0x01, 0x00, 0x0B, 0x00, 0x00, 0x01, 0x00, 1, 2, 3],
+            *written.lock().unwrap()
+        );
+        // Once the handshake is done, traffic passes through as it is
+        subject.finish_handshake();
+        let mut buffer = [0u8; 2];
+        subject.read_exact(&mut buffer).await.unwrap();
+        assert_eq!([6, 7], buffer);
+        subject.write_all(&[4]).await.unwrap();
+        subject.flush().await.unwrap();
+        assert_eq!(Some(&4), written.lock().unwrap().last());
+    }
+}
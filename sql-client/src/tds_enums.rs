@@ -20,3 +20,251 @@ impl LoginValidationRules {
     /// Maximum length of the attach DB file name.
     pub const MAXLEN_ATTACHDBFILE: usize = 260; // the filename for a database that is to be attached during the connection process
 }
+
+/// TDS protocol constants.
+pub(crate) struct TdsEnums;
+impl TdsEnums {
+    /// The length of a packet header.
+    pub const HEADER_LEN: usize = 8;
+    /// The packet size used until the server tells us otherwise.
+    pub const DEFAULT_PACKET_SIZE: usize = 4096;
+    /// The smallest packet size the server may ask for.
+    pub const MIN_PACKET_SIZE: usize = 512;
+    /// The largest packet size the server may ask for.
+    pub const MAX_PACKET_SIZE: usize = 32767;
+    /// The TCP port that SQL Server listens on by default.
+    pub const DEFAULT_PORT: u16 = 1433;
+    /// The TDS version we speak (7.4, as used by SQL Server 2012 and later).
+    pub const TDS_VERSION: u32 = 0x74000004;
+
+    // Packet (message) types
+    /// A SQL batch.
+    pub const MT_SQL: u8 = 1;
+    /// A remote procedure call.
+    pub const MT_RPC: u8 = 3;
+    /// A tabular result returned by the server.
+    pub const MT_TOKENS: u8 = 4;
+    /// An attention (cancel) signal.
+    pub const MT_ATTN: u8 = 6;
+    /// Bulk load data.
+    pub const MT_BULK: u8 = 7;
+    /// A transaction manager request.
+    pub const MT_TRANS: u8 = 14;
+    /// A TDS 7+ login.
+    pub const MT_LOGIN7: u8 = 16;
+    /// A pre-login handshake.
+    pub const MT_PRELOGIN: u8 = 18;
+
+    // Packet status bits
+    /// The packet is the last packet of the message.
+    pub const ST_EOM: u8 = 0x01;
+    /// The message should be ignored.
+    pub const ST_IGNORE: u8 = 0x02;
+    /// Reset the connection before processing the message.
+    pub const ST_RESET_CONNECTION: u8 = 0x08;
+    /// Reset the connection, keeping the transaction state.
+    pub const ST_RESET_CONNECTION_PRESERVE_TRANSACTION: u8 = 0x10;
+
+    // Token types
+    pub const SQLRETURNSTATUS: u8 = 0x79;
+    pub const SQLCOLMETADATA: u8 = 0x81;
+    pub const SQLALTMETADATA: u8 = 0x88;
+    pub const SQLTABNAME: u8 = 0xA4;
+    pub const SQLCOLINFO: u8 = 0xA5;
+    pub const SQLORDER: u8 = 0xA9;
+    pub const SQLERROR: u8 = 0xAA;
+    pub const SQLINFO: u8 = 0xAB;
+    pub const SQLRETURNVALUE: u8 = 0xAC;
+    pub const SQLLOGINACK: u8 = 0xAD;
+    pub const SQLFEATUREEXTACK: u8 = 0xAE;
+    pub const SQLROW: u8 = 0xD1;
+    pub const SQLNBCROW: u8 = 0xD2;
+    pub const SQLALTROW: u8 = 0xD3;
+    pub const SQLENVCHANGE: u8 = 0xE3;
+    pub const SQLSESSIONSTATE: u8 = 0xE4;
+    pub const SQLSSPI: u8 = 0xED;
+    pub const SQLFEDAUTHINFO: u8 = 0xEE;
+    pub const SQLDONE: u8 = 0xFD;
+    pub const SQLDONEPROC: u8 = 0xFE;
+    pub const SQLDONEINPROC: u8 = 0xFF;
+
+    // DONE token status bits
+    /// More results follow.
+    pub const DONE_MORE: u16 = 0x0001;
+    /// An error occurred on the current statement.
+    pub const DONE_ERROR: u16 = 0x0002;
+    /// A transaction is in progress.
+    pub const DONE_INXACT: u16 = 0x0004;
+    /// The row count is valid.
+    pub const DONE_COUNT: u16 = 0x0010;
+    /// The DONE acknowledges an attention.
+    pub const DONE_ATTN: u16 = 0x0020;
+    /// A severe error occurred and the result set should be discarded.
+    pub const DONE_SRVERROR: u16 = 0x0100;
+    /// The current command token of a SELECT statement.
+    pub const SELECT: u16 = 0xC1;
+
+    // Environment change types
+    pub const ENV_DATABASE: u8 = 1;
+    pub const ENV_LANG: u8 = 2;
+    pub const ENV_CHARSET: u8 = 3;
+    pub const ENV_PACKETSIZE: u8 = 4;
+    pub const ENV_COLLATION: u8 = 7;
+    pub const ENV_BEGINTRAN: u8 = 8;
+    pub const ENV_COMMITTRAN: u8 = 9;
+    pub const ENV_ROLLBACKTRAN: u8 = 10;
+    pub const ENV_ENLISTDTC: u8 = 11;
+    pub const ENV_DEFECTDTC: u8 = 12;
+
+    // Pre-login option tokens
+    /// The client or server version.
+    pub const PRELOGIN_VERSION: u8 = 0;
+    /// The encryption option.
+    pub const PRELOGIN_ENCRYPTION: u8 = 1;
+    /// The name of the instance to connect to.
+    pub const PRELOGIN_INSTOPT: u8 = 2;
+    /// The client's thread ID.
+    pub const PRELOGIN_THREADID: u8 = 3;
+    /// Whether MARS is requested.
+    pub const PRELOGIN_MARS: u8 = 4;
+    /// The end of the options.
+    pub const PRELOGIN_TERMINATOR: u8 = 0xFF;
+
+    // Pre-login encryption options
+    /// Encryption is available but off (only the login is encrypted).
+    pub const ENCRYPT_OFF: u8 = 0;
+    /// Encryption is available and on.
+    pub const ENCRYPT_ON: u8 = 1;
+    /// Encryption isn't available.
+    pub const ENCRYPT_NOT_SUP: u8 = 2;
+    /// Encryption is required.
+    pub const ENCRYPT_REQ: u8 = 3;
+
+    // LOGIN7 option flags
+    /// OptionFlags1: warn when the database changes, fail if the initial database can't be used,
+    /// and warn when the language changes.
+    pub const LOGIN_OPTION_FLAGS1: u8 = 0xE0;
+    /// OptionFlags2: fail if the initial language can't be used, and use ODBC defaults.
+    pub const LOGIN_OPTION_FLAGS2: u8 = 0x03;
+    /// TypeFlags: the application only reads data.
+    pub const LOGIN_READONLY_INTENT: u8 = 0x20;
+    /// OptionFlags3: the login has feature extensions.
+    pub const LOGIN_EXTENSION: u8 = 0x10;
+    /// The length of the fixed part of a LOGIN7 message.
+    pub const LOGIN_FIXED_LENGTH: usize = 94;
+
+    // Feature extension IDs
+    /// The client supports UTF-8 collations.
+    pub const FEATUREEXT_UTF8SUPPORT: u8 = 0x0A;
+    /// The end of the feature extensions.
+    pub const FEATUREEXT_TERMINATOR: u8 = 0xFF;
+
+    // ALL_HEADERS header types
+    /// The transaction descriptor header.
+    pub const HEADERTYPE_TRANSACTION_DESCRIPTOR: u16 = 2;
+
+    // RPC option and parameter status flags
+    /// Recompile the procedure.
+    pub const RPC_RECOMPILE: u16 = 0x01;
+    /// The parameter is passed by reference (i.e. is an output parameter).
+    pub const RPC_PARAM_BYREF: u8 = 0x01;
+    /// The parameter should use its default value.
+    pub const RPC_PARAM_DEFAULT: u8 = 0x02;
+    /// The procedure is identified by ID rather than by name.
+    pub const RPC_PROCID_MARKER: u16 = 0xFFFF;
+    /// RETURNVALUE status for an output parameter.
+    pub const RETURNVALUE_OUTPUT_PARAMETER: u8 = 0x01;
+    /// RETURNVALUE status for the return value of a user-defined function.
+    pub const RETURNVALUE_UDF: u8 = 0x02;
+
+    // Table-valued parameters
+    /// Ends the optional metadata or the rows of a table-valued parameter.
+    pub const TVP_END_TOKEN: u8 = 0x00;
+    /// Starts a row of a table-valued parameter.
+    pub const TVP_ROW_TOKEN: u8 = 0x01;
+    /// Sent in place of the column count of a table-valued parameter with no columns.
+    pub const TVP_NULL_TOKEN: u16 = 0xFFFF;
+
+    // Well-known procedure IDs
+    pub const SP_CURSOR: u16 = 1;
+    pub const SP_CURSOROPEN: u16 = 2;
+    pub const SP_CURSORPREPARE: u16 = 3;
+    pub const SP_CURSOREXECUTE: u16 = 4;
+    pub const SP_CURSORPREPEXEC: u16 = 5;
+    pub const SP_CURSORUNPREPARE: u16 = 6;
+    pub const SP_CURSORFETCH: u16 = 7;
+    pub const SP_CURSOROPTION: u16 = 8;
+    pub const SP_CURSORCLOSE: u16 = 9;
+    pub const SP_EXECUTESQL: u16 = 10;
+    pub const SP_PREPARE: u16 = 11;
+    pub const SP_EXECUTE: u16 = 12;
+    pub const SP_PREPEXEC: u16 = 13;
+    pub const SP_PREPEXECRPC: u16 = 14;
+    pub const SP_UNPREPARE: u16 = 15;
+
+    // Data types
+    pub const SQLVOID: u8 = 0x1F;
+    pub const SQLIMAGE: u8 = 0x22;
+    pub const SQLTEXT: u8 = 0x23;
+    pub const SQLUNIQUEID: u8 = 0x24;
+    pub const SQLVARBINARY: u8 = 0x25;
+    pub const SQLINTN: u8 = 0x26;
+    pub const SQLVARCHAR: u8 = 0x27;
+    pub const SQLDATE: u8 = 0x28;
+    pub const SQLTIME: u8 = 0x29;
+    pub const SQLDATETIME2: u8 = 0x2A;
+    pub const SQLDATETIMEOFFSET: u8 = 0x2B;
+    pub const SQLBINARY: u8 = 0x2D;
+    pub const SQLCHAR: u8 = 0x2F;
+    pub const SQLINT1: u8 = 0x30;
+    pub const SQLBIT: u8 = 0x32;
+    pub const SQLINT2: u8 = 0x34;
+    pub const SQLDECIMAL: u8 = 0x37;
+    pub const SQLINT4: u8 = 0x38;
+    pub const SQLDATETIM4: u8 = 0x3A;
+    pub const SQLFLT4: u8 = 0x3B;
+    pub const SQLMONEY: u8 = 0x3C;
+    pub const SQLDATETIME: u8 = 0x3D;
+    pub const SQLFLT8: u8 = 0x3E;
+    pub const SQLNUMERIC: u8 = 0x3F;
+    pub const SQLVARIANT: u8 = 0x62;
+    pub const SQLNTEXT: u8 = 0x63;
+    pub const SQLBITN: u8 = 0x68;
+    pub const SQLDECIMALN: u8 = 0x6A;
+    pub const SQLNUMERICN: u8 = 0x6C;
+    pub const SQLFLTN: u8 = 0x6D;
+    pub const SQLMONEYN: u8 = 0x6E;
+    pub const SQLDATETIMN: u8 = 0x6F;
+    pub const SQLMONEY4: u8 = 0x7A;
+    pub const SQLINT8: u8 = 0x7F;
+    pub const SQLBIGVARBINARY: u8 = 0xA5;
+    pub const SQLBIGVARCHAR: u8 = 0xA7;
+    pub const SQLBIGBINARY: u8 = 0xAD;
+    pub const SQLBIGCHAR: u8 = 0xAF;
+    pub const SQLNVARCHAR: u8 = 0xE7;
+    pub const SQLNCHAR: u8 = 0xEF;
+    pub const SQLUDT: u8 = 0xF0;
+    pub const SQLXMLTYPE: u8 = 0xF1;
+    pub const SQLTABLE: u8 = 0xF3;
+
+    /// The length of the text pointer of a text, ntext or image value.
+    pub const TEXT_POINTER_LENGTH: u8 = 16;
+    /// The largest non-MAX length of a variable length type, in bytes.
+    pub const MAXSIZE: u32 = 8000;
+    /// The TYPE_INFO length used for MAX (PLP) types.
+    pub const SQL_USHORTVARMAXLEN: u16 = 0xFFFF;
+    /// A PLP length indicating a null value.
+    pub const SQL_PLP_NULL: u64 = 0xFFFF_FFFF_FFFF_FFFF;
+    /// A PLP length indicating that the total length is not known in advance.
+    pub const SQL_PLP_UNKNOWNLEN: u64 = 0xFFFF_FFFF_FFFF_FFFE;
+    /// The chunk length that terminates a PLP value.
+    pub const SQL_PLP_CHUNK_TERMINATOR: u32 = 0;
+    /// A USHORTLEN value length indicating a null value.
+    pub const VARNULL: u16 = 0xFFFF;
+
+    /// The highest severity of an error that the user can correct.  Errors above it are raised
+    /// even when user errors are handled as informational messages.
+    pub const MAX_USER_CORRECTABLE_ERROR_CLASS: u8 = 16;
+    /// The lowest severity of an error that ends the connection.
+    pub const FATAL_ERROR_CLASS: u8 = 20;
+}
@@ -0,0 +1,993 @@
+use crate::sql_collation::SqlCollation;
+use crate::sql_error::SqlError;
+use crate::sql_info_message_event_args::{SqlInfoMessageEventArgs, SqlInfoMessageEventHandler};
+use crate::sql_login::SqlLogin;
+use crate::sql_parameter::ParameterStream;
+use crate::sql_stream::PlpState;
+use crate::tds_enums::TdsEnums;
+use crate::tds_parser_state_object::{
+    decode_utf16, encode_utf16, write_b_varchar, write_us_varchar, TdsParserStateObject, TdsStream,
+};
+use crate::tds_token::{ColumnMetaData, DoneToken, ReturnValueToken, TdsToken};
+use crate::tds_type_info::TypeInfo;
+use crate::tds_value::{
+    read_value, skip_value, to_type_system, write_plp_stream, write_row_value, write_table_value,
+    write_value, TableType,
+};
+use crate::{SqlClientError, SqlValue, TypeSystem};
+use std::sync::Arc;
+use uuid::Uuid;
+
+/// The name of this client library, as sent in the login.
+const CLIENT_INTERFACE_NAME: &str = "sql-client";
+
+/// Obfuscates a password for the login: each byte of its UTF-16 encoding has its nibbles swapped
+/// and is XORed with 0xA5.
+fn obfuscate_password(password: &str) -> Vec<u8> {
+    encode_utf16(password)
+        .into_iter()
+        .map(|b| b.rotate_left(4) ^ 0xA5)
+        .collect()
+}
+
+/// Adds a variable-length field to a LOGIN7 message: its offset and length go in the fixed part,
+/// and its bytes in the variable part.  Returns the offset.
+fn write_login_field(
+    fixed: &mut Vec<u8>,
+    data: &mut Vec<u8>,
+    bytes: &[u8],
+    length: usize,
+) -> usize {
+    let offset = TdsEnums::LOGIN_FIXED_LENGTH + data.len();
+    fixed.extend_from_slice(&(offset as u16).to_le_bytes());
+    fixed.extend_from_slice(&(length as u16).to_le_bytes());
+    data.extend_from_slice(bytes);
+    offset
+}
+
+/// The procedure called by an RPC request.
+#[derive(PartialEq, Debug, Clone, Copy)]
+pub(crate) enum RpcProcedure<'a> {
+    /// A well-known system procedure, sent by ID.
+    Id(u16),
+    /// A procedure sent by name.
+    Name(&'a str),
+}
+
+/// A parameter of an RPC request.
+#[derive(Debug, Clone)]
+pub(crate) struct RpcParameter<'a> {
+    /// The parameter name, including the leading "@" (or empty for a positional parameter).
+    pub name: &'a str,
+    /// Whether the parameter is an output parameter.
+    pub is_output: bool,
+    /// The type the parameter is sent as.
+    pub type_info: TypeInfo,
+    /// The value.
+    pub value: &'a SqlValue,
+    /// A stream to read the value from instead, if it has one.
+    pub stream: Option<&'a ParameterStream>,
+    /// The table to send instead, if it's a table-valued parameter.
+    pub table: Option<TableType<'a>>,
+}
+
+/// How far through the current row the parser has read.
+struct RowPosition {
+    /// Whether the row is an NBCROW, which starts with a null bitmap.
+    nbc: bool,
+    /// Which columns are null, once the start of the row has been read.
+    nulls: Option<Vec<bool>>,
+    /// The next column to be read.
+    next_column: usize,
+}
+
+/// Reads and writes TDS messages for a connection.
+pub(crate) struct TdsParser {
+    /// The packet reader/writer.
+    state: TdsParserStateObject,
+    /// The metadata of the current result set.
+    columns: Arc<Vec<ColumnMetaData>>,
+    /// The descriptor of the current transaction (zero when not in a transaction).
+    transaction_descriptor: u64,
+    /// The database's default collation.
+    default_collation: Option<SqlCollation>,
+    /// Whether the response to the last request has not been fully read.
+    pending_data: bool,
+    /// The position within the current row, if one hasn't been fully read.
+    row: Option<RowPosition>,
+    /// The progress through a PLP column value that's being streamed, if there is one.
+    plp: Option<PlpState>,
+    /// The type system that values are surfaced as.
+    type_system: TypeSystem,
+    /// Whether the server acknowledged the UTF8_SUPPORT feature extension.
+    utf8_support: bool,
+    /// The ID of the connection, reported with its errors.
+    client_connection_id: Uuid,
+    /// Handles INFO tokens.
+    info_message_handler: Option<SqlInfoMessageEventHandler>,
+    /// Whether errors that the user can correct are handled as INFO tokens instead.
+    fire_info_message_event_on_user_errors: bool,
+}
+
+impl TdsParser {
+    /// Creates a parser over a connected, logged-in stream.
+    pub fn new(stream: Box<dyn TdsStream>, packet_size: usize) -> Self {
+        Self {
+            state: TdsParserStateObject::new(stream, packet_size),
+            columns: Arc::new(Vec::new()),
+            transaction_descriptor: 0,
+            default_collation: None,
+            pending_data: false,
+            row: None,
+            plp: None,
+            type_system: TypeSystem::LATEST,
+            utf8_support: false,
+            client_connection_id: uuid::Builder::from_random_bytes(rand::random()).into_uuid(),
+            info_message_handler: None,
+            fire_info_message_event_on_user_errors: false,
+        }
+    }
+
+    /// Sets the handler of INFO tokens.
+    pub fn set_info_message_handler(&mut self, handler: Option<SqlInfoMessageEventHandler>) {
+        self.info_message_handler = handler;
+    }
+
+    /// Sets whether errors that the user can correct (with a severity of 16 or less) are handled
+    /// as INFO tokens instead.
+    pub fn set_fire_info_message_event_on_user_errors(&mut self, value: bool) {
+        self.fire_info_message_event_on_user_errors = value;
+    }
+
+    /// The ID of the connection, reported with its errors.
+    pub fn client_connection_id(&self) -> Uuid {
+        self.client_connection_id
+    }
+
+    /// The metadata of the current result set.
+    pub fn columns(&self) -> &Arc<Vec<ColumnMetaData>> {
+        &self.columns
+    }
+
+    /// The descriptor of the current transaction.
+    pub fn transaction_descriptor(&self) -> u64 {
+        self.transaction_descriptor
+    }
+
+    /// The database's default collation.
+    pub fn default_collation(&self) -> Option<SqlCollation> {
+        self.default_collation
+    }
+
+    /// Whether the response to the last request has not been fully read.
+    pub fn has_pending_data(&self) -> bool {
+        self.pending_data
+    }
+
+    /// The type system that values are surfaced as.
+    pub fn type_system(&self) -> TypeSystem {
+        self.type_system
+    }
+
+    /// Sets the type system that values are surfaced as.
+    pub fn set_type_system(&mut self, type_system: TypeSystem) {
+        self.type_system = type_system;
+    }
+
+    /// Whether the server acknowledged the UTF8_SUPPORT feature extension.
+    pub fn utf8_support(&self) -> bool {
+        self.utf8_support
+    }
+
+    /// Whether the connection has failed (e.g. with an I/O error or a fatal error from the
+    /// server), so that it can't be used again.
+    pub fn is_broken(&self) -> bool {
+        self.state.is_broken()
+    }
+
+    /// Gives access to the packet reader/writer.
+    pub fn state_mut(&mut self) -> &mut TdsParserStateObject {
+        &mut self.state
+    }
+
+    /// Writes the ALL_HEADERS section that starts SQL batch and RPC requests.
+    fn write_all_headers(&self, buffer: &mut Vec<u8>) {
+        // Total length, then the transaction descriptor header (length, type, descriptor, outstanding request count).
+        buffer.extend_from_slice(&22u32.to_le_bytes());
+        buffer.extend_from_slice(&18u32.to_le_bytes());
+        buffer.extend_from_slice(&TdsEnums::HEADERTYPE_TRANSACTION_DESCRIPTOR.to_le_bytes());
+        buffer.extend_from_slice(&self.transaction_descriptor.to_le_bytes());
+        buffer.extend_from_slice(&1u32.to_le_bytes());
+    }
+
+    /// Sends a LOGIN7 message.
+    pub async fn tds_login(&mut self, login: &SqlLogin) -> Result<(), SqlClientError> {
+        log::debug!(
+            "tds_login - server: {}, user: {}",
+            login.data_source(),
+            login.user_name()
+        );
+        let password = std::str::from_utf8(login.password().unsecure())
+            .map_err(|_| SqlClientError::UnsupportedValue("password".to_string(), String::new()))?;
+        // The fixed part, up to the offsets of the variable-length part
+        let mut fixed = Vec::with_capacity(TdsEnums::LOGIN_FIXED_LENGTH);
+        // The length, filled in at the end
+        fixed.extend_from_slice(&0u32.to_le_bytes());
+        fixed.extend_from_slice(&TdsEnums::TDS_VERSION.to_le_bytes());
+        fixed.extend_from_slice(&(login.packet_size() as u32).to_le_bytes());
+        // The client program version, process ID and connection ID
+        fixed.extend_from_slice(&0u32.to_le_bytes());
+        fixed.extend_from_slice(&std::process::id().to_le_bytes());
+        fixed.extend_from_slice(&0u32.to_le_bytes());
+        fixed.push(TdsEnums::LOGIN_OPTION_FLAGS1);
+        fixed.push(TdsEnums::LOGIN_OPTION_FLAGS2);
+        fixed.push(match login.read_only_intent() {
+            true => TdsEnums::LOGIN_READONLY_INTENT,
+            false => 0,
+        });
+        fixed.push(TdsEnums::LOGIN_EXTENSION);
+        // The time zone and LCID, which the server doesn't use
+        fixed.extend_from_slice(&0i32.to_le_bytes());
+        fixed.extend_from_slice(&0u32.to_le_bytes());
+        // Each variable-length field is given as its offset and its length (in characters for
+        // strings).
+        let mut data = Vec::new();
+        for value in [
+            encode_utf16(login.host_name()),
+            encode_utf16(login.user_name()),
+            obfuscate_password(password),
+            encode_utf16(login.application_name()),
+            encode_utf16(login.data_source()),
+        ] {
+            write_login_field(&mut fixed, &mut data, &value, value.len() / 2);
+        }
+        // The extension is the offset of the feature extensions, which follow everything else
+        let extension_offset = write_login_field(&mut fixed, &mut data, &[0; 4], 4);
+        for value in [
+            encode_utf16(CLIENT_INTERFACE_NAME),
+            encode_utf16(login.language()),
+            encode_utf16(login.database()),
+        ] {
+            write_login_field(&mut fixed, &mut data, &value, value.len() / 2);
+        }
+        // The client ID (a MAC address, which the server doesn't use)
+        fixed.extend_from_slice(&[0; 6]);
+        // No SSPI, then the file to attach, then no password change
+        for value in [
+            Vec::new(),
+            encode_utf16(login.attach_db_filename()),
+            Vec::new(),
+        ] {
+            write_login_field(&mut fixed, &mut data, &value, value.len() / 2);
+        }
+        // No long SSPI
+        fixed.extend_from_slice(&0u32.to_le_bytes());
+        // Ask for UTF-8 support
+        let feature_ext_offset = (TdsEnums::LOGIN_FIXED_LENGTH + data.len()) as u32;
+        let position = extension_offset - TdsEnums::LOGIN_FIXED_LENGTH;
+        data[position..position + 4].copy_from_slice(&feature_ext_offset.to_le_bytes());
+        data.push(TdsEnums::FEATUREEXT_UTF8SUPPORT);
+        data.extend_from_slice(&0u32.to_le_bytes());
+        data.push(TdsEnums::FEATUREEXT_TERMINATOR);
+        // Put the parts together
+        let mut buffer = fixed;
+        buffer.extend(data);
+        let length = buffer.len() as u32;
+        buffer[..4].copy_from_slice(&length.to_le_bytes());
+        self.state
+            .write_message(TdsEnums::MT_LOGIN7, &buffer)
+            .await?;
+        self.pending_data = true;
+        Ok(())
+    }
+
+    /// Sends a SQL batch.
+    pub async fn tds_execute_sql_batch(&mut self, text: &str) -> Result<(), SqlClientError> {
+        log::debug!("tds_execute_sql_batch - {}", text);
+        let mut buffer = Vec::new();
+        self.write_all_headers(&mut buffer);
+        buffer.extend(encode_utf16(text));
+        self.state.write_message(TdsEnums::MT_SQL, &buffer).await?;
+        self.pending_data = true;
+        Ok(())
+    }
+
+    /// Sends an RPC request.
+    pub async fn tds_execute_rpc(
+        &mut self,
+        procedure: RpcProcedure<'_>,
+        parameters: &[RpcParameter<'_>],
+    ) -> Result<(), SqlClientError> {
+        log::debug!("tds_execute_rpc - {:?}", procedure);
+        // Encode all the parameters before sending anything, so that a bad value doesn't leave a
+        // half-sent message.  Streamed values can only be read as they're sent.
+        let mut encoded_parameters = Vec::with_capacity(parameters.len());
+        for parameter in parameters {
+            let mut buffer = Vec::new();
+            write_b_varchar(&mut buffer, parameter.name);
+            buffer.push(if parameter.is_output {
+                TdsEnums::RPC_PARAM_BYREF
+            } else {
+                0
+            });
+            if let Some(table_type) = &parameter.table {
+                write_table_value(&mut buffer, table_type)?;
+            } else {
+                parameter.type_info.write(&mut buffer);
+                if parameter.stream.is_none() {
+                    write_value(&mut buffer, &parameter.type_info, parameter.value)?;
+                }
+            }
+            encoded_parameters.push(buffer);
+        }
+        // Write the header and procedure
+        let mut buffer = Vec::new();
+        self.write_all_headers(&mut buffer);
+        match procedure {
+            RpcProcedure::Id(id) => {
+                buffer.extend_from_slice(&TdsEnums::RPC_PROCID_MARKER.to_le_bytes());
+                buffer.extend_from_slice(&id.to_le_bytes());
+            }
+            RpcProcedure::Name(name) => write_us_varchar(&mut buffer, name),
+        }
+        // Option flags
+        buffer.extend_from_slice(&0u16.to_le_bytes());
+        self.state.start_message(TdsEnums::MT_RPC);
+        self.state.write(&buffer).await?;
+        // Write the parameters
+        for (parameter, encoded_parameter) in parameters.iter().zip(encoded_parameters) {
+            self.state.write(&encoded_parameter).await?;
+            if let Some(stream) = parameter.stream {
+                let mut stream = stream.lock().await;
+                let result =
+                    write_plp_stream(&mut self.state, &parameter.type_info, &mut **stream).await;
+                // If the stream fails, tell the server to ignore what's been sent.
+                if let Err(e) = result {
+                    if let Err(abort_error) = self.state.abort_message().await {
+                        log::warn!("tds_execute_rpc - failed to abort: {}", abort_error);
+                    }
+                    return Err(e);
+                }
+            }
+        }
+        self.state.end_message().await?;
+        self.pending_data = true;
+        Ok(())
+    }
+
+    /// Starts a bulk load message with the metadata of the columns being loaded.  The rows are
+    /// then written with [TdsParser::write_bulk_row] and the message is ended with
+    /// [TdsParser::end_bulk_load].
+    pub async fn start_bulk_load(
+        &mut self,
+        table_name: &str,
+        columns: &[ColumnMetaData],
+    ) -> Result<(), SqlClientError> {
+        log::debug!(
+            "start_bulk_load - {} ({} columns)",
+            table_name,
+            columns.len()
+        );
+        let mut buffer = vec![TdsEnums::SQLCOLMETADATA];
+        buffer.extend_from_slice(&(columns.len() as u16).to_le_bytes());
+        for column in columns {
+            buffer.extend_from_slice(&column.user_type.to_le_bytes());
+            buffer.extend_from_slice(&column.flags.to_le_bytes());
+            column.type_info.write(&mut buffer);
+            // The legacy large types include the name of their table.
+            if matches!(
+                column.type_info.tds_type,
+                TdsEnums::SQLTEXT | TdsEnums::SQLNTEXT | TdsEnums::SQLIMAGE
+            ) {
+                write_us_varchar(&mut buffer, table_name);
+            }
+            write_b_varchar(&mut buffer, &column.column_name);
+        }
+        self.state.start_message(TdsEnums::MT_BULK);
+        self.state.write(&buffer).await
+    }
+
+    /// Writes a row of a bulk load.  The row is encoded before anything is written, so a bad value
+    /// leaves the message intact.
+    pub async fn write_bulk_row(
+        &mut self,
+        columns: &[ColumnMetaData],
+        values: &[&SqlValue],
+    ) -> Result<(), SqlClientError> {
+        let mut buffer = vec![TdsEnums::SQLROW];
+        for (column, value) in columns.iter().zip(values) {
+            write_row_value(&mut buffer, &column.type_info, value)?;
+        }
+        self.state.write(&buffer).await
+    }
+
+    /// Ends a bulk load message.
+    pub async fn end_bulk_load(&mut self) -> Result<(), SqlClientError> {
+        let mut buffer = vec![TdsEnums::SQLDONE];
+        buffer.extend_from_slice(&0u16.to_le_bytes());
+        buffer.extend_from_slice(&0u16.to_le_bytes());
+        buffer.extend_from_slice(&0u64.to_le_bytes());
+        self.state.write(&buffer).await?;
+        self.state.end_message().await?;
+        self.pending_data = true;
+        Ok(())
+    }
+
+    /// Abandons a message that has been started, telling the server to ignore anything that has
+    /// already been sent.
+    pub async fn abort_message(&mut self) -> Result<(), SqlClientError> {
+        self.state.abort_message().await
+    }
+
+    /// Reads the next token.
+    ///
+    /// When [TdsToken::Row] or [TdsToken::NbcRow] is returned, the row must be read with
+    /// [TdsParser::read_row] or skipped with [TdsParser::skip_row] before the next token is read.
+    pub async fn next_token(&mut self) -> Result<TdsToken, SqlClientError> {
+        let token = self.state.read_u8().await?;
+        let result = match token {
+            TdsEnums::SQLCOLMETADATA => {
+                self.read_col_metadata().await?;
+                TdsToken::ColMetaData
+            }
+            TdsEnums::SQLROW | TdsEnums::SQLNBCROW => {
+                let nbc = token == TdsEnums::SQLNBCROW;
+                self.row = Some(RowPosition {
+                    nbc,
+                    nulls: None,
+                    next_column: 0,
+                });
+                match nbc {
+                    true => TdsToken::NbcRow,
+                    false => TdsToken::Row,
+                }
+            }
+            TdsEnums::SQLDONE | TdsEnums::SQLDONEPROC | TdsEnums::SQLDONEINPROC => {
+                let done = DoneToken {
+                    token,
+                    status: self.state.read_u16().await?,
+                    cur_cmd: self.state.read_u16().await?,
+                    row_count: self.state.read_u64().await?,
+                };
+                if done.is_final() {
+                    self.pending_data = false;
+                }
+                TdsToken::Done(done)
+            }
+            TdsEnums::SQLRETURNSTATUS => TdsToken::ReturnStatus(self.state.read_i32().await?),
+            TdsEnums::SQLRETURNVALUE => TdsToken::ReturnValue(self.read_return_value().await?),
+            TdsEnums::SQLERROR => TdsToken::Error(self.read_error().await?),
+            TdsEnums::SQLINFO => TdsToken::Info(self.read_error().await?),
+            TdsEnums::SQLENVCHANGE => TdsToken::EnvChange(self.read_env_change().await?),
+            TdsEnums::SQLFEATUREEXTACK => {
+                self.read_feature_ext_ack().await?;
+                TdsToken::Other(token)
+            }
+            // Tokens with a two-byte length that we don't use
+            TdsEnums::SQLORDER
+            | TdsEnums::SQLTABNAME
+            | TdsEnums::SQLCOLINFO
+            | TdsEnums::SQLLOGINACK
+            | TdsEnums::SQLSSPI => {
+                let length = self.state.read_u16().await?;
+                self.state.skip(length as usize).await?;
+                TdsToken::Other(token)
+            }
+            // Tokens with a four-byte length that we don't use
+            TdsEnums::SQLSESSIONSTATE | TdsEnums::SQLFEDAUTHINFO => {
+                let length = self.state.read_u32().await?;
+                self.state.skip(length as usize).await?;
+                TdsToken::Other(token)
+            }
+            _ => {
+                // The rest of the response can't be read.
+                self.state.set_broken();
+                return Err(SqlClientError::Protocol(format!(
+                    "Unexpected token 0x{:02X}",
+                    token
+                )));
+            }
+        };
+        log::trace!("next_token - {:?}", result);
+        // The server closes the connection after a fatal error.
+        if let TdsToken::Error(error) = &result {
+            if error.class() >= TdsEnums::FATAL_ERROR_CLASS {
+                self.state.set_broken();
+            }
+        }
+        // Messages are handled as they arrive, and may include errors the user can correct.
+        let result = match result {
+            TdsToken::Error(error)
+                if self.fire_info_message_event_on_user_errors
+                    && error.class() <= TdsEnums::MAX_USER_CORRECTABLE_ERROR_CLASS =>
+            {
+                TdsToken::Info(error)
+            }
+            result => result,
+        };
+        if let (TdsToken::Info(error), Some(handler)) = (&result, &self.info_message_handler) {
+            handler(&SqlInfoMessageEventArgs::new(error.clone()));
+        }
+        Ok(result)
+    }
+
+    /// Reads a FEATUREEXTACK token (a list of acknowledged features ending with a terminator).
+    async fn read_feature_ext_ack(&mut self) -> Result<(), SqlClientError> {
+        loop {
+            let feature_id = self.state.read_u8().await?;
+            if feature_id == TdsEnums::FEATUREEXT_TERMINATOR {
+                return Ok(());
+            }
+            let length = self.state.read_u32().await?;
+            let data = self.state.read_bytes(length as usize).await?;
+            if feature_id == TdsEnums::FEATUREEXT_UTF8SUPPORT {
+                // Bit 0 is set if the server supports UTF-8
+                self.utf8_support = data.first().is_some_and(|b| b & 0x01 != 0);
+            }
+        }
+    }
+
+    /// Reads a TYPE_INFO.  Unless UTF-8 support was negotiated, the server sends the data of UTF-8
+    /// collations in the locale's code page.
+    async fn read_type_info(&mut self) -> Result<TypeInfo, SqlClientError> {
+        let mut type_info = TypeInfo::read(&mut self.state).await?;
+        if !self.utf8_support {
+            type_info.collation = type_info.collation.map(SqlCollation::without_utf8);
+        }
+        Ok(type_info)
+    }
+
+    /// Reads a COLMETADATA token into the current column metadata.
+    async fn read_col_metadata(&mut self) -> Result<(), SqlClientError> {
+        let count = self.state.read_u16().await?;
+        let mut columns = Vec::new();
+        // 0xFFFF means there's no metadata.
+        if count != 0xFFFF {
+            for _ in 0..count {
+                let user_type = self.state.read_u32().await?;
+                let flags = self.state.read_u16().await?;
+                let type_info = self.read_type_info().await?;
+                // The legacy large types include the name of their table.
+                if matches!(
+                    type_info.tds_type,
+                    TdsEnums::SQLTEXT | TdsEnums::SQLNTEXT | TdsEnums::SQLIMAGE
+                ) {
+                    let parts = self.state.read_u8().await?;
+                    for _ in 0..parts {
+                        self.state.read_us_varchar().await?;
+                    }
+                }
+                let column_name = self.state.read_b_varchar().await?;
+                columns.push(ColumnMetaData {
+                    user_type,
+                    flags,
+                    type_info,
+                    column_name,
+                });
+            }
+        }
+        self.columns = Arc::new(columns);
+        Ok(())
+    }
+
+    /// Reads which columns of the current row are null.  Only an NBCROW has a null bitmap; the
+    /// nulls of a ROW are found as its values are read.
+    pub async fn read_row_nulls(&mut self, nbc: bool) -> Result<Vec<bool>, SqlClientError> {
+        let column_count = self.columns.len();
+        let nulls = match nbc {
+            true => {
+                let bitmap = self.state.read_bytes(column_count.div_ceil(8)).await?;
+                (0..column_count)
+                    .map(|i| bitmap[i / 8] & (1 << (i % 8)) != 0)
+                    .collect()
+            }
+            false => vec![false; column_count],
+        };
+        if let Some(row) = &mut self.row {
+            row.nulls = Some(nulls.clone());
+        }
+        Ok(nulls)
+    }
+
+    /// Records that the columns of the current row before column `i` have been read.
+    fn column_read(&mut self, i: usize) {
+        if let Some(row) = &mut self.row {
+            row.next_column = i + 1;
+            if row.next_column >= self.columns.len() {
+                self.row = None;
+            }
+        }
+    }
+
+    /// Reads the value of the next column of the current row, which is column `i`.
+    pub async fn read_column(&mut self, i: usize) -> Result<SqlValue, SqlClientError> {
+        let columns = self.columns.clone();
+        let type_info = &columns[i].type_info;
+        let value = read_value(&mut self.state, type_info).await?;
+        self.column_read(i);
+        Ok(to_type_system(type_info, value, self.type_system))
+    }
+
+    /// Skips the value of the next column of the current row, which is column `i`.
+    pub async fn skip_column(&mut self, i: usize) -> Result<(), SqlClientError> {
+        let columns = self.columns.clone();
+        skip_value(&mut self.state, &columns[i].type_info).await?;
+        self.column_read(i);
+        Ok(())
+    }
+
+    /// Starts streaming the value of the next column of the current row, which is column `i` and
+    /// has a PLP type.  Returns false if the value is null (so there's nothing to stream).
+    pub async fn start_plp_column(&mut self, i: usize) -> Result<bool, SqlClientError> {
+        let is_null = self.state.read_u64().await? == TdsEnums::SQL_PLP_NULL;
+        self.column_read(i);
+        if !is_null {
+            self.plp = Some(PlpState::default());
+        }
+        Ok(!is_null)
+    }
+
+    /// The connection and the progress through the PLP value being streamed, to stream it.
+    pub fn plp_stream(&mut self) -> Option<(&mut TdsParserStateObject, &mut PlpState)> {
+        let plp = self.plp.as_mut()?;
+        Some((&mut self.state, plp))
+    }
+
+    /// Skips whatever hasn't been read of the PLP value being streamed.
+    pub async fn skip_plp(&mut self) -> Result<(), SqlClientError> {
+        if let Some(plp) = &mut self.plp {
+            plp.skip(&mut self.state).await?;
+            self.plp = None;
+        }
+        Ok(())
+    }
+
+    /// Skips whatever hasn't been read of the current row (including a PLP value being streamed).
+    pub async fn skip_rest_of_row(&mut self) -> Result<(), SqlClientError> {
+        self.skip_plp().await?;
+        let Some(row) = &self.row else {
+            return Ok(());
+        };
+        let (nbc, next_column) = (row.nbc, row.next_column);
+        let nulls = match &row.nulls {
+            Some(nulls) => nulls.clone(),
+            None => self.read_row_nulls(nbc).await?,
+        };
+        for (i, is_null) in nulls.into_iter().enumerate().skip(next_column) {
+            if !is_null {
+                self.skip_column(i).await?;
+            }
+        }
+        self.row = None;
+        Ok(())
+    }
+
+    /// Reads all the values of the current row.
+    pub async fn read_row(&mut self, nbc: bool) -> Result<Vec<SqlValue>, SqlClientError> {
+        let nulls = self.read_row_nulls(nbc).await?;
+        let mut values = Vec::with_capacity(nulls.len());
+        for (i, is_null) in nulls.into_iter().enumerate() {
+            if is_null {
+                values.push(SqlValue::Null);
+            } else {
+                values.push(self.read_column(i).await?);
+            }
+        }
+        Ok(values)
+    }
+
+    /// Skips the current row.
+    pub async fn skip_row(&mut self, nbc: bool) -> Result<(), SqlClientError> {
+        let nulls = self.read_row_nulls(nbc).await?;
+        for (i, is_null) in nulls.into_iter().enumerate() {
+            if !is_null {
+                self.skip_column(i).await?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Reads a RETURNVALUE token.
+    async fn read_return_value(&mut self) -> Result<ReturnValueToken, SqlClientError> {
+        let ordinal = self.state.read_u16().await?;
+        let parameter_name = self.state.read_b_varchar().await?;
+        let status = self.state.read_u8().await?;
+        let _user_type = self.state.read_u32().await?;
+        let _flags = self.state.read_u16().await?;
+        let type_info = self.read_type_info().await?;
+        let value = read_value(&mut self.state, &type_info).await?;
+        let value = to_type_system(&type_info, value, self.type_system);
+        Ok(ReturnValueToken {
+            ordinal,
+            parameter_name,
+            status,
+            type_info,
+            value,
+        })
+    }
+
+    /// Reads an ERROR or INFO token.
+    async fn read_error(&mut self) -> Result<SqlError, SqlClientError> {
+        let _length = self.state.read_u16().await?;
+        let number = self.state.read_i32().await?;
+        let state = self.state.read_u8().await?;
+        let class = self.state.read_u8().await?;
+        let message = self.state.read_us_varchar().await?;
+        let server = self.state.read_b_varchar().await?;
+        let procedure = self.state.read_b_varchar().await?;
+        let line_number = self.state.read_i32().await?;
+        Ok(SqlError::new(
+            number,
+            state,
+            class,
+            &message,
+            &server,
+            &procedure,
+            line_number,
+        ))
+    }
+
+    /// Reads and applies an ENVCHANGE token, returning its type.
+    async fn read_env_change(&mut self) -> Result<u8, SqlClientError> {
+        let length = self.state.read_u16().await? as usize;
+        let data = self.state.read_bytes(length).await?;
+        let env_type = *data
+            .first()
+            .ok_or_else(|| SqlClientError::Protocol("Empty ENVCHANGE token".to_string()))?;
+        // Most changes have a one-byte length then the new value.
+        let new_value = data
+            .get(1)
+            .and_then(|length| data.get(2..2 + *length as usize))
+            .unwrap_or(&[]);
+        log::debug!("read_env_change - type: {}", env_type);
+        match env_type {
+            TdsEnums::ENV_PACKETSIZE => {
+                // The size is sent as a unicode string
+                let size = decode_utf16(new_value)?;
+                let size = size
+                    .parse::<usize>()
+                    .ok()
+                    .filter(|size| {
+                        (TdsEnums::MIN_PACKET_SIZE..=TdsEnums::MAX_PACKET_SIZE).contains(size)
+                    })
+                    .ok_or_else(|| {
+                        SqlClientError::Protocol(format!("Invalid packet size '{}'", size))
+                    })?;
+                self.state.set_packet_size(size);
+            }
+            TdsEnums::ENV_COLLATION => {
+                if let Ok(bytes) = <[u8; SqlCollation::SIZE]>::try_from(new_value) {
+                    self.default_collation = Some(SqlCollation::from_bytes(bytes));
+                }
+            }
+            TdsEnums::ENV_BEGINTRAN | TdsEnums::ENV_ENLISTDTC => {
+                let descriptor = <[u8; 8]>::try_from(new_value).map_err(|_| {
+                    SqlClientError::Protocol("Invalid transaction descriptor".to_string())
+                })?;
+                self.transaction_descriptor = u64::from_le_bytes(descriptor);
+            }
+            TdsEnums::ENV_COMMITTRAN | TdsEnums::ENV_ROLLBACKTRAN | TdsEnums::ENV_DEFECTDTC => {
+                self.transaction_descriptor = 0;
+            }
+            _ => {}
+        }
+        Ok(env_type)
+    }
+
+    /// Reads and discards the rest of the current response, starting with whatever hasn't been read
+    /// of the current row.
+    pub async fn drain(&mut self) -> Result<(), SqlClientError> {
+        self.skip_rest_of_row().await?;
+        while self.pending_data {
+            match self.next_token().await? {
+                TdsToken::Row => self.skip_row(false).await?,
+                TdsToken::NbcRow => self.skip_row(true).await?,
+                _ => {}
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::sql_connection_string::SqlConnectionString;
+    use crate::tds_test_utils::{packet, TokenBuilder};
+    use crate::SqlDbType;
+    use test_utils::MockStream;
+
+    /// Creates a parser that will read the given tokens.
+    fn parser(tokens: &TokenBuilder) -> TdsParser {
+        TdsParser::new(Box::new(MockStream::new(packet(tokens.bytes()))), 4096)
+    }
+
+    #[tokio::test]
+    async fn test_read_rows() {
+        let tokens = TokenBuilder::new()
+            .col_metadata(&[("id", SqlDbType::Int), ("name", SqlDbType::NVarChar)])
+            .row(&[SqlValue::Int(1), SqlValue::from("a")])
+            .nbc_row(&[SqlValue::Int(2), SqlValue::Null])
+            .done(TdsEnums::SQLDONE, TdsEnums::DONE_COUNT, TdsEnums::SELECT, 2);
+        let mut parser = parser(&tokens);
+        assert_eq!(TdsToken::ColMetaData, parser.next_token().await.unwrap());
+        assert_eq!("name", parser.columns()[1].column_name);
+        assert_eq!(TdsToken::Row, parser.next_token().await.unwrap());
+        assert_eq!(
+            vec![SqlValue::Int(1), SqlValue::from("a")],
+            parser.read_row(false).await.unwrap()
+        );
+        assert_eq!(TdsToken::NbcRow, parser.next_token().await.unwrap());
+        assert_eq!(
+            vec![SqlValue::Int(2), SqlValue::Null],
+            parser.read_row(true).await.unwrap()
+        );
+        match parser.next_token().await.unwrap() {
+            TdsToken::Done(done) => {
+                assert!(done.is_final());
+                assert_eq!(None, done.records_affected());
+            }
+            token => panic!("Unexpected token {:?}", token),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_read_error_and_env_change() {
+        let tokens = TokenBuilder::new()
+            .env_change_begin_transaction(0x1122334455667788)
+            .error(
+                TdsEnums::SQLERROR,
+                2627,
+                14,
+                "Violation of PRIMARY KEY constraint",
+            )
+            .done(TdsEnums::SQLDONE, TdsEnums::DONE_ERROR, 0, 0);
+        let mut parser = parser(&tokens);
+        assert_eq!(
+            TdsToken::EnvChange(TdsEnums::ENV_BEGINTRAN),
+            parser.next_token().await.unwrap()
+        );
+        assert_eq!(0x1122334455667788, parser.transaction_descriptor());
+        match parser.next_token().await.unwrap() {
+            TdsToken::Error(error) => {
+                assert_eq!(2627, error.number());
+                assert_eq!(14, error.class());
+                assert_eq!("Violation of PRIMARY KEY constraint", error.message());
+            }
+            token => panic!("Unexpected token {:?}", token),
+        }
+    }
+
+    #[rstest::rstest]
+    #[case::smallest("512", true)]
+    #[case::largest("32767", true)]
+    #[case::too_small("511", false)]
+    #[case::too_large("32768", false)]
+    #[case::zero("0", false)]
+    #[case::not_a_number("big", false)]
+    #[tokio::test]
+    async fn test_packet_size(#[case] size: &str, #[case] expected: bool) {
+        let tokens = TokenBuilder::new()
+            .env_change(TdsEnums::ENV_PACKETSIZE, &encode_utf16(size), &[])
+            .done(TdsEnums::SQLDONE, 0, 0, 0);
+        let mut parser = parser(&tokens);
+        assert_eq!(expected, parser.next_token().await.is_ok());
+    }
+
+    #[rstest::rstest]
+    #[case::user_error(16, false)]
+    #[case::fatal_error(20, true)]
+    #[tokio::test]
+    async fn test_fatal_error_breaks_connection(#[case] class: u8, #[case] expected: bool) {
+        let tokens = TokenBuilder::new()
+            .error(TdsEnums::SQLERROR, 50000, class, "error")
+            .done(TdsEnums::SQLDONE, TdsEnums::DONE_ERROR, 0, 0);
+        let mut parser = parser(&tokens);
+        assert!(matches!(
+            parser.next_token().await.unwrap(),
+            TdsToken::Error(_)
+        ));
+        assert_eq!(expected, parser.is_broken());
+    }
+
+    #[tokio::test]
+    async fn test_drain() {
+        let tokens = TokenBuilder::new()
+            .col_metadata(&[("id", SqlDbType::Int)])
+            .row(&[SqlValue::Int(1)])
+            .done(TdsEnums::SQLDONE, TdsEnums::DONE_COUNT, TdsEnums::SELECT, 1);
+        let mut parser = parser(&tokens);
+        parser.tds_execute_sql_batch("select 1").await.unwrap();
+        assert!(parser.has_pending_data());
+        parser.drain().await.unwrap();
+        assert!(!parser.has_pending_data());
+    }
+
+    #[tokio::test]
+    async fn test_login() {
+        let options = SqlConnectionString::try_from(
+            "Server=myserver;User ID=me;Password=pässword;Database=db;Workstation ID=ws;\
+             ApplicationIntent=ReadOnly",
+        )
+        .unwrap();
+        let login = SqlLogin::new(&options, options.sql_credential().unwrap().as_ref()).unwrap();
+        let stream = MockStream::new(Vec::new());
+        let written = stream.written();
+        let mut parser = TdsParser::new(Box::new(stream), 4096);
+        parser.tds_login(&login).await.unwrap();
+        assert!(parser.has_pending_data());
+        let written = written.lock().unwrap().clone();
+        assert_eq!(TdsEnums::MT_LOGIN7, written[0]);
+        let message = &written[TdsEnums::HEADER_LEN..];
+        let read_u32 = |at: usize| u32::from_le_bytes(message[at..at + 4].try_into().unwrap());
+        assert_eq!(message.len() as u32, read_u32(0));
+        assert_eq!(TdsEnums::TDS_VERSION, read_u32(4));
+        assert_eq!(TdsEnums::LOGIN_READONLY_INTENT, message[26]);
+        // Find the variable-length fields from their offsets and lengths
+        let field = |i: usize, width: usize| {
+            let at = 36 + i * 4;
+            let offset = u16::from_le_bytes([message[at], message[at + 1]]) as usize;
+            let length = u16::from_le_bytes([message[at + 2], message[at + 3]]) as usize;
+            &message[offset..offset + length * width]
+        };
+        let string = |i: usize| decode_utf16(field(i, 2)).unwrap();
+        assert_eq!("ws", string(0));
+        assert_eq!("me", string(1));
+        let password: Vec<u8> = field(2, 2)
+            .iter()
+            .map(|b| (b ^ 0xA5).rotate_left(4))
+            .collect();
+        assert_eq!("pässword", decode_utf16(&password).unwrap());
+        assert_eq!("myserver", string(4));
+        assert_eq!("db", string(8));
+        // The extension gives the offset of the feature extensions
+        let feature_ext_offset = u32::from_le_bytes(field(5, 1).try_into().unwrap()) as usize;
+        assert_eq!(
+            &[
+                TdsEnums::FEATUREEXT_UTF8SUPPORT,
+                0,
+                0,
+                0,
+                0,
+                TdsEnums::FEATUREEXT_TERMINATOR
+            ],
+            &message[feature_ext_offset..]
+        );
+    }
+
+    #[rstest::rstest]
+    #[case(None, "cafÃ©")]
+    #[case(Some(0x00), "cafÃ©")]
+    #[case(Some(0x01), "café")]
+    #[tokio::test]
+    async fn test_utf8_support(#[case] ack: Option<u8>, #[case] expected: &str) {
+        // Latin1_General_100_CI_AS_SC_UTF8
+        let type_info = TypeInfo {
+            tds_type: TdsEnums::SQLBIGVARCHAR,
+            max_length: 100,
+            collation: Some(SqlCollation::new(0x04E0_0409, 0)),
+            ..Default::default()
+        };
+        let mut tokens = TokenBuilder::new();
+        if let Some(ack) = ack {
+            tokens = tokens.feature_ext_ack(TdsEnums::FEATUREEXT_UTF8SUPPORT, &[ack]);
+        }
+        let tokens = tokens
+            .col_metadata_with_types(&[("name", type_info)])
+            .row(&[SqlValue::from("café")]);
+        let mut parser = parser(&tokens);
+        if ack.is_some() {
+            assert_eq!(
+                TdsToken::Other(TdsEnums::SQLFEATUREEXTACK),
+                parser.next_token().await.unwrap()
+            );
+        }
+        assert_eq!(ack == Some(0x01), parser.utf8_support());
+        assert_eq!(TdsToken::ColMetaData, parser.next_token().await.unwrap());
+        assert_eq!(TdsToken::Row, parser.next_token().await.unwrap());
+        assert_eq!(
+            vec![SqlValue::from(expected)],
+            parser.read_row(false).await.unwrap()
+        );
+    }
+}
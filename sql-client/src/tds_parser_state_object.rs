@@ -0,0 +1,483 @@
+use crate::tds_enums::TdsEnums;
+use crate::SqlClientError;
+use tokio::io::{AsyncRead, AsyncReadExt, AsyncWrite, AsyncWriteExt};
+
+/// A byte stream that TDS packets can be sent over (e.g. a TCP or TLS stream).
+pub(crate) trait TdsStream: AsyncRead + AsyncWrite + Unpin + Send {}
+impl<T: AsyncRead + AsyncWrite + Unpin + Send> TdsStream for T {}
+
+/// Sends and receives TDS packets.
+///
+/// Outgoing messages are split into packets of the negotiated size and incoming packets are
+/// presented as one continuous stream of bytes, so that callers don't need to care where the packet
+/// boundaries fall.
+pub(crate) struct TdsParserStateObject {
+    /// The underlying stream.
+    stream: Box<dyn TdsStream>,
+    /// The negotiated packet size, including the header.
+    packet_size: usize,
+    /// The payload of the packet currently being read.
+    in_buffer: Vec<u8>,
+    /// The read position within the current packet.
+    in_position: usize,
+    /// Whether the packet currently being read is the last packet of its message.
+    in_message_end: bool,
+    /// The type of the message currently being written.
+    out_packet_type: u8,
+    /// The unsent payload of the message currently being written.
+    out_buffer: Vec<u8>,
+    /// The number of the next packet to be written.
+    out_packet_number: u8,
+    /// Whether the first packet of the current message has been sent.
+    out_message_started: bool,
+    /// Whether the next message should ask the server to reset the connection.
+    reset_connection: bool,
+    /// Whether the stream has failed (or been left in a state it can't recover from), so that the
+    /// connection can't be used again.
+    broken: bool,
+    /// Whether a read or write of the stream is under way, so that one that's cancelled (e.g. by
+    /// a timeout) part way through leaves the connection broken.
+    io_pending: bool,
+}
+
+impl TdsParserStateObject {
+    /// Creates a new state object over a stream.
+    pub fn new(stream: Box<dyn TdsStream>, packet_size: usize) -> Self {
+        Self {
+            stream,
+            packet_size,
+            in_buffer: Vec::new(),
+            in_position: 0,
+            in_message_end: true,
+            out_packet_type: 0,
+            out_buffer: Vec::new(),
+            out_packet_number: 1,
+            out_message_started: false,
+            reset_connection: false,
+            broken: false,
+            io_pending: false,
+        }
+    }
+
+    /// The negotiated packet size.
+    pub fn packet_size(&self) -> usize {
+        self.packet_size
+    }
+
+    /// Sets the packet size (e.g. after the server has sent a packet size ENVCHANGE).
+    pub fn set_packet_size(&mut self, value: usize) {
+        self.packet_size = value;
+    }
+
+    /// Asks the server to reset the connection when it receives the next message.
+    pub fn set_reset_connection(&mut self, value: bool) {
+        self.reset_connection = value;
+    }
+
+    /// Whether the stream has failed, so that the connection can't be used again.
+    pub fn is_broken(&self) -> bool {
+        self.broken || self.io_pending
+    }
+
+    /// Marks the stream as failed (e.g. after a fatal error from the server).
+    pub fn set_broken(&mut self) {
+        self.broken = true;
+    }
+
+    /// Marks the stream as failed if an I/O operation on it failed.
+    fn check_io<T>(&mut self, result: std::io::Result<T>) -> Result<T, SqlClientError> {
+        self.io_pending = false;
+        result.map_err(|error| {
+            self.broken = true;
+            error.into()
+        })
+    }
+
+    /// Whether all the packets of the message being read have been consumed.
+    pub fn is_message_complete(&self) -> bool {
+        self.in_message_end && self.in_position == self.in_buffer.len()
+    }
+
+    /// Starts a new outgoing message of the given type.
+    pub fn start_message(&mut self, packet_type: u8) {
+        self.out_packet_type = packet_type;
+        self.out_buffer.clear();
+        self.out_packet_number = 1;
+        self.out_message_started = false;
+    }
+
+    /// Adds data to the current outgoing message, sending packets as they fill up.
+    pub async fn write(&mut self, data: &[u8]) -> Result<(), SqlClientError> {
+        // Add the data to the buffer
+        self.out_buffer.extend_from_slice(data);
+        // Send as many full packets as we can.  A full packet is only sent when there's more data
+        // after it, so that the final packet of the message can be flagged as such.
+        let payload_size = self.packet_size - TdsEnums::HEADER_LEN;
+        while self.out_buffer.len() > payload_size {
+            self.write_packet(payload_size, 0).await?;
+        }
+        Ok(())
+    }
+
+    /// Sends whatever remains of the current outgoing message, flagged as the end of the message.
+    pub async fn end_message(&mut self) -> Result<(), SqlClientError> {
+        self.write_packet(self.out_buffer.len(), TdsEnums::ST_EOM)
+            .await?;
+        self.io_pending = true;
+        let result = self.stream.flush().await;
+        self.check_io(result)
+    }
+
+    /// Abandons the current outgoing message.  If some of it has already been sent, the rest is
+    /// sent flagged so that the server ignores the whole message (and doesn't respond to it).
+    pub async fn abort_message(&mut self) -> Result<(), SqlClientError> {
+        if !self.out_message_started {
+            self.out_buffer.clear();
+            return Ok(());
+        }
+        self.write_packet(
+            self.out_buffer.len(),
+            TdsEnums::ST_EOM | TdsEnums::ST_IGNORE,
+        )
+        .await?;
+        self.io_pending = true;
+        let result = self.stream.flush().await;
+        self.check_io(result)
+    }
+
+    /// Sends a complete message.
+    pub async fn write_message(
+        &mut self,
+        packet_type: u8,
+        payload: &[u8],
+    ) -> Result<(), SqlClientError> {
+        self.start_message(packet_type);
+        self.write(payload).await?;
+        self.end_message().await
+    }
+
+    /// Sends one packet holding the first `length` bytes of the output buffer, with the given status
+    /// flags.
+    async fn write_packet(&mut self, length: usize, status: u8) -> Result<(), SqlClientError> {
+        let mut status = status;
+        // The reset flag goes on the first packet of the message only.
+        if !self.out_message_started && self.reset_connection {
+            status |= TdsEnums::ST_RESET_CONNECTION;
+            self.reset_connection = false;
+        }
+        // Build the packet
+        let total_length = (length + TdsEnums::HEADER_LEN) as u16;
+        let mut packet = Vec::with_capacity(length + TdsEnums::HEADER_LEN);
+        packet.push(self.out_packet_type);
+        packet.push(status);
+        packet.extend_from_slice(&total_length.to_be_bytes());
+        // The SPID is only informational when sent by the client
+        packet.extend_from_slice(&[0, 0]);
+        packet.push(self.out_packet_number);
+        // Window, which is unused
+        packet.push(0);
+        packet.extend(self.out_buffer.drain(..length));
+        log::trace!(
+            "write_packet - type: {}, status: {}, length: {}",
+            self.out_packet_type,
+            status,
+            total_length
+        );
+        // Send it
+        self.io_pending = true;
+        let result = self.stream.write_all(&packet).await;
+        self.check_io(result)?;
+        self.out_packet_number = self.out_packet_number.wrapping_add(1);
+        self.out_message_started = true;
+        Ok(())
+    }
+
+    /// Reads the next packet into the input buffer.
+    async fn read_packet(&mut self) -> Result<(), SqlClientError> {
+        // Read the header
+        let mut header = [0u8; TdsEnums::HEADER_LEN];
+        self.io_pending = true;
+        let result = self.stream.read_exact(&mut header).await;
+        self.check_io(result)?;
+        let status = header[1];
+        let length = u16::from_be_bytes([header[2], header[3]]) as usize;
+        log::trace!(
+            "read_packet - type: {}, status: {}, length: {}",
+            header[0],
+            status,
+            length
+        );
+        if length < TdsEnums::HEADER_LEN {
+            self.broken = true;
+            return Err(SqlClientError::Protocol(format!(
+                "Invalid packet length {}",
+                length
+            )));
+        }
+        // Read the payload
+        self.in_buffer.resize(length - TdsEnums::HEADER_LEN, 0);
+        self.io_pending = true;
+        let result = self.stream.read_exact(&mut self.in_buffer).await;
+        self.check_io(result)?;
+        self.in_position = 0;
+        self.in_message_end = status & TdsEnums::ST_EOM != 0;
+        Ok(())
+    }
+
+    /// Fills the buffer with the next bytes of the incoming stream, reading more packets as needed.
+    pub async fn read_exact(&mut self, buffer: &mut [u8]) -> Result<(), SqlClientError> {
+        let mut filled = 0;
+        while filled < buffer.len() {
+            // If we've used up the current packet, get the next one.
+            if self.in_position == self.in_buffer.len() {
+                self.read_packet().await?;
+                continue;
+            }
+            // Copy what we can from the current packet
+            let count = (buffer.len() - filled).min(self.in_buffer.len() - self.in_position);
+            buffer[filled..filled + count]
+                .copy_from_slice(&self.in_buffer[self.in_position..self.in_position + count]);
+            self.in_position += count;
+            filled += count;
+        }
+        Ok(())
+    }
+
+    /// Reads the whole of the next incoming message (e.g. a PRELOGIN response) and returns its
+    /// payload.
+    pub async fn read_message(&mut self) -> Result<Vec<u8>, SqlClientError> {
+        let mut payload = Vec::new();
+        loop {
+            self.read_packet().await?;
+            payload.extend_from_slice(&self.in_buffer);
+            self.in_position = self.in_buffer.len();
+            if self.in_message_end {
+                return Ok(payload);
+            }
+        }
+    }
+
+    /// Gives back the underlying stream (e.g. to start TLS on it).
+    pub fn into_stream(self) -> Box<dyn TdsStream> {
+        self.stream
+    }
+
+    /// Reads a fixed number of bytes.
+    async fn read_array<const N: usize>(&mut self) -> Result<[u8; N], SqlClientError> {
+        let mut buffer = [0u8; N];
+        self.read_exact(&mut buffer).await?;
+        Ok(buffer)
+    }
+
+    /// Reads a number of bytes.
+    pub async fn read_bytes(&mut self, length: usize) -> Result<Vec<u8>, SqlClientError> {
+        let mut buffer = vec![0u8; length];
+        self.read_exact(&mut buffer).await?;
+        Ok(buffer)
+    }
+
+    /// Skips a number of bytes.
+    pub async fn skip(&mut self, length: usize) -> Result<(), SqlClientError> {
+        let mut remaining = length;
+        while remaining > 0 {
+            if self.in_position == self.in_buffer.len() {
+                self.read_packet().await?;
+                continue;
+            }
+            let count = remaining.min(self.in_buffer.len() - self.in_position);
+            self.in_position += count;
+            remaining -= count;
+        }
+        Ok(())
+    }
+
+    /// Reads a byte.
+    pub async fn read_u8(&mut self) -> Result<u8, SqlClientError> {
+        Ok(self.read_array::<1>().await?[0])
+    }
+
+    /// Reads a little-endian u16.
+    pub async fn read_u16(&mut self) -> Result<u16, SqlClientError> {
+        Ok(u16::from_le_bytes(self.read_array().await?))
+    }
+
+    /// Reads a little-endian i16.
+    pub async fn read_i16(&mut self) -> Result<i16, SqlClientError> {
+        Ok(i16::from_le_bytes(self.read_array().await?))
+    }
+
+    /// Reads a little-endian u32.
+    pub async fn read_u32(&mut self) -> Result<u32, SqlClientError> {
+        Ok(u32::from_le_bytes(self.read_array().await?))
+    }
+
+    /// Reads a little-endian i32.
+    pub async fn read_i32(&mut self) -> Result<i32, SqlClientError> {
+        Ok(i32::from_le_bytes(self.read_array().await?))
+    }
+
+    /// Reads a little-endian u64.
+    pub async fn read_u64(&mut self) -> Result<u64, SqlClientError> {
+        Ok(u64::from_le_bytes(self.read_array().await?))
+    }
+
+    /// Reads a string with a one-byte character count (B_VARCHAR).
+    pub async fn read_b_varchar(&mut self) -> Result<String, SqlClientError> {
+        let length = self.read_u8().await? as usize;
+        let bytes = self.read_bytes(length * 2).await?;
+        decode_utf16(&bytes)
+    }
+
+    /// Reads a string with a two-byte character count (US_VARCHAR).
+    pub async fn read_us_varchar(&mut self) -> Result<String, SqlClientError> {
+        let length = self.read_u16().await? as usize;
+        let bytes = self.read_bytes(length * 2).await?;
+        decode_utf16(&bytes)
+    }
+}
+
+/// Decodes little-endian UTF-16 bytes into a string.
+pub(crate) fn decode_utf16(bytes: &[u8]) -> Result<String, SqlClientError> {
+    if !bytes.len().is_multiple_of(2) {
+        return Err(SqlClientError::Protocol(
+            "UTF-16 data had an odd number of bytes".to_string(),
+        ));
+    }
+    let units: Vec<u16> = bytes
+        .chunks_exact(2)
+        .map(|pair| u16::from_le_bytes([pair[0], pair[1]]))
+        .collect();
+    String::from_utf16(&units).map_err(|e| SqlClientError::Protocol(e.to_string()))
+}
+
+/// Encodes a string as little-endian UTF-16 bytes.
+pub(crate) fn encode_utf16(value: &str) -> Vec<u8> {
+    value
+        .encode_utf16()
+        .flat_map(|unit| unit.to_le_bytes())
+        .collect()
+}
+
+/// Writes a string with a one-byte character count (B_VARCHAR).
+pub(crate) fn write_b_varchar(buffer: &mut Vec<u8>, value: &str) {
+    let bytes = encode_utf16(value);
+    buffer.push((bytes.len() / 2) as u8);
+    buffer.extend_from_slice(&bytes);
+}
+
+/// Writes a string with a two-byte character count (US_VARCHAR).
+pub(crate) fn write_us_varchar(buffer: &mut Vec<u8>, value: &str) {
+    let bytes = encode_utf16(value);
+    buffer.extend_from_slice(&((bytes.len() / 2) as u16).to_le_bytes());
+    buffer.extend_from_slice(&bytes);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tds_test_utils::{packet, split_into_packets};
+    use test_utils::MockStream;
+
+    #[tokio::test]
+    async fn test_read_across_packets() {
+        // Split a message into tiny packets so every read crosses a packet boundary.
+        let data = vec![0x01, 0x02, 0x03, 0x04, 0x05, 0x06, 0x07];
+        let stream = MockStream::new(split_into_packets(&data, 3));
+        let mut subject = TdsParserStateObject::new(Box::new(stream), 4096);
+        assert_eq!(0x0201, subject.read_u16().await.unwrap());
+        assert_eq!(0x06050403, subject.read_u32().await.unwrap());
+        assert!(!subject.is_message_complete());
+        assert_eq!(0x07, subject.read_u8().await.unwrap());
+        assert!(subject.is_message_complete());
+    }
+
+    #[tokio::test]
+    async fn test_read_strings() {
+        let mut data = Vec::new();
+        write_b_varchar(&mut data, "abc");
+        write_us_varchar(&mut data, "défg");
+        let stream = MockStream::new(packet(&data));
+        let mut subject = TdsParserStateObject::new(Box::new(stream), 4096);
+        assert_eq!("abc", subject.read_b_varchar().await.unwrap());
+        assert_eq!("défg", subject.read_us_varchar().await.unwrap());
+    }
+
+    #[tokio::test]
+    async fn test_read_past_end() {
+        let stream = MockStream::new(packet(&[0x01]));
+        let mut subject = TdsParserStateObject::new(Box::new(stream), 4096);
+        assert!(matches!(
+            subject.read_u16().await,
+            Err(SqlClientError::Io(_, _))
+        ));
+        assert!(subject.is_broken());
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_cancelled_read() {
+        // The server never answers, so the read is cancelled part way through.
+        let (client, _server) = tokio::io::duplex(1024);
+        let mut subject = TdsParserStateObject::new(Box::new(client), 4096);
+        let read = tokio::time::timeout(std::time::Duration::from_secs(1), subject.read_u8());
+        assert!(read.await.is_err());
+        assert!(subject.is_broken());
+    }
+
+    #[tokio::test]
+    async fn test_read_message() {
+        let mut data = split_into_packets(&[1, 2, 3, 4, 5], 2);
+        data.extend(packet(&[6]));
+        let stream = MockStream::new(data);
+        let mut subject = TdsParserStateObject::new(Box::new(stream), 4096);
+        assert_eq!(vec![1, 2, 3, 4, 5], subject.read_message().await.unwrap());
+        assert!(subject.is_message_complete());
+        assert_eq!(vec![6], subject.read_message().await.unwrap());
+    }
+
+    #[tokio::test]
+    async fn test_write_message_splits_packets() {
+        let stream = MockStream::new(Vec::new());
+        let written = stream.written();
+        // A packet size of 12 leaves room for 4 bytes of payload per packet.
+        let mut subject = TdsParserStateObject::new(Box::new(stream), 12);
+        subject.set_reset_connection(true);
+        subject
+            .write_message(TdsEnums::MT_SQL, &[1, 2, 3, 4, 5, 6])
+            .await
+            .unwrap();
+        let written = written.lock().unwrap().clone();
+        assert_eq!(
+            vec![
+                // First packet: not the end of the message, resets the connection
+                0x01, 0x08, 0x00, 0x0C, 0x00, 0x00, 0x01, 0x00, 1, 2, 3, 4,
+                // Second packet: end of message
+                0x01, 0x01, 0x00, 0x0A, 0x00, 0x00, 0x02, 0x00, 5, 6,
+            ],
+            written
+        );
+    }
+
+    #[tokio::test]
+    async fn test_abort_message() {
+        let stream = MockStream::new(Vec::new());
+        let written = stream.written();
+        let mut subject = TdsParserStateObject::new(Box::new(stream), 12);
+        // Nothing has been sent, so nothing needs to be
+        subject.start_message(TdsEnums::MT_RPC);
+        subject.write(&[1, 2]).await.unwrap();
+        subject.abort_message().await.unwrap();
+        assert!(written.lock().unwrap().is_empty());
+        // Once a packet has gone, the rest is sent flagged to be ignored
+        subject.start_message(TdsEnums::MT_RPC);
+        subject.write(&[1, 2, 3, 4, 5]).await.unwrap();
+        subject.abort_message().await.unwrap();
+        let written = written.lock().unwrap().clone();
+        assert_eq!(
+            vec![
+                0x03, 0x00, 0x00, 0x0C, 0x00, 0x00, 0x01, 0x00, 1, 2, 3, 4, //
+                0x03, 0x03, 0x00, 0x09, 0x00, 0x00, 0x02, 0x00, 5,
+            ],
+            written
+        );
+    }
+}
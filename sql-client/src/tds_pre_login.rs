@@ -0,0 +1,106 @@
+use crate::tds_enums::TdsEnums;
+use crate::SqlClientError;
+
+/// The options exchanged in the PRELOGIN handshake that starts a connection, before the login.
+#[derive(PartialEq, Debug, Clone)]
+pub(crate) struct TdsPreLogin {
+    /// The encryption option (e.g. [TdsEnums::ENCRYPT_ON]).
+    encryption: u8,
+}
+
+impl TdsPreLogin {
+    /// The client version that we report (major, minor, build, sub-build).
+    const CLIENT_VERSION: [u8; 6] = [0, 1, 0, 0, 0, 0];
+
+    /// Creates the options with the given encryption option.
+    pub fn new(encryption: u8) -> Self {
+        Self { encryption }
+    }
+
+    /// The encryption option.
+    pub fn encryption(&self) -> u8 {
+        self.encryption
+    }
+
+    /// Encodes the options as the payload of a PRELOGIN message.
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let options: [(u8, Vec<u8>); 5] = [
+            (TdsEnums::PRELOGIN_VERSION, Self::CLIENT_VERSION.to_vec()),
+            (TdsEnums::PRELOGIN_ENCRYPTION, vec![self.encryption]),
+            // The default instance, as an empty null-terminated string
+            (TdsEnums::PRELOGIN_INSTOPT, vec![0]),
+            (
+                TdsEnums::PRELOGIN_THREADID,
+                std::process::id().to_be_bytes().to_vec(),
+            ),
+            // No MARS
+            (TdsEnums::PRELOGIN_MARS, vec![0]),
+        ];
+        // Each option is a token, the offset of its data and its length, followed by a terminator
+        // and then the data.
+        let mut offset = options.len() * 5 + 1;
+        let mut bytes = Vec::new();
+        for (token, data) in &options {
+            bytes.push(*token);
+            bytes.extend_from_slice(&(offset as u16).to_be_bytes());
+            bytes.extend_from_slice(&(data.len() as u16).to_be_bytes());
+            offset += data.len();
+        }
+        bytes.push(TdsEnums::PRELOGIN_TERMINATOR);
+        for (_, data) in options {
+            bytes.extend(data);
+        }
+        bytes
+    }
+
+    /// Decodes the payload of the server's PRELOGIN response.
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self, SqlClientError> {
+        let invalid = || SqlClientError::Protocol("Invalid PRELOGIN response".to_string());
+        let mut encryption = None;
+        let mut position = 0;
+        loop {
+            let token = *bytes.get(position).ok_or_else(invalid)?;
+            if token == TdsEnums::PRELOGIN_TERMINATOR {
+                break;
+            }
+            let header = bytes.get(position + 1..position + 5).ok_or_else(invalid)?;
+            let offset = u16::from_be_bytes([header[0], header[1]]) as usize;
+            let length = u16::from_be_bytes([header[2], header[3]]) as usize;
+            let data = bytes.get(offset..offset + length).ok_or_else(invalid)?;
+            if token == TdsEnums::PRELOGIN_ENCRYPTION {
+                encryption = Some(*data.first().ok_or_else(invalid)?);
+            }
+            position += 5;
+        }
+        Ok(Self {
+            encryption: encryption.ok_or_else(invalid)?,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_round_trip() {
+        let pre_login = TdsPreLogin::new(TdsEnums::ENCRYPT_ON);
+        let bytes = pre_login.to_bytes();
+        // Five options and a terminator, then the data
+        assert_eq!(TdsEnums::PRELOGIN_TERMINATOR, bytes[25]);
+        assert_eq!(26 + 6 + 1 + 1 + 4 + 1, bytes.len());
+        assert_eq!(pre_login, TdsPreLogin::from_bytes(&bytes).unwrap());
+    }
+
+    #[rstest::rstest]
+    #[case::empty(&[])]
+    #[case::no_terminator(&[TdsEnums::PRELOGIN_ENCRYPTION, 0, 5, 0, 1, 0])]
+    #[case::past_end(&[TdsEnums::PRELOGIN_ENCRYPTION, 0, 6, 0, 1, 0xFF])]
+    #[case::no_encryption(&[0xFF])]
+    fn test_invalid(#[case] bytes: &[u8]) {
+        assert!(matches!(
+            TdsPreLogin::from_bytes(bytes),
+            Err(SqlClientError::Protocol(_))
+        ));
+    }
+}
@@ -0,0 +1,313 @@
+//! Helpers for building server responses, and playing the server's side, in tests.
+use crate::ssl_over_tds_stream::SslOverTdsStream;
+use crate::tds_enums::TdsEnums;
+use crate::tds_parser_state_object::{write_b_varchar, write_us_varchar};
+use crate::tds_pre_login::TdsPreLogin;
+use crate::tds_type_info::TypeInfo;
+use crate::tds_value::write_value;
+use crate::{SqlDbType, SqlValue};
+use std::sync::Arc;
+use tokio::io::{AsyncRead, AsyncReadExt, AsyncWrite, AsyncWriteExt};
+use tokio_rustls::rustls::crypto;
+use tokio_rustls::rustls::pki_types::PrivateKeyDer;
+use tokio_rustls::rustls::{version, ServerConfig};
+use tokio_rustls::TlsAcceptor;
+
+/// Splits a message into packets with the given payload size.
+pub(crate) fn split_into_packets(data: &[u8], payload_size: usize) -> Vec<u8> {
+    let mut result = Vec::new();
+    let chunks: Vec<&[u8]> = if data.is_empty() {
+        vec![data]
+    } else {
+        data.chunks(payload_size).collect()
+    };
+    let count = chunks.len();
+    for (i, chunk) in chunks.into_iter().enumerate() {
+        let status = if i == count - 1 { TdsEnums::ST_EOM } else { 0 };
+        result.push(TdsEnums::MT_TOKENS);
+        result.push(status);
+        result.extend_from_slice(&((chunk.len() + TdsEnums::HEADER_LEN) as u16).to_be_bytes());
+        result.extend_from_slice(&[0, 0, (i + 1) as u8, 0]);
+        result.extend_from_slice(chunk);
+    }
+    result
+}
+
+/// Wraps a message in packets of the default size.
+pub(crate) fn packet(data: &[u8]) -> Vec<u8> {
+    split_into_packets(data, TdsEnums::DEFAULT_PACKET_SIZE - TdsEnums::HEADER_LEN)
+}
+
+/// Builds a stream of response tokens.
+#[derive(Default)]
+pub(crate) struct TokenBuilder {
+    /// The tokens so far.
+    bytes: Vec<u8>,
+    /// The types of the current result set's columns.
+    columns: Vec<TypeInfo>,
+}
+
+impl TokenBuilder {
+    /// Creates an empty builder.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// The tokens.
+    pub fn bytes(&self) -> &[u8] {
+        &self.bytes
+    }
+
+    /// The tokens wrapped in packets.
+    pub fn packets(&self) -> Vec<u8> {
+        packet(&self.bytes)
+    }
+
+    /// The TYPE_INFO used for columns and return values of a type.
+    pub fn type_info(sql_db_type: SqlDbType) -> TypeInfo {
+        TypeInfo::for_parameter(sql_db_type, 0, 0, 18, 2, None).unwrap()
+    }
+
+    /// Adds a COLMETADATA token.
+    pub fn col_metadata(self, columns: &[(&str, SqlDbType)]) -> Self {
+        let columns: Vec<(&str, TypeInfo)> = columns
+            .iter()
+            .map(|(name, sql_db_type)| (*name, Self::type_info(*sql_db_type)))
+            .collect();
+        self.col_metadata_with_types(&columns)
+    }
+
+    /// Adds a COLMETADATA token with explicit column types.
+    pub fn col_metadata_with_types(mut self, columns: &[(&str, TypeInfo)]) -> Self {
+        self.bytes.push(TdsEnums::SQLCOLMETADATA);
+        self.bytes
+            .extend_from_slice(&(columns.len() as u16).to_le_bytes());
+        self.columns.clear();
+        for (name, type_info) in columns {
+            // User type, then flags (nullable)
+            self.bytes.extend_from_slice(&0u32.to_le_bytes());
+            self.bytes.extend_from_slice(&1u16.to_le_bytes());
+            type_info.write(&mut self.bytes);
+            write_b_varchar(&mut self.bytes, name);
+            self.columns.push(type_info.clone());
+        }
+        self
+    }
+
+    /// Adds a FEATUREEXTACK token acknowledging a single feature.
+    pub fn feature_ext_ack(mut self, feature_id: u8, data: &[u8]) -> Self {
+        self.bytes.push(TdsEnums::SQLFEATUREEXTACK);
+        self.bytes.push(feature_id);
+        self.bytes
+            .extend_from_slice(&(data.len() as u32).to_le_bytes());
+        self.bytes.extend_from_slice(data);
+        self.bytes.push(TdsEnums::FEATUREEXT_TERMINATOR);
+        self
+    }
+
+    /// Adds raw bytes (e.g. a row with a value split into several PLP chunks).
+    pub fn raw(mut self, bytes: &[u8]) -> Self {
+        self.bytes.extend_from_slice(bytes);
+        self
+    }
+
+    /// Adds a ROW token.
+    pub fn row(mut self, values: &[SqlValue]) -> Self {
+        self.bytes.push(TdsEnums::SQLROW);
+        for (type_info, value) in self.columns.iter().zip(values) {
+            write_value(&mut self.bytes, type_info, value).unwrap();
+        }
+        self
+    }
+
+    /// Adds an NBCROW token.
+    pub fn nbc_row(mut self, values: &[SqlValue]) -> Self {
+        self.bytes.push(TdsEnums::SQLNBCROW);
+        let mut bitmap = vec![0u8; values.len().div_ceil(8)];
+        for (i, value) in values.iter().enumerate() {
+            if value.is_null() {
+                bitmap[i / 8] |= 1 << (i % 8);
+            }
+        }
+        self.bytes.extend(bitmap);
+        for (type_info, value) in self.columns.iter().zip(values) {
+            if !value.is_null() {
+                write_value(&mut self.bytes, type_info, value).unwrap();
+            }
+        }
+        self
+    }
+
+    /// Adds a DONE, DONEPROC or DONEINPROC token.
+    pub fn done(mut self, token: u8, status: u16, cur_cmd: u16, row_count: u64) -> Self {
+        self.bytes.push(token);
+        self.bytes.extend_from_slice(&status.to_le_bytes());
+        self.bytes.extend_from_slice(&cur_cmd.to_le_bytes());
+        self.bytes.extend_from_slice(&row_count.to_le_bytes());
+        self
+    }
+
+    /// Adds a RETURNSTATUS token.
+    pub fn return_status(mut self, value: i32) -> Self {
+        self.bytes.push(TdsEnums::SQLRETURNSTATUS);
+        self.bytes.extend_from_slice(&value.to_le_bytes());
+        self
+    }
+
+    /// Adds a RETURNVALUE token.
+    pub fn return_value(
+        mut self,
+        ordinal: u16,
+        name: &str,
+        status: u8,
+        type_info: &TypeInfo,
+        value: &SqlValue,
+    ) -> Self {
+        self.bytes.push(TdsEnums::SQLRETURNVALUE);
+        self.bytes.extend_from_slice(&ordinal.to_le_bytes());
+        write_b_varchar(&mut self.bytes, name);
+        self.bytes.push(status);
+        self.bytes.extend_from_slice(&0u32.to_le_bytes());
+        self.bytes.extend_from_slice(&1u16.to_le_bytes());
+        type_info.write(&mut self.bytes);
+        write_value(&mut self.bytes, type_info, value).unwrap();
+        self
+    }
+
+    /// Adds an ERROR or INFO token.
+    pub fn error(self, token: u8, number: i32, class: u8, message: &str) -> Self {
+        self.error_with_state(token, number, 1, class, message)
+    }
+
+    /// Adds an ERROR or INFO token with a specific state.
+    pub fn error_with_state(
+        mut self,
+        token: u8,
+        number: i32,
+        state: u8,
+        class: u8,
+        message: &str,
+    ) -> Self {
+        let mut body = Vec::new();
+        body.extend_from_slice(&number.to_le_bytes());
+        body.push(state);
+        body.push(class);
+        write_us_varchar(&mut body, message);
+        write_b_varchar(&mut body, "server");
+        write_b_varchar(&mut body, "");
+        body.extend_from_slice(&1i32.to_le_bytes());
+        self.bytes.push(token);
+        self.bytes
+            .extend_from_slice(&(body.len() as u16).to_le_bytes());
+        self.bytes.extend(body);
+        self
+    }
+
+    /// Adds an ENVCHANGE token.
+    pub fn env_change(mut self, env_type: u8, new_value: &[u8], old_value: &[u8]) -> Self {
+        self.bytes.push(TdsEnums::SQLENVCHANGE);
+        let length = 3 + new_value.len() + old_value.len();
+        self.bytes.extend_from_slice(&(length as u16).to_le_bytes());
+        self.bytes.push(env_type);
+        self.bytes.push(new_value.len() as u8);
+        self.bytes.extend_from_slice(new_value);
+        self.bytes.push(old_value.len() as u8);
+        self.bytes.extend_from_slice(old_value);
+        self
+    }
+
+    /// Adds an ENVCHANGE token that begins a transaction.
+    pub fn env_change_begin_transaction(self, descriptor: u64) -> Self {
+        self.env_change(TdsEnums::ENV_BEGINTRAN, &descriptor.to_le_bytes(), &[])
+    }
+
+    /// Adds a LOGINACK token.
+    pub fn login_ack(mut self) -> Self {
+        // The SQL interface, the TDS version, the program name and the program version
+        let mut data = vec![1];
+        data.extend_from_slice(&TdsEnums::TDS_VERSION.to_be_bytes());
+        write_b_varchar(&mut data, "Microsoft SQL Server");
+        data.extend_from_slice(&[16, 0, 0, 0]);
+        self.bytes.push(TdsEnums::SQLLOGINACK);
+        self.bytes
+            .extend_from_slice(&(data.len() as u16).to_le_bytes());
+        self.bytes.extend(data);
+        self
+    }
+}
+
+/// A TLS acceptor with a self-signed certificate for "localhost".  Like SQL Server with TDS 7, it
+/// only uses TLS 1.2.
+pub(crate) fn tls_acceptor() -> TlsAcceptor {
+    let certified_key = rcgen::generate_simple_self_signed(vec!["localhost".to_string()]).unwrap();
+    let key = PrivateKeyDer::Pkcs8(certified_key.signing_key.serialize_der().into());
+    let config = ServerConfig::builder_with_provider(Arc::new(crypto::ring::default_provider()))
+        .with_protocol_versions(&[&version::TLS12])
+        .unwrap()
+        .with_no_client_auth()
+        .with_single_cert(vec![certified_key.cert.der().clone()], key)
+        .unwrap();
+    TlsAcceptor::from(Arc::new(config))
+}
+
+/// Reads a whole message sent to the server and returns its payload.
+pub(crate) async fn read_message<S: AsyncRead + Unpin>(stream: &mut S) -> Vec<u8> {
+    let mut payload = Vec::new();
+    loop {
+        let mut header = [0u8; TdsEnums::HEADER_LEN];
+        stream.read_exact(&mut header).await.unwrap();
+        let length = u16::from_be_bytes([header[2], header[3]]) as usize;
+        let start = payload.len();
+        payload.resize(start + length - TdsEnums::HEADER_LEN, 0);
+        stream.read_exact(&mut payload[start..]).await.unwrap();
+        if header[1] & TdsEnums::ST_EOM != 0 {
+            return payload;
+        }
+    }
+}
+
+/// Answers a PRELOGIN with an encryption option, and returns the client's encryption option.
+pub(crate) async fn serve_pre_login<S: AsyncRead + AsyncWrite + Unpin>(
+    stream: &mut S,
+    encryption: u8,
+) -> u8 {
+    let client_encryption = TdsPreLogin::from_bytes(&read_message(stream).await)
+        .unwrap()
+        .encryption();
+    stream
+        .write_all(&packet(&TdsPreLogin::new(encryption).to_bytes()))
+        .await
+        .unwrap();
+    client_encryption
+}
+
+/// Plays the server's side of a login: answers the PRELOGIN with an encryption option, does the
+/// TLS handshake if given an acceptor, and answers the LOGIN7 with the given tokens (encrypted
+/// unless both sides only encrypt the login).  Returns the LOGIN7 payload.
+pub(crate) async fn serve_login<S: AsyncRead + AsyncWrite + Unpin>(
+    mut stream: S,
+    encryption: u8,
+    acceptor: Option<TlsAcceptor>,
+    response: &TokenBuilder,
+) -> Vec<u8> {
+    let client_encryption = serve_pre_login(&mut stream, encryption).await;
+    let Some(acceptor) = acceptor else {
+        let login = read_message(&mut stream).await;
+        stream.write_all(&response.packets()).await.unwrap();
+        return login;
+    };
+    let mut tls = acceptor
+        .accept(SslOverTdsStream::new(stream))
+        .await
+        .unwrap();
+    tls.get_mut().0.finish_handshake();
+    let login = read_message(&mut tls).await;
+    if client_encryption == TdsEnums::ENCRYPT_OFF && encryption == TdsEnums::ENCRYPT_OFF {
+        let (mut stream, _) = tls.into_inner();
+        stream.write_all(&response.packets()).await.unwrap();
+    } else {
+        tls.write_all(&response.packets()).await.unwrap();
+        tls.flush().await.unwrap();
+    }
+    login
+}
@@ -0,0 +1,125 @@
+use crate::sql_error::SqlError;
+use crate::tds_enums::TdsEnums;
+use crate::tds_type_info::TypeInfo;
+use crate::SqlValue;
+
+/// The metadata of a result set column (part of a COLMETADATA token).
+#[derive(PartialEq, Debug, Clone)]
+pub(crate) struct ColumnMetaData {
+    /// The user type ID of the column.
+    pub user_type: u32,
+    /// The column flags (nullable, identity, etc.).
+    pub flags: u16,
+    /// The column's type.
+    pub type_info: TypeInfo,
+    /// The column's name.
+    pub column_name: String,
+}
+
+impl ColumnMetaData {
+    /// The flag set when a column is nullable.
+    pub const FLAG_NULLABLE: u16 = 0x0001;
+    /// The flag set when a column is an identity column.
+    pub const FLAG_IDENTITY: u16 = 0x0010;
+    /// The flag set when a column is computed.
+    pub const FLAG_COMPUTED: u16 = 0x0020;
+    /// The user type of a timestamp (rowversion) column.
+    pub const USER_TYPE_TIMESTAMP: u32 = 0x50;
+
+    /// Whether the column can hold nulls.
+    pub fn is_nullable(&self) -> bool {
+        self.flags & Self::FLAG_NULLABLE != 0
+    }
+
+    /// Whether the column is an identity column.
+    pub fn is_identity(&self) -> bool {
+        self.flags & Self::FLAG_IDENTITY != 0
+    }
+
+    /// Whether the column's values are generated by the server (a computed or timestamp column),
+    /// so it can't be written to.
+    pub fn is_generated(&self) -> bool {
+        self.flags & Self::FLAG_COMPUTED != 0 || self.user_type == Self::USER_TYPE_TIMESTAMP
+    }
+}
+
+/// A DONE, DONEPROC or DONEINPROC token.
+#[derive(PartialEq, Debug, Clone, Copy)]
+pub(crate) struct DoneToken {
+    /// The token type.
+    pub token: u8,
+    /// The status flags.
+    pub status: u16,
+    /// The token of the current command (e.g. SELECT).
+    pub cur_cmd: u16,
+    /// The number of rows affected, if [TdsEnums::DONE_COUNT] is set.
+    pub row_count: u64,
+}
+
+impl DoneToken {
+    /// Whether this is the last token of the response.
+    pub fn is_final(&self) -> bool {
+        self.token != TdsEnums::SQLDONEINPROC && self.status & TdsEnums::DONE_MORE == 0
+    }
+
+    /// Whether the row count is valid.
+    pub fn has_count(&self) -> bool {
+        self.status & TdsEnums::DONE_COUNT != 0
+    }
+
+    /// Whether the statement resulted in an error.
+    pub fn has_error(&self) -> bool {
+        self.status & (TdsEnums::DONE_ERROR | TdsEnums::DONE_SRVERROR) != 0
+    }
+
+    /// The number of rows changed by the statement, if it was one that changes rows.
+    ///
+    /// As in .NET, SELECT statements don't count as affecting rows.
+    pub fn records_affected(&self) -> Option<u64> {
+        if self.has_count() && self.cur_cmd != TdsEnums::SELECT {
+            Some(self.row_count)
+        } else {
+            None
+        }
+    }
+}
+
+/// A RETURNVALUE token, sent for each output parameter and for the return value of a UDF.
+#[derive(PartialEq, Debug, Clone)]
+pub(crate) struct ReturnValueToken {
+    /// The ordinal of the parameter.
+    pub ordinal: u16,
+    /// The name of the parameter, including the leading "@".
+    pub parameter_name: String,
+    /// The status ([TdsEnums::RETURNVALUE_OUTPUT_PARAMETER] or [TdsEnums::RETURNVALUE_UDF]).
+    pub status: u8,
+    /// The parameter's type.
+    pub type_info: TypeInfo,
+    /// The parameter's value.
+    pub value: SqlValue,
+}
+
+/// A token read from a server response.
+#[derive(PartialEq, Debug, Clone)]
+pub(crate) enum TdsToken {
+    /// A new result set is starting.  The metadata is held by the parser.
+    ColMetaData,
+    /// A row follows, which must be read or skipped before reading the next token.
+    Row,
+    /// A null-bitmap compressed row follows, which must be read or skipped before reading the next token.
+    NbcRow,
+    /// A DONE, DONEPROC or DONEINPROC token.
+    Done(DoneToken),
+    /// The return status of a stored procedure.
+    ReturnStatus(i32),
+    /// The value of an output parameter.
+    ReturnValue(ReturnValueToken),
+    /// An error.
+    Error(SqlError),
+    /// An informational message.
+    Info(SqlError),
+    /// An environment change (already applied by the parser).
+    EnvChange(u8),
+    /// A token the client has no use for, which has been skipped.
+    Other(u8),
+}
@@ -0,0 +1,514 @@
+use crate::sql_collation::SqlCollation;
+use crate::tds_enums::TdsEnums;
+use crate::tds_parser_state_object::TdsParserStateObject;
+use crate::{SqlClientError, SqlDbType, TypeSystem};
+
+/// How the length of a value is sent on the wire.
+#[derive(PartialEq, Debug, Clone, Copy)]
+pub(crate) enum TdsLengthKind {
+    /// The value has a fixed size and no length prefix.
+    Fixed(usize),
+    /// The value is prefixed with a one-byte length.
+    ByteLen,
+    /// The value is prefixed with a two-byte length.
+    UShortLen,
+    /// The value is prefixed with a four-byte length.
+    LongLen,
+    /// The value is sent in chunks (a partially length-prefixed value).
+    Plp,
+}
+
+/// The TYPE_INFO of a column, parameter or return value.
+#[derive(PartialEq, Debug, Clone, Default)]
+pub(crate) struct TypeInfo {
+    /// The TDS type code.
+    pub tds_type: u8,
+    /// The maximum length of the value in bytes (or [TdsEnums::SQL_USHORTVARMAXLEN] for a "(max)" type).
+    pub max_length: u32,
+    /// The precision of a decimal.
+    pub precision: u8,
+    /// The scale of a decimal, or the fractional seconds scale of a time.
+    pub scale: u8,
+    /// The collation of a character type.
+    pub collation: Option<SqlCollation>,
+}
+
+impl TypeInfo {
+    /// The fixed size of types that don't send a length.
+    fn fixed_size(tds_type: u8) -> Option<usize> {
+        match tds_type {
+            TdsEnums::SQLVOID => Some(0),
+            TdsEnums::SQLINT1 | TdsEnums::SQLBIT => Some(1),
+            TdsEnums::SQLINT2 => Some(2),
+            TdsEnums::SQLINT4 | TdsEnums::SQLDATETIM4 | TdsEnums::SQLFLT4 | TdsEnums::SQLMONEY4 => {
+                Some(4)
+            }
+            TdsEnums::SQLMONEY | TdsEnums::SQLDATETIME | TdsEnums::SQLFLT8 | TdsEnums::SQLINT8 => {
+                Some(8)
+            }
+            _ => None,
+        }
+    }
+
+    /// Whether the type is a character type that carries a collation.
+    pub fn has_collation(tds_type: u8) -> bool {
+        matches!(
+            tds_type,
+            TdsEnums::SQLBIGCHAR
+                | TdsEnums::SQLBIGVARCHAR
+                | TdsEnums::SQLTEXT
+                | TdsEnums::SQLNCHAR
+                | TdsEnums::SQLNVARCHAR
+                | TdsEnums::SQLNTEXT
+        )
+    }
+
+    /// Whether the type is one of the date and time types that carry a fractional seconds scale.
+    fn has_time_scale(tds_type: u8) -> bool {
+        matches!(
+            tds_type,
+            TdsEnums::SQLTIME | TdsEnums::SQLDATETIME2 | TdsEnums::SQLDATETIMEOFFSET
+        )
+    }
+
+    /// Whether the type is a decimal.
+    fn is_decimal(tds_type: u8) -> bool {
+        matches!(
+            tds_type,
+            TdsEnums::SQLDECIMAL
+                | TdsEnums::SQLNUMERIC
+                | TdsEnums::SQLDECIMALN
+                | TdsEnums::SQLNUMERICN
+        )
+    }
+
+    /// Whether the type is a two-byte length type.
+    fn is_ushort_len(tds_type: u8) -> bool {
+        matches!(
+            tds_type,
+            TdsEnums::SQLBIGVARBINARY
+                | TdsEnums::SQLBIGVARCHAR
+                | TdsEnums::SQLBIGBINARY
+                | TdsEnums::SQLBIGCHAR
+                | TdsEnums::SQLNVARCHAR
+                | TdsEnums::SQLNCHAR
+        )
+    }
+
+    /// How values of this type are sent.
+    pub fn length_kind(&self) -> TdsLengthKind {
+        if let Some(size) = Self::fixed_size(self.tds_type) {
+            return TdsLengthKind::Fixed(size);
+        }
+        match self.tds_type {
+            TdsEnums::SQLXMLTYPE | TdsEnums::SQLUDT => TdsLengthKind::Plp,
+            t if Self::is_ushort_len(t) => {
+                if self.max_length == TdsEnums::SQL_USHORTVARMAXLEN as u32 {
+                    TdsLengthKind::Plp
+                } else {
+                    TdsLengthKind::UShortLen
+                }
+            }
+            TdsEnums::SQLTEXT | TdsEnums::SQLNTEXT | TdsEnums::SQLIMAGE | TdsEnums::SQLVARIANT => {
+                TdsLengthKind::LongLen
+            }
+            _ => TdsLengthKind::ByteLen,
+        }
+    }
+
+    /// Whether values are sent in chunks.
+    pub fn is_plp(&self) -> bool {
+        self.length_kind() == TdsLengthKind::Plp
+    }
+
+    /// Reads a TYPE_INFO.
+    pub async fn read(state: &mut TdsParserStateObject) -> Result<TypeInfo, SqlClientError> {
+        let tds_type = state.read_u8().await?;
+        let mut type_info = TypeInfo {
+            tds_type,
+            ..Default::default()
+        };
+        // If it's a fixed size type, there's nothing more to read.
+        if let Some(size) = Self::fixed_size(tds_type) {
+            type_info.max_length = size as u32;
+            return Ok(type_info);
+        }
+        match tds_type {
+            // Dates have no length in the TYPE_INFO
+            TdsEnums::SQLDATE => type_info.max_length = 3,
+            // The time types have only a scale
+            t if Self::has_time_scale(t) => type_info.scale = state.read_u8().await?,
+            // XML may have a schema collection
+            TdsEnums::SQLXMLTYPE => {
+                type_info.max_length = TdsEnums::SQL_USHORTVARMAXLEN as u32;
+                let schema_present = state.read_u8().await?;
+                if schema_present != 0 {
+                    let _database_name = state.read_b_varchar().await?;
+                    let _owning_schema = state.read_b_varchar().await?;
+                    let _schema_collection = state.read_us_varchar().await?;
+                }
+            }
+            // UDTs describe their CLR type
+            TdsEnums::SQLUDT => {
+                type_info.max_length = state.read_u16().await? as u32;
+                let _database_name = state.read_b_varchar().await?;
+                let _schema_name = state.read_b_varchar().await?;
+                let _type_name = state.read_b_varchar().await?;
+                let _assembly_qualified_name = state.read_us_varchar().await?;
+            }
+            t if Self::is_ushort_len(t) => type_info.max_length = state.read_u16().await? as u32,
+            TdsEnums::SQLTEXT | TdsEnums::SQLNTEXT | TdsEnums::SQLIMAGE | TdsEnums::SQLVARIANT => {
+                type_info.max_length = state.read_u32().await?
+            }
+            _ => {
+                type_info.max_length = state.read_u8().await? as u32;
+                // Decimals also have a precision and scale
+                if Self::is_decimal(tds_type) {
+                    type_info.precision = state.read_u8().await?;
+                    type_info.scale = state.read_u8().await?;
+                }
+            }
+        }
+        // If it's a character type, read the collation
+        if Self::has_collation(tds_type) {
+            let mut bytes = [0u8; SqlCollation::SIZE];
+            state.read_exact(&mut bytes).await?;
+            type_info.collation = Some(SqlCollation::from_bytes(bytes));
+        }
+        Ok(type_info)
+    }
+
+    /// Writes the TYPE_INFO.
+    pub fn write(&self, buffer: &mut Vec<u8>) {
+        buffer.push(self.tds_type);
+        match self.tds_type {
+            t if Self::fixed_size(t).is_some() => {}
+            TdsEnums::SQLDATE => {}
+            t if Self::has_time_scale(t) => buffer.push(self.scale),
+            // No schema collection
+            TdsEnums::SQLXMLTYPE => buffer.push(0),
+            t if Self::is_ushort_len(t) => {
+                buffer.extend_from_slice(&(self.max_length as u16).to_le_bytes())
+            }
+            TdsEnums::SQLTEXT | TdsEnums::SQLNTEXT | TdsEnums::SQLIMAGE | TdsEnums::SQLVARIANT => {
+                buffer.extend_from_slice(&self.max_length.to_le_bytes())
+            }
+            t => {
+                buffer.push(self.max_length as u8);
+                if Self::is_decimal(t) {
+                    buffer.push(self.precision);
+                    buffer.push(self.scale);
+                }
+            }
+        }
+        if Self::has_collation(self.tds_type) {
+            buffer.extend_from_slice(&self.collation.unwrap_or_default().to_bytes());
+        }
+    }
+
+    /// Creates the TYPE_INFO used to send a parameter.
+    ///
+    /// `size` is the declared size of the parameter in characters or bytes (-1 for "max") and
+    /// `value_length` is the length of the value being sent in the same units.
+    pub fn for_parameter(
+        sql_db_type: SqlDbType,
+        size: i32,
+        value_length: usize,
+        precision: u8,
+        scale: u8,
+        collation: Option<SqlCollation>,
+    ) -> Result<TypeInfo, SqlClientError> {
+        // Works out the maximum length of a variable-length type.  If the value fits then
+        // we use the largest non-max size so that the server can reuse the query plan whatever the
+        // length of the value.
+        let variable = |tds_type: u8, max_units: usize, bytes_per_unit: usize| {
+            let max_length = if size < 0 || size as usize > max_units || value_length > max_units {
+                TdsEnums::SQL_USHORTVARMAXLEN as u32
+            } else if matches!(
+                tds_type,
+                TdsEnums::SQLNCHAR | TdsEnums::SQLBIGCHAR | TdsEnums::SQLBIGBINARY
+            ) {
+                // Fixed-length types are sent with their declared size
+                (size.max(value_length as i32).max(1) as usize * bytes_per_unit) as u32
+            } else {
+                TdsEnums::MAXSIZE
+            };
+            TypeInfo {
+                tds_type,
+                max_length,
+                collation: if TypeInfo::has_collation(tds_type) {
+                    Some(collation.unwrap_or_default())
+                } else {
+                    None
+                },
+                ..Default::default()
+            }
+        };
+        let fixed = |tds_type: u8, max_length: u32| TypeInfo {
+            tds_type,
+            max_length,
+            ..Default::default()
+        };
+        let type_info = match sql_db_type {
+            SqlDbType::BigInt => fixed(TdsEnums::SQLINTN, 8),
+            SqlDbType::Int => fixed(TdsEnums::SQLINTN, 4),
+            SqlDbType::SmallInt => fixed(TdsEnums::SQLINTN, 2),
+            SqlDbType::TinyInt => fixed(TdsEnums::SQLINTN, 1),
+            SqlDbType::Bit => fixed(TdsEnums::SQLBITN, 1),
+            SqlDbType::Float => fixed(TdsEnums::SQLFLTN, 8),
+            SqlDbType::Real => fixed(TdsEnums::SQLFLTN, 4),
+            SqlDbType::Money => fixed(TdsEnums::SQLMONEYN, 8),
+            SqlDbType::SmallMoney => fixed(TdsEnums::SQLMONEYN, 4),
+            SqlDbType::DateTime => fixed(TdsEnums::SQLDATETIMN, 8),
+            SqlDbType::SmallDateTime => fixed(TdsEnums::SQLDATETIMN, 4),
+            SqlDbType::UniqueIdentifier => fixed(TdsEnums::SQLUNIQUEID, 16),
+            SqlDbType::Decimal => TypeInfo {
+                tds_type: TdsEnums::SQLDECIMALN,
+                max_length: 17,
+                precision,
+                scale,
+                collation: None,
+            },
+            SqlDbType::Date => fixed(TdsEnums::SQLDATE, 3),
+            SqlDbType::Time | SqlDbType::DateTime2 | SqlDbType::DateTimeOffset => {
+                let tds_type = match sql_db_type {
+                    SqlDbType::Time => TdsEnums::SQLTIME,
+                    SqlDbType::DateTime2 => TdsEnums::SQLDATETIME2,
+                    _ => TdsEnums::SQLDATETIMEOFFSET,
+                };
+                TypeInfo {
+                    tds_type,
+                    scale,
+                    ..Default::default()
+                }
+            }
+            SqlDbType::NVarChar | SqlDbType::NText => variable(TdsEnums::SQLNVARCHAR, 4000, 2),
+            SqlDbType::NChar => variable(TdsEnums::SQLNCHAR, 4000, 2),
+            SqlDbType::VarChar | SqlDbType::Text => variable(TdsEnums::SQLBIGVARCHAR, 8000, 1),
+            SqlDbType::Char => variable(TdsEnums::SQLBIGCHAR, 8000, 1),
+            SqlDbType::VarBinary | SqlDbType::Image => variable(TdsEnums::SQLBIGVARBINARY, 8000, 1),
+            SqlDbType::Binary => variable(TdsEnums::SQLBIGBINARY, 8000, 1),
+            SqlDbType::Timestamp => fixed(TdsEnums::SQLBIGBINARY, 8),
+            SqlDbType::Xml => fixed(TdsEnums::SQLXMLTYPE, TdsEnums::SQL_USHORTVARMAXLEN as u32),
+            SqlDbType::Variant => fixed(TdsEnums::SQLVARIANT, 8009),
+            SqlDbType::Structured | SqlDbType::Udt => {
+                return Err(SqlClientError::NotSupported(format!(
+                    "Parameters of type {}",
+                    sql_db_type
+                )))
+            }
+        };
+        Ok(type_info)
+    }
+
+    /// The type as surfaced to the application.
+    pub fn sql_db_type(&self) -> SqlDbType {
+        match self.tds_type {
+            TdsEnums::SQLINT1 => SqlDbType::TinyInt,
+            TdsEnums::SQLINT2 => SqlDbType::SmallInt,
+            TdsEnums::SQLINT4 => SqlDbType::Int,
+            TdsEnums::SQLINT8 => SqlDbType::BigInt,
+            TdsEnums::SQLINTN => match self.max_length {
+                1 => SqlDbType::TinyInt,
+                2 => SqlDbType::SmallInt,
+                4 => SqlDbType::Int,
+                _ => SqlDbType::BigInt,
+            },
+            TdsEnums::SQLBIT | TdsEnums::SQLBITN => SqlDbType::Bit,
+            TdsEnums::SQLFLT4 => SqlDbType::Real,
+            TdsEnums::SQLFLT8 => SqlDbType::Float,
+            TdsEnums::SQLFLTN if self.max_length == 4 => SqlDbType::Real,
+            TdsEnums::SQLFLTN => SqlDbType::Float,
+            TdsEnums::SQLMONEY4 => SqlDbType::SmallMoney,
+            TdsEnums::SQLMONEY => SqlDbType::Money,
+            TdsEnums::SQLMONEYN if self.max_length == 4 => SqlDbType::SmallMoney,
+            TdsEnums::SQLMONEYN => SqlDbType::Money,
+            TdsEnums::SQLDATETIM4 => SqlDbType::SmallDateTime,
+            TdsEnums::SQLDATETIME => SqlDbType::DateTime,
+            TdsEnums::SQLDATETIMN if self.max_length == 4 => SqlDbType::SmallDateTime,
+            TdsEnums::SQLDATETIMN => SqlDbType::DateTime,
+            t if Self::is_decimal(t) => SqlDbType::Decimal,
+            TdsEnums::SQLUNIQUEID => SqlDbType::UniqueIdentifier,
+            TdsEnums::SQLDATE => SqlDbType::Date,
+            TdsEnums::SQLTIME => SqlDbType::Time,
+            TdsEnums::SQLDATETIME2 => SqlDbType::DateTime2,
+            TdsEnums::SQLDATETIMEOFFSET => SqlDbType::DateTimeOffset,
+            TdsEnums::SQLCHAR | TdsEnums::SQLBIGCHAR => SqlDbType::Char,
+            TdsEnums::SQLVARCHAR | TdsEnums::SQLBIGVARCHAR => SqlDbType::VarChar,
+            TdsEnums::SQLTEXT => SqlDbType::Text,
+            TdsEnums::SQLNCHAR => SqlDbType::NChar,
+            TdsEnums::SQLNVARCHAR => SqlDbType::NVarChar,
+            TdsEnums::SQLNTEXT => SqlDbType::NText,
+            TdsEnums::SQLBINARY | TdsEnums::SQLBIGBINARY => SqlDbType::Binary,
+            TdsEnums::SQLVARBINARY | TdsEnums::SQLBIGVARBINARY => SqlDbType::VarBinary,
+            TdsEnums::SQLIMAGE => SqlDbType::Image,
+            TdsEnums::SQLXMLTYPE => SqlDbType::Xml,
+            TdsEnums::SQLUDT => SqlDbType::Udt,
+            TdsEnums::SQLTABLE => SqlDbType::Structured,
+            _ => SqlDbType::Variant,
+        }
+    }
+
+    /// The T-SQL declaration of the type, with its size, precision or scale (e.g. "nvarchar(50)").
+    pub fn declaration(&self) -> String {
+        let sql_db_type = self.sql_db_type();
+        if sql_db_type.has_size() {
+            if self.max_length == TdsEnums::SQL_USHORTVARMAXLEN as u32 {
+                format!("{}(max)", sql_db_type)
+            } else {
+                let units = if sql_db_type.is_unicode() { 2 } else { 1 };
+                format!("{}({})", sql_db_type, self.max_length / units)
+            }
+        } else if sql_db_type == SqlDbType::Decimal {
+            format!("{}({},{})", sql_db_type, self.precision, self.scale)
+        } else if sql_db_type.has_time_scale() {
+            format!("{}({})", sql_db_type, self.scale)
+        } else {
+            sql_db_type.to_string()
+        }
+    }
+
+    /// Whether the type is one of the date and time types added in SQL Server 2008.
+    pub fn is_new_date_time_type(&self) -> bool {
+        self.tds_type == TdsEnums::SQLDATE || Self::has_time_scale(self.tds_type)
+    }
+
+    /// The type that a column of this type is surfaced as in the given type system.
+    ///
+    /// As in .NET, the SQL Server 2000 type system exposes the "(max)" types, xml and UDTs as
+    /// text, ntext and image, and the 2000 and 2005 type systems expose the SQL Server 2008 date
+    /// and time types as nvarchar.
+    pub fn for_type_system(&self, type_system: TypeSystem) -> TypeInfo {
+        let legacy = |tds_type: u8| TypeInfo {
+            tds_type,
+            max_length: i32::MAX as u32,
+            collation: if Self::has_collation(tds_type) {
+                Some(self.collation.unwrap_or_default())
+            } else {
+                None
+            },
+            ..Default::default()
+        };
+        if type_system < TypeSystem::SqlServer2008 && self.is_new_date_time_type() {
+            return TypeInfo {
+                tds_type: TdsEnums::SQLNVARCHAR,
+                max_length: TdsEnums::MAXSIZE,
+                collation: Some(SqlCollation::default()),
+                ..Default::default()
+            };
+        }
+        if type_system > TypeSystem::SqlServer2000 {
+            return self.clone();
+        }
+        match self.tds_type {
+            TdsEnums::SQLBIGVARCHAR if self.is_plp() => legacy(TdsEnums::SQLTEXT),
+            TdsEnums::SQLNVARCHAR if self.is_plp() => legacy(TdsEnums::SQLNTEXT),
+            TdsEnums::SQLBIGVARBINARY if self.is_plp() => legacy(TdsEnums::SQLIMAGE),
+            TdsEnums::SQLXMLTYPE => legacy(TdsEnums::SQLNTEXT),
+            TdsEnums::SQLUDT => legacy(TdsEnums::SQLIMAGE),
+            _ => self.clone(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tds_test_utils::packet;
+    use test_utils::MockStream;
+
+    /// Writes a TYPE_INFO and reads it back.
+    async fn round_trip(type_info: &TypeInfo) -> TypeInfo {
+        let mut buffer = Vec::new();
+        type_info.write(&mut buffer);
+        let stream = MockStream::new(packet(&buffer));
+        let mut state = TdsParserStateObject::new(Box::new(stream), 4096);
+        let result = TypeInfo::read(&mut state).await.unwrap();
+        assert!(state.is_message_complete());
+        result
+    }
+
+    #[rstest::rstest]
+    #[case(SqlDbType::Int, 0, 0, TdsEnums::SQLINTN, 4, TdsLengthKind::ByteLen)]
+    #[case(
+        SqlDbType::NVarChar,
+        0,
+        10,
+        TdsEnums::SQLNVARCHAR,
+        8000,
+        TdsLengthKind::UShortLen
+    )]
+    #[case(SqlDbType::NVarChar, -1, 10, TdsEnums::SQLNVARCHAR, 0xFFFF, TdsLengthKind::Plp)]
+    #[case(
+        SqlDbType::NVarChar,
+        0,
+        5000,
+        TdsEnums::SQLNVARCHAR,
+        0xFFFF,
+        TdsLengthKind::Plp
+    )]
+    #[case(
+        SqlDbType::NChar,
+        10,
+        3,
+        TdsEnums::SQLNCHAR,
+        20,
+        TdsLengthKind::UShortLen
+    )]
+    #[case(
+        SqlDbType::VarBinary,
+        0,
+        9000,
+        TdsEnums::SQLBIGVARBINARY,
+        0xFFFF,
+        TdsLengthKind::Plp
+    )]
+    #[case(SqlDbType::Xml, 0, 0, TdsEnums::SQLXMLTYPE, 0xFFFF, TdsLengthKind::Plp)]
+    fn test_for_parameter(
+        #[case] sql_db_type: SqlDbType,
+        #[case] size: i32,
+        #[case] value_length: usize,
+        #[case] tds_type: u8,
+        #[case] max_length: u32,
+        #[case] length_kind: TdsLengthKind,
+    ) {
+        let type_info =
+            TypeInfo::for_parameter(sql_db_type, size, value_length, 0, 0, None).unwrap();
+        assert_eq!(tds_type, type_info.tds_type);
+        assert_eq!(max_length, type_info.max_length);
+        assert_eq!(length_kind, type_info.length_kind());
+    }
+
+    #[rstest::rstest]
+    #[case(SqlDbType::Int, 0, 0, "int")]
+    #[case(SqlDbType::NVarChar, 50, 0, "nvarchar(4000)")]
+    #[case(SqlDbType::VarBinary, -1, 0, "varbinary(max)")]
+    #[case(SqlDbType::Decimal, 0, 4, "decimal(18,4)")]
+    #[case(SqlDbType::DateTime2, 0, 3, "datetime2(3)")]
+    fn test_declaration(
+        #[case] sql_db_type: SqlDbType,
+        #[case] size: i32,
+        #[case] scale: u8,
+        #[case] expected: &str,
+    ) {
+        let type_info = TypeInfo::for_parameter(sql_db_type, size, 0, 18, scale, None).unwrap();
+        assert_eq!(expected, type_info.declaration());
+    }
+
+    #[rstest::rstest]
+    #[case(SqlDbType::BigInt)]
+    #[case(SqlDbType::Decimal)]
+    #[case(SqlDbType::Date)]
+    #[case(SqlDbType::DateTime2)]
+    #[case(SqlDbType::NVarChar)]
+    #[case(SqlDbType::VarChar)]
+    #[case(SqlDbType::Binary)]
+    #[case(SqlDbType::Xml)]
+    #[case(SqlDbType::Variant)]
+    #[tokio::test]
+    async fn test_round_trip(#[case] sql_db_type: SqlDbType) {
+        let collation = Some(SqlCollation::new(0x00D0_0409, 0x34));
+        let type_info = TypeInfo::for_parameter(sql_db_type, 0, 1, 18, 2, collation).unwrap();
+        assert_eq!(type_info, round_trip(&type_info).await);
+    }
+}
@@ -0,0 +1,1253 @@
+use crate::sql_collation::SqlCollation;
+use crate::tds_enums::TdsEnums;
+use crate::tds_parser_state_object::{
+    decode_utf16, encode_utf16, write_b_varchar, TdsParserStateObject,
+};
+use crate::tds_token::ColumnMetaData;
+use crate::tds_type_info::{TdsLengthKind, TypeInfo};
+use crate::{SqlClientError, SqlValue};
+use crate::{SqlDecimal, SqlMoney, TypeSystem};
+use chrono::{DateTime, Duration, FixedOffset, NaiveDate, NaiveDateTime, NaiveTime, Timelike};
+use tokio::io::{AsyncRead, AsyncReadExt};
+use uuid::Uuid;
+
+/// The number of datetime ticks (1/300ths of a second) in a day.
+const DATETIME_TICKS_PER_DAY: i64 = 300 * 60 * 60 * 24;
+
+/// The base date of the datetime and smalldatetime types.
+fn datetime_base() -> NaiveDateTime {
+    NaiveDate::from_ymd_opt(1900, 1, 1)
+        .unwrap()
+        .and_hms_opt(0, 0, 0)
+        .unwrap()
+}
+
+/// The base date of the date, datetime2 and datetimeoffset types.
+fn date_base() -> NaiveDate {
+    NaiveDate::from_ymd_opt(1, 1, 1).unwrap()
+}
+
+/// Reads an unsigned little-endian integer of up to eight bytes.
+fn read_uint_le(bytes: &[u8]) -> u64 {
+    bytes
+        .iter()
+        .rev()
+        .fold(0u64, |value, byte| (value << 8) | *byte as u64)
+}
+
+/// Decodes a datetime (days since 1900 and 1/300ths of a second since midnight).
+fn decode_datetime(bytes: &[u8]) -> NaiveDateTime {
+    let days = i32::from_le_bytes(bytes[0..4].try_into().unwrap());
+    let ticks = u32::from_le_bytes(bytes[4..8].try_into().unwrap());
+    // Round the ticks to the nearest millisecond
+    let milliseconds = (ticks as i64 * 10 + 1) / 3;
+    datetime_base() + Duration::days(days as i64) + Duration::milliseconds(milliseconds)
+}
+
+/// Decodes a smalldatetime (days since 1900 and minutes since midnight).
+fn decode_smalldatetime(bytes: &[u8]) -> NaiveDateTime {
+    let days = u16::from_le_bytes(bytes[0..2].try_into().unwrap());
+    let minutes = u16::from_le_bytes(bytes[2..4].try_into().unwrap());
+    datetime_base() + Duration::days(days as i64) + Duration::minutes(minutes as i64)
+}
+
+/// Decodes a date (days since 0001-01-01 in three bytes).
+fn decode_date(bytes: &[u8]) -> NaiveDate {
+    date_base() + Duration::days(read_uint_le(bytes) as i64)
+}
+
+/// Decodes the time part of a time, datetime2 or datetimeoffset, as nanoseconds since midnight.
+fn decode_time_nanoseconds(bytes: &[u8], scale: u8) -> i64 {
+    let units = read_uint_le(bytes) as i64;
+    units * 10i64.pow(9 - scale.min(7) as u32)
+}
+
+/// Decodes a datetime2 (a time followed by a date).
+fn decode_datetime2(bytes: &[u8], scale: u8) -> NaiveDateTime {
+    let split = bytes.len() - 3;
+    let date = decode_date(&bytes[split..]);
+    date.and_hms_opt(0, 0, 0).unwrap()
+        + Duration::nanoseconds(decode_time_nanoseconds(&bytes[..split], scale))
+}
+
+/// Decodes a time.
+fn decode_time(bytes: &[u8], scale: u8) -> NaiveTime {
+    NaiveTime::MIN + Duration::nanoseconds(decode_time_nanoseconds(bytes, scale))
+}
+
+/// Decodes a datetimeoffset (a UTC datetime2 followed by the offset in minutes).
+fn decode_datetimeoffset(bytes: &[u8], scale: u8) -> Result<DateTime<FixedOffset>, SqlClientError> {
+    let split = bytes.len() - 2;
+    let utc = decode_datetime2(&bytes[..split], scale);
+    let minutes = i16::from_le_bytes(bytes[split..].try_into().unwrap());
+    let offset = FixedOffset::east_opt(minutes as i32 * 60)
+        .ok_or_else(|| SqlClientError::Protocol(format!("Invalid time zone offset {}", minutes)))?;
+    Ok(DateTime::from_naive_utc_and_offset(utc, offset))
+}
+
+/// Decodes a sql_variant (the base type, its properties, then the value).
+fn decode_variant(bytes: &[u8]) -> Result<SqlValue, SqlClientError> {
+    let invalid = || SqlClientError::Protocol("Invalid sql_variant value".to_string());
+    let tds_type = *bytes.first().ok_or_else(invalid)?;
+    let property_count = *bytes.get(1).ok_or_else(invalid)? as usize;
+    let properties = bytes.get(2..2 + property_count).ok_or_else(invalid)?;
+    let data = &bytes[2 + property_count..];
+    let mut type_info = TypeInfo {
+        tds_type,
+        max_length: data.len() as u32,
+        ..Default::default()
+    };
+    match (tds_type, properties.len()) {
+        (TdsEnums::SQLDECIMALN | TdsEnums::SQLNUMERICN, 2) => {
+            type_info.precision = properties[0];
+            type_info.scale = properties[1];
+        }
+        (TdsEnums::SQLTIME | TdsEnums::SQLDATETIME2 | TdsEnums::SQLDATETIMEOFFSET, 1) => {
+            type_info.scale = properties[0];
+        }
+        // Character types have a collation then a maximum length.
+        (_, 7) => {
+            type_info.collation = Some(SqlCollation::from_bytes(
+                properties[0..SqlCollation::SIZE].try_into().unwrap(),
+            ));
+        }
+        _ => {}
+    }
+    decode_value(&type_info, Some(data))
+}
+
+/// The number of bytes used for the time part of a time, datetime2 or datetimeoffset.
+fn time_length(scale: u8) -> usize {
+    match scale {
+        0..=2 => 3,
+        3 | 4 => 4,
+        _ => 5,
+    }
+}
+
+/// Encodes a date as the number of days since 0001-01-01.
+fn encode_date(date: NaiveDate) -> Vec<u8> {
+    let days = (date - date_base()).num_days() as u32;
+    days.to_le_bytes()[..3].to_vec()
+}
+
+/// Encodes the time part of a time, datetime2 or datetimeoffset, truncating to the scale.
+fn encode_time(time: NaiveTime, scale: u8) -> Vec<u8> {
+    let nanoseconds = (time - NaiveTime::MIN).num_nanoseconds().unwrap();
+    let units = nanoseconds / 10i64.pow(9 - scale.min(7) as u32);
+    units.to_le_bytes()[..time_length(scale)].to_vec()
+}
+
+/// Encodes a datetime2 (a time followed by a date).
+fn encode_datetime2(value: NaiveDateTime, scale: u8) -> Vec<u8> {
+    let mut bytes = encode_time(value.time(), scale);
+    bytes.extend(encode_date(value.date()));
+    bytes
+}
+
+/// Encodes a datetime, rounding to the nearest 1/300th of a second.
+fn encode_datetime(value: NaiveDateTime) -> Option<Vec<u8>> {
+    let nanoseconds = (value.time() - NaiveTime::MIN).num_nanoseconds().unwrap();
+    let mut days = (value.date() - datetime_base().date()).num_days();
+    let mut ticks = (nanoseconds * 3 + 5_000_000) / 10_000_000;
+    // Rounding up may take us to the next day.
+    if ticks >= DATETIME_TICKS_PER_DAY {
+        days += 1;
+        ticks -= DATETIME_TICKS_PER_DAY;
+    }
+    // datetime can only hold 1753-01-01 to 9999-12-31.
+    let first_day =
+        (NaiveDate::from_ymd_opt(1753, 1, 1).unwrap() - datetime_base().date()).num_days();
+    let last_day =
+        (NaiveDate::from_ymd_opt(9999, 12, 31).unwrap() - datetime_base().date()).num_days();
+    if days < first_day || days > last_day {
+        return None;
+    }
+    let mut bytes = (days as i32).to_le_bytes().to_vec();
+    bytes.extend_from_slice(&(ticks as u32).to_le_bytes());
+    Some(bytes)
+}
+
+/// Encodes a smalldatetime, rounding to the nearest minute.
+fn encode_smalldatetime(value: NaiveDateTime) -> Option<Vec<u8>> {
+    let seconds = value.num_seconds_from_midnight() as i64;
+    let mut days = (value.date() - datetime_base().date()).num_days();
+    let mut minutes = (seconds + 30) / 60;
+    if minutes >= 24 * 60 {
+        days += 1;
+        minutes -= 24 * 60;
+    }
+    let days = u16::try_from(days).ok()?;
+    let mut bytes = days.to_le_bytes().to_vec();
+    bytes.extend_from_slice(&(minutes as u16).to_le_bytes());
+    Some(bytes)
+}
+
+/// Encodes a datetimeoffset (a UTC datetime2 followed by the offset in minutes).
+fn encode_datetimeoffset(value: DateTime<FixedOffset>, scale: u8) -> Vec<u8> {
+    let mut bytes = encode_datetime2(value.naive_utc(), scale);
+    let minutes = (value.offset().local_minus_utc() / 60) as i16;
+    bytes.extend_from_slice(&minutes.to_le_bytes());
+    bytes
+}
+
+/// Gets a value as a decimal, if it's numeric.
+fn to_decimal(value: &SqlValue) -> Option<SqlDecimal> {
+    match value {
+        SqlValue::Decimal(v) => Some(*v),
+        SqlValue::Money(v) => Some((*v).into()),
+        // Use the shortest representation of the float rather than its exact binary value
+        SqlValue::Real(v) => v.to_string().parse().ok(),
+        SqlValue::Float(v) => SqlDecimal::try_from(*v).ok(),
+        SqlValue::Bit(v) => Some((*v as i64).into()),
+        SqlValue::TinyInt(v) => Some((*v as i64).into()),
+        SqlValue::SmallInt(v) => Some((*v as i64).into()),
+        SqlValue::Int(v) => Some((*v as i64).into()),
+        SqlValue::BigInt(v) => Some((*v).into()),
+        _ => None,
+    }
+}
+
+/// Encodes a decimal with the given precision and scale, rounding to the scale, or None if it doesn't fit.
+fn encode_decimal(value: SqlDecimal, precision: u8, scale: u8) -> Option<Vec<u8>> {
+    let adjusted = value.adjust_scale(scale, true).ok()?;
+    let decimal = SqlDecimal::new(adjusted.unscaled_value(), precision, scale).ok()?;
+    Some(decimal.to_tds())
+}
+
+/// The base type a value is sent as inside a sql_variant.
+fn variant_type_info(value: &SqlValue) -> Result<TypeInfo, SqlClientError> {
+    let fixed = |tds_type: u8, max_length: u32| TypeInfo {
+        tds_type,
+        max_length,
+        ..Default::default()
+    };
+    let type_info = match value {
+        SqlValue::Bit(_) => fixed(TdsEnums::SQLBIT, 1),
+        SqlValue::TinyInt(_) => fixed(TdsEnums::SQLINT1, 1),
+        SqlValue::SmallInt(_) => fixed(TdsEnums::SQLINT2, 2),
+        SqlValue::Int(_) => fixed(TdsEnums::SQLINT4, 4),
+        SqlValue::BigInt(_) => fixed(TdsEnums::SQLINT8, 8),
+        SqlValue::Real(_) => fixed(TdsEnums::SQLFLT4, 4),
+        SqlValue::Float(_) => fixed(TdsEnums::SQLFLT8, 8),
+        SqlValue::Decimal(v) => TypeInfo {
+            tds_type: TdsEnums::SQLNUMERICN,
+            max_length: 17,
+            precision: v.precision(),
+            scale: v.scale(),
+            collation: None,
+        },
+        SqlValue::Money(_) => fixed(TdsEnums::SQLMONEY, 8),
+        SqlValue::String(_) => TypeInfo {
+            tds_type: TdsEnums::SQLNVARCHAR,
+            max_length: TdsEnums::MAXSIZE,
+            collation: Some(SqlCollation::default()),
+            ..Default::default()
+        },
+        SqlValue::Binary(_) => TypeInfo {
+            tds_type: TdsEnums::SQLBIGVARBINARY,
+            max_length: TdsEnums::MAXSIZE,
+            ..Default::default()
+        },
+        SqlValue::Guid(_) => fixed(TdsEnums::SQLUNIQUEID, 16),
+        SqlValue::Date(_) => fixed(TdsEnums::SQLDATE, 3),
+        SqlValue::Time(_) | SqlValue::DateTime(_) | SqlValue::DateTimeOffset(_) => TypeInfo {
+            tds_type: match value {
+                SqlValue::Time(_) => TdsEnums::SQLTIME,
+                SqlValue::DateTime(_) => TdsEnums::SQLDATETIME2,
+                _ => TdsEnums::SQLDATETIMEOFFSET,
+            },
+            scale: 7,
+            ..Default::default()
+        },
+        SqlValue::Null => fixed(TdsEnums::SQLVOID, 0),
+    };
+    Ok(type_info)
+}
+
+/// Encodes a sql_variant (the base type, its properties, then the value).
+fn encode_variant(value: &SqlValue) -> Result<Vec<u8>, SqlClientError> {
+    let type_info = variant_type_info(value)?;
+    let mut properties = Vec::new();
+    match type_info.tds_type {
+        TdsEnums::SQLNUMERICN => {
+            properties.extend_from_slice(&[type_info.precision, type_info.scale])
+        }
+        TdsEnums::SQLTIME | TdsEnums::SQLDATETIME2 | TdsEnums::SQLDATETIMEOFFSET => {
+            properties.push(type_info.scale)
+        }
+        TdsEnums::SQLNVARCHAR => {
+            properties.extend_from_slice(&type_info.collation.unwrap_or_default().to_bytes());
+            properties.extend_from_slice(&(type_info.max_length as u16).to_le_bytes());
+        }
+        TdsEnums::SQLBIGVARBINARY => {
+            properties.extend_from_slice(&(type_info.max_length as u16).to_le_bytes())
+        }
+        _ => {}
+    }
+    let data = encode_value(&type_info, value)?.unwrap_or_default();
+    if data.len() > TdsEnums::MAXSIZE as usize {
+        return Err(SqlClientError::UnsupportedValue(
+            "sql_variant".to_string(),
+            format!("{:?}", value),
+        ));
+    }
+    let mut bytes = vec![type_info.tds_type, properties.len() as u8];
+    bytes.extend(properties);
+    bytes.extend(data);
+    Ok(bytes)
+}
+
+/// Reads the raw bytes of a value, returning None for a null.
+pub(crate) async fn read_value_bytes(
+    state: &mut TdsParserStateObject,
+    type_info: &TypeInfo,
+) -> Result<Option<Vec<u8>>, SqlClientError> {
+    match type_info.length_kind() {
+        TdsLengthKind::Fixed(size) => Ok(Some(state.read_bytes(size).await?)),
+        TdsLengthKind::ByteLen => {
+            let length = state.read_u8().await? as usize;
+            // A zero length is a null for everything but the legacy char types, which the server doesn't send.
+            if length == 0 {
+                Ok(None)
+            } else {
+                Ok(Some(state.read_bytes(length).await?))
+            }
+        }
+        TdsLengthKind::UShortLen => {
+            let length = state.read_u16().await?;
+            if length == TdsEnums::VARNULL {
+                Ok(None)
+            } else {
+                Ok(Some(state.read_bytes(length as usize).await?))
+            }
+        }
+        TdsLengthKind::LongLen => {
+            // sql_variant is just a four-byte length
+            if type_info.tds_type == TdsEnums::SQLVARIANT {
+                let length = state.read_u32().await? as usize;
+                return if length == 0 {
+                    Ok(None)
+                } else {
+                    Ok(Some(state.read_bytes(length).await?))
+                };
+            }
+            // text, ntext and image have a text pointer and timestamp before the data.
+            let text_pointer_length = state.read_u8().await? as usize;
+            if text_pointer_length == 0 {
+                return Ok(None);
+            }
+            state.skip(text_pointer_length + 8).await?;
+            let length = state.read_u32().await? as usize;
+            Ok(Some(state.read_bytes(length).await?))
+        }
+        TdsLengthKind::Plp => {
+            let total_length = state.read_u64().await?;
+            if total_length == TdsEnums::SQL_PLP_NULL {
+                return Ok(None);
+            }
+            // Read the chunks up to the terminator
+            let mut bytes = Vec::new();
+            if total_length != TdsEnums::SQL_PLP_UNKNOWNLEN {
+                bytes.reserve(total_length as usize);
+            }
+            loop {
+                let chunk_length = state.read_u32().await?;
+                if chunk_length == TdsEnums::SQL_PLP_CHUNK_TERMINATOR {
+                    break;
+                }
+                bytes.extend(state.read_bytes(chunk_length as usize).await?);
+            }
+            Ok(Some(bytes))
+        }
+    }
+}
+
+/// Skips the chunks of a PLP value, up to and including the terminator.
+pub(crate) async fn skip_plp_chunks(
+    state: &mut TdsParserStateObject,
+) -> Result<(), SqlClientError> {
+    loop {
+        let chunk_length = state.read_u32().await?;
+        if chunk_length == TdsEnums::SQL_PLP_CHUNK_TERMINATOR {
+            return Ok(());
+        }
+        state.skip(chunk_length as usize).await?;
+    }
+}
+
+/// Skips over a value without decoding it.
+pub(crate) async fn skip_value(
+    state: &mut TdsParserStateObject,
+    type_info: &TypeInfo,
+) -> Result<(), SqlClientError> {
+    // Large values are skipped a chunk at a time rather than being read into memory.
+    if type_info.is_plp() {
+        if state.read_u64().await? != TdsEnums::SQL_PLP_NULL {
+            skip_plp_chunks(state).await?;
+        }
+        return Ok(());
+    }
+    read_value_bytes(state, type_info).await.map(|_| ())
+}
+
+/// Converts the raw bytes of a value into a [SqlValue].
+pub(crate) fn decode_value(
+    type_info: &TypeInfo,
+    bytes: Option<&[u8]>,
+) -> Result<SqlValue, SqlClientError> {
+    // If it's null, we're done.
+    let bytes = match bytes {
+        Some(bytes) => bytes,
+        None => return Ok(SqlValue::Null),
+    };
+    let invalid_length = || {
+        SqlClientError::Protocol(format!(
+            "Invalid length {} for type 0x{:02X}",
+            bytes.len(),
+            type_info.tds_type
+        ))
+    };
+    let value = match type_info.tds_type {
+        TdsEnums::SQLINT1
+        | TdsEnums::SQLINT2
+        | TdsEnums::SQLINT4
+        | TdsEnums::SQLINT8
+        | TdsEnums::SQLINTN => match bytes.len() {
+            1 => SqlValue::TinyInt(bytes[0]),
+            2 => SqlValue::SmallInt(i16::from_le_bytes(bytes.try_into().unwrap())),
+            4 => SqlValue::Int(i32::from_le_bytes(bytes.try_into().unwrap())),
+            8 => SqlValue::BigInt(i64::from_le_bytes(bytes.try_into().unwrap())),
+            _ => return Err(invalid_length()),
+        },
+        TdsEnums::SQLBIT | TdsEnums::SQLBITN => match bytes.len() {
+            1 => SqlValue::Bit(bytes[0] != 0),
+            _ => return Err(invalid_length()),
+        },
+        TdsEnums::SQLFLT4 | TdsEnums::SQLFLT8 | TdsEnums::SQLFLTN => match bytes.len() {
+            4 => SqlValue::Real(f32::from_le_bytes(bytes.try_into().unwrap())),
+            8 => SqlValue::Float(f64::from_le_bytes(bytes.try_into().unwrap())),
+            _ => return Err(invalid_length()),
+        },
+        TdsEnums::SQLNVARCHAR | TdsEnums::SQLNCHAR | TdsEnums::SQLNTEXT => {
+            SqlValue::String(decode_utf16(bytes)?)
+        }
+        TdsEnums::SQLDATETIME | TdsEnums::SQLDATETIM4 | TdsEnums::SQLDATETIMN => {
+            match bytes.len() {
+                4 => SqlValue::DateTime(decode_smalldatetime(bytes)),
+                8 => SqlValue::DateTime(decode_datetime(bytes)),
+                _ => return Err(invalid_length()),
+            }
+        }
+        TdsEnums::SQLDATETIME2 => match bytes.len() {
+            6..=8 => SqlValue::DateTime(decode_datetime2(bytes, type_info.scale)),
+            _ => return Err(invalid_length()),
+        },
+        TdsEnums::SQLDATE => match bytes.len() {
+            3 => SqlValue::Date(decode_date(bytes)),
+            _ => return Err(invalid_length()),
+        },
+        TdsEnums::SQLTIME => match bytes.len() {
+            3..=5 => SqlValue::Time(decode_time(bytes, type_info.scale)),
+            _ => return Err(invalid_length()),
+        },
+        TdsEnums::SQLDATETIMEOFFSET => match bytes.len() {
+            8..=10 => SqlValue::DateTimeOffset(decode_datetimeoffset(bytes, type_info.scale)?),
+            _ => return Err(invalid_length()),
+        },
+        TdsEnums::SQLBIGVARCHAR
+        | TdsEnums::SQLBIGCHAR
+        | TdsEnums::SQLVARCHAR
+        | TdsEnums::SQLCHAR
+        | TdsEnums::SQLTEXT => {
+            SqlValue::String(type_info.collation.unwrap_or_default().decode(bytes))
+        }
+        TdsEnums::SQLXMLTYPE => {
+            // XML may start with a byte order mark
+            let bytes = bytes.strip_prefix(&[0xFF, 0xFE]).unwrap_or(bytes);
+            SqlValue::String(decode_utf16(bytes)?)
+        }
+        TdsEnums::SQLVARIANT => decode_variant(bytes)?,
+        TdsEnums::SQLDECIMALN
+        | TdsEnums::SQLNUMERICN
+        | TdsEnums::SQLDECIMAL
+        | TdsEnums::SQLNUMERIC => match bytes.len() {
+            5 | 9 | 13 | 17 => SqlValue::Decimal(SqlDecimal::from_tds(
+                bytes,
+                type_info.precision,
+                type_info.scale,
+            )?),
+            _ => return Err(invalid_length()),
+        },
+        TdsEnums::SQLMONEY | TdsEnums::SQLMONEY4 | TdsEnums::SQLMONEYN => match bytes.len() {
+            4 | 8 => SqlValue::Money(SqlMoney::from_tds(bytes)?),
+            _ => return Err(invalid_length()),
+        },
+        TdsEnums::SQLUNIQUEID => match <[u8; 16]>::try_from(bytes) {
+            // The first three groups are little-endian
+            Ok(bytes) => SqlValue::Guid(Uuid::from_bytes_le(bytes)),
+            Err(_) => return Err(invalid_length()),
+        },
+        TdsEnums::SQLBIGVARBINARY
+        | TdsEnums::SQLBIGBINARY
+        | TdsEnums::SQLVARBINARY
+        | TdsEnums::SQLBINARY
+        | TdsEnums::SQLIMAGE
+        | TdsEnums::SQLUDT => SqlValue::Binary(bytes.to_vec()),
+        tds_type => {
+            return Err(SqlClientError::NotSupported(format!(
+                "Reading values of type 0x{:02X}",
+                tds_type
+            )))
+        }
+    };
+    Ok(value)
+}
+
+/// Reads and decodes a value.
+pub(crate) async fn read_value(
+    state: &mut TdsParserStateObject,
+    type_info: &TypeInfo,
+) -> Result<SqlValue, SqlClientError> {
+    let bytes = read_value_bytes(state, type_info).await?;
+    decode_value(type_info, bytes.as_deref())
+}
+
+/// Converts a value into its raw bytes for the given type, returning None for a null.
+pub(crate) fn encode_value(
+    type_info: &TypeInfo,
+    value: &SqlValue,
+) -> Result<Option<Vec<u8>>, SqlClientError> {
+    let unsupported = || {
+        SqlClientError::UnsupportedValue(
+            format!("TDS type 0x{:02X}", type_info.tds_type),
+            format!("{:?}", value),
+        )
+    };
+    // Gets the value as an integer, if it is one.
+    let integer = || -> Option<i64> {
+        match value {
+            SqlValue::Bit(v) => Some(*v as i64),
+            SqlValue::TinyInt(v) => Some(*v as i64),
+            SqlValue::SmallInt(v) => Some(*v as i64),
+            SqlValue::Int(v) => Some(*v as i64),
+            SqlValue::BigInt(v) => Some(*v),
+            _ => None,
+        }
+    };
+    if value.is_null() {
+        return Ok(None);
+    }
+    let bytes = match type_info.tds_type {
+        TdsEnums::SQLINT1
+        | TdsEnums::SQLINT2
+        | TdsEnums::SQLINT4
+        | TdsEnums::SQLINT8
+        | TdsEnums::SQLINTN => {
+            let integer = integer().ok_or_else(unsupported)?;
+            // Make sure the value fits in the parameter's type.
+            match type_info.max_length {
+                1 => vec![u8::try_from(integer).map_err(|_| unsupported())?],
+                2 => i16::try_from(integer)
+                    .map_err(|_| unsupported())?
+                    .to_le_bytes()
+                    .to_vec(),
+                4 => i32::try_from(integer)
+                    .map_err(|_| unsupported())?
+                    .to_le_bytes()
+                    .to_vec(),
+                _ => integer.to_le_bytes().to_vec(),
+            }
+        }
+        TdsEnums::SQLBIT | TdsEnums::SQLBITN => {
+            vec![(integer().ok_or_else(unsupported)? != 0) as u8]
+        }
+        TdsEnums::SQLFLT4 | TdsEnums::SQLFLT8 | TdsEnums::SQLFLTN => {
+            let float = match value {
+                SqlValue::Real(v) => *v as f64,
+                SqlValue::Float(v) => *v,
+                _ => integer().ok_or_else(unsupported)? as f64,
+            };
+            if type_info.max_length == 4 {
+                (float as f32).to_le_bytes().to_vec()
+            } else {
+                float.to_le_bytes().to_vec()
+            }
+        }
+        TdsEnums::SQLNVARCHAR | TdsEnums::SQLNCHAR | TdsEnums::SQLNTEXT | TdsEnums::SQLXMLTYPE => {
+            match value {
+                SqlValue::String(v) => encode_utf16(v),
+                _ => return Err(unsupported()),
+            }
+        }
+        TdsEnums::SQLBIGVARCHAR | TdsEnums::SQLBIGCHAR | TdsEnums::SQLTEXT => match value {
+            SqlValue::String(v) => type_info.collation.unwrap_or_default().encode(v),
+            _ => return Err(unsupported()),
+        },
+        TdsEnums::SQLDECIMALN
+        | TdsEnums::SQLNUMERICN
+        | TdsEnums::SQLMONEY
+        | TdsEnums::SQLMONEY4
+        | TdsEnums::SQLMONEYN => {
+            let decimal = to_decimal(value).ok_or_else(unsupported)?;
+            let bytes = if matches!(
+                type_info.tds_type,
+                TdsEnums::SQLDECIMALN | TdsEnums::SQLNUMERICN
+            ) {
+                encode_decimal(decimal, type_info.precision, type_info.scale)
+            } else {
+                SqlMoney::try_from(decimal)
+                    .ok()
+                    .and_then(|money| money.to_tds(type_info.max_length))
+            };
+            bytes.ok_or_else(unsupported)?
+        }
+        TdsEnums::SQLDATE => match value {
+            SqlValue::Date(v) => encode_date(*v),
+            SqlValue::DateTime(v) => encode_date(v.date()),
+            _ => return Err(unsupported()),
+        },
+        TdsEnums::SQLTIME => match value {
+            SqlValue::Time(v) => encode_time(*v, type_info.scale),
+            _ => return Err(unsupported()),
+        },
+        TdsEnums::SQLDATETIME2 => match value {
+            SqlValue::DateTime(v) => encode_datetime2(*v, type_info.scale),
+            SqlValue::Date(v) => encode_datetime2(v.and_time(NaiveTime::MIN), type_info.scale),
+            _ => return Err(unsupported()),
+        },
+        TdsEnums::SQLDATETIME | TdsEnums::SQLDATETIM4 | TdsEnums::SQLDATETIMN => {
+            let datetime = match value {
+                SqlValue::DateTime(v) => *v,
+                SqlValue::Date(v) => v.and_time(NaiveTime::MIN),
+                _ => return Err(unsupported()),
+            };
+            let bytes = if type_info.max_length == 4 {
+                encode_smalldatetime(datetime)
+            } else {
+                encode_datetime(datetime)
+            };
+            bytes.ok_or_else(unsupported)?
+        }
+        TdsEnums::SQLDATETIMEOFFSET => match value {
+            SqlValue::DateTimeOffset(v) => encode_datetimeoffset(*v, type_info.scale),
+            _ => return Err(unsupported()),
+        },
+        TdsEnums::SQLVARIANT => encode_variant(value)?,
+        TdsEnums::SQLUNIQUEID => match value {
+            SqlValue::Guid(v) => v.to_bytes_le().to_vec(),
+            _ => return Err(unsupported()),
+        },
+        TdsEnums::SQLBIGVARBINARY
+        | TdsEnums::SQLBIGBINARY
+        | TdsEnums::SQLIMAGE
+        | TdsEnums::SQLUDT => match value {
+            SqlValue::Binary(v) => v.clone(),
+            _ => return Err(unsupported()),
+        },
+        tds_type => {
+            return Err(SqlClientError::NotSupported(format!(
+                "Sending values of type 0x{:02X}",
+                tds_type
+            )))
+        }
+    };
+    Ok(Some(bytes))
+}
+
+/// Formats the fractional seconds of a time to the given scale (e.g. ".1234567").
+fn format_fraction(time: NaiveTime, scale: u8) -> String {
+    if scale == 0 {
+        return String::new();
+    }
+    let digits = format!("{:09}", time.nanosecond());
+    format!(".{}", &digits[..scale.min(7) as usize])
+}
+
+/// Converts a value read from the server to the type it is surfaced as in the given type system.
+///
+/// The SQL Server 2000 and 2005 type systems don't know the date and time types added in SQL
+/// Server 2008, so (as in .NET) their values are surfaced as strings.
+pub(crate) fn to_type_system(
+    type_info: &TypeInfo,
+    value: SqlValue,
+    type_system: TypeSystem,
+) -> SqlValue {
+    if type_system >= TypeSystem::SqlServer2008 || !type_info.is_new_date_time_type() {
+        return value;
+    }
+    let scale = type_info.scale;
+    let text = match value {
+        SqlValue::Date(v) => v.format("%Y-%m-%d").to_string(),
+        SqlValue::Time(v) => format!("{}{}", v.format("%H:%M:%S"), format_fraction(v, scale)),
+        SqlValue::DateTime(v) => format!(
+            "{}{}",
+            v.format("%Y-%m-%d %H:%M:%S"),
+            format_fraction(v.time(), scale)
+        ),
+        SqlValue::DateTimeOffset(v) => format!(
+            "{}{} {}",
+            v.format("%Y-%m-%d %H:%M:%S"),
+            format_fraction(v.time(), scale),
+            v.format("%:z")
+        ),
+        value => return value,
+    };
+    SqlValue::String(text)
+}
+
+/// Writes a value's length prefix and bytes.
+pub(crate) fn write_value_bytes(
+    buffer: &mut Vec<u8>,
+    type_info: &TypeInfo,
+    bytes: Option<&[u8]>,
+) -> Result<(), SqlClientError> {
+    let too_long = |length: usize| {
+        SqlClientError::Protocol(format!(
+            "A value of length {} is too long for type 0x{:02X}",
+            length, type_info.tds_type
+        ))
+    };
+    match (type_info.length_kind(), bytes) {
+        (TdsLengthKind::Fixed(_), None) => {
+            return Err(SqlClientError::Protocol(format!(
+                "Type 0x{:02X} can't be null",
+                type_info.tds_type
+            )))
+        }
+        (TdsLengthKind::Fixed(_), Some(bytes)) => buffer.extend_from_slice(bytes),
+        (TdsLengthKind::ByteLen, None) => buffer.push(0),
+        (TdsLengthKind::ByteLen, Some(bytes)) => {
+            buffer.push(u8::try_from(bytes.len()).map_err(|_| too_long(bytes.len()))?);
+            buffer.extend_from_slice(bytes);
+        }
+        (TdsLengthKind::UShortLen, None) => {
+            buffer.extend_from_slice(&TdsEnums::VARNULL.to_le_bytes())
+        }
+        (TdsLengthKind::UShortLen, Some(bytes)) => {
+            if bytes.len() > type_info.max_length as usize {
+                return Err(too_long(bytes.len()));
+            }
+            buffer.extend_from_slice(&(bytes.len() as u16).to_le_bytes());
+            buffer.extend_from_slice(bytes);
+        }
+        (TdsLengthKind::LongLen, None) => buffer.extend_from_slice(&0u32.to_le_bytes()),
+        (TdsLengthKind::LongLen, Some(bytes)) => {
+            buffer.extend_from_slice(&(bytes.len() as u32).to_le_bytes());
+            buffer.extend_from_slice(bytes);
+        }
+        (TdsLengthKind::Plp, None) => {
+            buffer.extend_from_slice(&TdsEnums::SQL_PLP_NULL.to_le_bytes())
+        }
+        (TdsLengthKind::Plp, Some(bytes)) => {
+            buffer.extend_from_slice(&(bytes.len() as u64).to_le_bytes());
+            // Send it as a single chunk
+            if !bytes.is_empty() {
+                buffer.extend_from_slice(&(bytes.len() as u32).to_le_bytes());
+                buffer.extend_from_slice(bytes);
+            }
+            buffer.extend_from_slice(&TdsEnums::SQL_PLP_CHUNK_TERMINATOR.to_le_bytes());
+        }
+    }
+    Ok(())
+}
+
+/// Encodes and writes a value.
+pub(crate) fn write_value(
+    buffer: &mut Vec<u8>,
+    type_info: &TypeInfo,
+    value: &SqlValue,
+) -> Result<(), SqlClientError> {
+    let bytes = encode_value(type_info, value)?;
+    write_value_bytes(buffer, type_info, bytes.as_deref())
+}
+
+/// Encodes and writes a value of a row (as sent in a bulk load).  Unlike parameters, the legacy
+/// text, ntext and image types have a text pointer and timestamp before the data.
+pub(crate) fn write_row_value(
+    buffer: &mut Vec<u8>,
+    type_info: &TypeInfo,
+    value: &SqlValue,
+) -> Result<(), SqlClientError> {
+    if !matches!(
+        type_info.tds_type,
+        TdsEnums::SQLTEXT | TdsEnums::SQLNTEXT | TdsEnums::SQLIMAGE
+    ) {
+        return write_value(buffer, type_info, value);
+    }
+    match encode_value(type_info, value)? {
+        // A null has a zero-length text pointer
+        None => buffer.push(0),
+        Some(bytes) => {
+            // The server ignores the pointer and timestamp
+            buffer.push(TdsEnums::TEXT_POINTER_LENGTH);
+            buffer.extend_from_slice(&[0xFF; TdsEnums::TEXT_POINTER_LENGTH as usize + 8]);
+            buffer.extend_from_slice(&(bytes.len() as u32).to_le_bytes());
+            buffer.extend_from_slice(&bytes);
+        }
+    }
+    Ok(())
+}
+
+/// The user-defined table type and columns of a table-valued parameter.
+#[derive(PartialEq, Debug, Clone)]
+pub(crate) struct TableType<'a> {
+    /// The schema of the type (or empty for the default schema).
+    pub schema: String,
+    /// The name of the type.
+    pub name: String,
+    /// The TYPE_INFO each column is sent with.
+    pub columns: Vec<TypeInfo>,
+    /// The rows.
+    pub rows: &'a [Vec<SqlValue>],
+}
+
+/// Writes the TVP_TYPE_INFO and rows of a table-valued parameter.
+pub(crate) fn write_table_value(
+    buffer: &mut Vec<u8>,
+    table_type: &TableType<'_>,
+) -> Result<(), SqlClientError> {
+    // The type's name.  The database name must be empty.
+    buffer.push(TdsEnums::SQLTABLE);
+    write_b_varchar(buffer, "");
+    write_b_varchar(buffer, &table_type.schema);
+    write_b_varchar(buffer, &table_type.name);
+    // The columns, which are unnamed
+    if table_type.columns.is_empty() {
+        buffer.extend_from_slice(&TdsEnums::TVP_NULL_TOKEN.to_le_bytes());
+    } else {
+        buffer.extend_from_slice(&(table_type.columns.len() as u16).to_le_bytes());
+        for type_info in &table_type.columns {
+            buffer.extend_from_slice(&0u32.to_le_bytes());
+            buffer.extend_from_slice(&ColumnMetaData::FLAG_NULLABLE.to_le_bytes());
+            type_info.write(buffer);
+            write_b_varchar(buffer, "");
+        }
+    }
+    // No optional metadata (ordering or default columns)
+    buffer.push(TdsEnums::TVP_END_TOKEN);
+    // The rows
+    for row in table_type.rows {
+        buffer.push(TdsEnums::TVP_ROW_TOKEN);
+        for (type_info, value) in table_type.columns.iter().zip(row) {
+            write_value(buffer, type_info, value)?;
+        }
+    }
+    buffer.push(TdsEnums::TVP_END_TOKEN);
+    Ok(())
+}
+
+/// Converts the start of a chunk of UTF-8 text read from a stream into the encoding of a character
+/// type.  Returns the encoded bytes and the number of bytes used; a character split across the end of
+/// the chunk is left for the next one.
+fn encode_text_chunk(
+    type_info: &TypeInfo,
+    bytes: &[u8],
+) -> Result<(Vec<u8>, usize), SqlClientError> {
+    let (text, used) = match std::str::from_utf8(bytes) {
+        Ok(text) => (text, bytes.len()),
+        Err(e) if e.error_len().is_none() => (
+            std::str::from_utf8(&bytes[..e.valid_up_to()]).unwrap(),
+            e.valid_up_to(),
+        ),
+        Err(e) => {
+            return Err(SqlClientError::UnsupportedValue(
+                "UTF-8 text".to_string(),
+                e.to_string(),
+            ))
+        }
+    };
+    let encoded = match type_info.tds_type {
+        TdsEnums::SQLNVARCHAR | TdsEnums::SQLNCHAR | TdsEnums::SQLNTEXT | TdsEnums::SQLXMLTYPE => {
+            encode_utf16(text)
+        }
+        _ => type_info.collation.unwrap_or_default().encode(text),
+    };
+    Ok((encoded, used))
+}
+
+/// Writes a PLP value read from a stream, a chunk at a time, so that it's never held in memory.
+///
+/// The stream's bytes are sent as they are for binary types.  For character types the stream is
+/// read as UTF-8 text and converted to the type's encoding.
+pub(crate) async fn write_plp_stream(
+    state: &mut TdsParserStateObject,
+    type_info: &TypeInfo,
+    stream: &mut (dyn AsyncRead + Send + Unpin),
+) -> Result<(), SqlClientError> {
+    let is_text =
+        TypeInfo::has_collation(type_info.tds_type) || type_info.tds_type == TdsEnums::SQLXMLTYPE;
+    state
+        .write(&TdsEnums::SQL_PLP_UNKNOWNLEN.to_le_bytes())
+        .await?;
+    // Read up to a packet's worth at a time
+    let mut buffer = vec![0u8; state.packet_size() - TdsEnums::HEADER_LEN];
+    // The number of bytes at the start of the buffer left over from the last read (the start of a
+    // character split between reads).
+    let mut pending = 0;
+    loop {
+        let count = stream.read(&mut buffer[pending..]).await?;
+        if count == 0 {
+            if pending > 0 {
+                return Err(SqlClientError::UnsupportedValue(
+                    "UTF-8 text".to_string(),
+                    "incomplete character at the end of the stream".to_string(),
+                ));
+            }
+            break;
+        }
+        let length = pending + count;
+        let chunk = if is_text {
+            let (encoded, used) = encode_text_chunk(type_info, &buffer[..length])?;
+            buffer.copy_within(used..length, 0);
+            pending = length - used;
+            encoded
+        } else {
+            buffer[..length].to_vec()
+        };
+        if !chunk.is_empty() {
+            state.write(&(chunk.len() as u32).to_le_bytes()).await?;
+            state.write(&chunk).await?;
+        }
+    }
+    state
+        .write(&TdsEnums::SQL_PLP_CHUNK_TERMINATOR.to_le_bytes())
+        .await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tds_test_utils::packet;
+    use crate::SqlDbType;
+    use test_utils::MockStream;
+
+    /// Writes a value and reads it back.
+    async fn round_trip(type_info: &TypeInfo, value: &SqlValue) -> SqlValue {
+        let mut buffer = Vec::new();
+        write_value(&mut buffer, type_info, value).unwrap();
+        let stream = MockStream::new(packet(&buffer));
+        let mut state = TdsParserStateObject::new(Box::new(stream), 4096);
+        let result = read_value(&mut state, type_info).await.unwrap();
+        assert!(state.is_message_complete());
+        result
+    }
+
+    #[rstest::rstest]
+    #[case(SqlDbType::Bit, SqlValue::Bit(true))]
+    #[case(SqlDbType::TinyInt, SqlValue::TinyInt(255))]
+    #[case(SqlDbType::SmallInt, SqlValue::SmallInt(-32768))]
+    #[case(SqlDbType::Int, SqlValue::Int(123456))]
+    #[case(SqlDbType::BigInt, SqlValue::BigInt(i64::MIN))]
+    #[case(SqlDbType::Real, SqlValue::Real(1.5))]
+    #[case(SqlDbType::Float, SqlValue::Float(-2.25))]
+    #[case(SqlDbType::NVarChar, SqlValue::String("héllo".to_string()))]
+    #[case(SqlDbType::NVarChar, SqlValue::String(String::new()))]
+    #[case(SqlDbType::VarBinary, SqlValue::Binary(vec![1, 2, 3]))]
+    #[case(SqlDbType::Timestamp, SqlValue::Binary(vec![0, 0, 0, 0, 0, 0, 7, 0xD1]))]
+    #[case(SqlDbType::VarChar, SqlValue::String("café".to_string()))]
+    #[case(SqlDbType::Xml, SqlValue::String("<a>é</a>".to_string()))]
+    #[case(SqlDbType::Decimal, SqlValue::Decimal("-12345.6780000".parse().unwrap()))]
+    #[case(SqlDbType::Money, SqlValue::Money(SqlMoney::MIN))]
+    #[case(SqlDbType::SmallMoney, SqlValue::Money("214748.3647".parse().unwrap()))]
+    #[case(
+        SqlDbType::UniqueIdentifier,
+        SqlValue::Guid(Uuid::from_u128(0x0011_2233_4455_6677_8899_AABB_CCDD_EEFF))
+    )]
+    #[case(SqlDbType::Date, SqlValue::Date(NaiveDate::from_ymd_opt(9999, 12, 31).unwrap()))]
+    #[case(SqlDbType::Time, SqlValue::Time(NaiveTime::from_hms_nano_opt(23, 59, 59, 999_999_900).unwrap()))]
+    #[case(SqlDbType::DateTime2, SqlValue::DateTime("0001-01-01T00:00:00.1234567".parse().unwrap()))]
+    #[case(SqlDbType::DateTime, SqlValue::DateTime("1753-01-01T12:34:56.997".parse().unwrap()))]
+    #[case(SqlDbType::SmallDateTime, SqlValue::DateTime("2079-06-06T23:59:00".parse().unwrap()))]
+    #[case(SqlDbType::DateTimeOffset, SqlValue::DateTimeOffset("2020-02-03T04:05:06.5-08:00".parse().unwrap()))]
+    #[case(SqlDbType::Variant, SqlValue::Int(42))]
+    #[case(SqlDbType::Variant, SqlValue::String("héllo".to_string()))]
+    #[case(SqlDbType::Variant, SqlValue::Decimal("1.5".parse().unwrap()))]
+    #[case(SqlDbType::Variant, SqlValue::Money("-1.5".parse().unwrap()))]
+    #[case(SqlDbType::Variant, SqlValue::DateTime("2020-02-03T04:05:06.5".parse().unwrap()))]
+    #[case(SqlDbType::Int, SqlValue::Null)]
+    #[case(SqlDbType::NVarChar, SqlValue::Null)]
+    #[case(SqlDbType::Date, SqlValue::Null)]
+    #[case(SqlDbType::Variant, SqlValue::Null)]
+    #[tokio::test]
+    async fn test_round_trip(#[case] sql_db_type: SqlDbType, #[case] value: SqlValue) {
+        let type_info = TypeInfo::for_parameter(sql_db_type, 0, 0, 18, 7, None).unwrap();
+        assert_eq!(value, round_trip(&type_info, &value).await);
+    }
+
+    #[rstest::rstest]
+    #[case(SqlValue::String("x".repeat(5000)))]
+    #[case(SqlValue::String(String::new()))]
+    #[case(SqlValue::Null)]
+    #[tokio::test]
+    async fn test_round_trip_plp(#[case] value: SqlValue) {
+        let type_info = TypeInfo::for_parameter(SqlDbType::NVarChar, -1, 0, 0, 0, None).unwrap();
+        assert!(type_info.is_plp());
+        assert_eq!(value, round_trip(&type_info, &value).await);
+    }
+
+    #[rstest::rstest]
+    // 2020-02-03 04:05:06.500
+    #[case(
+        TdsEnums::SQLDATETIMN,
+        8,
+        0,
+        "56AB00002E524300",
+        "2020-02-03T04:05:06.500"
+    )]
+    #[case(TdsEnums::SQLDATETIMN, 4, 0, "56ABF500", "2020-02-03T04:05:00")]
+    #[case(
+        TdsEnums::SQLDATETIME2,
+        8,
+        7,
+        "40D0C13D22B1400B",
+        "2020-02-03T04:05:06.500"
+    )]
+    fn test_decode_datetime(
+        #[case] tds_type: u8,
+        #[case] max_length: u32,
+        #[case] scale: u8,
+        #[case] bytes: &str,
+        #[case] expected: &str,
+    ) {
+        let type_info = TypeInfo {
+            tds_type,
+            max_length,
+            scale,
+            ..Default::default()
+        };
+        let bytes = hex::decode(bytes).unwrap();
+        assert_eq!(
+            SqlValue::DateTime(expected.parse().unwrap()),
+            decode_value(&type_info, Some(&bytes)).unwrap()
+        );
+    }
+
+    #[rstest::rstest]
+    #[case(TdsEnums::SQLDECIMALN, 2, "0039300000", SqlValue::Decimal("-123.45".parse().unwrap()))]
+    #[case(TdsEnums::SQLDECIMALN, 2, "0139300000", SqlValue::Decimal("123.45".parse().unwrap()))]
+    #[case(
+        TdsEnums::SQLMONEYN,
+        0,
+        "0000000050C30000",
+        SqlValue::Money(SqlMoney::from(5))
+    )]
+    #[case(TdsEnums::SQLMONEYN, 0, "50C30000", SqlValue::Money(SqlMoney::from(5)))]
+    fn test_decode_decimal(
+        #[case] tds_type: u8,
+        #[case] scale: u8,
+        #[case] bytes: &str,
+        #[case] expected: SqlValue,
+    ) {
+        let type_info = TypeInfo {
+            tds_type,
+            scale,
+            ..Default::default()
+        };
+        let bytes = hex::decode(bytes).unwrap();
+        assert_eq!(expected, decode_value(&type_info, Some(&bytes)).unwrap());
+    }
+
+    #[test]
+    fn test_decode_guid() {
+        let type_info = TypeInfo {
+            tds_type: TdsEnums::SQLUNIQUEID,
+            max_length: 16,
+            ..Default::default()
+        };
+        let bytes = hex::decode("33221100554477668899AABBCCDDEEFF").unwrap();
+        assert_eq!(
+            SqlValue::Guid("00112233-4455-6677-8899-aabbccddeeff".parse().unwrap()),
+            decode_value(&type_info, Some(&bytes)).unwrap()
+        );
+    }
+
+    #[rstest::rstest]
+    // Latin1_General_CI_AS uses code page 1252
+    #[case(SqlCollation::new(0x00D0_0409, 0), "636166E9", "café")]
+    // Latin1_General_100_CI_AS_SC_UTF8
+    #[case(SqlCollation::new(0x04E0_0409, 0), "636166C3A9", "café")]
+    fn test_decode_varchar(
+        #[case] collation: SqlCollation,
+        #[case] bytes: &str,
+        #[case] expected: &str,
+    ) {
+        let type_info = TypeInfo {
+            tds_type: TdsEnums::SQLBIGVARCHAR,
+            max_length: 8000,
+            collation: Some(collation),
+            ..Default::default()
+        };
+        let bytes = hex::decode(bytes).unwrap();
+        assert_eq!(
+            SqlValue::String(expected.to_string()),
+            decode_value(&type_info, Some(&bytes)).unwrap()
+        );
+    }
+
+    #[rstest::rstest]
+    #[case(TdsEnums::SQLTEXT, "616263", SqlValue::String("abc".to_string()))]
+    #[case(TdsEnums::SQLNTEXT, "610062006300", SqlValue::String("abc".to_string()))]
+    #[case(TdsEnums::SQLIMAGE, "010203", SqlValue::Binary(vec![1, 2, 3]))]
+    #[tokio::test]
+    async fn test_read_text(#[case] tds_type: u8, #[case] data: &str, #[case] expected: SqlValue) {
+        let type_info = TypeInfo {
+            tds_type,
+            max_length: 0x7FFF_FFFF,
+            collation: TypeInfo::has_collation(tds_type).then(SqlCollation::default),
+            ..Default::default()
+        };
+        // A text pointer, a timestamp, then the data
+        let data = hex::decode(data).unwrap();
+        let mut buffer = vec![16];
+        buffer.extend_from_slice(&[0xAA; 16]);
+        buffer.extend_from_slice(&[0xBB; 8]);
+        buffer.extend_from_slice(&(data.len() as u32).to_le_bytes());
+        buffer.extend_from_slice(&data);
+        // A null has a zero-length text pointer
+        buffer.push(0);
+        let stream = MockStream::new(packet(&buffer));
+        let mut state = TdsParserStateObject::new(Box::new(stream), 4096);
+        assert_eq!(expected, read_value(&mut state, &type_info).await.unwrap());
+        assert_eq!(
+            SqlValue::Null,
+            read_value(&mut state, &type_info).await.unwrap()
+        );
+        assert!(state.is_message_complete());
+    }
+
+    #[rstest::rstest]
+    #[case(SqlDbType::Time, 0, "CBA800", "12:00:11")]
+    #[case(SqlDbType::Time, 3, "E2599302", "12:00:11.234")]
+    #[case(SqlDbType::Time, 7, "4E22E79B64", "12:00:11.2345678")]
+    fn test_encode_time(
+        #[case] sql_db_type: SqlDbType,
+        #[case] scale: u8,
+        #[case] expected: &str,
+        #[case] time: &str,
+    ) {
+        let type_info = TypeInfo::for_parameter(sql_db_type, 0, 0, 0, scale, None).unwrap();
+        let bytes = encode_value(&type_info, &SqlValue::Time(time.parse().unwrap())).unwrap();
+        assert_eq!(expected, hex::encode_upper(bytes.unwrap()));
+    }
+
+    #[rstest::rstest]
+    // Rounds to the nearest 1/300th of a second
+    #[case("2020-02-03T04:05:06.501", "2020-02-03T04:05:06.500")]
+    #[case("2020-02-03T04:05:06.502", "2020-02-03T04:05:06.503")]
+    // Rounds into the next day
+    #[case("2020-02-03T23:59:59.999", "2020-02-04T00:00:00")]
+    #[tokio::test]
+    async fn test_round_trip_datetime_rounding(#[case] value: &str, #[case] expected: &str) {
+        let type_info = TypeInfo::for_parameter(SqlDbType::DateTime, 0, 0, 0, 0, None).unwrap();
+        let value = SqlValue::DateTime(value.parse().unwrap());
+        assert_eq!(
+            SqlValue::DateTime(expected.parse().unwrap()),
+            round_trip(&type_info, &value).await
+        );
+    }
+
+    #[rstest::rstest]
+    #[case(SqlDbType::DateTime, SqlValue::DateTime("1752-12-31T23:59:59".parse().unwrap()))]
+    #[case(SqlDbType::SmallDateTime, SqlValue::DateTime("2079-06-07T00:00:00".parse().unwrap()))]
+    #[case(SqlDbType::Decimal, SqlValue::Float(1e20))]
+    #[case(SqlDbType::Decimal, SqlValue::Decimal(SqlDecimal::from(i64::MAX)))]
+    #[case(SqlDbType::SmallMoney, SqlValue::Money("214748.3648".parse().unwrap()))]
+    #[case(SqlDbType::Money, SqlValue::Float(1e16))]
+    #[case(SqlDbType::Date, SqlValue::Int(1))]
+    fn test_encode_unsupported(#[case] sql_db_type: SqlDbType, #[case] value: SqlValue) {
+        let type_info = TypeInfo::for_parameter(sql_db_type, 0, 0, 18, 2, None).unwrap();
+        assert!(matches!(
+            encode_value(&type_info, &value),
+            Err(SqlClientError::UnsupportedValue(_, _))
+        ));
+    }
+
+    #[rstest::rstest]
+    #[case(TdsEnums::SQLDATE, 0, SqlValue::Date(NaiveDate::from_ymd_opt(2020, 2, 3).unwrap()), "2020-02-03")]
+    #[case(TdsEnums::SQLTIME, 0, SqlValue::Time(NaiveTime::from_hms_milli_opt(4, 5, 6, 500).unwrap()), "04:05:06")]
+    #[case(TdsEnums::SQLTIME, 3, SqlValue::Time(NaiveTime::from_hms_milli_opt(4, 5, 6, 500).unwrap()), "04:05:06.500")]
+    #[case(TdsEnums::SQLDATETIME2, 7, SqlValue::DateTime("2020-02-03T04:05:06.5".parse().unwrap()), "2020-02-03 04:05:06.5000000")]
+    #[case(TdsEnums::SQLDATETIMEOFFSET, 2, SqlValue::DateTimeOffset("2020-02-03T04:05:06.5-08:00".parse().unwrap()), "2020-02-03 04:05:06.50 -08:00")]
+    fn test_to_type_system(
+        #[case] tds_type: u8,
+        #[case] scale: u8,
+        #[case] value: SqlValue,
+        #[case] expected: &str,
+    ) {
+        let type_info = TypeInfo {
+            tds_type,
+            scale,
+            ..Default::default()
+        };
+        for type_system in [TypeSystem::SqlServer2000, TypeSystem::SqlServer2005] {
+            assert_eq!(
+                SqlValue::String(expected.to_string()),
+                to_type_system(&type_info, value.clone(), type_system)
+            );
+        }
+        assert_eq!(
+            value.clone(),
+            to_type_system(&type_info, value, TypeSystem::SqlServer2008)
+        );
+    }
+
+    #[test]
+    fn test_encode_out_of_range() {
+        let type_info = TypeInfo::for_parameter(SqlDbType::TinyInt, 0, 0, 0, 0, None).unwrap();
+        assert!(matches!(
+            encode_value(&type_info, &SqlValue::Int(256)),
+            Err(SqlClientError::UnsupportedValue(_, _))
+        ));
+    }
+
+    #[test]
+    fn test_write_table_value() {
+        let int = TypeInfo::for_parameter(SqlDbType::Int, 0, 0, 0, 0, None).unwrap();
+        let rows = vec![vec![SqlValue::Int(1)], vec![SqlValue::Null]];
+        let table_type = TableType {
+            schema: "dbo".to_string(),
+            name: "IdList".to_string(),
+            columns: vec![int.clone()],
+            rows: &rows,
+        };
+        let mut buffer = Vec::new();
+        write_table_value(&mut buffer, &table_type).unwrap();
+        let mut expected = vec![TdsEnums::SQLTABLE];
+        write_b_varchar(&mut expected, "");
+        write_b_varchar(&mut expected, "dbo");
+        write_b_varchar(&mut expected, "IdList");
+        expected.extend_from_slice(&1u16.to_le_bytes());
+        expected.extend_from_slice(&0u32.to_le_bytes());
+        expected.extend_from_slice(&ColumnMetaData::FLAG_NULLABLE.to_le_bytes());
+        int.write(&mut expected);
+        expected.push(0);
+        expected.push(TdsEnums::TVP_END_TOKEN);
+        expected.extend_from_slice(&[TdsEnums::TVP_ROW_TOKEN, 4, 1, 0, 0, 0]);
+        expected.extend_from_slice(&[TdsEnums::TVP_ROW_TOKEN, 0]);
+        expected.push(TdsEnums::TVP_END_TOKEN);
+        assert_eq!(expected, buffer);
+    }
+
+    #[rstest::rstest]
+    #[case(SqlDbType::Int, SqlValue::Int(5))]
+    #[case(SqlDbType::Text, SqlValue::from("abc"))]
+    #[case(SqlDbType::Image, SqlValue::Binary(vec![1, 2]))]
+    #[case(SqlDbType::Text, SqlValue::Null)]
+    #[tokio::test]
+    async fn test_write_row_value(#[case] sql_db_type: SqlDbType, #[case] value: SqlValue) {
+        let mut type_info = TypeInfo::for_parameter(sql_db_type, 0, 0, 0, 0, None).unwrap();
+        // Rows use the legacy types themselves
+        type_info.tds_type = match sql_db_type {
+            SqlDbType::Text => TdsEnums::SQLTEXT,
+            SqlDbType::Image => TdsEnums::SQLIMAGE,
+            _ => type_info.tds_type,
+        };
+        let mut buffer = Vec::new();
+        write_row_value(&mut buffer, &type_info, &value).unwrap();
+        let stream = MockStream::new(packet(&buffer));
+        let mut state = TdsParserStateObject::new(Box::new(stream), 4096);
+        assert_eq!(value, read_value(&mut state, &type_info).await.unwrap());
+        assert!(state.is_message_complete());
+    }
+}
@@ -0,0 +1,173 @@
+use crate::{SqlDecimal, SqlMoney, SqlValue};
+use chrono::{DateTime, FixedOffset, NaiveDate, NaiveDateTime, NaiveTime};
+use secstr::SecStr;
+use uuid::Uuid;
+
+/// A type that can be sent as a parameter value (e.g. with [crate::SqlParameter::with_value]).
+///
+/// Applications can implement this for their own types, such as newtypes or enums stored as
+/// strings, by converting to one of the built-in types:
+///
+/// ```
+/// use sql_client::{SqlValue, ToSql};
+///
+/// enum Status {
+///     Active,
+///     Closed,
+/// }
+///
+/// impl ToSql for Status {
+///     fn to_sql(&self) -> SqlValue {
+///         match self {
+///             Status::Active => "active".to_sql(),
+///             Status::Closed => "closed".to_sql(),
+///         }
+///     }
+/// }
+/// ```
+pub trait ToSql {
+    /// Converts the value.
+    fn to_sql(&self) -> SqlValue;
+}
+
+/// Implements [ToSql] for a type that [SqlValue] can be created from.
+macro_rules! to_sql_from {
+    ($type:ty) => {
+        impl ToSql for $type {
+            fn to_sql(&self) -> SqlValue {
+                SqlValue::from(self.clone())
+            }
+        }
+    };
+}
+
+to_sql_from!(bool);
+to_sql_from!(u8);
+to_sql_from!(i16);
+to_sql_from!(i32);
+to_sql_from!(i64);
+to_sql_from!(f32);
+to_sql_from!(f64);
+to_sql_from!(SqlDecimal);
+to_sql_from!(SqlMoney);
+to_sql_from!(String);
+to_sql_from!(Vec<u8>);
+to_sql_from!(NaiveDate);
+to_sql_from!(NaiveTime);
+to_sql_from!(NaiveDateTime);
+to_sql_from!(DateTime<FixedOffset>);
+to_sql_from!(Uuid);
+to_sql_from!(SqlValue);
+#[cfg(feature = "rust_decimal")]
+to_sql_from!(rust_decimal::Decimal);
+
+impl ToSql for str {
+    fn to_sql(&self) -> SqlValue {
+        SqlValue::from(self)
+    }
+}
+
+impl ToSql for [u8] {
+    fn to_sql(&self) -> SqlValue {
+        SqlValue::from(self)
+    }
+}
+
+/// A secure string is sent as a character string.  Note that the value is copied into the
+/// parameter, so it's only held securely until then.
+impl ToSql for SecStr {
+    fn to_sql(&self) -> SqlValue {
+        SqlValue::String(String::from_utf8_lossy(self.unsecure()).to_string())
+    }
+}
+
+impl<T: ToSql> ToSql for Option<T> {
+    fn to_sql(&self) -> SqlValue {
+        match self {
+            Some(value) => value.to_sql(),
+            None => SqlValue::Null,
+        }
+    }
+}
+
+impl<T: ToSql + ?Sized> ToSql for &T {
+    fn to_sql(&self) -> SqlValue {
+        (**self).to_sql()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{FromSql, SqlClientError};
+
+    /// A newtype, as an application might define.
+    #[derive(PartialEq, Debug)]
+    struct CustomerId(i64);
+
+    impl ToSql for CustomerId {
+        fn to_sql(&self) -> SqlValue {
+            self.0.to_sql()
+        }
+    }
+
+    impl FromSql for CustomerId {
+        fn from_sql(value: &SqlValue) -> Result<Self, SqlClientError> {
+            i64::from_sql(value).map(CustomerId)
+        }
+    }
+
+    /// An enum stored as a string, as an application might define.
+    #[derive(PartialEq, Debug)]
+    enum Status {
+        Active,
+        Closed,
+    }
+
+    impl ToSql for Status {
+        fn to_sql(&self) -> SqlValue {
+            match self {
+                Status::Active => "active".to_sql(),
+                Status::Closed => "closed".to_sql(),
+            }
+        }
+    }
+
+    impl FromSql for Status {
+        fn from_sql(value: &SqlValue) -> Result<Self, SqlClientError> {
+            match String::from_sql(value)?.as_str() {
+                "active" => Ok(Status::Active),
+                "closed" => Ok(Status::Closed),
+                _ => Err(crate::from_sql::unsupported("Status", value)),
+            }
+        }
+    }
+
+    #[test]
+    fn test_to_sql() {
+        assert_eq!(SqlValue::Int(42), 42.to_sql());
+        assert_eq!(SqlValue::from("abc"), "abc".to_sql());
+        assert_eq!(SqlValue::Binary(vec![1, 2]), [1u8, 2][..].to_sql());
+        assert_eq!(SqlValue::Null, None::<i32>.to_sql());
+        assert_eq!(SqlValue::Bit(true), Some(&true).to_sql());
+        assert_eq!(SqlValue::from("secret"), SecStr::from("secret").to_sql());
+    }
+
+    #[test]
+    fn test_custom_types() {
+        assert_eq!(SqlValue::BigInt(7), CustomerId(7).to_sql());
+        assert_eq!(
+            CustomerId(7),
+            CustomerId::from_sql(&SqlValue::BigInt(7)).unwrap()
+        );
+        assert_eq!(SqlValue::from("closed"), Status::Closed.to_sql());
+        assert_eq!(
+            Status::Active,
+            Status::from_sql(&SqlValue::from("active")).unwrap()
+        );
+        assert!(matches!(
+            Status::from_sql(&SqlValue::from("unknown")),
+            Err(SqlClientError::UnsupportedValue(_, _))
+        ));
+    }
+}
@@ -0,0 +1,106 @@
+use crate::{SqlValue, ToSql};
+
+/// A type that can be sent as a row of a [crate::DataTable] (e.g. with
+/// [crate::DataTable::from_rows]).
+///
+/// This is implemented for tuples of values and for single values, and can be derived for structs,
+/// which sends each field in order:
+///
+/// ```ignore
+/// #[derive(ToSqlRow)]
+/// struct OrderLine {
+///     product_id: i64,
+///     quantity: i32,
+///     // Sent as the columns of the inner struct
+///     #[sql(flatten)]
+///     price: Price,
+/// }
+/// ```
+///
+/// Each field's type must implement [ToSql] (or [ToSqlRow] if it's flattened).
+pub trait ToSqlRow {
+    /// Converts the value to the values of a row, one per column.
+    fn to_row(&self) -> Vec<SqlValue>;
+}
+
+impl<T: ToSql> ToSqlRow for T {
+    fn to_row(&self) -> Vec<SqlValue> {
+        vec![self.to_sql()]
+    }
+}
+
+/// Implements [ToSqlRow] for tuples of values.
+macro_rules! to_sql_row_tuple {
+    ($($name:ident),+) => {
+        impl<$($name: ToSql),+> ToSqlRow for ($($name,)+) {
+            #[allow(non_snake_case)]
+            fn to_row(&self) -> Vec<SqlValue> {
+                let ($($name,)+) = self;
+                vec![$($name.to_sql()),+]
+            }
+        }
+    };
+}
+
+to_sql_row_tuple!(A);
+to_sql_row_tuple!(A, B);
+to_sql_row_tuple!(A, B, C);
+to_sql_row_tuple!(A, B, C, D);
+to_sql_row_tuple!(A, B, C, D, E);
+to_sql_row_tuple!(A, B, C, D, E, F);
+to_sql_row_tuple!(A, B, C, D, E, F, G);
+to_sql_row_tuple!(A, B, C, D, E, F, G, H);
+to_sql_row_tuple!(A, B, C, D, E, F, G, H, I);
+to_sql_row_tuple!(A, B, C, D, E, F, G, H, I, J);
+to_sql_row_tuple!(A, B, C, D, E, F, G, H, I, J, K);
+to_sql_row_tuple!(A, B, C, D, E, F, G, H, I, J, K, L);
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ToSqlRow;
+
+    #[derive(ToSqlRow)]
+    struct Price {
+        amount: i64,
+        currency: &'static str,
+    }
+
+    #[derive(ToSqlRow)]
+    struct OrderLine {
+        product_id: i64,
+        quantity: Option<i32>,
+        #[sql(flatten)]
+        price: Price,
+    }
+
+    #[test]
+    fn test_to_row() {
+        assert_eq!(vec![SqlValue::BigInt(1)], 1i64.to_row());
+        assert_eq!(
+            vec![SqlValue::Int(1), SqlValue::from("a"), SqlValue::Null],
+            (1, "a", None::<bool>).to_row()
+        );
+    }
+
+    #[test]
+    fn test_derive() {
+        let line = OrderLine {
+            product_id: 7,
+            quantity: None,
+            price: Price {
+                amount: 250,
+                currency: "EUR",
+            },
+        };
+        assert_eq!(
+            vec![
+                SqlValue::BigInt(7),
+                SqlValue::Null,
+                SqlValue::BigInt(250),
+                SqlValue::from("EUR"),
+            ],
+            line.to_row()
+        );
+    }
+}
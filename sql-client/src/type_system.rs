@@ -1,5 +1,5 @@
 use crate::SqlClientError;
-use crate::TypeSystem::SqlServer2008;
+use crate::TypeSystem::SqlServer2012;
 use std::fmt::{Display, Formatter};
 
 pub(crate) struct TypeSystemVersion;
@@ -11,7 +11,7 @@ impl TypeSystemVersion {
     pub const SQL_SERVER_2012: &'static str = "SQL Server 2012";
 }
 
-#[derive(PartialEq, Debug, Clone, Copy)]
+#[derive(PartialEq, Eq, PartialOrd, Ord, Debug, Clone, Copy)]
 /// SQL Type System constants.
 pub(crate) enum TypeSystem {
     SqlServer2000 = 2000,
@@ -20,7 +20,7 @@ pub(crate) enum TypeSystem {
     SqlServer2012 = 2012,
 }
 impl TypeSystem {
-    pub const LATEST: TypeSystem = SqlServer2008;
+    pub const LATEST: TypeSystem = SqlServer2012;
 }
 
 impl TryFrom<&str> for TypeSystem {
@@ -32,6 +32,7 @@ impl TryFrom<&str> for TypeSystem {
             "sql server 2005" => Ok(TypeSystem::SqlServer2005),
             "sql server 2008" => Ok(TypeSystem::SqlServer2008),
             "sql server 2012" => Ok(TypeSystem::SqlServer2012),
+            "latest" => Ok(TypeSystem::LATEST),
             _ => {
                 log::warn!("Unsupported type system version {:?}", value);
                 Err(SqlClientError::UnsupportedValue(
@@ -71,6 +72,7 @@ mod tests {
     #[case("SQL Server 2005", TypeSystem::SqlServer2005)]
     #[case("SQL Server 2008", TypeSystem::SqlServer2008)]
     #[case("SQL Server 2012", TypeSystem::SqlServer2012)]
+    #[case("Latest", TypeSystem::SqlServer2012)]
     fn test_from_string(#[case] value: &str, #[case] expected: TypeSystem) {
         let actual: TypeSystem = value.try_into().unwrap();
         assert_eq!(expected, actual);
@@ -1,8 +1,99 @@
+use std::io;
+use std::pin::Pin;
+use std::sync::{Arc, Mutex};
+use std::task::{Context, Poll};
+use tokio::io::{AsyncRead, AsyncWrite, ReadBuf};
+
+/// An in-memory stream that plays back canned server bytes and records everything the client writes.
+///
+/// Used in place of a network stream so that protocol code can be tested without a server.
+pub struct MockStream {
+    /// The bytes the "server" will send, in order.
+    read_data: Vec<u8>,
+    /// How many of the read bytes have been consumed.
+    read_position: usize,
+    /// Everything that has been written to the stream.
+    written: Arc<Mutex<Vec<u8>>>,
+}
+
+impl MockStream {
+    /// Creates a new stream that will return the given bytes when read.
+    pub fn new(read_data: Vec<u8>) -> Self {
+        Self {
+            read_data,
+            read_position: 0,
+            written: Arc::new(Mutex::new(Vec::new())),
+        }
+    }
+
+    /// Returns a handle to the written bytes that remains usable after the stream has been moved.
+    pub fn written(&self) -> Arc<Mutex<Vec<u8>>> {
+        self.written.clone()
+    }
+}
+
+impl AsyncRead for MockStream {
+    fn poll_read(
+        mut self: Pin<&mut Self>,
+        _cx: &mut Context<'_>,
+        buf: &mut ReadBuf<'_>,
+    ) -> Poll<io::Result<()>> {
+        // Copy as much of the remaining data as will fit.  Once the data is exhausted this reads
+        // zero bytes, which the caller will see as the end of the stream.
+        let remaining = &self.read_data[self.read_position..];
+        let count = remaining.len().min(buf.remaining());
+        buf.put_slice(&remaining[..count]);
+        self.read_position += count;
+        Poll::Ready(Ok(()))
+    }
+}
+
+impl AsyncWrite for MockStream {
+    fn poll_write(
+        self: Pin<&mut Self>,
+        _cx: &mut Context<'_>,
+        buf: &[u8],
+    ) -> Poll<io::Result<usize>> {
+        // Record the bytes
+        self.written.lock().unwrap().extend_from_slice(buf);
+        Poll::Ready(Ok(buf.len()))
+    }
+
+    fn poll_flush(self: Pin<&mut Self>, _cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        Poll::Ready(Ok(()))
+    }
+
+    fn poll_shutdown(self: Pin<&mut Self>, _cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        Poll::Ready(Ok(()))
+    }
+}
+
 #[cfg(test)]
 mod tests {
+    use super::*;
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
     #[test]
     fn it_works() {
         let result = 2 + 2;
         assert_eq!(result, 4);
     }
+
+    #[test]
+    fn test_mock_stream() {
+        let runtime = tokio::runtime::Builder::new_current_thread()
+            .build()
+            .unwrap();
+        runtime.block_on(async {
+            let mut stream = MockStream::new(vec![1, 2, 3]);
+            let written = stream.written();
+            // Reading should return the canned data, then the end of the stream.
+            let mut buffer = Vec::new();
+            stream.read_to_end(&mut buffer).await.unwrap();
+            assert_eq!(vec![1, 2, 3], buffer);
+            // Writes should be recorded.
+            stream.write_all(&[4, 5]).await.unwrap();
+            assert_eq!(vec![4, 5], *written.lock().unwrap());
+        });
+    }
 }